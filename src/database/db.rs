@@ -18,4 +18,22 @@ pub static DB: Lazy<Pool<Sqlite>> = Lazy::new(|| {
     SqlitePoolOptions::new()
         .max_connections(5)
         .connect_lazy_with(opts)
-});
\ No newline at end of file
+});
+
+/// Добавить колонку, если её ещё нет (миграция для уже существующего bot.db).
+pub async fn add_column_if_missing(table: &str, column: &str, ddl: &str) -> sqlx::Result<()> {
+    let exists = sqlx::query(&format!(
+            "SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1", table
+        ))
+        .bind(column)
+        .fetch_optional(&*DB)
+        .await?
+        .is_some();
+
+    if !exists {
+        sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, ddl))
+            .execute(&*DB)
+            .await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,161 @@
+// src/database/exit_policy.rs
+//
+// Правила выхода из диапазона по пулам (отдельно для пробоя вверх и вниз)
+// и журнал решений: когда цена вышла, вернулась и почему закрылись.
+use chrono::Utc;
+use sqlx::Row;
+use crate::database::db::DB;
+
+/// Правило выхода для одной стороны.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExitRule {
+    /// сколько минут цена должна быть вне диапазона (`None` — `min_restart` пула)
+    pub wait_min:      Option<u64>,
+    /// окно TWAP по тикам цены, секунды (0 — смотрим на текущую цену)
+    pub twap_sec:      u64,
+    /// сколько тиков подряд цена (или TWAP) должна быть вне диапазона
+    pub confirmations: u32,
+    /// выйти сразу, если цена ушла за границу больше чем на столько %, (0 — выкл.)
+    pub overshoot_pct: f64,
+}
+
+impl ExitRule {
+    pub fn describe(&self) -> String {
+        format!(
+            "wait {} · twap {}s · confirm {} · overshoot {}",
+            self.wait_min.map(|m| format!("{m}m")).unwrap_or_else(|| "min_restart".into()),
+            self.twap_sec,
+            self.confirmations,
+            if self.overshoot_pct > 0.0 { format!("{:.2}%", self.overshoot_pct) } else { "off".into() },
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExitPolicy {
+    pub up:   ExitRule,
+    pub down: ExitRule,
+}
+
+impl Default for ExitPolicy {
+    /// Прежнее поведение: вверх — ждём `min_restart`, вниз — без ожидания,
+    /// но первый тик за границей только взводит выход, закрываемся на втором.
+    fn default() -> Self {
+        ExitPolicy {
+            up:   ExitRule { wait_min: None,    twap_sec: 0, confirmations: 1, overshoot_pct: 0.0 },
+            down: ExitRule { wait_min: Some(0), twap_sec: 0, confirmations: 2, overshoot_pct: 0.0 },
+        }
+    }
+}
+
+pub async fn init_exit_policy_module() -> sqlx::Result<()> {
+    sqlx::query(r#"
+        CREATE TABLE IF NOT EXISTS exit_policies (
+            pool_address        TEXT PRIMARY KEY NOT NULL,
+            up_wait_min         INTEGER,
+            up_twap_sec         INTEGER NOT NULL DEFAULT 0,
+            up_confirmations    INTEGER NOT NULL DEFAULT 1,
+            up_overshoot_pct    REAL    NOT NULL DEFAULT 0,
+            down_wait_min       INTEGER,
+            down_twap_sec       INTEGER NOT NULL DEFAULT 0,
+            down_confirmations  INTEGER NOT NULL DEFAULT 1,
+            down_overshoot_pct  REAL    NOT NULL DEFAULT 0
+        );
+    "#)
+    .execute(&*DB)
+    .await?;
+    sqlx::query(r#"
+        CREATE TABLE IF NOT EXISTS exit_decisions (
+            id            INTEGER PRIMARY KEY AUTOINCREMENT,
+            pool_address  TEXT NOT NULL,
+            pool_name     TEXT NOT NULL,
+            side          TEXT NOT NULL,
+            event         TEXT NOT NULL,
+            price         REAL NOT NULL,
+            ref_price     REAL NOT NULL,
+            reason        TEXT NOT NULL,
+            created_at    TEXT NOT NULL
+        );
+    "#)
+    .execute(&*DB)
+    .await?;
+    Ok(())
+}
+
+fn row_to_rule(row: &sqlx::sqlite::SqliteRow, side: &str) -> sqlx::Result<ExitRule> {
+    Ok(ExitRule {
+        wait_min:      row.try_get::<Option<i64>, _>(format!("{side}_wait_min").as_str())?.map(|m| m as u64),
+        twap_sec:      row.try_get::<i64, _>(format!("{side}_twap_sec").as_str())? as u64,
+        confirmations: row.try_get::<i64, _>(format!("{side}_confirmations").as_str())? as u32,
+        overshoot_pct: row.try_get(format!("{side}_overshoot_pct").as_str())?,
+    })
+}
+
+/// Сохранённая политика пула (`None` — берётся политика стратегии).
+pub async fn get_exit_policy(pool_address: &str) -> sqlx::Result<Option<ExitPolicy>> {
+    let row = sqlx::query("SELECT * FROM exit_policies WHERE pool_address = ?1")
+        .bind(pool_address)
+        .fetch_optional(&*DB)
+        .await?;
+    row.map(|r| Ok(ExitPolicy { up: row_to_rule(&r, "up")?, down: row_to_rule(&r, "down")? }))
+        .transpose()
+}
+
+/// Записать политику пула целиком.
+pub async fn set_exit_policy(pool_address: &str, p: &ExitPolicy) -> sqlx::Result<()> {
+    sqlx::query(r#"
+        INSERT OR REPLACE INTO exit_policies (
+            pool_address,
+            up_wait_min, up_twap_sec, up_confirmations, up_overshoot_pct,
+            down_wait_min, down_twap_sec, down_confirmations, down_overshoot_pct
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+    "#)
+    .bind(pool_address)
+    .bind(p.up.wait_min.map(|m| m as i64))
+    .bind(p.up.twap_sec as i64)
+    .bind(p.up.confirmations as i64)
+    .bind(p.up.overshoot_pct)
+    .bind(p.down.wait_min.map(|m| m as i64))
+    .bind(p.down.twap_sec as i64)
+    .bind(p.down.confirmations as i64)
+    .bind(p.down.overshoot_pct)
+    .execute(&*DB)
+    .await?;
+    Ok(())
+}
+
+/// Сбросить политику пула к политике стратегии.
+pub async fn delete_exit_policy(pool_address: &str) -> sqlx::Result<()> {
+    sqlx::query("DELETE FROM exit_policies WHERE pool_address = ?1")
+        .bind(pool_address)
+        .execute(&*DB)
+        .await?;
+    Ok(())
+}
+
+/// Записать решение по выходу (вышла / вернулась / закрываемся) и его причину.
+pub async fn log_decision(
+    pool_address: &str,
+    pool_name: &str,
+    side: &str,
+    event: &str,
+    price: f64,
+    ref_price: f64,
+    reason: &str,
+) -> sqlx::Result<()> {
+    sqlx::query(r#"
+        INSERT INTO exit_decisions (pool_address, pool_name, side, event, price, ref_price, reason, created_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+    "#)
+    .bind(pool_address)
+    .bind(pool_name)
+    .bind(side)
+    .bind(event)
+    .bind(price)
+    .bind(ref_price)
+    .bind(reason)
+    .bind(Utc::now().to_rfc3339())
+    .execute(&*DB)
+    .await?;
+    Ok(())
+}
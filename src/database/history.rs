@@ -1,13 +1,41 @@
 // src/database/history.rs
 use once_cell::sync::Lazy;
 use sqlx::{Row, Sqlite, sqlite::SqlitePoolOptions, Pool};
-use crate::database::db::DB;
-use crate::database::positions::get_pool_config;
+use crate::database::db::{DB, add_column_if_missing};
+use crate::database::positions::{get_pool_config, get_rewards_pending};
+use crate::database::tx_costs::costs_since;
 use chrono::{DateTime, Utc};
 use chrono::Duration;
-use crate::utils;
+use crate::dex_services::executor;
 use chrono::NaiveDate;
 
+/// Из чего посчитаны `sum_open` / `sum_close` сессии.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfitBasis {
+    /// баланс кошелька: в разнице уже сидят собранные комиссии, награды,
+    /// комиссии сети и рента
+    Wallet,
+    /// стоимость позиций пула: комиссии и издержки — отдельно
+    Positions,
+}
+
+impl ProfitBasis {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ProfitBasis::Wallet    => "wallet",
+            ProfitBasis::Positions => "positions",
+        }
+    }
+}
+
+/// Старые записи (до колонки `basis`) посчитаны по кошельку.
+fn row_basis(row: &sqlx::sqlite::SqliteRow) -> sqlx::Result<ProfitBasis> {
+    Ok(match row.try_get::<String, _>("basis")?.as_str() {
+        "positions" => ProfitBasis::Positions,
+        _           => ProfitBasis::Wallet,
+    })
+}
+
 /// Одна запись о сессии открытия/закрытия позиции
 #[derive(Debug, Clone)]
 pub struct SessionHistory {
@@ -20,6 +48,14 @@ pub struct SessionHistory {
     pub sum_open:       f64,
     pub sum_close:      f64,
     pub commissions:    f64,
+    /// комиссии сети (база + приоритет) за все транзакции сессии, USD
+    pub tx_fees:        f64,
+    /// рента: внесённая минус вернувшаяся, USD (может быть < 0)
+    pub rent_net:       f64,
+    /// сессия бумажного режима (виртуальный кошелёк)
+    pub paper:          bool,
+    /// новые сессии и бэктест — по позициям, старые записи — по кошельку
+    pub basis:          ProfitBasis,
 }
 
 #[derive(Debug)]
@@ -30,11 +66,16 @@ pub struct SessionStatistics {
     pub period_end:      DateTime<Utc>,
     /// сколько сессий в периоде
     pub session_count:   usize,
-    /// суммарные комиссии
+    /// суммарные комиссии пулов (для сессий по кошельку — справочно, они
+    /// уже в total_profit)
     pub total_commissions: f64,
-    /// суммарная прибыль/убыток (sum_close − sum_open)
+    /// суммарная разница sum_close − sum_open
     pub total_profit:    f64,
-    /// net-прибыль: total_profit + total_commissions
+    /// издержки транзакций: комиссии сети + чистая рента (для сессий по
+    /// кошельку — справочно, они уже в total_profit)
+    pub total_costs:     f64,
+    /// net-прибыль: по кошельку — сама разница, по позициям — разница
+    /// + комиссии − издержки
     pub net_profit:      f64,
     /// средняя продолжительность сессии
     pub average_duration: Duration,
@@ -53,7 +94,47 @@ pub async fn init_history_module() -> sqlx::Result<()> {
             range_upper    REAL NOT NULL,
             sum_open       REAL NOT NULL,
             sum_close      REAL NOT NULL,
-            commissions    REAL NOT NULL
+            commissions    REAL NOT NULL,
+            tx_fees        REAL NOT NULL DEFAULT 0,
+            rent_net       REAL NOT NULL DEFAULT 0,
+            paper          INTEGER NOT NULL DEFAULT 0,
+            basis          TEXT NOT NULL DEFAULT 'wallet'
+        );
+    "#)
+    .execute(&*DB)
+    .await?;
+    add_column_if_missing("session_history", "paper", "INTEGER NOT NULL DEFAULT 0").await?;
+    add_column_if_missing("session_history", "basis", "TEXT NOT NULL DEFAULT 'wallet'").await?;
+    add_column_if_missing("session_history", "tx_fees", "REAL NOT NULL DEFAULT 0").await?;
+    add_column_if_missing("session_history", "rent_net", "REAL NOT NULL DEFAULT 0").await?;
+    sqlx::query(r#"
+        CREATE TABLE IF NOT EXISTS fee_harvests (
+            id             INTEGER PRIMARY KEY AUTOINCREMENT,
+            pool_address   TEXT NOT NULL,
+            pool_name      TEXT NOT NULL,
+            position_mint  TEXT NOT NULL,
+            amount_a       REAL NOT NULL,
+            amount_b       REAL NOT NULL,
+            usd            REAL NOT NULL,
+            action         TEXT NOT NULL,
+            paper          INTEGER NOT NULL DEFAULT 0,
+            created_at     TEXT NOT NULL
+        );
+    "#)
+    .execute(&*DB)
+    .await?;
+    sqlx::query(r#"
+        CREATE TABLE IF NOT EXISTS pool_migrations (
+            id             INTEGER PRIMARY KEY AUTOINCREMENT,
+            pool_name      TEXT NOT NULL,
+            from_address   TEXT NOT NULL,
+            to_address     TEXT NOT NULL,
+            from_apr       REAL NOT NULL,
+            to_apr         REAL NOT NULL,
+            est_gain_usd   REAL NOT NULL,
+            est_cost_usd   REAL NOT NULL,
+            paper          INTEGER NOT NULL DEFAULT 0,
+            created_at     TEXT NOT NULL
         );
     "#)
     .execute(&*DB)
@@ -61,18 +142,114 @@ pub async fn init_history_module() -> sqlx::Result<()> {
     Ok(())
 }
 
-/// Создаёт новую запись истории на основе текущего pool_config,
+/// Переезд пула в другой fee tier (см. `migration.rs`).
+#[derive(Debug, Clone)]
+pub struct PoolMigration {
+    pub pool_name:    String,
+    pub from_address: String,
+    pub to_address:   String,
+    pub from_apr:     f64,
+    pub to_apr:       f64,
+    pub est_gain_usd: f64,
+    pub est_cost_usd: f64,
+}
+
+/// Записать переезд пула.
+pub async fn record_pool_migration(m: &PoolMigration) -> sqlx::Result<i64> {
+    let res = sqlx::query(r#"
+        INSERT INTO pool_migrations (
+            pool_name, from_address, to_address, from_apr, to_apr, est_gain_usd, est_cost_usd, paper, created_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+    "#)
+    .bind(&m.pool_name)
+    .bind(&m.from_address)
+    .bind(&m.to_address)
+    .bind(m.from_apr)
+    .bind(m.to_apr)
+    .bind(m.est_gain_usd)
+    .bind(m.est_cost_usd)
+    .bind(executor::is_paper() as i32)
+    .bind(Utc::now().to_rfc3339())
+    .execute(&*DB)
+    .await?;
+    Ok(res.last_insert_rowid())
+}
+
+/// Переезды пула по имени (`SOL/USDC`), от старых к новым.
+pub async fn list_pool_migrations(pool_name: &str) -> sqlx::Result<Vec<PoolMigration>> {
+    let rows = sqlx::query(
+        "SELECT * FROM pool_migrations WHERE pool_name = ?1 AND paper = ?2 ORDER BY id ASC"
+    )
+    .bind(pool_name)
+    .bind(executor::is_paper() as i32)
+    .fetch_all(&*DB)
+    .await?;
+    rows.iter().map(|r| Ok(PoolMigration {
+        pool_name:    r.try_get("pool_name")?,
+        from_address: r.try_get("from_address")?,
+        to_address:   r.try_get("to_address")?,
+        from_apr:     r.try_get("from_apr")?,
+        to_apr:       r.try_get("to_apr")?,
+        est_gain_usd: r.try_get("est_gain_usd")?,
+        est_cost_usd: r.try_get("est_cost_usd")?,
+    }))
+    .collect()
+}
+
+/// Записать собранные комиссии позиции (реализованный доход).
+/// `action` — куда они ушли: `compound` или `reserve`.
+pub async fn record_fee_harvest(
+    pool_address: &str,
+    pool_name: &str,
+    position_mint: &str,
+    amount_a: f64,
+    amount_b: f64,
+    usd: f64,
+    action: &str,
+) -> sqlx::Result<()> {
+    sqlx::query(r#"
+        INSERT INTO fee_harvests (
+            pool_address, pool_name, position_mint, amount_a, amount_b, usd, action, paper, created_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+    "#)
+    .bind(pool_address)
+    .bind(pool_name)
+    .bind(position_mint)
+    .bind(amount_a)
+    .bind(amount_b)
+    .bind(usd)
+    .bind(action)
+    .bind(executor::is_paper() as i32)
+    .bind(Utc::now().to_rfc3339())
+    .execute(&*DB)
+    .await?;
+    Ok(())
+}
+
+/// Сколько комиссий (USD) собрано по пулу с момента `since`.
+pub async fn harvested_usd_since(pool_address: &str, since: DateTime<Utc>) -> sqlx::Result<f64> {
+    let row = sqlx::query(
+        "SELECT COALESCE(SUM(usd), 0.0) AS total FROM fee_harvests WHERE pool_address = ?1 AND created_at >= ?2"
+    )
+    .bind(pool_address)
+    .bind(since.to_rfc3339())
+    .fetch_one(&*DB)
+    .await?;
+    row.try_get("total")
+}
+
+/// Создаёт новую запись истории на основе pool_config указанного пула,
 /// фиксируя дату открытия, дату закрытия (now), имя пула,
 /// минимальный lower_price, максимальный upper_price,
-/// sum_open = total_value_open,
-/// sum_close = total_value_current,
-/// commissions = сумма всех трёх commission_collected.
-pub async fn record_session_history() -> sqlx::Result<i64> {
-    // 1) Получаем текущую конфигурацию
-    let end_wallet_balance: crate::types::WalletBalanceInfo = utils::fetch_wallet_balance_info().await.unwrap_or_default();
-    println!("Wallet Balance: {}", end_wallet_balance);
-
-    let cfg_opt = get_pool_config().await?;
+/// sum_open / sum_close = стоимость позиций пула при открытии / перед
+/// закрытием (`ProfitBasis::Positions`; кошелёк общий для всех пулов и
+/// в историю одного пула не годится),
+/// commissions = сумма всех трёх commission_collected и наград пула,
+/// tx_fees / rent_net = издержки транзакций пула за сессию (tx_costs),
+/// paper = сессия открыта бумажным исполнителем.
+pub async fn record_session_history(pool_address: &str) -> sqlx::Result<i64> {
+    // 1) Получаем текущую конфигурацию (стоимость позиций записана перед закрытием)
+    let cfg_opt = get_pool_config(pool_address).await?;
     let cfg: crate::types::PoolConfig = cfg_opt.ok_or_else(|| sqlx::Error::Protocol(
         "No pool config found to record history".into()
     ))?;
@@ -95,12 +272,18 @@ pub async fn record_session_history() -> sqlx::Result<i64> {
     let range_lower = lowers.into_iter().fold(f64::INFINITY, f64::min).min(0.0);
     let range_upper = uppers.into_iter().fold(0.0, f64::max);
 
-    // 3) Сумма комиссий
+    // 3) Сумма комиссий: ещё не собранные + собранные за сессию + награды,
+    //    которые закрытие позиций забирает вместе с ликвидностью
     let commissions = cfg.commission_collected_1
         + cfg.commission_collected_2
-        + cfg.commission_collected_3;
+        + cfg.commission_collected_3
+        + harvested_usd_since(pool_address, cfg.date_opened).await?
+        + get_rewards_pending(pool_address).await?;
 
-    // 4) Вставляем запись
+    // 4) Издержки транзакций за сессию: комиссии сети и рента
+    let (tx_fees, rent_net) = costs_since(pool_address, cfg.date_opened).await?;
+
+    // 5) Вставляем запись
     let now = Utc::now();
     let result = sqlx::query(r#"
         INSERT INTO session_history (
@@ -111,17 +294,25 @@ pub async fn record_session_history() -> sqlx::Result<i64> {
             range_upper,
             sum_open,
             sum_close,
-            commissions
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            commissions,
+            tx_fees,
+            rent_net,
+            paper,
+            basis
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
     "#)
     .bind(cfg.date_opened.to_rfc3339())
     .bind(now.to_rfc3339())
     .bind(&cfg.name)
     .bind(range_lower)
     .bind(range_upper)
-    .bind(cfg.wallet_balance)
-    .bind(end_wallet_balance.total_usd)
+    .bind(cfg.total_value_open)
+    .bind(cfg.total_value_current)
     .bind(commissions)
+    .bind(tx_fees)
+    .bind(rent_net)
+    .bind(executor::is_paper() as i32)
+    .bind(ProfitBasis::Positions.as_str())
     .execute(&*DB)
     .await?;
 
@@ -152,6 +343,10 @@ pub async fn get_all_sessions() -> sqlx::Result<Vec<SessionHistory>> {
             sum_open:    row.try_get("sum_open")?,
             sum_close:   row.try_get("sum_close")?,
             commissions: row.try_get("commissions")?,
+            tx_fees:     row.try_get("tx_fees")?,
+            rent_net:    row.try_get("rent_net")?,
+            paper:       row.try_get::<i32, _>("paper")? != 0,
+            basis:       row_basis(&row)?,
         };
         out.push(entry);
     }
@@ -181,6 +376,10 @@ pub async fn get_session(id: i64) -> sqlx::Result<Option<SessionHistory>> {
             sum_open:    row.try_get("sum_open")?,
             sum_close:   row.try_get("sum_close")?,
             commissions: row.try_get("commissions")?,
+            tx_fees:     row.try_get("tx_fees")?,
+            rent_net:    row.try_get("rent_net")?,
+            paper:       row.try_get::<i32, _>("paper")? != 0,
+            basis:       row_basis(&row)?,
         };
         Ok(Some(entry))
     } else {
@@ -217,7 +416,7 @@ pub async fn get_session_history(
         r#"
         SELECT id, date_opened, date_closed, pool_name,
                range_lower, range_upper,
-               sum_open, sum_close, commissions
+               sum_open, sum_close, commissions, tx_fees, rent_net, paper, basis
           FROM session_history
          WHERE date_opened >= ?1
            AND date_opened <= ?2
@@ -252,6 +451,10 @@ pub async fn get_session_history(
             sum_open:      row.try_get("sum_open")?,
             sum_close:     row.try_get("sum_close")?,
             commissions:   row.try_get("commissions")?,
+            tx_fees:       row.try_get("tx_fees")?,
+            rent_net:      row.try_get("rent_net")?,
+            paper:         row.try_get::<i32, _>("paper")? != 0,
+            basis:         row_basis(&row)?,
         });
     }
 
@@ -272,13 +475,30 @@ pub async fn get_session_statistics(
     // 2) получаем все сессии за период
     let sessions = get_session_history(from_days, to_days).await?;
 
-    // 3) агрегация
+    Ok(summarize_sessions(&sessions, period_start, period_end))
+}
+
+/// Агрегация сессий за период (общая для истории из БД и бэктеста).
+pub fn summarize_sessions(
+    sessions: &[SessionHistory],
+    period_start: DateTime<Utc>,
+    period_end: DateTime<Utc>,
+) -> SessionStatistics {
+    // 1) агрегация
     let session_count    = sessions.len();
     let total_commissions: f64 = sessions.iter().map(|s| s.commissions).sum();
     let total_profit: f64       = sessions.iter().map(|s| s.sum_close - s.sum_open).sum();
-    let net_profit = total_profit + total_commissions;
+    let total_costs: f64        = sessions.iter().map(|s| s.tx_fees + s.rent_net).sum();
+    // разница кошелька уже учла комиссии и издержки — добавляем их только
+    // к разнице стоимости позиций
+    let net_profit: f64 = sessions.iter()
+        .map(|s| match s.basis {
+            ProfitBasis::Wallet    => s.sum_close - s.sum_open,
+            ProfitBasis::Positions => s.sum_close - s.sum_open + s.commissions - s.tx_fees - s.rent_net,
+        })
+        .sum();
 
-    // 4) средняя продолжительность
+    // 2) средняя продолжительность
     let total_secs: i64 = sessions.iter()
         .map(|s| (s.date_closed - s.date_opened).num_seconds())
         .sum();
@@ -288,13 +508,14 @@ pub async fn get_session_statistics(
         Duration::zero()
     };
 
-    Ok(SessionStatistics {
+    SessionStatistics {
         period_start,
         period_end,
         session_count,
         total_commissions,
         total_profit,
+        total_costs,
         net_profit,
         average_duration,
-    })
+    }
 }
\ No newline at end of file
@@ -0,0 +1,82 @@
+// src/database/lookup_tables.rs
+//
+// Таблицы адресов (ALT) по пулам: адрес таблицы, в которую складываем
+// аккаунты пула для v0-транзакций (см. dex_services/alt.rs), и заменённые
+// таблицы, которые ждут закрытия.
+use chrono::Utc;
+use sqlx::Row;
+use crate::database::db::DB;
+
+pub async fn init_lookup_tables_module() -> sqlx::Result<()> {
+    sqlx::query(r#"
+        CREATE TABLE IF NOT EXISTS lookup_tables (
+            pool_address   TEXT PRIMARY KEY NOT NULL,
+            table_address  TEXT NOT NULL,
+            updated_at     TEXT NOT NULL
+        );
+    "#)
+    .execute(&*DB)
+    .await?;
+    sqlx::query(r#"
+        CREATE TABLE IF NOT EXISTS retired_lookup_tables (
+            table_address  TEXT PRIMARY KEY NOT NULL,
+            retired_at     TEXT NOT NULL
+        );
+    "#)
+    .execute(&*DB)
+    .await?;
+    Ok(())
+}
+
+/// Текущая таблица пула (`None` — ещё не создавали).
+pub async fn get_lookup_table(pool_address: &str) -> sqlx::Result<Option<String>> {
+    let row = sqlx::query("SELECT table_address FROM lookup_tables WHERE pool_address = ?1")
+        .bind(pool_address)
+        .fetch_optional(&*DB)
+        .await?;
+    row.map(|r| r.try_get("table_address")).transpose()
+}
+
+/// Запомнить таблицу пула (новая заменяет заполненную).
+pub async fn set_lookup_table(pool_address: &str, table_address: &str) -> sqlx::Result<()> {
+    sqlx::query(r#"
+        INSERT OR REPLACE INTO lookup_tables (pool_address, table_address, updated_at)
+        VALUES (?1, ?2, ?3)
+    "#)
+    .bind(pool_address)
+    .bind(table_address)
+    .bind(Utc::now().to_rfc3339())
+    .execute(&*DB)
+    .await?;
+    Ok(())
+}
+
+/// Таблица деактивирована и ждёт закрытия.
+pub async fn retire_lookup_table(table_address: &str) -> sqlx::Result<()> {
+    sqlx::query(r#"
+        INSERT OR REPLACE INTO retired_lookup_tables (table_address, retired_at)
+        VALUES (?1, ?2)
+    "#)
+    .bind(table_address)
+    .bind(Utc::now().to_rfc3339())
+    .execute(&*DB)
+    .await?;
+    Ok(())
+}
+
+/// Деактивированные, но ещё не закрытые таблицы.
+pub async fn retired_lookup_tables() -> sqlx::Result<Vec<String>> {
+    let rows = sqlx::query("SELECT table_address FROM retired_lookup_tables ORDER BY retired_at")
+        .fetch_all(&*DB)
+        .await?;
+    rows.iter().map(|r| r.try_get("table_address")).collect()
+}
+
+/// Таблица закрыта.
+pub async fn forget_retired(table_address: &str) -> sqlx::Result<()> {
+    sqlx::query("DELETE FROM retired_lookup_tables WHERE table_address = ?1")
+        .bind(table_address)
+        .execute(&*DB)
+        .await?;
+    Ok(())
+}
@@ -2,4 +2,9 @@ pub mod triggers;
 pub mod db;
 pub mod positions;
 pub mod history;
-pub mod general_settings;
\ No newline at end of file
+pub mod general_settings;
+pub mod pool_settings;
+pub mod open_journal;
+pub mod exit_policy;
+pub mod tx_costs;
+pub mod lookup_tables;
@@ -0,0 +1,289 @@
+// src/database/open_journal.rs
+//
+// Журнал открытия диапазонов. Пишется до первой транзакции и обновляется
+// после каждой: что собирались открыть, какие позиции уже заминчены
+// (mint + подпись) и какие свапы были сделаны по ходу.
+// Если процесс упал посреди открытия, при старте по журналу видно,
+// что достраивать или что закрывать.
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use sqlx::Row;
+use crate::database::db::DB;
+use crate::types::{Range, Role};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JournalStatus {
+    /// открытие идёт (или процесс упал посреди него)
+    Opening,
+    /// все диапазоны открыты
+    Done,
+    /// частично открытое закрыто
+    RolledBack,
+}
+
+impl JournalStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            JournalStatus::Opening    => "opening",
+            JournalStatus::Done       => "done",
+            JournalStatus::RolledBack => "rolled_back",
+        }
+    }
+}
+
+impl FromStr for JournalStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "opening"     => Ok(JournalStatus::Opening),
+            "done"        => Ok(JournalStatus::Done),
+            "rolled_back" => Ok(JournalStatus::RolledBack),
+            _             => Err(format!("unknown journal status: {s}")),
+        }
+    }
+}
+
+/// Один диапазон из плана открытия (цены — сырые «B per A», как в `RangeAlloc`).
+#[derive(Debug, Clone)]
+pub struct JournalLeg {
+    pub role:          Role,
+    pub range_idx:     usize,
+    pub lower_price:   f64,
+    pub upper_price:   f64,
+    /// сколько вносится (см. open_allocations)
+    pub deposit:       f64,
+    pub position_mint: Option<String>,
+    pub signature:     Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct OpenJournal {
+    pub id:           i64,
+    pub pool_address: String,
+    pub pool_name:    String,
+    pub strategy:     Range,
+    pub capital_usd:  f64,
+    pub status:       JournalStatus,
+    pub created_at:   DateTime<Utc>,
+    pub legs:         Vec<JournalLeg>,
+}
+
+pub async fn init_open_journal_module() -> sqlx::Result<()> {
+    sqlx::query(r#"
+        CREATE TABLE IF NOT EXISTS open_journal (
+            id            INTEGER PRIMARY KEY AUTOINCREMENT,
+            pool_address  TEXT NOT NULL,
+            pool_name     TEXT NOT NULL,
+            strategy      TEXT NOT NULL,
+            capital_usd   REAL NOT NULL,
+            status        TEXT NOT NULL DEFAULT 'opening',
+            created_at    TEXT NOT NULL,
+            updated_at    TEXT NOT NULL
+        );
+    "#)
+    .execute(&*DB)
+    .await?;
+    sqlx::query(r#"
+        CREATE TABLE IF NOT EXISTS open_journal_legs (
+            journal_id     INTEGER NOT NULL,
+            role           TEXT    NOT NULL,
+            range_idx      INTEGER NOT NULL,
+            lower_price    REAL    NOT NULL,
+            upper_price    REAL    NOT NULL,
+            deposit        REAL    NOT NULL,
+            position_mint  TEXT,
+            signature      TEXT,
+            PRIMARY KEY (journal_id, role)
+        );
+    "#)
+    .execute(&*DB)
+    .await?;
+    sqlx::query(r#"
+        CREATE TABLE IF NOT EXISTS open_journal_swaps (
+            id          INTEGER PRIMARY KEY AUTOINCREMENT,
+            journal_id  INTEGER NOT NULL,
+            sell_mint   TEXT    NOT NULL,
+            buy_mint    TEXT    NOT NULL,
+            amount_in   REAL    NOT NULL,
+            signature   TEXT,
+            created_at  TEXT    NOT NULL
+        );
+    "#)
+    .execute(&*DB)
+    .await?;
+    Ok(())
+}
+
+/// Записать план открытия до первой транзакции. Возвращает id журнала.
+pub async fn begin(
+    pool_address: &str,
+    pool_name: &str,
+    strategy: &Range,
+    capital_usd: f64,
+    legs: &[JournalLeg],
+) -> sqlx::Result<i64> {
+    let now = Utc::now().to_rfc3339();
+    let mut tx = DB.begin().await?;
+    let id = sqlx::query(r#"
+        INSERT INTO open_journal (pool_address, pool_name, strategy, capital_usd, status, created_at, updated_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)
+    "#)
+    .bind(pool_address)
+    .bind(pool_name)
+    .bind(strategy.as_str())
+    .bind(capital_usd)
+    .bind(JournalStatus::Opening.as_str())
+    .bind(&now)
+    .execute(&mut *tx)
+    .await?
+    .last_insert_rowid();
+
+    for leg in legs {
+        sqlx::query(r#"
+            INSERT INTO open_journal_legs (journal_id, role, range_idx, lower_price, upper_price, deposit)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        "#)
+        .bind(id)
+        .bind(leg.role.as_str())
+        .bind(leg.range_idx as i64)
+        .bind(leg.lower_price)
+        .bind(leg.upper_price)
+        .bind(leg.deposit)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(id)
+}
+
+/// Диапазон заминчен.
+pub async fn record_mint(
+    journal_id: i64,
+    role: &Role,
+    position_mint: &str,
+    signature: Option<&str>,
+) -> sqlx::Result<()> {
+    sqlx::query(r#"
+        UPDATE open_journal_legs SET position_mint = ?1, signature = ?2
+         WHERE journal_id = ?3 AND role = ?4
+    "#)
+    .bind(position_mint)
+    .bind(signature)
+    .bind(journal_id)
+    .bind(role.as_str())
+    .execute(&*DB)
+    .await?;
+    touch(journal_id).await
+}
+
+/// Свап во время открытия — пишется в незавершённый журнал пула, если он есть.
+pub async fn record_swap(
+    pool_address: &str,
+    sell_mint: &str,
+    buy_mint: &str,
+    amount_in: f64,
+    signature: Option<&str>,
+) -> sqlx::Result<()> {
+    let Some(journal_id) = active_journal_id(pool_address).await? else {
+        return Ok(());
+    };
+    sqlx::query(r#"
+        INSERT INTO open_journal_swaps (journal_id, sell_mint, buy_mint, amount_in, signature, created_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+    "#)
+    .bind(journal_id)
+    .bind(sell_mint)
+    .bind(buy_mint)
+    .bind(amount_in)
+    .bind(signature)
+    .bind(Utc::now().to_rfc3339())
+    .execute(&*DB)
+    .await?;
+    Ok(())
+}
+
+/// Закрыть журнал с итоговым статусом.
+pub async fn finish(journal_id: i64, status: JournalStatus) -> sqlx::Result<()> {
+    sqlx::query("UPDATE open_journal SET status = ?1, updated_at = ?2 WHERE id = ?3")
+        .bind(status.as_str())
+        .bind(Utc::now().to_rfc3339())
+        .bind(journal_id)
+        .execute(&*DB)
+        .await?;
+    Ok(())
+}
+
+/// Все журналы, которые остались в статусе `opening`.
+pub async fn unfinished() -> sqlx::Result<Vec<OpenJournal>> {
+    let rows = sqlx::query("SELECT * FROM open_journal WHERE status = ?1 ORDER BY id")
+        .bind(JournalStatus::Opening.as_str())
+        .fetch_all(&*DB)
+        .await?;
+
+    let mut out = Vec::with_capacity(rows.len());
+    for row in rows {
+        let id: i64 = row.try_get("id")?;
+        let created: String = row.try_get("created_at")?;
+        out.push(OpenJournal {
+            id,
+            pool_address: row.try_get("pool_address")?,
+            pool_name:    row.try_get("pool_name")?,
+            strategy:     row.try_get::<String, _>("strategy")?
+                              .parse::<Range>()
+                              .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            capital_usd:  row.try_get("capital_usd")?,
+            status:       row.try_get::<String, _>("status")?
+                              .parse::<JournalStatus>()
+                              .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            created_at:   DateTime::parse_from_rfc3339(&created)
+                              .map_err(|e| sqlx::Error::Protocol(e.to_string()))?
+                              .with_timezone(&Utc),
+            legs:         get_legs(id).await?,
+        });
+    }
+    Ok(out)
+}
+
+async fn get_legs(journal_id: i64) -> sqlx::Result<Vec<JournalLeg>> {
+    let rows = sqlx::query("SELECT * FROM open_journal_legs WHERE journal_id = ?1 ORDER BY range_idx")
+        .bind(journal_id)
+        .fetch_all(&*DB)
+        .await?;
+    rows.iter()
+        .map(|row| {
+            let role_s: String = row.try_get("role")?;
+            Ok(JournalLeg {
+                role: Role::from_str(&role_s)
+                    .ok_or_else(|| sqlx::Error::Protocol(format!("Invalid role: {role_s}")))?,
+                range_idx:     row.try_get::<i64, _>("range_idx")? as usize,
+                lower_price:   row.try_get("lower_price")?,
+                upper_price:   row.try_get("upper_price")?,
+                deposit:       row.try_get("deposit")?,
+                position_mint: row.try_get("position_mint")?,
+                signature:     row.try_get("signature")?,
+            })
+        })
+        .collect()
+}
+
+async fn active_journal_id(pool_address: &str) -> sqlx::Result<Option<i64>> {
+    let row = sqlx::query(
+        "SELECT id FROM open_journal WHERE pool_address = ?1 AND status = ?2 ORDER BY id DESC LIMIT 1"
+    )
+    .bind(pool_address)
+    .bind(JournalStatus::Opening.as_str())
+    .fetch_optional(&*DB)
+    .await?;
+    row.map(|r| r.try_get("id")).transpose()
+}
+
+async fn touch(journal_id: i64) -> sqlx::Result<()> {
+    sqlx::query("UPDATE open_journal SET updated_at = ?1 WHERE id = ?2")
+        .bind(Utc::now().to_rfc3339())
+        .bind(journal_id)
+        .execute(&*DB)
+        .await?;
+    Ok(())
+}
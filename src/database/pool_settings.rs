@@ -0,0 +1,190 @@
+// src/database/pool_settings.rs
+use crate::database::db::{DB, add_column_if_missing};
+use crate::types::{HarvestMode, Range};
+use sqlx::Row;
+
+/// Настройки конкретного пула (одна строка на pool_address).
+/// Общие вещи (pct-листы, веса, compress) остаются в `general_settings`.
+#[derive(Debug, Clone)]
+pub struct PoolSettings {
+    pub pool_address: String,
+    pub name:         String,
+    pub enabled:      bool,
+    pub amount:       f64,
+    pub pct_number:   u16,
+    pub min_restart:  u64,
+    pub range:        Option<f32>,
+    /// какой стратегией пул открывает диапазоны
+    pub strategy:     Range,
+    /// при выходе из центра перевыставлять только дальнюю ногу
+    pub recenter:     bool,
+    /// сбор комиссий по порогу: off | compound | reserve
+    pub harvest:      HarvestMode,
+    /// открывать диапазоны внутри одного position bundle (без NFT на каждый)
+    pub bundle:       bool,
+}
+
+/// Инициализация модуля — создаём таблицу `pool_settings`.
+pub async fn init_pool_settings_module() -> sqlx::Result<()> {
+    sqlx::query(r#"
+        CREATE TABLE IF NOT EXISTS pool_settings (
+            pool_address  TEXT PRIMARY KEY NOT NULL,
+            name          TEXT    NOT NULL,
+            enabled       INTEGER NOT NULL CHECK(enabled IN (0,1)) DEFAULT 1,
+            amount        REAL    NOT NULL,
+            pct_number    INTEGER NOT NULL,
+            min_restart   INTEGER NOT NULL DEFAULT 1,
+            range         REAL,
+            strategy      TEXT    NOT NULL DEFAULT 'three',
+            recenter      INTEGER NOT NULL CHECK(recenter IN (0,1)) DEFAULT 0,
+            harvest       TEXT    NOT NULL DEFAULT 'off',
+            bundle        INTEGER NOT NULL CHECK(bundle IN (0,1)) DEFAULT 0
+        );
+    "#)
+    .execute(&*DB)
+    .await?;
+    add_column_if_missing("pool_settings", "strategy", "TEXT NOT NULL DEFAULT 'three'").await?;
+    add_column_if_missing("pool_settings", "recenter", "INTEGER NOT NULL DEFAULT 0").await?;
+    add_column_if_missing("pool_settings", "harvest", "TEXT NOT NULL DEFAULT 'off'").await?;
+    add_column_if_missing("pool_settings", "bundle", "INTEGER NOT NULL DEFAULT 0").await?;
+    Ok(())
+}
+
+/// Создать запись для пула, если её ещё нет (существующую не трогаем).
+pub async fn ensure_pool_settings(defaults: &PoolSettings) -> sqlx::Result<()> {
+    insert_settings("INSERT OR IGNORE", defaults).await
+}
+
+/// Записать настройки пула целиком (перезаписывая существующие).
+pub async fn save_pool_settings(s: &PoolSettings) -> sqlx::Result<()> {
+    insert_settings("INSERT OR REPLACE", s).await
+}
+
+async fn insert_settings(verb: &str, defaults: &PoolSettings) -> sqlx::Result<()> {
+    sqlx::query(&format!(r#"
+        {verb} INTO pool_settings (
+            pool_address, name, enabled, amount, pct_number, min_restart, range, strategy, recenter, harvest, bundle
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+    "#))
+    .bind(&defaults.pool_address)
+    .bind(&defaults.name)
+    .bind(defaults.enabled as i32)
+    .bind(defaults.amount)
+    .bind(defaults.pct_number as i32)
+    .bind(defaults.min_restart as i64)
+    .bind(defaults.range.map(|r| r as f64))
+    .bind(defaults.strategy.as_str())
+    .bind(defaults.recenter as i32)
+    .bind(defaults.harvest.as_str())
+    .bind(defaults.bundle as i32)
+    .execute(&*DB)
+    .await?;
+    Ok(())
+}
+
+fn row_to_settings(row: &sqlx::sqlite::SqliteRow) -> sqlx::Result<PoolSettings> {
+    Ok(PoolSettings {
+        pool_address: row.try_get("pool_address")?,
+        name:         row.try_get("name")?,
+        enabled:      row.try_get::<i32, _>("enabled")? != 0,
+        amount:       row.try_get("amount")?,
+        pct_number:   row.try_get::<i32, _>("pct_number")? as u16,
+        min_restart:  row.try_get::<i64, _>("min_restart")? as u64,
+        range:        row.try_get::<Option<f64>, _>("range")?.map(|r| r as f32),
+        strategy:     row.try_get::<String, _>("strategy")?
+                          .parse::<Range>()
+                          .map_err(|e| sqlx::Error::Decode(e.into()))?,
+        recenter:     row.try_get::<i32, _>("recenter")? != 0,
+        harvest:      row.try_get::<String, _>("harvest")?
+                          .parse::<HarvestMode>()
+                          .map_err(|e| sqlx::Error::Decode(e.into()))?,
+        bundle:       row.try_get::<i32, _>("bundle")? != 0,
+    })
+}
+
+/// Получить настройки пула
+pub async fn get_pool_settings(pool_address: &str) -> sqlx::Result<Option<PoolSettings>> {
+    let row = sqlx::query("SELECT * FROM pool_settings WHERE pool_address = ?1")
+        .bind(pool_address)
+        .fetch_optional(&*DB)
+        .await?;
+    row.as_ref().map(row_to_settings).transpose()
+}
+
+/// Все пулы, для которых есть настройки
+pub async fn list_pool_settings() -> sqlx::Result<Vec<PoolSettings>> {
+    let rows = sqlx::query("SELECT * FROM pool_settings ORDER BY name")
+        .fetch_all(&*DB)
+        .await?;
+    rows.iter().map(row_to_settings).collect()
+}
+
+/// Обновить amount пула
+pub async fn update_pool_amount(pool_address: &str, new: f64) -> sqlx::Result<()> {
+    sqlx::query("UPDATE pool_settings SET amount = ?1 WHERE pool_address = ?2")
+        .bind(new)
+        .bind(pool_address)
+        .execute(&*DB)
+        .await?;
+    Ok(())
+}
+
+/// Включить / выключить пул
+pub async fn update_pool_enabled(pool_address: &str, enabled: bool) -> sqlx::Result<()> {
+    sqlx::query("UPDATE pool_settings SET enabled = ?1 WHERE pool_address = ?2")
+        .bind(enabled as i32)
+        .bind(pool_address)
+        .execute(&*DB)
+        .await?;
+    Ok(())
+}
+
+/// Обновить pct_number пула
+pub async fn update_pool_pct_number(pool_address: &str, new: u16) -> sqlx::Result<()> {
+    sqlx::query("UPDATE pool_settings SET pct_number = ?1 WHERE pool_address = ?2")
+        .bind(new as i32)
+        .bind(pool_address)
+        .execute(&*DB)
+        .await?;
+    Ok(())
+}
+
+/// Сменить стратегию пула (вступает в силу со следующего открытия)
+pub async fn update_pool_strategy(pool_address: &str, strategy: &Range) -> sqlx::Result<()> {
+    sqlx::query("UPDATE pool_settings SET strategy = ?1 WHERE pool_address = ?2")
+        .bind(strategy.as_str())
+        .bind(pool_address)
+        .execute(&*DB)
+        .await?;
+    Ok(())
+}
+
+/// Включить / выключить частичное перецентрирование
+pub async fn update_pool_recenter(pool_address: &str, recenter: bool) -> sqlx::Result<()> {
+    sqlx::query("UPDATE pool_settings SET recenter = ?1 WHERE pool_address = ?2")
+        .bind(recenter as i32)
+        .bind(pool_address)
+        .execute(&*DB)
+        .await?;
+    Ok(())
+}
+
+/// Открывать новые диапазоны в бандле
+pub async fn update_pool_bundle(pool_address: &str, bundle: bool) -> sqlx::Result<()> {
+    sqlx::query("UPDATE pool_settings SET bundle = ?1 WHERE pool_address = ?2")
+        .bind(bundle as i32)
+        .bind(pool_address)
+        .execute(&*DB)
+        .await?;
+    Ok(())
+}
+
+/// Режим сбора комиссий пула
+pub async fn update_pool_harvest(pool_address: &str, mode: &HarvestMode) -> sqlx::Result<()> {
+    sqlx::query("UPDATE pool_settings SET harvest = ?1 WHERE pool_address = ?2")
+        .bind(mode.as_str())
+        .bind(pool_address)
+        .execute(&*DB)
+        .await?;
+    Ok(())
+}
@@ -1,7 +1,7 @@
 // db/positions.rs
 use once_cell::sync::Lazy;
 use sqlx::{Pool, Row, Sqlite, sqlite::SqlitePoolOptions};
-use crate::database::db::DB;
+use crate::database::db::{DB, add_column_if_missing};
 use chrono::{DateTime, Utc};
 use anyhow::Result;
 use crate::types::{PoolConfig, LiqPosition, Role};
@@ -9,16 +9,12 @@ use crate::types::{PoolConfig, LiqPosition, Role};
 /// Роли позиции
 
 
-/// Инициализация: создаём таблицу с единственной строкой (PK id=1)
-pub async fn init_positions_module() -> sqlx::Result<()> {
-    sqlx::query(
-        r#"
+const POOL_CONFIGS_DDL: &str = r#"
         CREATE TABLE IF NOT EXISTS pool_configs (
-            id                        INTEGER PRIMARY KEY NOT NULL CHECK(id=1),
+            pool_address              TEXT PRIMARY KEY NOT NULL,
             amount                    REAL NOT NULL,
             program                   TEXT NOT NULL,
             name                      TEXT NOT NULL,
-            pool_address              TEXT NOT NULL,
             mint_a                    TEXT NOT NULL,
             mint_b                    TEXT NOT NULL,
             decimal_a                 INTEGER NOT NULL,
@@ -49,16 +45,49 @@ pub async fn init_positions_module() -> sqlx::Result<()> {
             is_closed                 INTEGER NOT NULL DEFAULT 0,
             total_value_open          REAL NOT NULL,
             total_value_current       REAL NOT NULL,
-            wallet_balance            REAL NOT NULL
+            wallet_balance            REAL NOT NULL,
+            rewards_pending           REAL NOT NULL DEFAULT 0
         );
-        "#
-    )
-    .execute(&*DB)
-    .await?;
+        "#;
+
+/// Инициализация: таблица пулов, одна строка на pool_address.
+/// Старую схему (единственная строка id=1) переносим как есть.
+pub async fn init_positions_module() -> sqlx::Result<()> {
+    let legacy = sqlx::query("SELECT name FROM pragma_table_info('pool_configs') WHERE name = 'id'")
+        .fetch_optional(&*DB)
+        .await?
+        .is_some();
+
+    if legacy {
+        let mut tx = DB.begin().await?;
+        sqlx::query("ALTER TABLE pool_configs RENAME TO pool_configs_legacy")
+            .execute(&mut *tx).await?;
+        sqlx::query(POOL_CONFIGS_DDL).execute(&mut *tx).await?;
+        sqlx::query(&format!(
+            "INSERT INTO pool_configs ({cols}) SELECT {cols} FROM pool_configs_legacy",
+            cols = POOL_CONFIGS_COLUMNS
+        ))
+        .execute(&mut *tx).await?;
+        sqlx::query("DROP TABLE pool_configs_legacy").execute(&mut *tx).await?;
+        tx.commit().await?;
+        return Ok(());
+    }
+
+    sqlx::query(POOL_CONFIGS_DDL)
+        .execute(&*DB)
+        .await?;
+    add_column_if_missing("pool_configs", "rewards_pending", "REAL NOT NULL DEFAULT 0").await?;
     Ok(())
 }
 
-/// Вставить или обновить (id=1) запись пула
+const POOL_CONFIGS_COLUMNS: &str = "pool_address, amount, program, name, \
+    mint_a, mint_b, decimal_a, decimal_b, \
+    position_role_1, position_address_1, position_nft_1, upper_price_1, lower_price_1, commission_collected_1, \
+    position_role_2, position_address_2, position_nft_2, upper_price_2, lower_price_2, commission_collected_2, \
+    position_role_3, position_address_3, position_nft_3, upper_price_3, lower_price_3, commission_collected_3, \
+    date_opened, is_closed, total_value_open, total_value_current, wallet_balance";
+
+/// Вставить или обновить запись пула (ключ — pool_address)
 pub async fn upsert_pool_config(
     amount: f64,
     program: &str,
@@ -83,25 +112,24 @@ pub async fn upsert_pool_config(
     sqlx::query(
         r#"
         INSERT INTO pool_configs (
-            id, amount, program, name, pool_address,
+            amount, program, name, pool_address,
             mint_a, mint_b, decimal_a, decimal_b,
             position_role_1, position_address_1, position_nft_1, upper_price_1, lower_price_1, commission_collected_1,
             position_role_2, position_address_2, position_nft_2, upper_price_2, lower_price_2, commission_collected_2,
             position_role_3, position_address_3, position_nft_3, upper_price_3, lower_price_3, commission_collected_3,
             date_opened, is_closed, total_value_open, total_value_current, wallet_balance
         ) VALUES (
-            1, ?1, ?2, ?3, ?4,
+            ?1, ?2, ?3, ?4,
             ?5, ?6, ?7, ?8,
             ?9, ?10, ?11, ?12, ?13, ?14,
             ?15, ?16, ?17, ?18, ?19, ?20,
             ?21, ?22, ?23, ?24, ?25, ?26,
             ?27, ?28, ?29, ?30, ?31
         )
-        ON CONFLICT(id) DO UPDATE SET
+        ON CONFLICT(pool_address) DO UPDATE SET
             amount                   = excluded.amount,
             program                  = excluded.program,
             name                     = excluded.name,
-            pool_address             = excluded.pool_address,
             mint_a                   = excluded.mint_a,
             mint_b                   = excluded.mint_b,
             decimal_a                = excluded.decimal_a,
@@ -172,9 +200,10 @@ pub async fn upsert_pool_config(
     Ok(())
 }
 
-/// Вернуть запись (id = 1), теперь с wallet_balance
-pub async fn get_pool_config() -> sqlx::Result<Option<PoolConfig>> {
-    if let Some(row) = sqlx::query("SELECT * FROM pool_configs WHERE id = 1")
+/// Вернуть запись пула, теперь с wallet_balance
+pub async fn get_pool_config(pool_address: &str) -> sqlx::Result<Option<PoolConfig>> {
+    if let Some(row) = sqlx::query("SELECT * FROM pool_configs WHERE pool_address = ?1")
+        .bind(pool_address)
         .fetch_optional(&*DB)
         .await?
     {
@@ -236,12 +265,13 @@ pub async fn record_position_metrics(
     commission2: f64,
     commission3: f64,
     total_value_current: f64,
-    wallet_balance: f64
+    wallet_balance: f64,
+    rewards_pending: f64,
 ) -> sqlx::Result<()> {
     let now = Utc::now();
 
     // 1) Смотрим, что в БД
-    let existing = get_pool_config().await?;
+    let existing = get_pool_config(&cfg.pool_address).await?;
 
     // 2) Если нет записи или запись помечена is_closed=true — первый запуск
     let is_first_run = existing
@@ -278,27 +308,30 @@ pub async fn record_position_metrics(
     } else {
         // ─── НЕ первый запуск ────────────────────────────────────────────────
         // обновляем только цифры: комиссии и текущее TVL
-        update_commission(1, commission1).await?;
-        update_commission(2, commission2).await?;
-        update_commission(3, commission3).await?;
-        update_total_value_current(total_value_current).await?;
+        update_commission(&cfg.pool_address, 1, commission1).await?;
+        update_commission(&cfg.pool_address, 2, commission2).await?;
+        update_commission(&cfg.pool_address, 3, commission3).await?;
+        update_total_value_current(&cfg.pool_address, total_value_current).await?;
     }
+    update_rewards_pending(&cfg.pool_address, rewards_pending).await?;
 
     Ok(())
 }
 
 
-/// Удалить единственную запись (id = 1), вернуть true если была
-pub async fn delete_pool_config() -> sqlx::Result<bool> {
-    let res = sqlx::query("DELETE FROM pool_configs WHERE id = 1")
+/// Удалить запись пула, вернуть true если была
+pub async fn delete_pool_config(pool_address: &str) -> sqlx::Result<bool> {
+    let res = sqlx::query("DELETE FROM pool_configs WHERE pool_address = ?1")
+        .bind(pool_address)
         .execute(&*DB)
         .await?;
     Ok(res.rows_affected() > 0)
 }
 
-/// Пометить как закрытую (id = 1)
-pub async fn close_pool_config() -> sqlx::Result<()> {
-    sqlx::query("UPDATE pool_configs SET is_closed = 1 WHERE id = 1")
+/// Пометить как закрытую
+pub async fn close_pool_config(pool_address: &str) -> sqlx::Result<()> {
+    sqlx::query("UPDATE pool_configs SET is_closed = 1 WHERE pool_address = ?1")
+        .bind(pool_address)
         .execute(&*DB)
         .await?;
     Ok(())
@@ -306,6 +339,7 @@ pub async fn close_pool_config() -> sqlx::Result<()> {
 
 /// Обновить собранную комиссию в позиции i=1..=3
 pub async fn update_commission(
+    pool_address: &str,
     position_index: u8,
     commission: f64,
 ) -> sqlx::Result<()> {
@@ -315,37 +349,74 @@ pub async fn update_commission(
         3 => "commission_collected_3",
         _ => return Err(sqlx::Error::Protocol("Invalid position_index".into())),
     };
-    let sql = format!("UPDATE pool_configs SET {} = ?1 WHERE id = 1", col);
+    let sql = format!("UPDATE pool_configs SET {} = ?1 WHERE pool_address = ?2", col);
     sqlx::query(&sql)
         .bind(commission)
+        .bind(pool_address)
         .execute(&*DB)
         .await?;
     Ok(())
 }
 
-/// Обновить текущее значение (id = 1)
+/// Обновить текущее значение
 pub async fn update_total_value_current(
+    pool_address: &str,
     new_value: f64
 ) -> sqlx::Result<()> {
-    sqlx::query("UPDATE pool_configs SET total_value_current = ?1 WHERE id = 1")
+    sqlx::query("UPDATE pool_configs SET total_value_current = ?1 WHERE pool_address = ?2")
         .bind(new_value)
+        .bind(pool_address)
+        .execute(&*DB)
+        .await?;
+    Ok(())
+}
+
+/// Несобранные награды пула (emissions), USD
+pub async fn update_rewards_pending(pool_address: &str, usd: f64) -> sqlx::Result<()> {
+    sqlx::query("UPDATE pool_configs SET rewards_pending = ?1 WHERE pool_address = ?2")
+        .bind(usd)
+        .bind(pool_address)
+        .execute(&*DB)
+        .await?;
+    Ok(())
+}
+
+/// Награды на `usd` собраны и записаны в fee_harvests — убрать их из
+/// несобранных, чтобы история сессии не посчитала их дважды.
+pub async fn take_rewards_pending(pool_address: &str, usd: f64) -> sqlx::Result<()> {
+    sqlx::query("UPDATE pool_configs SET rewards_pending = MAX(rewards_pending - ?1, 0) WHERE pool_address = ?2")
+        .bind(usd)
+        .bind(pool_address)
         .execute(&*DB)
         .await?;
     Ok(())
 }
 
 /// Записать новое значение wallet_balance
-pub async fn update_wallet_balance(new_balance: f64) -> sqlx::Result<()> {
-    sqlx::query("UPDATE pool_configs SET wallet_balance = ?1 WHERE id = 1")
+pub async fn update_wallet_balance(pool_address: &str, new_balance: f64) -> sqlx::Result<()> {
+    sqlx::query("UPDATE pool_configs SET wallet_balance = ?1 WHERE pool_address = ?2")
         .bind(new_balance)
+        .bind(pool_address)
         .execute(&*DB)
         .await?;
     Ok(())
 }
 
+/// Несобранные награды пула на момент последнего отчёта, USD
+pub async fn get_rewards_pending(pool_address: &str) -> sqlx::Result<f64> {
+    let row = sqlx::query("SELECT rewards_pending FROM pool_configs WHERE pool_address = ?1")
+        .bind(pool_address)
+        .fetch_optional(&*DB)
+        .await?;
+    Ok(row
+        .and_then(|r| r.try_get::<f64, _>("rewards_pending").ok())
+        .unwrap_or(0.0))
+}
+
 /// Получить текущее значение wallet_balance
-pub async fn get_wallet_balance() -> sqlx::Result<Option<f64>> {
-    let row = sqlx::query("SELECT wallet_balance FROM pool_configs WHERE id = 1")
+pub async fn get_wallet_balance(pool_address: &str) -> sqlx::Result<Option<f64>> {
+    let row = sqlx::query("SELECT wallet_balance FROM pool_configs WHERE pool_address = ?1")
+        .bind(pool_address)
         .fetch_optional(&*DB)
         .await?;
     Ok(row
@@ -355,6 +426,7 @@ pub async fn get_wallet_balance() -> sqlx::Result<Option<f64>> {
 /// Обновить address и/или nft для позиции index = 1..=3.
 /// Если какой-то из параметров = `None`, он не меняется.
 pub async fn update_position_fields(
+    pool_address: &str,
     position_index: u8,
     new_address: Option<&str>,
     new_nft: Option<&str>,
@@ -382,7 +454,7 @@ pub async fn update_position_fields(
 
     // финальный SQL
     let sql = format!(
-        "UPDATE pool_configs SET {} WHERE id = 1",
+        "UPDATE pool_configs SET {} WHERE pool_address = ?",
         sets.join(", ")
     );
 
@@ -396,18 +468,43 @@ pub async fn update_position_fields(
     if let Some(nft) = new_nft {
         q = q.bind(nft);
     }
+    q = q.bind(pool_address);
 
     // выполняем
     q.execute(&*DB).await?;
     Ok(())
 }
 
+/// Переписать все три слота (роль, адрес, nft, границы) — например, после
+/// перестановки ног при перецентрировании. Комиссии не трогаем.
+pub async fn update_slots(cfg: &PoolConfig) -> sqlx::Result<()> {
+    for (i, pos) in [(1u8, &cfg.position_1), (2, &cfg.position_2), (3, &cfg.position_3)] {
+        let sql = format!(
+            "UPDATE pool_configs SET position_role_{i} = ?1, position_address_{i} = ?2, \
+             position_nft_{i} = ?3, upper_price_{i} = ?4, lower_price_{i} = ?5 \
+             WHERE pool_address = ?6"
+        );
+        sqlx::query(&sql)
+            .bind(pos.as_ref().map(|p| p.role.as_str()))
+            .bind(pos.as_ref().and_then(|p| p.position_address.clone()))
+            .bind(pos.as_ref().and_then(|p| p.position_nft.clone()))
+            .bind(pos.as_ref().map(|p| p.upper_price))
+            .bind(pos.as_ref().map(|p| p.lower_price))
+            .bind(&cfg.pool_address)
+            .execute(&*DB)
+            .await?;
+    }
+    Ok(())
+}
+
 pub async fn find_position_index_by_nft(position_mint: &str) -> Result<Option<u8>> {
-    // Выбираем из единственной строки три столбца с mint’ами
+    // mint позиции уникален, поэтому ищем по всем пулам сразу
     let row_opt = sqlx::query(
         "SELECT position_nft_1, position_nft_2, position_nft_3 \
-         FROM pool_configs WHERE id = 1"
+         FROM pool_configs \
+         WHERE ?1 IN (position_nft_1, position_nft_2, position_nft_3)"
     )
+    .bind(position_mint)
     .fetch_optional(&*DB)
     .await?;
 
@@ -429,3 +526,11 @@ pub async fn find_position_index_by_nft(position_mint: &str) -> Result<Option<u8
     // либо записи вообще нет, либо ни один не совпал
     Ok(None)
 }
+
+/// Адреса всех пулов, по которым есть запись
+pub async fn list_pool_addresses() -> sqlx::Result<Vec<String>> {
+    let rows = sqlx::query("SELECT pool_address FROM pool_configs")
+        .fetch_all(&*DB)
+        .await?;
+    rows.iter().map(|r| r.try_get("pool_address")).collect()
+}
@@ -134,9 +134,20 @@ pub async fn update_trigger(tr: &Trigger) {
     .expect(&format!("DB error: failed to update trigger `{}`", tr.name));
 }
 
-pub async fn auto_trade_switch(switch: bool, tx: Option<&UnboundedSender<ServiceCommand>>) -> Result<()>  {
+/// Имя триггера, привязанного к пулу: `auto_trade@SOL/USDC`.
+/// Глобальные триггеры (`closing`, `limit`) остаются без суффикса.
+pub fn pool_trigger(name: &str, pool: &str) -> String {
+    format!("{name}@{pool}")
+}
+
+/// Получить триггер конкретного пула.
+pub async fn get_pool_trigger(name: &str, pool: &str) -> Trigger {
+    get_trigger(&pool_trigger(name, pool)).await
+}
+
+pub async fn auto_trade_switch(pool: &str, switch: bool, tx: Option<&UnboundedSender<ServiceCommand>>) -> Result<()>  {
     let mut t = Trigger {
-        name: "auto_trade".into(),
+        name: pool_trigger("auto_trade", pool),
         state: switch,
         position: "opening".into(),
     };
@@ -145,7 +156,7 @@ pub async fn auto_trade_switch(switch: bool, tx: Option<&UnboundedSender<Service
         Ok(_) => {
             if let Some(tx) = tx {
                 // Если передан канал — шлём сообщение
-                let msg: String = format!("Trigger `auto_trade_switch` {}", switch);
+                let msg: String = format!("Trigger `auto_trade_switch` {} [{}]", switch, pool);
                 let _ = tx.send(ServiceCommand::SendMessage(
                     msg,
                 ));
@@ -162,15 +173,15 @@ pub async fn auto_trade_switch(switch: bool, tx: Option<&UnboundedSender<Service
     Ok(())
 }
 
-pub async fn pool_report_run(switch: bool, tx: &UnboundedSender<ServiceCommand>) -> Result<()>  {
+pub async fn pool_report_run(pool: &str, switch: bool, tx: &UnboundedSender<ServiceCommand>) -> Result<()>  {
     let mut t = Trigger {
-        name: "pool_report_run".into(),
+        name: pool_trigger("pool_report_run", pool),
         state: switch,
         position: "".into(),
     };
     match upsert_trigger(&t).await {
         Ok(_) => {
-            let msg = format!("Trigger `pool_report_run` {}", switch);
+            let msg = format!("Trigger `pool_report_run` {} [{}]", switch, pool);
             let _ = tx.send(ServiceCommand::SendMessage(
                 msg,
             ));
@@ -219,12 +230,13 @@ pub async fn closing_switcher(
 }
 
 pub async fn report_info_reset(
+    pool: &str,
     switch: bool,
     tx: Option<&UnboundedSender<ServiceCommand>>,
 ) -> Result<()> {
     // Формируем триггер
     let t = Trigger {
-        name: "report_info_reset".into(),
+        name: pool_trigger("report_info_reset", pool),
         state: switch,
         position: String::default(),
     };
@@ -234,7 +246,7 @@ pub async fn report_info_reset(
         Ok(_) => {
             if let Some(tx) = tx {
                 // Если передан канал — шлём сообщение
-                let msg = format!("Trigger `report_info_reset` {}", switch);
+                let msg = format!("Trigger `report_info_reset` {} [{}]", switch, pool);
                 let _ = tx.send(ServiceCommand::SendMessage(
                     msg,
                 ));
@@ -287,12 +299,13 @@ pub async fn limit_switcher(
 }
 
 pub async fn opening_switcher(
+    pool: &str,
     switch: bool,
     tx: Option<&UnboundedSender<ServiceCommand>>,
 ) -> Result<()> {
     // Формируем триггер
     let t = Trigger {
-        name: "opening".into(),
+        name: pool_trigger("opening", pool),
         state: switch,
         position: String::default(),
     };
@@ -302,7 +315,7 @@ pub async fn opening_switcher(
         Ok(_) => {
             if let Some(tx) = tx {
                 // Если передан канал — шлём сообщение
-                let msg = format!("Trigger `opening_switcher` {}", switch);
+                let msg = format!("Trigger `opening_switcher` {} [{}]", switch, pool);
                 let _ = tx.send(ServiceCommand::SendMessage(
                     msg,
                 ));
@@ -0,0 +1,225 @@
+// src/database/tx_costs.rs
+//
+// Издержки транзакций: комиссия сети (база + приоритет), рента за созданные
+// аккаунты и рента, вернувшаяся при их закрытии. Всё берём из meta
+// подтверждённой транзакции и пишем по пулу, чтобы история сессий
+// считала net-прибыль за вычетом этих трат.
+use std::collections::HashSet;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::Signature;
+use sqlx::Row;
+use tokio::time::{sleep, Duration};
+
+use crate::database::db::DB;
+use crate::dex_services::executor;
+use crate::params::WSOL;
+use crate::utils::{get_sol_price_usd, utils::init_rpc};
+
+/// Откуда транзакция: пул (если известен) и вид операции.
+#[derive(Debug, Clone)]
+struct TxContext {
+    pool: Option<String>,
+    kind: &'static str,
+}
+
+tokio::task_local! {
+    static TX_CONTEXT: TxContext;
+}
+
+/// Издержки одной транзакции в лампортах.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TxCost {
+    pub fee_lamports:   u64,
+    pub rent_paid:      u64,
+    pub rent_reclaimed: u64,
+    /// потраченные CU (в БД не пишем — нужны только отправителю)
+    pub compute_units:  Option<u64>,
+}
+
+impl TxCost {
+    /// Чистые траты в SOL (рента могла вернуться больше, чем ушло).
+    pub fn net_sol(&self) -> f64 {
+        (self.fee_lamports as f64 + self.rent_paid as f64 - self.rent_reclaimed as f64) / 1e9
+    }
+}
+
+pub async fn init_tx_costs_module() -> sqlx::Result<()> {
+    sqlx::query(r#"
+        CREATE TABLE IF NOT EXISTS tx_costs (
+            id              INTEGER PRIMARY KEY AUTOINCREMENT,
+            pool_address    TEXT,
+            kind            TEXT NOT NULL,
+            signature       TEXT NOT NULL,
+            fee_lamports    INTEGER NOT NULL,
+            rent_paid       INTEGER NOT NULL,
+            rent_reclaimed  INTEGER NOT NULL,
+            usd             REAL NOT NULL,
+            rent_usd        REAL NOT NULL,
+            paper           INTEGER NOT NULL DEFAULT 0,
+            created_at      TEXT NOT NULL
+        );
+    "#)
+    .execute(&*DB)
+    .await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_tx_costs_pool ON tx_costs (pool_address, created_at)")
+        .execute(&*DB)
+        .await?;
+    Ok(())
+}
+
+/// Выполнить `fut`, помечая все его транзакции пулом и видом операции.
+/// `pool = None` — пул берётся из внешней области (например, воркера пула).
+pub async fn scoped<F: std::future::Future>(pool: Option<String>, kind: &'static str, fut: F) -> F::Output {
+    let pool = pool.or_else(current_pool);
+    TX_CONTEXT.scope(TxContext { pool, kind }, fut).await
+}
+
+/// Записать издержки подтверждённой транзакции. Ждём записи, чтобы закрытие,
+/// после которого сразу пишется история сессии, в неё уже попало.
+/// Возвращает издержки (`None` — meta получить не удалось).
+pub async fn track(sig: Signature) -> Option<TxCost> {
+    let ctx = TX_CONTEXT.try_with(|c| c.clone())
+        .unwrap_or(TxContext { pool: None, kind: "other" });
+    record_logged(sig, ctx).await
+}
+
+fn current_pool() -> Option<String> {
+    TX_CONTEXT.try_with(|c| c.pool.clone()).ok().flatten()
+}
+
+async fn record_logged(sig: Signature, ctx: TxContext) -> Option<TxCost> {
+    let cost = match fetch_cost(&sig).await {
+        Ok(c) => c,
+        Err(e) => {
+            log::warn!("tx_costs: {sig} ({}) не записан: {e:#}", ctx.kind);
+            return None;
+        }
+    };
+    if let Err(e) = insert(sig, &ctx, cost).await {
+        log::warn!("tx_costs: {sig} ({}) не записан: {e:#}", ctx.kind);
+    }
+    Some(cost)
+}
+
+/// Чаевые Jito-бандла: в meta это обычный перевод, а не комиссия и не рента,
+/// поэтому пишем их отдельной строкой.
+pub async fn track_tip(sig: Signature, lamports: u64) {
+    let ctx  = TxContext { pool: current_pool(), kind: "tip" };
+    let cost = TxCost { fee_lamports: lamports, ..Default::default() };
+    if let Err(e) = insert(sig, &ctx, cost).await {
+        log::warn!("tx_costs: чаевые {sig} не записаны: {e:#}");
+    }
+}
+
+async fn insert(sig: Signature, ctx: &TxContext, cost: TxCost) -> Result<()> {
+    let sol  = get_sol_price_usd(WSOL, true).await?;
+    let rent_usd = (cost.rent_paid as f64 - cost.rent_reclaimed as f64) / 1e9 * sol;
+
+    sqlx::query(r#"
+        INSERT INTO tx_costs (
+            pool_address, kind, signature, fee_lamports, rent_paid, rent_reclaimed, usd, rent_usd, paper, created_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+    "#)
+    .bind(ctx.pool.as_deref())
+    .bind(ctx.kind)
+    .bind(sig.to_string())
+    .bind(cost.fee_lamports as i64)
+    .bind(cost.rent_paid as i64)
+    .bind(cost.rent_reclaimed as i64)
+    .bind(cost.net_sol() * sol)
+    .bind(rent_usd)
+    .bind(executor::is_paper() as i32)
+    .bind(Utc::now().to_rfc3339())
+    .execute(&*DB)
+    .await?;
+    Ok(())
+}
+
+/// Разобрать meta транзакции: комиссия и движение ренты.
+/// Аккаунты wSOL не считаем — их лампорты это обёрнутые SOL, а не рента.
+pub async fn fetch_cost(sig: &Signature) -> Result<TxCost> {
+    let rpc = init_rpc();
+    let cfg = RpcTransactionConfig {
+        commitment: Some(CommitmentConfig::confirmed()),
+        max_supported_transaction_version: Some(0),
+        ..Default::default()
+    };
+
+    // узел может ещё не отдать только что подтверждённую транзакцию
+    let mut last_err = None;
+    for attempt in 0..5u64 {
+        match rpc.get_transaction_with_config(sig, cfg).await {
+            Ok(tx) => {
+                let meta = tx.transaction.meta
+                    .ok_or_else(|| anyhow!("у транзакции нет meta"))?;
+
+                let mut wsol: HashSet<usize> = HashSet::new();
+                for list in [meta.pre_token_balances, meta.post_token_balances] {
+                    let list: Option<Vec<_>> = list.into();
+                    for b in list.unwrap_or_default() {
+                        if b.mint == WSOL {
+                            wsol.insert(b.account_index as usize);
+                        }
+                    }
+                }
+
+                let mut cost = TxCost {
+                    fee_lamports:  meta.fee,
+                    compute_units: meta.compute_units_consumed.into(),
+                    ..Default::default()
+                };
+                // индекс 0 — плательщик, его баланс и так уменьшился на всё сразу
+                for (i, (pre, post)) in meta.pre_balances.iter().zip(&meta.post_balances).enumerate().skip(1) {
+                    if wsol.contains(&i) {
+                        continue;
+                    }
+                    match (*pre, *post) {
+                        (0, p) if p > 0 => cost.rent_paid += p,
+                        (p, 0) if p > 0 => cost.rent_reclaimed += p,
+                        _ => {}
+                    }
+                }
+                return Ok(cost);
+            }
+            Err(e) => {
+                last_err = Some(e);
+                sleep(Duration::from_secs(2 * (attempt + 1))).await;
+            }
+        }
+    }
+    Err(anyhow!("getTransaction {sig}: {}", last_err.map(|e| e.to_string()).unwrap_or_default()))
+}
+
+/// Издержки пула с момента `since`, USD: (комиссии сети, чистая рента).
+pub async fn costs_since(pool_address: &str, since: DateTime<Utc>) -> sqlx::Result<(f64, f64)> {
+    let row = sqlx::query(r#"
+        SELECT COALESCE(SUM(usd - rent_usd), 0.0) AS fees,
+               COALESCE(SUM(rent_usd), 0.0)       AS rent
+          FROM tx_costs
+         WHERE pool_address = ?1 AND created_at >= ?2
+    "#)
+    .bind(pool_address)
+    .bind(since.to_rfc3339())
+    .fetch_one(&*DB)
+    .await?;
+    Ok((row.try_get("fees")?, row.try_get("rent")?))
+}
+
+/// Средние издержки (USD) последних `limit` транзакций вида `kind` по всем пулам;
+/// `None` — таких ещё не было.
+pub async fn average_usd(kind: &str, limit: i64) -> sqlx::Result<Option<f64>> {
+    let row = sqlx::query(r#"
+        SELECT AVG(usd) AS avg FROM (
+            SELECT usd FROM tx_costs WHERE kind = ?1 AND paper = 0 ORDER BY id DESC LIMIT ?2
+        )
+    "#)
+    .bind(kind)
+    .bind(limit)
+    .fetch_one(&*DB)
+    .await?;
+    row.try_get("avg")
+}
@@ -0,0 +1,187 @@
+// src/dex_services/alt.rs
+//
+// Таблицы адресов (Address Lookup Table) пулов для v0-транзакций.
+// В таблицу пула складываем только постоянные аккаунты: сам пул, его
+// хранилища и минты, tick-array-и и программы — в транзакции от каждого
+// остаётся байт индекса вместо 32 байт ключа. Одноразовые адреса (PDA и
+// NFT новой позиции, её токен-счёт) в таблицу не идут, иначе она растёт
+// с каждым открытием. Дописанный адрес можно использовать только со
+// следующего слота, поэтому после расширения ждём смены слота.
+// Заполненную таблицу заменяем новой, а старую деактивируем и, когда
+// пройдёт остывание, закрываем — рента возвращается в кошелёк.
+
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use orca_whirlpools_client::{get_tick_array_address, Whirlpool};
+use orca_whirlpools_core::get_tick_array_start_tick_index;
+use solana_sdk::address_lookup_table::instruction::{
+    close_lookup_table, create_lookup_table, deactivate_lookup_table, extend_lookup_table,
+};
+use solana_sdk::address_lookup_table::state::AddressLookupTable;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::message::AddressLookupTableAccount;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use tokio::time::{sleep, Duration};
+
+use crate::database::lookup_tables;
+use crate::utils::{op, utils};
+
+/// адресов в одной таблице
+const TABLE_CAPACITY: usize = 256;
+/// адресов в одной транзакции расширения
+const EXTEND_CHUNK: usize = 20;
+/// сколько слотов деактивированная таблица остывает до закрытия (размер SlotHashes + 1)
+const DEACTIVATION_COOLDOWN_SLOTS: u64 = 513;
+
+/// Постоянные аккаунты пула Whirlpool для его таблицы: пул, хранилища,
+/// минты, программы и tick-array-и, покрывающие тики `ticks`.
+pub fn whirlpool_accounts(whirl_pk: &Pubkey, whirl: &Whirlpool, ticks: &[i32]) -> Result<Vec<Pubkey>> {
+    let mut out = vec![
+        *whirl_pk,
+        whirl.token_vault_a,
+        whirl.token_vault_b,
+        whirl.token_mint_a,
+        whirl.token_mint_b,
+        spl_token::id(),
+        spl_token_2022::ID,
+        spl_memo::id(),
+        spl_associated_token_account::id(),
+        solana_sdk::system_program::id(),
+    ];
+    for tick in ticks {
+        let array = get_tick_array_address(whirl_pk, get_tick_array_start_tick_index(*tick, whirl.tick_spacing))?.0;
+        if !out.contains(&array) {
+            out.push(array);
+        }
+    }
+    Ok(out)
+}
+
+/// Таблица пула `pool_address`, в которой есть все `addresses` (только
+/// постоянные — см. `whirlpool_accounts`): недостающие дописываем, при
+/// нехватке места заводим новую таблицу, а старую выводим из оборота.
+pub async fn ensure_pool_table(
+    rpc: &Arc<RpcClient>,
+    wallet: &Keypair,
+    pool_address: &str,
+    addresses: &[Pubkey],
+) -> Result<AddressLookupTableAccount> {
+    let owner = wallet.pubkey();
+    // заодно возвращаем ренту остывших таблиц
+    if let Err(e) = close_retired(rpc, wallet).await {
+        log::warn!("lookup tables: закрыть старые таблицы не вышло: {e:#}");
+    }
+    let mut table = match lookup_tables::get_lookup_table(pool_address).await? {
+        Some(a) => Some(fetch(rpc, Pubkey::from_str(&a)?).await?),
+        None    => None,
+    };
+
+    let mut missing: Vec<Pubkey> = Vec::new();
+    for a in addresses {
+        let known = table.as_ref().is_some_and(|t| t.addresses.contains(a));
+        if !known && !missing.contains(a) {
+            missing.push(*a);
+        }
+    }
+    if let Some(t) = &table {
+        if missing.is_empty() {
+            return Ok(t.clone());
+        }
+    }
+
+    let full = table.as_ref().map_or(true, |t| t.addresses.len() + missing.len() > TABLE_CAPACITY);
+    let key = if full {
+        // новая таблица — в неё всё нужное, а не только недостающее
+        // без повторов, в исходном порядке
+        let mut seen = HashSet::new();
+        missing = addresses.iter().copied().filter(|a| seen.insert(*a)).collect();
+        let slot = rpc.get_slot_with_commitment(CommitmentConfig::finalized()).await
+            .map_err(op("get_slot"))?;
+        let (ix, key) = create_lookup_table(owner, owner, slot);
+        utils::send_and_confirm(rpc.clone(), vec![ix], &[wallet])
+            .await
+            .map_err(op("create_lookup_table"))?;
+        lookup_tables::set_lookup_table(pool_address, &key.to_string()).await?;
+        log::info!("{pool_address}: новая таблица адресов {key}");
+        if let Some(old) = table.take() {
+            retire(rpc, wallet, old.key).await?;
+        }
+        key
+    } else {
+        table.take().map(|t| t.key).ok_or_else(|| anyhow!("lookup table missing"))?
+    };
+
+    for chunk in missing.chunks(EXTEND_CHUNK) {
+        let ix = extend_lookup_table(key, owner, Some(owner), chunk.to_vec());
+        utils::send_and_confirm(rpc.clone(), vec![ix], &[wallet])
+            .await
+            .map_err(op("extend_lookup_table"))?;
+    }
+
+    // новые адреса видны только со следующего слота
+    let landed = rpc.get_slot().await.map_err(op("get_slot"))?;
+    while rpc.get_slot().await.map_err(op("get_slot"))? <= landed {
+        sleep(Duration::from_millis(200)).await;
+    }
+    fetch(rpc, key).await
+}
+
+/// Деактивировать заменённую таблицу; закроет её `close_retired`.
+async fn retire(rpc: &Arc<RpcClient>, wallet: &Keypair, key: Pubkey) -> Result<()> {
+    let owner = wallet.pubkey();
+    utils::send_and_confirm(rpc.clone(), vec![deactivate_lookup_table(key, owner)], &[wallet])
+        .await
+        .map_err(op("deactivate_lookup_table"))?;
+    lookup_tables::retire_lookup_table(&key.to_string()).await?;
+    log::info!("таблица адресов {key} деактивирована");
+    Ok(())
+}
+
+/// Закрыть деактивированные таблицы, которые уже остыли.
+async fn close_retired(rpc: &Arc<RpcClient>, wallet: &Keypair) -> Result<()> {
+    let retired = lookup_tables::retired_lookup_tables().await?;
+    if retired.is_empty() {
+        return Ok(());
+    }
+    let owner = wallet.pubkey();
+    let slot  = rpc.get_slot().await.map_err(op("get_slot"))?;
+    for addr in retired {
+        let key = Pubkey::from_str(&addr)?;
+        let acc = match rpc.get_account_with_commitment(&key, CommitmentConfig::confirmed()).await
+            .map_err(op("get_account(lookup table)"))?
+            .value
+        {
+            Some(acc) => acc,
+            // уже закрыта
+            None => {
+                lookup_tables::forget_retired(&addr).await?;
+                continue;
+            }
+        };
+        let table = AddressLookupTable::deserialize(&acc.data)
+            .map_err(|e| anyhow!("lookup table {key}: {e}"))?;
+        let deactivated = table.meta.deactivation_slot;
+        if deactivated == u64::MAX || slot < deactivated.saturating_add(DEACTIVATION_COOLDOWN_SLOTS) {
+            continue;
+        }
+        utils::send_and_confirm(rpc.clone(), vec![close_lookup_table(key, owner, owner)], &[wallet])
+            .await
+            .map_err(op("close_lookup_table"))?;
+        lookup_tables::forget_retired(&addr).await?;
+        log::info!("таблица адресов {key} закрыта, рента возвращена");
+    }
+    Ok(())
+}
+
+/// Текущее содержимое таблицы `key`.
+pub async fn fetch(rpc: &RpcClient, key: Pubkey) -> Result<AddressLookupTableAccount> {
+    let acc = rpc.get_account(&key).await.map_err(op("get_account(lookup table)"))?;
+    let table = AddressLookupTable::deserialize(&acc.data)
+        .map_err(|e| anyhow!("lookup table {key}: {e}"))?;
+    Ok(AddressLookupTableAccount { key, addresses: table.addresses.to_vec() })
+}
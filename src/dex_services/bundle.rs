@@ -0,0 +1,480 @@
+// src/dex_services/bundle.rs
+//
+// Позиции внутри Position Bundle Whirlpool: один NFT бандла на 256 позиций
+// вместо отдельного mint-а (и ренты за него) на каждый диапазон.
+// SDK бандлы только читает, поэтому открытие, сбор и закрытие собраны
+// из инструкций `orca_whirlpools_client`.
+// Своего NFT у позиции бандла нет (`position_mint` у всех — mint бандла),
+// поэтому в `OwnedPosition.mint` и в БД её ключ — адрес аккаунта позиции.
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use orca_whirlpools::{fetch_positions_for_owner, set_whirlpools_config_address, PositionOrBundle, WhirlpoolsConfigInput};
+use orca_whirlpools_client::{
+    get_bundled_position_address, get_position_address, get_position_bundle_address,
+    get_tick_array_address, CloseBundledPositionBuilder, CollectFeesV2Builder, CollectRewardV2Builder,
+    DecreaseLiquidityV2Builder, IncreaseLiquidityV2Builder, InitializePositionBundleBuilder,
+    InitializeTickArrayBuilder, OpenBundledPositionBuilder, Position, PositionBundle, Whirlpool, ID,
+};
+use orca_whirlpools_core::{
+    decrease_liquidity_quote, get_tick_array_start_tick_index, increase_liquidity_quote_a,
+    increase_liquidity_quote_b, sqrt_price_to_price, tick_index_to_price, CollectFeesQuote,
+    IncreaseLiquidityQuote, U128,
+};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction,
+};
+use spl_associated_token_account::get_associated_token_address;
+
+use crate::dex_services::get_info::pending_fees_offchain;
+use crate::dex_services::token::{self, MintInfo};
+use crate::dex_services::wirlpool::{gap_b_for, nearest_valid_ticks, prepare_tick_arrays, rebalance_before_open};
+use crate::params::{OVR, WALLET_MUTEX, WSOL};
+use crate::types::{OpenPositionResult, PoolConfig};
+use crate::utils::{op, utils};
+
+/// позиций в одном бандле
+const BUNDLE_CAPACITY: u16 = 256;
+/// сколько SOL оставляем в кошельке на ренту и комиссии
+pub(crate) const RESERVE_LAMPORTS: u64 = 120_000_000;
+
+/// Позиция бандла, найденная по адресу.
+#[derive(Debug, Clone)]
+pub struct BundledRef {
+    pub address:      Pubkey,
+    pub bundle:       Pubkey,
+    pub bundle_mint:  Pubkey,
+    pub bundle_index: u16,
+    pub position:     Position,
+}
+
+fn slot_taken(bundle: &PositionBundle, index: u16) -> bool {
+    bundle.position_bitmap[(index / 8) as usize] & (1 << (index % 8)) != 0
+}
+
+/// `n` свободных слотов бандла (`None` — столько нет).
+fn free_slots(bundle: &PositionBundle, n: usize) -> Option<Vec<u16>> {
+    let slots: Vec<u16> = (0..BUNDLE_CAPACITY).filter(|i| !slot_taken(bundle, *i)).take(n).collect();
+    (slots.len() == n).then_some(slots)
+}
+
+/// Если `key` — адрес позиции из бандла, вернуть её бандл и индекс.
+/// Обычные позиции (ключ — NFT mint, аккаунт токен-программы) дают `None`.
+pub async fn resolve(rpc: &RpcClient, key: Pubkey) -> Result<Option<BundledRef>> {
+    let Ok(acc) = rpc.get_account(&key).await else {
+        return Ok(None);
+    };
+    if acc.owner != ID {
+        return Ok(None);
+    }
+    let position = Position::from_bytes(&acc.data)?;
+    // у обычной позиции адрес выводится из её mint-а
+    if get_position_address(&position.position_mint)?.0 == key {
+        return Ok(None);
+    }
+    let bundle_mint = position.position_mint;
+    let bundle      = get_position_bundle_address(&bundle_mint)?.0;
+    let data        = PositionBundle::from_bytes(&rpc.get_account(&bundle).await?.data)?;
+    let bundle_index = (0..BUNDLE_CAPACITY)
+        .filter(|i| slot_taken(&data, *i))
+        .find(|i| get_bundled_position_address(&bundle_mint, *i as u8).map(|(a, _)| a == key).unwrap_or(false))
+        .ok_or_else(|| anyhow!("позиция {key} не найдена в бандле {bundle}"))?;
+    Ok(Some(BundledRef { address: key, bundle, bundle_mint, bundle_index, position }))
+}
+
+/// Бандл владельца со свободным слотом; если такого нет — создаём новый.
+async fn bundle_with_free_slot(rpc: &RpcClient, wallet: &Keypair) -> Result<(Pubkey, u16)> {
+    let (mint, slots) = bundle_with_free_slots(rpc, wallet, 1).await?;
+    Ok((mint, slots[0]))
+}
+
+/// Бандл владельца, в котором свободно `n` слотов; если такого нет — создаём новый.
+pub(crate) async fn bundle_with_free_slots(rpc: &RpcClient, wallet: &Keypair, n: usize) -> Result<(Pubkey, Vec<u16>)> {
+    let owner = wallet.pubkey();
+    let found = fetch_positions_for_owner(rpc, owner)
+        .await
+        .map_err(|e| anyhow!("fetch_positions_for_owner failed: {e}"))?
+        .into_iter()
+        .find_map(|p| match p {
+            PositionOrBundle::PositionBundle(pb) => {
+                free_slots(&pb.data, n).map(|s| (pb.data.position_bundle_mint, s))
+            }
+            _ => None,
+        });
+    if let Some(v) = found {
+        return Ok(v);
+    }
+
+    let mint_kp = Keypair::new();
+    let mint    = mint_kp.pubkey();
+    let ix = InitializePositionBundleBuilder::new()
+        .position_bundle(get_position_bundle_address(&mint)?.0)
+        .position_bundle_mint(mint)
+        .position_bundle_token_account(get_associated_token_address(&owner, &mint))
+        .position_bundle_owner(owner)
+        .funder(owner)
+        .token_program(spl_token::id())
+        .associated_token_program(spl_associated_token_account::id())
+        .instruction();
+    utils::send_and_confirm(utils::init_rpc(), vec![ix], &[wallet, &mint_kp])
+        .await
+        .map_err(op("initialize_position_bundle"))?;
+    log::info!("position bundle создан: {mint}");
+    Ok((mint, (0..n as u16).collect()))
+}
+
+/// Создать tick-array-и диапазона, если их ещё нет.
+async fn tick_array_ixs(
+    rpc: &RpcClient,
+    whirl_pk: Pubkey,
+    spacing: u16,
+    ticks: [i32; 2],
+    funder: Pubkey,
+) -> Result<(Pubkey, Pubkey, Vec<Instruction>)> {
+    let starts = ticks.map(|t| get_tick_array_start_tick_index(t, spacing));
+    let lower  = get_tick_array_address(&whirl_pk, starts[0])?.0;
+    let upper  = get_tick_array_address(&whirl_pk, starts[1])?.0;
+    let accs   = rpc.get_multiple_accounts(&[lower, upper]).await?;
+
+    let mut ixs = Vec::new();
+    for ((addr, start), acc) in [(lower, starts[0]), (upper, starts[1])].into_iter().zip(accs) {
+        if acc.is_none() && !ixs.iter().any(|ix: &Instruction| ix.accounts.iter().any(|a| a.pubkey == addr)) {
+            ixs.push(
+                InitializeTickArrayBuilder::new()
+                    .whirlpool(whirl_pk)
+                    .funder(funder)
+                    .tick_array(addr)
+                    .start_tick_index(start)
+                    .instruction(),
+            );
+        }
+    }
+    Ok((lower, upper, ixs))
+}
+
+/// Открыть диапазон внутри бандла владельца, тратя не больше `initial_amount_b`
+/// (та же логика депозита и докупки токенов, что и у обычного открытия).
+pub async fn open_bundled_position(
+    price_low: f64,
+    price_high: f64,
+    initial_amount_b: f64,
+    pool: PoolConfig,
+    slippage: u16,
+) -> Result<OpenPositionResult> {
+    set_whirlpools_config_address(WhirlpoolsConfigInput::SolanaMainnet)
+        .map_err(|e| anyhow!("SDK config failed: {e}"))?;
+    let rpc    = utils::init_rpc();
+    let _guard = WALLET_MUTEX.lock().await;
+    let wallet = utils::load_wallet()?;
+    let owner  = wallet.pubkey();
+
+    // ───── 1. Пул, тики, квота ─────────────────────────────────────────
+    let whirl_pk = Pubkey::from_str(&pool.pool_address)?;
+    let whirl    = Whirlpool::from_bytes(&rpc.get_account(&whirl_pk).await?.data)?;
+    let dec_a    = pool.decimal_a as u8;
+    let dec_b    = pool.decimal_b as u8;
+    let (tick_l, tick_u) = nearest_valid_ticks(price_low, price_high, whirl.tick_spacing as i32, dec_a, dec_b);
+    // недостающие tick-array-и создаём сами — их рента идёт в резерв ниже
+    let ta = prepare_tick_arrays(&rpc, whirl_pk, whirl.tick_spacing, tick_l, tick_u).await?;
+    let (tick_l, tick_u) = (ta.tick_l, ta.tick_u);
+
+    let mint_a   = token::mint_info(&rpc, &whirl.token_mint_a).await?;
+    let mint_b   = token::mint_info(&rpc, &whirl.token_mint_b).await?;
+
+    let price_a_in_b = sqrt_price_to_price(U128::from(whirl.sqrt_price), dec_a, dec_b);
+    let quote = deposit_quote(&whirl, tick_l, tick_u, initial_amount_b, slippage, &mint_a, &mint_b, dec_a, dec_b)?;
+
+    // ───── 2. Докупаем недостающее ─────────────────────────────────────
+    // token_max_* уже включают комиссию transfer-fee
+    let need_sol  = quote.token_max_a as f64 / 10f64.powi(dec_a as i32) * OVR;
+    let need_tokb = quote.token_max_b as f64 / 10f64.powi(dec_b as i32) * OVR;
+    let mut sol_free  = rpc.get_balance(&owner).await?
+        .saturating_sub(RESERVE_LAMPORTS + ta.init_lamports) as f64 / 1e9;
+    let ata_b = token::ata(&owner, &whirl.token_mint_b, &mint_b.program);
+    let mut tokb_free = rpc.get_token_account_balance(&ata_b).await
+        .ok()
+        .and_then(|r| r.amount.parse::<u64>().ok())
+        .map(|a| a as f64 / 10f64.powi(dec_b as i32))
+        .unwrap_or(0.0);
+    rebalance_before_open(
+        &rpc, &owner, &pool, need_sol, need_tokb, price_a_in_b, gap_b_for(&pool), dec_b,
+        &mut sol_free, &mut tokb_free,
+    )
+    .await?;
+
+    // ───── 3. Бандл и слот ─────────────────────────────────────────────
+    let (bundle_mint, index) = bundle_with_free_slot(&rpc, &wallet).await?;
+    let bundle_ata   = get_associated_token_address(&owner, &bundle_mint);
+    let position     = get_bundled_position_address(&bundle_mint, index as u8)?.0;
+
+    // ───── 4. Инструкции ───────────────────────────────────────────────
+    let (ta_lower, ta_upper, mut ixs) =
+        tick_array_ixs(&rpc, whirl_pk, whirl.tick_spacing, [tick_l, tick_u], owner).await?;
+    let ata_a = token::ata(&owner, &whirl.token_mint_a, &mint_a.program);
+    for (mint, info) in [(whirl.token_mint_a, mint_a), (whirl.token_mint_b, mint_b)] {
+        ixs.push(token::create_ata_ix(&owner, &owner, &mint, &info.program));
+    }
+    let wrap_sol = whirl.token_mint_a.to_string() == WSOL;
+    if wrap_sol {
+        ixs.push(system_instruction::transfer(&owner, &ata_a, quote.token_max_a));
+        ixs.push(spl_token::instruction::sync_native(&spl_token::id(), &ata_a)?);
+    }
+    ixs.push(open_bundled_ix(owner, whirl_pk, bundle_mint, index, tick_l, tick_u)?);
+    ixs.push(increase_ix(owner, whirl_pk, &whirl, &mint_a, &mint_b, position, bundle_ata, [ta_lower, ta_upper], &quote));
+    if wrap_sol {
+        // остаток wSOL обратно в SOL
+        ixs.push(spl_token::instruction::close_account(&spl_token::id(), &ata_a, &owner, &owner, &[])?);
+    }
+
+    let signature = utils::send_and_confirm(rpc.clone(), ixs, &[&wallet])
+        .await
+        .map_err(op("open_bundled_position"))?
+        .signature;
+    log::info!("{}: позиция {position} открыта в бандле {bundle_mint} (#{index})", pool.name);
+
+    Ok(OpenPositionResult {
+        position_mint: position,
+        amount_wsol:   need_sol,
+        amount_usdc:   need_tokb,
+        signature:     Some(signature),
+    })
+}
+
+/// Квота депозита `initial_amount_b` (в единицах B) в диапазон `[tick_l; tick_u]`:
+/// выше рынка — всё в A, ниже — всё в B, пересекает — B + эквивалент A.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn deposit_quote(
+    whirl: &Whirlpool,
+    tick_l: i32,
+    tick_u: i32,
+    initial_amount_b: f64,
+    slippage: u16,
+    mint_a: &MintInfo,
+    mint_b: &MintInfo,
+    dec_a: u8,
+    dec_b: u8,
+) -> Result<IncreaseLiquidityQuote> {
+    let price_a_in_b = sqrt_price_to_price(U128::from(whirl.sqrt_price), dec_a, dec_b);
+    let dep_b_atoms  = (initial_amount_b * 10f64.powi(dec_b as i32)) as u64;
+    let dep_a_atoms  = ((initial_amount_b / price_a_in_b) * 10f64.powi(dec_a as i32)) as u64;
+
+    let sqrt = whirl.sqrt_price.into();
+    if tick_index_to_price(tick_l, dec_a, dec_b) > price_a_in_b {
+        increase_liquidity_quote_a(dep_a_atoms.max(1), slippage, sqrt, tick_l, tick_u, mint_a.orca_fee(), mint_b.orca_fee())
+    } else {
+        increase_liquidity_quote_b(dep_b_atoms.max(1), slippage, sqrt, tick_l, tick_u, mint_a.orca_fee(), mint_b.orca_fee())
+    }
+    .map_err(|e| anyhow!("increase_liquidity_quote: {e:?}"))
+}
+
+/// Открыть слот `index` бандла `bundle_mint` под диапазон (без ликвидности).
+pub(crate) fn open_bundled_ix(
+    owner: Pubkey,
+    whirl_pk: Pubkey,
+    bundle_mint: Pubkey,
+    index: u16,
+    tick_l: i32,
+    tick_u: i32,
+) -> Result<Instruction> {
+    Ok(OpenBundledPositionBuilder::new()
+        .bundled_position(get_bundled_position_address(&bundle_mint, index as u8)?.0)
+        .position_bundle(get_position_bundle_address(&bundle_mint)?.0)
+        .position_bundle_token_account(get_associated_token_address(&owner, &bundle_mint))
+        .position_bundle_authority(owner)
+        .whirlpool(whirl_pk)
+        .funder(owner)
+        .bundle_index(index)
+        .tick_lower_index(tick_l)
+        .tick_upper_index(tick_u)
+        .instruction())
+}
+
+/// Внести ликвидность по квоте в позицию `position`
+/// (`position_token_account` — где лежит её NFT или NFT бандла).
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn increase_ix(
+    owner: Pubkey,
+    whirl_pk: Pubkey,
+    whirl: &Whirlpool,
+    mint_a: &MintInfo,
+    mint_b: &MintInfo,
+    position: Pubkey,
+    position_token_account: Pubkey,
+    tick_arrays: [Pubkey; 2],
+    quote: &IncreaseLiquidityQuote,
+) -> Instruction {
+    IncreaseLiquidityV2Builder::new()
+        .whirlpool(whirl_pk)
+        .token_program_a(mint_a.program)
+        .token_program_b(mint_b.program)
+        .memo_program(spl_memo::id())
+        .position_authority(owner)
+        .position(position)
+        .position_token_account(position_token_account)
+        .token_mint_a(whirl.token_mint_a)
+        .token_mint_b(whirl.token_mint_b)
+        .token_owner_account_a(token::ata(&owner, &whirl.token_mint_a, &mint_a.program))
+        .token_owner_account_b(token::ata(&owner, &whirl.token_mint_b, &mint_b.program))
+        .token_vault_a(whirl.token_vault_a)
+        .token_vault_b(whirl.token_vault_b)
+        .tick_array_lower(tick_arrays[0])
+        .tick_array_upper(tick_arrays[1])
+        .liquidity_amount(quote.liquidity_delta)
+        .token_max_a(quote.token_max_a)
+        .token_max_b(quote.token_max_b)
+        .instruction()
+}
+
+/// Вывести ликвидность, собрать комиссии и награды в кошелёк.
+/// `close` — ещё и закрыть позицию (вернуть ренту).
+async fn drain_ixs(
+    rpc: &RpcClient,
+    owner: Pubkey,
+    b: &BundledRef,
+    slippage: u16,
+    close: bool,
+) -> Result<(Vec<Instruction>, CollectFeesQuote)> {
+    let pos      = &b.position;
+    let whirl_pk = pos.whirlpool;
+    let whirl    = Whirlpool::from_bytes(&rpc.get_account(&whirl_pk).await?.data)?;
+    let (fees, _) = pending_fees_offchain(rpc, whirl_pk, &whirl, pos).await?;
+    let mint_a = token::mint_info(rpc, &whirl.token_mint_a).await?;
+    let mint_b = token::mint_info(rpc, &whirl.token_mint_b).await?;
+
+    let bundle_ata = get_associated_token_address(&owner, &b.bundle_mint);
+    let ata_a = token::ata(&owner, &whirl.token_mint_a, &mint_a.program);
+    let ata_b = token::ata(&owner, &whirl.token_mint_b, &mint_b.program);
+    let mut ixs: Vec<Instruction> = vec![
+        token::create_ata_ix(&owner, &owner, &whirl.token_mint_a, &mint_a.program),
+        token::create_ata_ix(&owner, &owner, &whirl.token_mint_b, &mint_b.program),
+    ];
+
+    if close && pos.liquidity > 0 {
+        let q = decrease_liquidity_quote(
+            pos.liquidity, slippage, whirl.sqrt_price.into(), pos.tick_lower_index, pos.tick_upper_index,
+            mint_a.orca_fee(), mint_b.orca_fee(),
+        )
+        .map_err(|e| anyhow!("decrease_liquidity_quote: {e:?}"))?;
+        let starts = [pos.tick_lower_index, pos.tick_upper_index]
+            .map(|t| get_tick_array_start_tick_index(t, whirl.tick_spacing));
+        ixs.push(
+            DecreaseLiquidityV2Builder::new()
+                .whirlpool(whirl_pk)
+                .token_program_a(mint_a.program)
+                .token_program_b(mint_b.program)
+                .memo_program(spl_memo::id())
+                .position_authority(owner)
+                .position(b.address)
+                .position_token_account(bundle_ata)
+                .token_mint_a(whirl.token_mint_a)
+                .token_mint_b(whirl.token_mint_b)
+                .token_owner_account_a(ata_a)
+                .token_owner_account_b(ata_b)
+                .token_vault_a(whirl.token_vault_a)
+                .token_vault_b(whirl.token_vault_b)
+                .tick_array_lower(get_tick_array_address(&whirl_pk, starts[0])?.0)
+                .tick_array_upper(get_tick_array_address(&whirl_pk, starts[1])?.0)
+                .liquidity_amount(q.liquidity_delta)
+                .token_min_a(q.token_min_a)
+                .token_min_b(q.token_min_b)
+                .instruction(),
+        );
+    } else if pos.liquidity > 0 {
+        // без вывода ликвидности fee_owed_* в аккаунте сами не обновятся
+        let starts = [pos.tick_lower_index, pos.tick_upper_index]
+            .map(|t| get_tick_array_start_tick_index(t, whirl.tick_spacing));
+        ixs.push(
+            orca_whirlpools_client::UpdateFeesAndRewardsBuilder::new()
+                .whirlpool(whirl_pk)
+                .position(b.address)
+                .tick_array_lower(get_tick_array_address(&whirl_pk, starts[0])?.0)
+                .tick_array_upper(get_tick_array_address(&whirl_pk, starts[1])?.0)
+                .instruction(),
+        );
+    }
+
+    ixs.push(
+        CollectFeesV2Builder::new()
+            .whirlpool(whirl_pk)
+            .position_authority(owner)
+            .position(b.address)
+            .position_token_account(bundle_ata)
+            .token_mint_a(whirl.token_mint_a)
+            .token_mint_b(whirl.token_mint_b)
+            .token_owner_account_a(ata_a)
+            .token_vault_a(whirl.token_vault_a)
+            .token_owner_account_b(ata_b)
+            .token_vault_b(whirl.token_vault_b)
+            .token_program_a(mint_a.program)
+            .token_program_b(mint_b.program)
+            .memo_program(spl_memo::id())
+            .instruction(),
+    );
+    for (i, r) in whirl.reward_infos.iter().enumerate() {
+        if r.mint == Pubkey::default() {
+            continue;
+        }
+        let reward_program = token::mint_info(rpc, &r.mint).await?.program;
+        let reward_ata     = token::ata(&owner, &r.mint, &reward_program);
+        ixs.push(token::create_ata_ix(&owner, &owner, &r.mint, &reward_program));
+        ixs.push(
+            CollectRewardV2Builder::new()
+                .whirlpool(whirl_pk)
+                .position_authority(owner)
+                .position(b.address)
+                .position_token_account(bundle_ata)
+                .reward_owner_account(reward_ata)
+                .reward_mint(r.mint)
+                .reward_vault(r.vault)
+                .reward_token_program(reward_program)
+                .memo_program(spl_memo::id())
+                .reward_index(i as u8)
+                .instruction(),
+        );
+    }
+
+    if close {
+        ixs.push(
+            CloseBundledPositionBuilder::new()
+                .bundled_position(b.address)
+                .position_bundle(b.bundle)
+                .position_bundle_token_account(bundle_ata)
+                .position_bundle_authority(owner)
+                .receiver(owner)
+                .bundle_index(b.bundle_index)
+                .instruction(),
+        );
+    }
+    if whirl.token_mint_a.to_string() == WSOL {
+        ixs.push(spl_token::instruction::close_account(&spl_token::id(), &ata_a, &owner, &owner, &[])?);
+    }
+    Ok((ixs, fees))
+}
+
+/// Закрыть позицию бандла: вывод ликвидности, сбор, закрытие.
+/// Сам бандл остаётся — следующие диапазоны откроются в его свободных слотах.
+pub async fn close_bundled_position(b: &BundledRef, slippage: u16) -> Result<()> {
+    let rpc    = utils::init_rpc();
+    let wallet = utils::load_wallet()?;
+    let (ixs, _) = drain_ixs(&rpc, wallet.pubkey(), b, slippage, true).await?;
+    utils::send_urgent(rpc.clone(), ixs, &[&wallet])
+        .await
+        .map_err(op("close_bundled_position"))?;
+    Ok(())
+}
+
+/// Собрать комиссии и награды позиции бандла, ликвидность не трогаем.
+pub async fn harvest_bundled_position(b: &BundledRef) -> Result<CollectFeesQuote> {
+    let rpc    = utils::init_rpc();
+    let wallet = utils::load_wallet()?;
+    let (ixs, fees) = drain_ixs(&rpc, wallet.pubkey(), b, 0, false).await?;
+    utils::send_and_confirm(rpc.clone(), ixs, &[&wallet])
+        .await
+        .map_err(op("harvest_bundled_position"))?;
+    Ok(fees)
+}
@@ -0,0 +1,184 @@
+// src/dex_services/discovery.rs
+//
+// Поиск Whirlpool-пулов пары по всем tick spacing (fee tier) и их ранжирование
+// по оценке APR комиссий на единицу ликвидности.
+// Доход единицы ликвидности — прирост `fee_growth_global_*` между двумя
+// снимками состояния пула; снимки копятся в памяти (фоновый сэмплер и
+// каждый запрос ранжирования).
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use once_cell::sync::Lazy;
+use orca_whirlpools_client::{get_whirlpool_address, Whirlpool};
+use solana_sdk::pubkey::Pubkey;
+use tokio::sync::RwLock;
+use tokio::time::sleep;
+
+use crate::dex_services::get_info::compute_amounts;
+use crate::params::WHIRLPOOLS_CONFIG;
+use crate::pool_registry::{PoolSpec, POOL_SPECS};
+use crate::utils::utils::init_rpc;
+
+/// tick spacing всех fee tier-ов Whirlpool (32896 — адаптивные пулы)
+pub const TICK_SPACINGS: &[u16] = &[1, 2, 4, 8, 16, 32, 64, 96, 128, 256, 32896];
+/// диапазон ±% вокруг цены, в котором оцениваем стоимость единицы ликвидности
+pub const REF_RANGE_PCT: f64 = 5.0;
+/// минимальное окно между снимками для оценки APR, сек
+pub const MIN_WINDOW_SEC: i64 = 60;
+/// как часто фоновый сэмплер снимает пулы всех пар реестра, сек
+pub const SAMPLE_INTERVAL_SEC: u64 = 900;
+/// сколько храним снимки
+const SNAPSHOT_KEEP_H: i64 = 24;
+
+const Q64: f64 = 18_446_744_073_709_551_616.0;
+const YEAR_SEC: f64 = 365.0 * 86_400.0;
+
+#[derive(Debug, Clone, Copy)]
+struct Snapshot {
+    at:       DateTime<Utc>,
+    growth_a: u128,
+    growth_b: u128,
+}
+
+static SNAPSHOTS: Lazy<RwLock<HashMap<Pubkey, Vec<Snapshot>>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Найденный пул и его оценка.
+#[derive(Debug, Clone)]
+pub struct DiscoveredPool {
+    pub address:      Pubkey,
+    pub tick_spacing: u16,
+    /// комиссия свапа, доля (fee_rate / 1e6)
+    pub fee_rate:     f64,
+    pub liquidity:    u128,
+    pub sqrt_price:   u128,
+    /// окно между снимками, по которому посчитан APR, сек
+    pub window_sec:   i64,
+    /// APR комиссий позиции в ±`REF_RANGE_PCT`, % (`None` — снимков ещё мало)
+    pub fee_apr:      Option<f64>,
+}
+
+impl DiscoveredPool {
+    pub fn describe(&self) -> String {
+        let apr = match self.fee_apr {
+            Some(a) => format!("APR {a:.1}% ({} мин)", self.window_sec / 60),
+            None    => "APR —".to_string(),
+        };
+        format!(
+            "ts {} · fee {:.2}% · L {:.2e} · {} · {}",
+            self.tick_spacing, self.fee_rate * 100.0, self.liquidity as f64, apr, self.address,
+        )
+    }
+}
+
+/// Все инициализированные Whirlpool пары `spec` с оценкой по накопленным снимкам.
+/// Порядок минтов в пуле не важен: пробуем оба.
+pub async fn discover(spec: &PoolSpec) -> Result<Vec<DiscoveredPool>> {
+    let config = Pubkey::from_str(WHIRLPOOLS_CONFIG)?;
+    let mint_a = Pubkey::from_str(spec.mint_a)?;
+    let mint_b = Pubkey::from_str(spec.mint_b)?;
+
+    let mut candidates = Vec::with_capacity(TICK_SPACINGS.len() * 2);
+    for &ts in TICK_SPACINGS {
+        for (x, y) in [(mint_a, mint_b), (mint_b, mint_a)] {
+            let (addr, _) = get_whirlpool_address(&config, &x, &y, ts)
+                .map_err(|e| anyhow!("get_whirlpool_address: {e}"))?;
+            candidates.push(addr);
+        }
+    }
+
+    let rpc  = init_rpc();
+    let accs = rpc.get_multiple_accounts(&candidates).await
+        .map_err(|e| anyhow!("get_multiple_accounts failed: {e}"))?;
+
+    let now = Utc::now();
+    let mut out = Vec::new();
+    let mut snaps = SNAPSHOTS.write().await;
+    for (addr, acc) in candidates.into_iter().zip(accs) {
+        let Some(acc) = acc else { continue };
+        if acc.owner != orca_whirlpools_client::ID {
+            continue;
+        }
+        let whirl = Whirlpool::from_bytes(&acc.data)?;
+
+        let hist = snaps.entry(addr).or_default();
+        hist.retain(|s| now - s.at < Duration::hours(SNAPSHOT_KEEP_H));
+        let oldest = hist.first().copied();
+        hist.push(Snapshot { at: now, growth_a: whirl.fee_growth_global_a, growth_b: whirl.fee_growth_global_b });
+
+        let (window_sec, fee_apr) = match oldest {
+            Some(old) => {
+                let dt = (now - old.at).num_seconds();
+                (dt, fee_apr(&whirl, &old, dt))
+            }
+            None => (0, None),
+        };
+        out.push(DiscoveredPool {
+            address:      addr,
+            tick_spacing: whirl.tick_spacing,
+            fee_rate:     whirl.fee_rate as f64 / 1_000_000.0,
+            liquidity:    whirl.liquidity,
+            sqrt_price:   whirl.sqrt_price,
+            window_sec,
+            fee_apr,
+        });
+    }
+    Ok(out)
+}
+
+/// Пулы пары, лучшие сначала. Если снимков для оценки нет и `wait` —
+/// снимаем второй раз через `MIN_WINDOW_SEC`.
+pub async fn rank(spec: &PoolSpec, wait: bool) -> Result<Vec<DiscoveredPool>> {
+    let mut pools = discover(spec).await?;
+    if wait && !pools.is_empty() && pools.iter().all(|p| p.fee_apr.is_none()) {
+        sleep(std::time::Duration::from_secs(MIN_WINDOW_SEC as u64)).await;
+        pools = discover(spec).await?;
+    }
+    // без оценки — в конец, между собой по ликвидности
+    pools.sort_by(|a, b| match (a.fee_apr, b.fee_apr) {
+        (Some(x), Some(y)) => y.total_cmp(&x),
+        (Some(_), None)    => std::cmp::Ordering::Less,
+        (None, Some(_))    => std::cmp::Ordering::Greater,
+        (None, None)       => b.liquidity.cmp(&a.liquidity),
+    });
+    Ok(pools)
+}
+
+/// Фоновый сэмплер: раз в `SAMPLE_INTERVAL_SEC` снимает пулы всех пар реестра,
+/// чтобы к запросу ранжирования окно было длиннее минуты.
+pub async fn run_sampler() {
+    loop {
+        for spec in POOL_SPECS {
+            if let Err(e) = discover(spec).await {
+                log::warn!("discovery {}: {e:#}", spec.name);
+            }
+        }
+        sleep(std::time::Duration::from_secs(SAMPLE_INTERVAL_SEC)).await;
+    }
+}
+
+/// APR комиссий позиции в ±`REF_RANGE_PCT` вокруг текущей цены:
+/// доход единицы ликвидности за окно (в атомах B) к её стоимости, в годовых.
+/// Позиция считается всё окно в диапазоне — это оценка сверху.
+fn fee_apr(whirl: &Whirlpool, old: &Snapshot, dt: i64) -> Option<f64> {
+    if dt < MIN_WINDOW_SEC || whirl.liquidity == 0 {
+        return None;
+    }
+    let sqrt_p = whirl.sqrt_price as f64 / Q64;
+    let price  = sqrt_p * sqrt_p;               // атомов B за атом A
+
+    let earned_a = whirl.fee_growth_global_a.wrapping_sub(old.growth_a) as f64 / Q64;
+    let earned_b = whirl.fee_growth_global_b.wrapping_sub(old.growth_b) as f64 / Q64;
+    let earned   = earned_a * price + earned_b;
+
+    let r = REF_RANGE_PCT / 100.0;
+    let (unit_a, unit_b) = compute_amounts(1.0, sqrt_p, sqrt_p * (1.0 - r).sqrt(), sqrt_p * (1.0 + r).sqrt());
+    let unit = unit_a * price + unit_b;
+    if unit <= 0.0 {
+        return None;
+    }
+    Some(earned / unit * YEAR_SEC / dt as f64 * 100.0)
+}
@@ -0,0 +1,899 @@
+// src/dex_services/executor.rs
+//
+// Исполнитель операций с CLMM-позициями.
+// Боевой режим шлёт транзакции в сеть (площадку выбирает venue.rs), бумажный (PAPER_TRADING=1 в .env)
+// держит виртуальный кошелёк и виртуальные позиции, которые оцениваются
+// по живому `sqrt_price` пула. Режим выбирается один раз при старте.
+
+use std::collections::HashMap;
+use std::env;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use once_cell::sync::Lazy;
+use orca_whirlpools_client::Whirlpool;
+use orca_whirlpools_core::{sqrt_price_to_price, tick_index_to_price, U128};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use tokio::sync::Mutex;
+
+use crate::database::positions::{self, find_position_index_by_nft};
+use crate::database::triggers;
+use crate::database::tx_costs;
+use crate::dex_services::get_info::{compute_amounts, liquidity_for_deposit};
+use crate::dex_services::jito;
+use crate::dex_services::multi_open::{open_legs_atomic, LegRequest};
+use crate::dex_services::reopen;
+use crate::dex_services::swap::{execute_swap_tokens, SwapResult};
+use crate::dex_services::venue::{venue_for, venue_for_pool, venue_of_position, ClmmVenue, ORCA, RAYDIUM};
+use crate::dex_services::wirlpool::{finalize_pool_sessions, nearest_valid_ticks, HarvestSummary};
+use crate::orchestrator::owned_in_pool;
+use crate::params::{USDC, USDT, WSOL};
+use crate::pool_registry;
+use crate::types::{OpenPositionResult, PoolConfig, PoolPositionInfo, WalletBalanceInfo};
+use crate::utils::{self, get_sol_price_usd, get_token_balance, safe_get_account};
+
+const Q64: f64 = 18_446_744_073_709_551_616.0;
+
+/// Стартовый виртуальный кошелёк (перекрывается PAPER_SOL / PAPER_USDC)
+const PAPER_SOL_DEFAULT:  f64 = 1.0;
+const PAPER_USDC_DEFAULT: f64 = 1_000.0;
+/// спред + проскальзывание виртуального свапа
+const PAPER_SWAP_COST:    f64 = 0.001;
+/// комиссия сети за одну «транзакцию»
+const PAPER_TX_FEE_SOL:   f64 = 0.000_05;
+
+pub type ExecFuture<'a, T> = BoxFuture<'a, Result<T>>;
+
+/// Перед закрытием: стоимость позиций, несобранные комиссии и награды
+/// каждого пула — в pool_configs. История сессии считается по ним, а не по
+/// общему кошельку (его двигают все пулы сразу).
+async fn snapshot_session_values(pool: Option<Pubkey>) {
+    let addrs = match pool {
+        Some(pk) => vec![pk.to_string()],
+        None     => positions::list_pool_addresses().await.unwrap_or_default(),
+    };
+    for addr in addrs {
+        let res = async {
+            let Some(cfg) = positions::get_pool_config(&addr).await? else { return Ok(()) };
+            let (mut value, mut fees, mut rewards) = (0.0, 0.0, 0.0);
+            for p in owned_in_pool(Pubkey::from_str(&addr)?).await? {
+                let info = executor().position_info(&cfg, &p).await?;
+                value   += info.value_a + info.value_b;
+                fees    += info.sum;
+                rewards += info.rewards_usd;
+            }
+            positions::update_total_value_current(&addr, value).await?;
+            // комиссии по слотам больше не нужны — история берёт их сумму
+            positions::update_commission(&addr, 1, fees).await?;
+            positions::update_commission(&addr, 2, 0.0).await?;
+            positions::update_commission(&addr, 3, 0.0).await?;
+            positions::update_rewards_pending(&addr, rewards).await?;
+            Ok::<_, anyhow::Error>(())
+        }.await;
+        if let Err(e) = res {
+            log::warn!("{addr}: снимок стоимости перед закрытием не записан: {e:#}");
+        }
+    }
+}
+
+/// Позиция владельца — реальная или виртуальная.
+#[derive(Debug, Clone)]
+pub struct OwnedPosition {
+    /// адрес аккаунта позиции (PDA)
+    pub address:    Pubkey,
+    /// NFT позиции; у позиции из бандла своего NFT нет — здесь её адрес
+    pub mint:       Pubkey,
+    pub whirlpool:  Pubkey,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    /// бандл, в котором лежит позиция (`None` — обычная позиция)
+    pub bundle:     Option<Pubkey>,
+}
+
+/// Всё, что бот делает с кошельком и позициями Whirlpool.
+pub trait WhirlpoolExecutor: Send + Sync {
+    fn is_paper(&self) -> bool;
+
+    fn open_position(
+        &self,
+        price_low: f64,
+        price_high: f64,
+        initial_amount_b: f64,
+        pool: PoolConfig,
+        slippage: u16,
+        number: usize,
+    ) -> ExecFuture<'_, OpenPositionResult>;
+
+    /// Открыть все диапазоны пула разом — либо все, либо ни одного.
+    /// `None` — площадка так не умеет.
+    fn open_positions_atomic<'a>(
+        &'a self,
+        legs: &'a [LegRequest],
+        pool: &'a PoolConfig,
+        slippage: u16,
+    ) -> ExecFuture<'a, Option<Vec<OpenPositionResult>>>;
+
+    fn close_position(&self, position_mint: Pubkey, slippage: u16) -> ExecFuture<'_, ()>;
+
+    /// Закрыть позицию и открыть на её средства новую одним бандлом
+    /// (close → swap → open). `None` — так не получится, закрываем и
+    /// открываем по отдельности.
+    fn reopen_position<'a>(
+        &'a self,
+        position_mint: Pubkey,
+        price_low: f64,
+        price_high: f64,
+        deposit: f64,
+        pool: &'a PoolConfig,
+        slippage: u16,
+    ) -> ExecFuture<'a, Option<OpenPositionResult>>;
+
+    /// Закрыть все позиции (или только позиции `pool`) и записать историю сессии.
+    fn close_all(&self, slippage: u16, pool: Option<Pubkey>) -> ExecFuture<'_, ()>;
+
+    /// Позиции владельца, включая лежащие в бандлах.
+    fn list_positions(&self, pool: Option<Pubkey>) -> ExecFuture<'_, Vec<OwnedPosition>>;
+
+    fn position_info<'a>(
+        &'a self,
+        pool_cfg: &'a PoolConfig,
+        pos: &'a OwnedPosition,
+    ) -> ExecFuture<'a, PoolPositionInfo>;
+
+    fn swap<'a>(&'a self, sell_mint: &'a str, buy_mint: &'a str, amount_in: f64) -> ExecFuture<'a, SwapResult>;
+
+    fn increase_liquidity<'a>(
+        &'a self,
+        position_mint: Pubkey,
+        usd_budget: f64,
+        pool: &'a PoolConfig,
+        slippage: u16,
+    ) -> ExecFuture<'a, ()>;
+
+    fn decrease_liquidity(&self, position_mint: Pubkey, pct: f64, slippage: u16) -> ExecFuture<'_, ()>;
+
+    /// Собрать накопленные комиссии позиции в кошелёк.
+    fn harvest<'a>(&'a self, position_mint: Pubkey, pool: &'a PoolConfig) -> ExecFuture<'a, HarvestSummary>;
+
+    /// Баланс токена в кошельке (SOL — нативный баланс).
+    fn balance<'a>(&'a self, mint: &'a str, dec: u8) -> ExecFuture<'a, f64>;
+
+    fn wallet_balance(&self) -> ExecFuture<'_, WalletBalanceInfo>;
+}
+
+static EXECUTOR: Lazy<Box<dyn WhirlpoolExecutor>> = Lazy::new(|| {
+    let paper = env::var("PAPER_TRADING")
+        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "on"))
+        .unwrap_or(false);
+    if paper {
+        log::warn!("PAPER_TRADING: сделки виртуальные, транзакции в сеть не отправляются");
+        Box::new(PaperExecutor::from_env())
+    } else {
+        Box::new(LiveExecutor)
+    }
+});
+
+/// Текущий исполнитель.
+pub fn executor() -> &'static dyn WhirlpoolExecutor {
+    EXECUTOR.as_ref()
+}
+
+pub fn is_paper() -> bool {
+    executor().is_paper()
+}
+
+/// Метка режима для сообщений в Telegram.
+pub fn mode_tag() -> &'static str {
+    if is_paper() { "🧪 PAPER " } else { "" }
+}
+
+// ───── 1. Боевой исполнитель ───────────────────────────────────────────
+/// Шлёт транзакции в сеть; конкретную площадку (Whirlpool / Raydium)
+/// выбирает `venue` — по пулу при открытии и по позиции дальше.
+pub struct LiveExecutor;
+
+impl WhirlpoolExecutor for LiveExecutor {
+    fn is_paper(&self) -> bool { false }
+
+    fn open_position(
+        &self,
+        price_low: f64,
+        price_high: f64,
+        initial_amount_b: f64,
+        pool: PoolConfig,
+        slippage: u16,
+        number: usize,
+    ) -> ExecFuture<'_, OpenPositionResult> {
+        let addr = Some(pool.pool_address.clone());
+        tx_costs::scoped(addr, "open", venue_for(&pool.program).open(price_low, price_high, initial_amount_b, pool, slippage, number))
+            .boxed()
+    }
+
+    fn open_positions_atomic<'a>(
+        &'a self,
+        legs: &'a [LegRequest],
+        pool: &'a PoolConfig,
+        slippage: u16,
+    ) -> ExecFuture<'a, Option<Vec<OpenPositionResult>>> {
+        // одной транзакцией умеем только на Whirlpool
+        if venue_for(&pool.program).name() != ORCA.name() {
+            return async { Ok(None) }.boxed();
+        }
+        let addr = Some(pool.pool_address.clone());
+        tx_costs::scoped(addr, "open", async move { open_legs_atomic(pool, legs, slippage).await.map(Some) }).boxed()
+    }
+
+    fn close_position(&self, position_mint: Pubkey, slippage: u16) -> ExecFuture<'_, ()> {
+        let fut = async move { venue_of_position(&position_mint).await?.close(position_mint, slippage).await };
+        tx_costs::scoped(None, "close", fut).boxed()
+    }
+
+    fn reopen_position<'a>(
+        &'a self,
+        position_mint: Pubkey,
+        price_low: f64,
+        price_high: f64,
+        deposit: f64,
+        pool: &'a PoolConfig,
+        slippage: u16,
+    ) -> ExecFuture<'a, Option<OpenPositionResult>> {
+        // бандлом умеем только на Whirlpool и только с Jito
+        if !jito::enabled() || venue_for(&pool.program).name() != ORCA.name() {
+            return async { Ok(None) }.boxed();
+        }
+        let addr = Some(pool.pool_address.clone());
+        let fut = reopen::reopen_position(position_mint, price_low, price_high, deposit, pool, slippage);
+        tx_costs::scoped(addr, "reopen", fut).boxed()
+    }
+
+    fn close_all(&self, slippage: u16, pool: Option<Pubkey>) -> ExecFuture<'_, ()> {
+        let fut = async move {
+            snapshot_session_values(pool).await;
+            match pool {
+                Some(pk) => venue_for_pool(&pk).close_all(slippage, Some(pk)).await,
+                // Whirlpool последним: без пула он финализирует все сессии из БД
+                None => {
+                    RAYDIUM.close_all(slippage, None).await?;
+                    ORCA.close_all(slippage, None).await
+                }
+            }
+        };
+        tx_costs::scoped(pool.map(|pk| pk.to_string()), "close", fut).boxed()
+    }
+
+    fn list_positions(&self, pool: Option<Pubkey>) -> ExecFuture<'_, Vec<OwnedPosition>> {
+        async move {
+            match pool {
+                Some(pk) => venue_for_pool(&pk).list(Some(pk)).await,
+                None => {
+                    let mut out = ORCA.list(None).await?;
+                    out.extend(RAYDIUM.list(None).await?);
+                    Ok(out)
+                }
+            }
+        }
+        .boxed()
+    }
+
+    fn position_info<'a>(
+        &'a self,
+        pool_cfg: &'a PoolConfig,
+        pos: &'a OwnedPosition,
+    ) -> ExecFuture<'a, PoolPositionInfo> {
+        venue_for(&pool_cfg.program).info(pool_cfg, pos)
+    }
+
+    fn swap<'a>(&'a self, sell_mint: &'a str, buy_mint: &'a str, amount_in: f64) -> ExecFuture<'a, SwapResult> {
+        tx_costs::scoped(None, "swap", execute_swap_tokens(sell_mint, buy_mint, amount_in)).boxed()
+    }
+
+    fn increase_liquidity<'a>(
+        &'a self,
+        position_mint: Pubkey,
+        usd_budget: f64,
+        pool: &'a PoolConfig,
+        slippage: u16,
+    ) -> ExecFuture<'a, ()> {
+        let fut = async move {
+            venue_of_position(&position_mint).await?
+                .increase(position_mint, usd_budget, pool, slippage)
+                .await
+        };
+        tx_costs::scoped(Some(pool.pool_address.clone()), "increase", fut).boxed()
+    }
+
+    fn decrease_liquidity(&self, position_mint: Pubkey, pct: f64, slippage: u16) -> ExecFuture<'_, ()> {
+        let fut = async move { venue_of_position(&position_mint).await?.decrease(position_mint, pct, slippage).await };
+        tx_costs::scoped(None, "decrease", fut).boxed()
+    }
+
+    fn harvest<'a>(&'a self, position_mint: Pubkey, pool: &'a PoolConfig) -> ExecFuture<'a, HarvestSummary> {
+        let fut = async move { venue_of_position(&position_mint).await?.harvest(position_mint, pool).await };
+        tx_costs::scoped(Some(pool.pool_address.clone()), "harvest", fut).boxed()
+    }
+
+    fn balance<'a>(&'a self, mint: &'a str, dec: u8) -> ExecFuture<'a, f64> {
+        async move {
+            let rpc    = utils::utils::init_rpc();
+            let wallet = utils::utils::load_wallet()?;
+            get_token_balance(&rpc, &wallet.pubkey(), mint, dec).await
+        }
+        .boxed()
+    }
+
+    fn wallet_balance(&self) -> ExecFuture<'_, WalletBalanceInfo> {
+        utils::fetch_wallet_balance_info().boxed()
+    }
+}
+
+// ───── 2. Бумажный исполнитель ─────────────────────────────────────────
+/// Виртуальная позиция: только ликвидность и тики, всё остальное
+/// каждый раз пересчитывается от текущего `sqrt_price`.
+#[derive(Debug, Clone)]
+struct VirtualPosition {
+    pos:       OwnedPosition,
+    mint_a:    String,
+    mint_b:    String,
+    dec_a:     u8,
+    dec_b:     u8,
+    /// L в тех же единицах, что и on-chain `liquidity`
+    liquidity: f64,
+    /// накопленные комиссии в атомах
+    fee_a:     f64,
+    fee_b:     f64,
+    /// последний увиденный `fee_growth_global_*` пула
+    growth_a:  u128,
+    growth_b:  u128,
+    opened_at: DateTime<Utc>,
+}
+
+impl VirtualPosition {
+    fn sqrt_bounds(&self) -> (f64, f64) {
+        (sqrt_at_tick(self.pos.tick_lower), sqrt_at_tick(self.pos.tick_upper))
+    }
+
+    /// Начисляем комиссии за время, пока цена была внутри диапазона.
+    /// Рост `fee_growth_global` между опросами приписываем позиции,
+    /// только если текущий тик в её диапазоне — грубо, но без чтения tick-array.
+    fn accrue(&mut self, whirl: &Whirlpool) {
+        let in_range = whirl.tick_current_index >= self.pos.tick_lower
+            && whirl.tick_current_index < self.pos.tick_upper;
+        if in_range {
+            let d_a = whirl.fee_growth_global_a.wrapping_sub(self.growth_a) as f64 / Q64;
+            let d_b = whirl.fee_growth_global_b.wrapping_sub(self.growth_b) as f64 / Q64;
+            self.fee_a += self.liquidity * d_a;
+            self.fee_b += self.liquidity * d_b;
+        }
+        self.growth_a = whirl.fee_growth_global_a;
+        self.growth_b = whirl.fee_growth_global_b;
+    }
+
+    /// Состав позиции в атомах (без комиссий).
+    fn amounts(&self, whirl: &Whirlpool, liquidity: f64) -> (f64, f64) {
+        let (sqrt_l, sqrt_u) = self.sqrt_bounds();
+        compute_amounts(liquidity, whirl.sqrt_price as f64 / Q64, sqrt_l, sqrt_u)
+    }
+}
+
+#[derive(Debug, Default)]
+struct PaperState {
+    /// mint → количество в «целых» единицах
+    balances:  HashMap<String, f64>,
+    positions: Vec<VirtualPosition>,
+}
+
+impl PaperState {
+    fn bal(&self, mint: &str) -> f64 {
+        self.balances.get(mint).copied().unwrap_or(0.0)
+    }
+
+    fn add(&mut self, mint: &str, delta: f64) {
+        *self.balances.entry(mint.to_string()).or_insert(0.0) += delta;
+    }
+
+    fn charge_tx(&mut self) {
+        self.add(WSOL, -PAPER_TX_FEE_SOL);
+    }
+
+    /// Докупаем недостающий токен за второй токен пары (как `rebalance_before_open`).
+    /// `price` — сколько B за 1 A.
+    fn ensure_funds(&mut self, mint_a: &str, mint_b: &str, need_a: f64, need_b: f64, price: f64) -> Result<()> {
+        let free_a = self.bal(mint_a) - if mint_a == WSOL { PAPER_TX_FEE_SOL } else { 0.0 };
+        let free_b = self.bal(mint_b);
+
+        if free_a < need_a {
+            let cost_b = (need_a - free_a) * price / (1.0 - PAPER_SWAP_COST);
+            if free_b - need_b < cost_b {
+                bail!("paper: всё ещё не хватает средств (A {:.6}/{:.6}, B {:.6}/{:.6})",
+                      free_a, need_a, free_b, need_b + cost_b);
+            }
+            self.add(mint_b, -cost_b);
+            self.add(mint_a, need_a - free_a);
+        } else if free_b < need_b {
+            let cost_a = (need_b - free_b) / price / (1.0 - PAPER_SWAP_COST);
+            if free_a - need_a < cost_a {
+                bail!("paper: всё ещё не хватает средств (A {:.6}/{:.6}, B {:.6}/{:.6})",
+                      free_a, need_a + cost_a, free_b, need_b);
+            }
+            self.add(mint_a, -cost_a);
+            self.add(mint_b, need_b - free_b);
+        }
+        self.add(mint_a, -need_a);
+        self.add(mint_b, -need_b);
+        Ok(())
+    }
+}
+
+pub struct PaperExecutor {
+    state: Mutex<PaperState>,
+}
+
+impl PaperExecutor {
+    fn from_env() -> Self {
+        let read = |key: &str, default: f64| {
+            env::var(key).ok().and_then(|v| v.parse::<f64>().ok()).unwrap_or(default)
+        };
+        let mut st = PaperState::default();
+        st.add(WSOL, read("PAPER_SOL", PAPER_SOL_DEFAULT));
+        st.add(USDC, read("PAPER_USDC", PAPER_USDC_DEFAULT));
+        Self { state: Mutex::new(st) }
+    }
+
+    async fn open(
+        &self,
+        price_low: f64,
+        price_high: f64,
+        initial_amount_b: f64,
+        pool: PoolConfig,
+        number: usize,
+    ) -> Result<OpenPositionResult> {
+        // бумажная модель опирается на аккаунт Whirlpool
+        if venue_for(&pool.program).name() != ORCA.name() {
+            bail!("paper: пул {} ({}) — в бумажном режиме поддерживается только Whirlpool", pool.name, pool.program);
+        }
+        let whirl_pk = Pubkey::from_str(&pool.pool_address)?;
+        let whirl    = load_whirlpool(&whirl_pk).await?;
+        let dec_a    = pool.decimal_a as u8;
+        let dec_b    = pool.decimal_b as u8;
+
+        // те же тики, что выставил бы боевой режим
+        let (tick_l, tick_u) = nearest_valid_ticks(price_low, price_high, whirl.tick_spacing as i32, dec_a, dec_b);
+        let price_a_in_b = sqrt_price_to_price(U128::from(whirl.sqrt_price), dec_a, dec_b);
+
+        let sqrt_p = whirl.sqrt_price as f64 / Q64;
+        let (sqrt_l, sqrt_u) = (sqrt_at_tick(tick_l), sqrt_at_tick(tick_u));
+        let dep_b = initial_amount_b * 10f64.powi(dec_b as i32);
+        let dep_a = initial_amount_b / price_a_in_b * 10f64.powi(dec_a as i32);
+
+        let liquidity = liquidity_for_deposit(dep_a, dep_b, sqrt_p, sqrt_l, sqrt_u);
+        let (raw_a, raw_b) = compute_amounts(liquidity, sqrt_p, sqrt_l, sqrt_u);
+        let need_a = raw_a / 10f64.powi(dec_a as i32);
+        let need_b = raw_b / 10f64.powi(dec_b as i32);
+
+        let mint = Pubkey::new_unique();
+        let (address, _) = Pubkey::find_program_address(&[b"position", mint.as_ref()], &orca_whirlpools_client::ID);
+
+        let mut st = self.state.lock().await;
+        st.ensure_funds(&pool.mint_a, &pool.mint_b, need_a, need_b, price_a_in_b)?;
+        st.charge_tx();
+        st.positions.push(VirtualPosition {
+            pos: OwnedPosition { address, mint, whirlpool: whirl_pk, tick_lower: tick_l, tick_upper: tick_u, bundle: None },
+            mint_a: pool.mint_a.clone(),
+            mint_b: pool.mint_b.clone(),
+            dec_a,
+            dec_b,
+            liquidity,
+            fee_a: 0.0,
+            fee_b: 0.0,
+            growth_a: whirl.fee_growth_global_a,
+            growth_b: whirl.fee_growth_global_b,
+            opened_at: Utc::now(),
+        });
+        log::info!(
+            "paper: open #{number} {} [{:.6}; {:.6}] A {:.6} B {:.6} mint {}",
+            pool.name,
+            tick_index_to_price(tick_l, dec_a, dec_b),
+            tick_index_to_price(tick_u, dec_a, dec_b),
+            need_a, need_b, mint
+        );
+
+        Ok(OpenPositionResult { position_mint: mint, amount_wsol: need_a, amount_usdc: need_b, signature: None })
+    }
+
+    async fn close(&self, position_mint: Pubkey) -> Result<()> {
+        let whirl_pk = self.whirlpool_of(&position_mint).await?;
+        let whirl    = load_whirlpool(&whirl_pk).await?;
+
+        let mut st = self.state.lock().await;
+        let idx = st.positions.iter().position(|p| p.pos.mint == position_mint)
+            .ok_or_else(|| anyhow!("paper: позиция {} не найдена", position_mint))?;
+        let mut vp = st.positions.remove(idx);
+        vp.accrue(&whirl);
+        let (raw_a, raw_b) = vp.amounts(&whirl, vp.liquidity);
+        st.add(&vp.mint_a, (raw_a + vp.fee_a) / 10f64.powi(vp.dec_a as i32));
+        st.add(&vp.mint_b, (raw_b + vp.fee_b) / 10f64.powi(vp.dec_b as i32));
+        st.charge_tx();
+        log::info!(
+            "paper: close {} (open since {}), fees A {:.0} B {:.0} atoms",
+            position_mint, vp.opened_at.format("%Y-%m-%d %H:%M"), vp.fee_a, vp.fee_b
+        );
+        Ok(())
+    }
+
+    async fn close_all_virtual(&self, pool: Option<Pubkey>) -> Result<()> {
+        triggers::closing_switcher(true, None).await?;
+        snapshot_session_values(pool).await;
+        for p in self.list(pool).await {
+            if let Err(e) = self.close(p.mint).await {
+                log::error!("❌ paper close failed mint={} err={:?}", p.mint, e);
+            }
+        }
+        finalize_pool_sessions(pool).await?;
+        triggers::closing_switcher(false, None).await?;
+        Ok(())
+    }
+
+    async fn list(&self, pool: Option<Pubkey>) -> Vec<OwnedPosition> {
+        let st = self.state.lock().await;
+        st.positions
+            .iter()
+            .filter(|p| pool.map_or(true, |pk| p.pos.whirlpool == pk))
+            .map(|p| p.pos.clone())
+            .collect()
+    }
+
+    async fn whirlpool_of(&self, position_mint: &Pubkey) -> Result<Pubkey> {
+        let st = self.state.lock().await;
+        st.positions.iter()
+            .find(|p| p.pos.mint == *position_mint)
+            .map(|p| p.pos.whirlpool)
+            .ok_or_else(|| anyhow!("paper: позиция {} не найдена", position_mint))
+    }
+
+    async fn info(&self, pool_cfg: &PoolConfig, pos: &OwnedPosition) -> Result<PoolPositionInfo> {
+        let whirl = load_whirlpool(&pos.whirlpool).await?;
+        let dec_a = pool_cfg.decimal_a as u8;
+        let dec_b = pool_cfg.decimal_b as u8;
+        let price_ab = sqrt_price_to_price(U128::from(whirl.sqrt_price), dec_a, dec_b);
+
+        let vp = {
+            let mut st = self.state.lock().await;
+            let vp = st.positions.iter_mut()
+                .find(|p| p.pos.mint == pos.mint)
+                .ok_or_else(|| anyhow!("paper: позиция {} не найдена", pos.mint))?;
+            vp.accrue(&whirl);
+            vp.clone()
+        };
+
+        let disp_invert   = !pool_cfg.name.starts_with("SOL/");
+        let display_price = if disp_invert { 1.0 / price_ab } else { price_ab };
+
+        let sol_usd  = get_sol_price_usd(WSOL, true).await.unwrap_or(0.0);
+        let tokb_usd = if pool_cfg.mint_b == USDC || pool_cfg.mint_b == USDT {
+            1.0
+        } else {
+            sol_usd / price_ab.max(1e-12)
+        };
+
+        let pending_a = vp.fee_a / 10f64.powi(dec_a as i32);
+        let pending_b = vp.fee_b / 10f64.powi(dec_b as i32);
+        let (raw_a, raw_b) = vp.amounts(&whirl, vp.liquidity);
+        let amount_a = raw_a / 10f64.powi(dec_a as i32);
+        let amount_b = raw_b / 10f64.powi(dec_b as i32);
+        let value_a  = amount_a * sol_usd;
+        let value_b  = amount_b * tokb_usd;
+        let total    = value_a + value_b;
+        let (pct_a, pct_b) = if total > 0.0 {
+            let a = value_a / total * 100.0;
+            (a, 100.0 - a)
+        } else {
+            (0.0, 0.0)
+        };
+
+        let lo_raw = tick_index_to_price(pos.tick_lower, dec_a, dec_b);
+        let hi_raw = tick_index_to_price(pos.tick_upper, dec_a, dec_b);
+        let (lower_price, upper_price) = if disp_invert {
+            (1.0 / hi_raw, 1.0 / lo_raw)
+        } else {
+            (lo_raw, hi_raw)
+        };
+
+        Ok(PoolPositionInfo {
+            pending_a,
+            pending_b,
+            pending_a_usd: pending_a * sol_usd,
+            sum:           pending_a * sol_usd + pending_b * tokb_usd,
+            amount_a,
+            amount_b,
+            value_a,
+            value_b,
+            pct_a,
+            pct_b,
+            current_price: display_price,
+            lower_price,
+            upper_price,
+            pct_down: (display_price - lower_price) / display_price * 100.0,
+            pct_up:   (upper_price - display_price) / display_price * 100.0,
+            index:    find_position_index_by_nft(&pos.mint.to_string()).await?.unwrap_or_default(),
+            // эмиссии в бумаге не моделируем
+            rewards:     Vec::new(),
+            rewards_usd: 0.0,
+        })
+    }
+
+    async fn swap_virtual(&self, sell_mint: &str, buy_mint: &str, amount_in: f64) -> Result<SwapResult> {
+        let rate = usd_price(sell_mint).await? / usd_price(buy_mint).await?;
+
+        let mut st = self.state.lock().await;
+        let have = st.bal(sell_mint);
+        if have + 1e-9 < amount_in {
+            bail!("paper swap: недостаточно {sell_mint}: {have:.6} < {amount_in:.6}");
+        }
+        st.add(sell_mint, -amount_in);
+        st.add(buy_mint, amount_in * rate * (1.0 - PAPER_SWAP_COST));
+        st.charge_tx();
+        Ok(SwapResult { balance_sell: st.bal(sell_mint), balance_buy: st.bal(buy_mint), signature: None })
+    }
+
+    async fn increase(&self, position_mint: Pubkey, usd_budget: f64) -> Result<()> {
+        let whirl_pk = self.whirlpool_of(&position_mint).await?;
+        let whirl    = load_whirlpool(&whirl_pk).await?;
+
+        let vp = {
+            let st = self.state.lock().await;
+            st.positions.iter().find(|p| p.pos.mint == position_mint).cloned()
+                .ok_or_else(|| anyhow!("paper: позиция {} не найдена", position_mint))?
+        };
+        let usd_a = usd_price(&vp.mint_a).await?;
+        let usd_b = usd_price(&vp.mint_b).await?;
+
+        // стоимость единицы L в USD при текущей цене
+        let (a1, b1) = vp.amounts(&whirl, 1.0);
+        let usd_per_l = a1 / 10f64.powi(vp.dec_a as i32) * usd_a + b1 / 10f64.powi(vp.dec_b as i32) * usd_b;
+        if usd_per_l <= 0.0 {
+            bail!("paper: не удалось оценить ликвидность позиции {}", position_mint);
+        }
+        let delta = usd_budget / usd_per_l;
+        let (raw_a, raw_b) = vp.amounts(&whirl, delta);
+
+        let mut st = self.state.lock().await;
+        st.ensure_funds(
+            &vp.mint_a, &vp.mint_b,
+            raw_a / 10f64.powi(vp.dec_a as i32),
+            raw_b / 10f64.powi(vp.dec_b as i32),
+            usd_a / usd_b,
+        )?;
+        st.charge_tx();
+        let p = st.positions.iter_mut().find(|p| p.pos.mint == position_mint)
+            .ok_or_else(|| anyhow!("paper: позиция {} закрыта во время пополнения", position_mint))?;
+        p.accrue(&whirl);
+        p.liquidity += delta;
+        Ok(())
+    }
+
+    async fn decrease(&self, position_mint: Pubkey, pct: f64) -> Result<()> {
+        if !(0.0 < pct && pct <= 100.0) {
+            bail!("pct must be within (0;100]");
+        }
+        let whirl_pk = self.whirlpool_of(&position_mint).await?;
+        let whirl    = load_whirlpool(&whirl_pk).await?;
+
+        let mut st = self.state.lock().await;
+        let p = st.positions.iter_mut().find(|p| p.pos.mint == position_mint)
+            .ok_or_else(|| anyhow!("paper: позиция {} не найдена", position_mint))?;
+        p.accrue(&whirl);
+        let delta = p.liquidity * pct / 100.0;
+        p.liquidity -= delta;
+        let (raw_a, raw_b) = p.amounts(&whirl, delta);
+        let (mint_a, mint_b) = (p.mint_a.clone(), p.mint_b.clone());
+        let (dec_a, dec_b)   = (p.dec_a, p.dec_b);
+        st.add(&mint_a, raw_a / 10f64.powi(dec_a as i32));
+        st.add(&mint_b, raw_b / 10f64.powi(dec_b as i32));
+        st.charge_tx();
+        Ok(())
+    }
+
+    async fn collect(&self, position_mint: Pubkey, pool: &PoolConfig) -> Result<HarvestSummary> {
+        let whirl_pk = self.whirlpool_of(&position_mint).await?;
+        let whirl    = load_whirlpool(&whirl_pk).await?;
+        let price_ab = sqrt_price_to_price(U128::from(whirl.sqrt_price), pool.decimal_a as u8, pool.decimal_b as u8);
+        // USD-цены обоих токенов: B не обязательно стейбл (LST-пулы, RAY/SOL)
+        let (a_usd, b_usd) = match usd_price(&pool.mint_a).await {
+            Ok(a)  => (a, a / price_ab.max(1e-12)),
+            Err(_) => {
+                let b = usd_price(&pool.mint_b).await?;
+                (b * price_ab, b)
+            }
+        };
+
+        let mut st = self.state.lock().await;
+        let p = st.positions.iter_mut().find(|p| p.pos.mint == position_mint)
+            .ok_or_else(|| anyhow!("paper: позиция {} не найдена", position_mint))?;
+        p.accrue(&whirl);
+        let amount_a = p.fee_a / 10f64.powi(p.dec_a as i32);
+        let amount_b = p.fee_b / 10f64.powi(p.dec_b as i32);
+        if amount_a <= 0.0 && amount_b <= 0.0 {
+            bail!("No fees to collect for position {}", position_mint);
+        }
+        p.fee_a = 0.0;
+        p.fee_b = 0.0;
+        let (mint_a, mint_b) = (p.mint_a.clone(), p.mint_b.clone());
+        st.add(&mint_a, amount_a);
+        st.add(&mint_b, amount_b);
+        st.charge_tx();
+        Ok(HarvestSummary {
+            amount_a,
+            amount_b,
+            price_a_in_usd: a_usd,
+            total_usd: amount_a * a_usd + amount_b * b_usd,
+        })
+    }
+
+    async fn wallet(&self) -> Result<WalletBalanceInfo> {
+        let sol_usd_price = get_sol_price_usd(WSOL, true).await?;
+        let balances = self.state.lock().await.balances.clone();
+        let bal = |mint: &str| balances.get(mint).copied().unwrap_or(0.0);
+        let sol_balance  = bal(WSOL);
+        // USDT в виртуальном кошельке считаем вместе с USDC
+        let usdc_balance = bal(USDC) + bal(USDT);
+        let sol_in_usd   = sol_balance * sol_usd_price;
+
+        // прочие токены (LST после свапов в SOL/JitoSOL, SOL/mSOL и т.п.) — по их цене
+        let mut other_usd = 0.0;
+        for (mint, amount) in &balances {
+            if [WSOL, USDC, USDT].contains(&mint.as_str()) || amount.abs() < f64::EPSILON {
+                continue;
+            }
+            match usd_price(mint).await {
+                Ok(p)  => other_usd += amount * p,
+                Err(e) => log::warn!("paper: {mint} не вошёл в стоимость кошелька: {e}"),
+            }
+        }
+        Ok(WalletBalanceInfo {
+            sol_balance,
+            usdc_balance,
+            sol_usd_price,
+            sol_in_usd,
+            usdc_in_usd: usdc_balance,
+            total_usd:   sol_in_usd + usdc_balance + other_usd,
+        })
+    }
+}
+
+impl WhirlpoolExecutor for PaperExecutor {
+    fn is_paper(&self) -> bool { true }
+
+    fn open_position(
+        &self,
+        price_low: f64,
+        price_high: f64,
+        initial_amount_b: f64,
+        pool: PoolConfig,
+        _slippage: u16,
+        number: usize,
+    ) -> ExecFuture<'_, OpenPositionResult> {
+        self.open(price_low, price_high, initial_amount_b, pool, number).boxed()
+    }
+
+    fn open_positions_atomic<'a>(
+        &'a self,
+        legs: &'a [LegRequest],
+        pool: &'a PoolConfig,
+        _slippage: u16,
+    ) -> ExecFuture<'a, Option<Vec<OpenPositionResult>>> {
+        async move {
+            // виртуальные ноги открываются мгновенно; при ошибке откатываем уже открытые
+            let mut opened = Vec::with_capacity(legs.len());
+            for (i, leg) in legs.iter().enumerate() {
+                match self.open(leg.lower_price, leg.upper_price, leg.deposit, pool.clone(), i + 1).await {
+                    Ok(res) => opened.push(res),
+                    Err(e) => {
+                        for res in &opened {
+                            let _ = self.close(res.position_mint).await;
+                        }
+                        return Err(e);
+                    }
+                }
+            }
+            Ok(Some(opened))
+        }
+        .boxed()
+    }
+
+    fn close_position(&self, position_mint: Pubkey, _slippage: u16) -> ExecFuture<'_, ()> {
+        self.close(position_mint).boxed()
+    }
+
+    fn reopen_position<'a>(
+        &'a self,
+        _position_mint: Pubkey,
+        _price_low: f64,
+        _price_high: f64,
+        _deposit: f64,
+        _pool: &'a PoolConfig,
+        _slippage: u16,
+    ) -> ExecFuture<'a, Option<OpenPositionResult>> {
+        // виртуальным позициям бандл не нужен — закрытие и открытие и так мгновенные
+        async { Ok(None) }.boxed()
+    }
+
+    fn close_all(&self, _slippage: u16, pool: Option<Pubkey>) -> ExecFuture<'_, ()> {
+        self.close_all_virtual(pool).boxed()
+    }
+
+    fn list_positions(&self, pool: Option<Pubkey>) -> ExecFuture<'_, Vec<OwnedPosition>> {
+        async move { Ok(self.list(pool).await) }.boxed()
+    }
+
+    fn position_info<'a>(
+        &'a self,
+        pool_cfg: &'a PoolConfig,
+        pos: &'a OwnedPosition,
+    ) -> ExecFuture<'a, PoolPositionInfo> {
+        self.info(pool_cfg, pos).boxed()
+    }
+
+    fn swap<'a>(&'a self, sell_mint: &'a str, buy_mint: &'a str, amount_in: f64) -> ExecFuture<'a, SwapResult> {
+        self.swap_virtual(sell_mint, buy_mint, amount_in).boxed()
+    }
+
+    fn increase_liquidity<'a>(
+        &'a self,
+        position_mint: Pubkey,
+        usd_budget: f64,
+        _pool: &'a PoolConfig,
+        _slippage: u16,
+    ) -> ExecFuture<'a, ()> {
+        self.increase(position_mint, usd_budget).boxed()
+    }
+
+    fn decrease_liquidity(&self, position_mint: Pubkey, pct: f64, _slippage: u16) -> ExecFuture<'_, ()> {
+        self.decrease(position_mint, pct).boxed()
+    }
+
+    fn harvest<'a>(&'a self, position_mint: Pubkey, pool: &'a PoolConfig) -> ExecFuture<'a, HarvestSummary> {
+        self.collect(position_mint, pool).boxed()
+    }
+
+    fn balance<'a>(&'a self, mint: &'a str, _dec: u8) -> ExecFuture<'a, f64> {
+        async move { Ok(self.state.lock().await.bal(mint)) }.boxed()
+    }
+
+    fn wallet_balance(&self) -> ExecFuture<'_, WalletBalanceInfo> {
+        self.wallet().boxed()
+    }
+}
+
+// ───── 3. helpers ──────────────────────────────────────────────────────
+fn sqrt_at_tick(tick: i32) -> f64 {
+    1.0001_f64.powf(tick as f64 / 2.0)
+}
+
+async fn load_whirlpool(pk: &Pubkey) -> Result<Whirlpool> {
+    let rpc  = utils::utils::init_rpc();
+    let acct = safe_get_account(&rpc, pk).await?;
+    Ok(Whirlpool::from_bytes(&acct.data)?)
+}
+
+/// Цена токена в USD для виртуальных свапов:
+/// стейблы — 1, SOL — Jupiter, прочее — через SOL-пул из реестра.
+async fn usd_price(mint: &str) -> Result<f64> {
+    if mint == USDC || mint == USDT {
+        return Ok(1.0);
+    }
+    let sol_usd = get_sol_price_usd(WSOL, true).await?;
+    if mint == WSOL {
+        return Ok(sol_usd);
+    }
+    let cfg = pool_registry::enabled_pools()
+        .into_iter()
+        .find(|c| c.mint_a == WSOL && c.mint_b == mint)
+        .ok_or_else(|| anyhow!("paper: нет пула для оценки {mint}"))?;
+    let whirl = load_whirlpool(&Pubkey::from_str(&cfg.pool_address)?).await?;
+    let price_ab = sqrt_price_to_price(U128::from(whirl.sqrt_price), cfg.decimal_a as u8, cfg.decimal_b as u8);
+    Ok(sol_usd / price_ab.max(1e-12))
+}
@@ -1,7 +1,7 @@
 // src/utils.rs
 
 
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::{str::FromStr, result::Result as StdResult};
 
 use anyhow::{anyhow, Result};
@@ -17,30 +17,47 @@ use crate::utils::safe_get_account;
 use solana_sdk::{
     pubkey::Pubkey,
     signature::{read_keypair_file, Keypair},
-    transaction::Transaction,
 };
-use solana_sdk::signature::Signer;
-use orca_whirlpools_client::{
-    get_tick_array_address, Position, UpdateFeesAndRewardsBuilder, Whirlpool,
+use orca_whirlpools_client::{get_position_address, get_tick_array_address, Position, Tick, TickArray, Whirlpool};
+use orca_whirlpools_core::{
+    collect_fees_quote, collect_rewards_quote, decrease_liquidity_quote, get_tick_array_start_tick_index,
+    get_tick_index_in_array, CollectFeesQuote, CollectRewardsQuote, TransferFee,
 };
 use crate::database::positions::{update_position_fields, find_position_index_by_nft};
-use crate::types::PoolConfig;
+use crate::dex_services::token;
+use crate::types::{PendingReward, PoolConfig};
+use crate::utils::get_token_price_usd;
+use spl_token::solana_program::program_pack::Pack;
+use spl_token::state::Mint;
 use crate::{
-    params::{KEYPAIR_FILENAME, RPC_URL},
+    params::{KEYPAIR_FILENAME, RPC_URL, USDC, USDT},
     types::PoolPositionInfo,
 };
 
-const Q64: f64 = 1.8446744073709552e19;
-
-/// Цена из тика
-fn tick_price(tick: i32) -> f64 {
-    1.0001_f64.powi(tick)
+/// Точный состав ликвидности `liquidity` в диапазоне `[tick_lower; tick_upper)`,
+/// в атомах «на руки»: квота `decrease_liquidity_quote` (U128, округление как
+/// в программе) за вычетом transfer-fee. Математика тиков у Whirlpool и
+/// Raydium CLMM общая, поэтому функция годится для обеих площадок.
+pub fn exact_amounts(
+    liquidity: u128,
+    sqrt_price: u128,
+    tick_lower: i32,
+    tick_upper: i32,
+    fee_a: Option<TransferFee>,
+    fee_b: Option<TransferFee>,
+) -> Result<(u64, u64)> {
+    if liquidity == 0 {
+        return Ok((0, 0));
+    }
+    let q = decrease_liquidity_quote(
+        U128::from(liquidity), 0, U128::from(sqrt_price), tick_lower, tick_upper, fee_a, fee_b,
+    )
+    .map_err(|e| anyhow!("decrease_liquidity_quote: {e:?}"))?;
+    Ok((q.token_est_a, q.token_est_b))
 }
 
-
-
-/// Рассчёт объёмов токенов внутри текущего диапазона
-fn compute_amounts(liquidity: f64, sqrt_p: f64, sqrt_l: f64, sqrt_u: f64) -> (f64, f64) {
+/// Рассчёт объёмов токенов внутри текущего диапазона (f64, для прикидок и бэктеста)
+pub fn compute_amounts(liquidity: f64, sqrt_p: f64, sqrt_l: f64, sqrt_u: f64) -> (f64, f64) {
     if sqrt_p <= sqrt_l {
         let a = liquidity * (sqrt_u - sqrt_l) / (sqrt_l * sqrt_u);
         (a, 0.0)
@@ -52,6 +69,18 @@ fn compute_amounts(liquidity: f64, sqrt_p: f64, sqrt_l: f64, sqrt_u: f64) -> (f6
         (0.0, liquidity * (sqrt_u - sqrt_l))
     }
 }
+
+/// Ликвидность по депозиту — по тем же правилам, что и при открытии позиции:
+/// диапазон выше рынка — только A, ниже рынка — только B, пересекает — по B.
+pub fn liquidity_for_deposit(dep_a: f64, dep_b: f64, sqrt_p: f64, sqrt_l: f64, sqrt_u: f64) -> f64 {
+    if sqrt_p <= sqrt_l {
+        dep_a * sqrt_l * sqrt_u / (sqrt_u - sqrt_l)
+    } else if sqrt_p >= sqrt_u {
+        dep_b / (sqrt_u - sqrt_l)
+    } else {
+        dep_b / (sqrt_p - sqrt_l)
+    }
+}
 pub async fn fetch_pool_position_info(
     pool_cfg: &PoolConfig,
     position_address: Option<&str>,
@@ -71,8 +100,8 @@ pub async fn fetch_pool_position_info(
     // базовая цена «B per A»
     let price_ab = sqrt_price_to_price(U128::from(whirl.sqrt_price), dec_a, dec_b);
 
-    // для отображения ‒ инвертируем всё, кроме SOL/USDC
-    let disp_invert   = pool_cfg.name != "SOL/USDC";
+    // для отображения ‒ инвертируем всё, кроме пулов SOL/…
+    let disp_invert   = !pool_cfg.name.starts_with("SOL/");
     let display_price = if disp_invert { 1.0 / price_ab } else { price_ab };
 
     //------------------------------------------------------------------//
@@ -94,7 +123,9 @@ pub async fn fetch_pool_position_info(
         upper_price:   display_price,
         pct_down:      0.0,
         pct_up:        0.0,
-        index: 0
+        index: 0,
+        rewards:       Vec::new(),
+        rewards_usd:   0.0,
     };
 
     //------------------------------------------------------------------//
@@ -103,24 +134,28 @@ pub async fn fetch_pool_position_info(
     if let Some(addr_str) = position_address {
         let pos_pk = Pubkey::from_str(addr_str)?;
 
-        // 2.1 принудительно обновляем fee-счётчики (иначе они «старые»)
-        refresh_fees(&rpc, whirl_pk, pos_pk, whirl.tick_spacing as i32).await?;
-
-        // 2.2 читаем аккаунт ещё раз – поля fee_owed_* уже свежие
+        // 2.1 позиция; fee_owed_* в ней обновляются только транзакцией,
+        //     поэтому накопленное считаем off-chain по tick-array-ям
         let pos_acc = safe_get_account(&rpc, &pos_pk).await?;
         let pos     = Position::from_bytes(&pos_acc.data)?;
+        let (fees, rewards) = pending_fees_offchain(&rpc, whirl_pk, &whirl, &pos).await?;
 
-        let mint_str = pos.position_mint.to_string();
+        // у позиции из бандла ключ в БД — её адрес, а не общий mint бандла
+        let mint_str = if get_position_address(&pos.position_mint)?.0 == pos_pk {
+            pos.position_mint.to_string()
+        } else {
+            pos_pk.to_string()
+        };
         let index_pos = find_position_index_by_nft(&mint_str).await?.unwrap_or_default();
         info.index = index_pos;
         //---------------------- комиссии ------------------------------//
-        info.pending_a = pos.fee_owed_a as f64 / 10_f64.powi(dec_a as i32);
-        info.pending_b = pos.fee_owed_b as f64 / 10_f64.powi(dec_b as i32);
+        info.pending_a = fees.fee_owed_a as f64 / 10_f64.powi(dec_a as i32);
+        info.pending_b = fees.fee_owed_b as f64 / 10_f64.powi(dec_b as i32);
 
         // переводим **в доллары** без лишних допущений
         let sol_usd  = get_sol_price_usd().await.unwrap_or(0.0);
-        let tokb_usd = if pool_cfg.name == "SOL/USDC" {
-            1.0                            // B = USDC
+        let tokb_usd = if pool_cfg.mint_b == USDC || pool_cfg.mint_b == USDT {
+            1.0                            // B = USDC / USDT
         } else {
             sol_usd / price_ab.max(1e-12)  // $/B  = $/SOL / (B/SOL)
         };
@@ -129,15 +164,31 @@ pub async fn fetch_pool_position_info(
         let pending_b_usd  = info.pending_b * tokb_usd;
         info.sum           = info.pending_a_usd + pending_b_usd;
 
+        //---------------------- награды -------------------------------//
+        for (i, mint, dec) in whirlpool_reward_mints(&rpc, &whirl).await? {
+            let owed = rewards.rewards[i].rewards_owed;
+            if owed == 0 {
+                continue;
+            }
+            let mint   = mint.to_string();
+            let amount = owed as f64 / 10_f64.powi(dec as i32);
+            let usd    = match get_token_price_usd(&mint).await {
+                Ok(px) => amount * px,
+                Err(e) => { log::warn!("reward {mint}: нет цены ({e})"); 0.0 }
+            };
+            info.rewards_usd += usd;
+            info.rewards.push(PendingReward { mint, amount, usd });
+        }
+
         //---------------------- состав позиции ------------------------//
-        let sqrt_p = whirl.sqrt_price as f64 / Q64;
-        let sqrt_l = tick_price(pos.tick_lower_index / 2);
-        let sqrt_u = tick_price(pos.tick_upper_index / 2);
-        let (raw_a, raw_b) =
-            compute_amounts(pos.liquidity as f64, sqrt_p, sqrt_l, sqrt_u);
-
-        info.amount_a = raw_a / 10_f64.powi(dec_a as i32);
-        info.amount_b = raw_b / 10_f64.powi(dec_b as i32);
+        let fee_a = token::mint_info(&rpc, &whirl.token_mint_a).await?.orca_fee();
+        let fee_b = token::mint_info(&rpc, &whirl.token_mint_b).await?.orca_fee();
+        let (raw_a, raw_b) = exact_amounts(
+            pos.liquidity, whirl.sqrt_price, pos.tick_lower_index, pos.tick_upper_index, fee_a, fee_b,
+        )?;
+
+        info.amount_a = raw_a as f64 / 10_f64.powi(dec_a as i32);
+        info.amount_b = raw_b as f64 / 10_f64.powi(dec_b as i32);
         info.value_a  = info.amount_a * sol_usd;   // в USD
         info.value_b  = info.amount_b * tokb_usd;
         let total_usd = info.value_a + info.value_b;
@@ -187,46 +238,79 @@ pub async fn fetch_pool_position_info(
 }
 
 
-pub async fn refresh_fees(
+/// Инициализированные `reward_infos` пула: (индекс, mint, decimals).
+pub async fn whirlpool_reward_mints(rpc: &RpcClient, whirl: &Whirlpool) -> anyhow::Result<Vec<(usize, Pubkey, u8)>> {
+    let active: Vec<(usize, Pubkey)> = whirl.reward_infos.iter()
+        .enumerate()
+        .filter(|(_, r)| r.mint != Pubkey::default())
+        .map(|(i, r)| (i, r.mint))
+        .collect();
+    if active.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mints: Vec<Pubkey> = active.iter().map(|(_, m)| *m).collect();
+    let accs = rpc.get_multiple_accounts(&mints).await
+        .map_err(|e| anyhow!("get_multiple_accounts failed: {e}"))?;
+
+    let mut out = Vec::with_capacity(active.len());
+    for ((i, mint), acc) in active.into_iter().zip(accs) {
+        let acc = acc.ok_or_else(|| anyhow!("reward mint {mint} не найден"))?;
+        // базовая часть mint одинакова у Token и Token-2022
+        let dec = Mint::unpack(&acc.data[..Mint::LEN])?.decimals;
+        out.push((i, mint, dec));
+    }
+    Ok(out)
+}
+
+/// Комиссии и награды позиции без транзакции `UpdateFeesAndRewards`:
+/// считаем off-chain из `fee_growth_global` пула, `fee_growth_outside` граничных
+/// тиков и чекпоинтов позиции — теми же формулами, что и программа.
+pub async fn pending_fees_offchain(
     rpc: &RpcClient,
-    whirlpool: Pubkey,
-    position:  Pubkey,
-    tick_spacing: i32,
-) -> anyhow::Result<()> {
-    const TICK_ARRAY_SIZE: i32 = 88;   // из SDK
-
-    let payer = utils::load_wallet()?;          // ваш helper
-    let pos_acc = rpc.get_account(&position).await
-    .map_err(|e| anyhow!("get_account failed: {e}"))?;
-    let pos     = Position::from_bytes(&pos_acc.data)?;
-
-    let span   = tick_spacing * TICK_ARRAY_SIZE;
-    let start_l = pos.tick_lower_index.div_euclid(span) * span;
-    let start_u = pos.tick_upper_index.div_euclid(span) * span;
-
-    let (ta_l, _) = get_tick_array_address(&whirlpool, start_l)?;
-    let (ta_u, _) = get_tick_array_address(&whirlpool, start_u)?;
-
-    let ix = UpdateFeesAndRewardsBuilder::new()
-        .whirlpool(whirlpool)
-        .position(position)
-        .tick_array_lower(ta_l)
-        .tick_array_upper(ta_u)
-        .instruction();
-
-    let recent = rpc.get_latest_blockhash().await
-    .map_err(|e| anyhow!("get_latest_blockhash failed: {e}"))?;
-
-    let tx     = Transaction::new_signed_with_payer(
-        &[ix],
-        Some(&payer.pubkey()),
-        &[&payer],
-        recent,
-    );
-
-    rpc.send_and_confirm_transaction(&tx).await
-    .map_err(|e| anyhow!("send_and_confirm_transaction failed: {e}"))?;
-    Ok(())
+    whirl_pk: Pubkey,
+    whirl: &Whirlpool,
+    pos: &Position,
+) -> anyhow::Result<(CollectFeesQuote, CollectRewardsQuote)> {
+    let spacing = whirl.tick_spacing;
+    let start_l = get_tick_array_start_tick_index(pos.tick_lower_index, spacing);
+    let start_u = get_tick_array_start_tick_index(pos.tick_upper_index, spacing);
+    let (ta_l, _) = get_tick_array_address(&whirl_pk, start_l)?;
+    let (ta_u, _) = get_tick_array_address(&whirl_pk, start_u)?;
+
+    // оба tick-array одним запросом
+    let accs = rpc.get_multiple_accounts(&[ta_l, ta_u]).await
+        .map_err(|e| anyhow!("get_multiple_accounts failed: {e}"))?;
+    let tick_at = |i: usize, start: i32, tick: i32| -> anyhow::Result<Tick> {
+        let acc = accs.get(i).cloned().flatten()
+            .ok_or_else(|| anyhow!("tick array {start} не инициализирован"))?;
+        let array = TickArray::from_bytes(&acc.data)?;
+        let idx = get_tick_index_in_array(tick, start, spacing)
+            .map_err(|e| anyhow!("get_tick_index_in_array: {e:?}"))?;
+        Ok(array.ticks[idx as usize].clone())
+    };
+    let lower = tick_at(0, start_l, pos.tick_lower_index)?;
+    let upper = tick_at(1, start_u, pos.tick_upper_index)?;
+
+    // суммы «на руки»: за вычетом transfer-fee Token-2022
+    let fee_a = token::mint_info(rpc, &whirl.token_mint_a).await?.orca_fee();
+    let fee_b = token::mint_info(rpc, &whirl.token_mint_b).await?.orca_fee();
+    let fees = collect_fees_quote(
+        whirl.clone().into(), pos.clone().into(), lower.clone().into(), upper.clone().into(), fee_a, fee_b,
+    ).map_err(|e| anyhow!("collect_fees_quote: {e:?}"))?;
+
+    let mut reward_fees = [None; 3];
+    for (i, r) in whirl.reward_infos.iter().enumerate() {
+        if r.mint != Pubkey::default() {
+            reward_fees[i] = token::mint_info(rpc, &r.mint).await?.orca_fee();
+        }
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let rewards = collect_rewards_quote(
+        whirl.clone().into(), pos.clone().into(), lower.into(), upper.into(), now,
+        reward_fees[0], reward_fees[1], reward_fees[2],
+    ).map_err(|e| anyhow!("collect_rewards_quote: {e:?}"))?;
+
+    Ok((fees, rewards))
 }
 
 pub async fn get_sol_price_usd() -> anyhow::Result<f64> {
@@ -254,4 +338,82 @@ pub async fn get_sol_price_usd() -> anyhow::Result<f64> {
     let resp: CbResp = Client::new().get(url_cb).send().await?.json().await?;
     let price: f64 = resp.data.amount.parse()?;
     Ok(price)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use orca_whirlpools_core::tick_index_to_sqrt_price;
+
+    const Q64: f64 = 18_446_744_073_709_551_616.0;
+    /// допуск сверки: относительный и в атомах (точный расчёт округляет вниз)
+    const REL_TOL:   f64 = 1e-6;
+    const ATOMS_TOL: f64 = 2.0;
+    const LIQUIDITY: u128 = 1_000_000_000_000;
+
+    fn sqrt_f64(tick: i32) -> f64 {
+        1.0001_f64.powf(tick as f64 / 2.0)
+    }
+
+    /// Точный расчёт против прежней f64-формулы (с поправкой на transfer-fee).
+    fn assert_matches(price_tick: i32, tick_lower: i32, tick_upper: i32, fee: Option<TransferFee>) {
+        let sqrt_price: u128 = tick_index_to_sqrt_price(price_tick).into();
+        let (a, b) = exact_amounts(LIQUIDITY, sqrt_price, tick_lower, tick_upper, fee, fee).unwrap();
+        let (raw_a, raw_b) = compute_amounts(
+            LIQUIDITY as f64,
+            sqrt_price as f64 / Q64,
+            sqrt_f64(tick_lower),
+            sqrt_f64(tick_upper),
+        );
+        let net = |raw: f64| match fee {
+            Some(f) => raw - (raw * f.fee_bps as f64 / 10_000.0).min(f.max_fee as f64),
+            None    => raw,
+        };
+        for (name, approx, exact) in [("A", net(raw_a), a), ("B", net(raw_b), b)] {
+            let diff = (approx - exact as f64).abs();
+            assert!(
+                diff <= ATOMS_TOL || diff <= approx.abs() * REL_TOL,
+                "{name}: точно {exact}, f64 {approx:.0} (Δ {diff:.0}), цена {price_tick}, [{tick_lower}; {tick_upper})"
+            );
+        }
+    }
+
+    #[test]
+    fn in_range_odd_negative_ticks() {
+        assert_matches(-17_533, -18_001, -16_999, None);
+        assert_matches(-3, -887, 443, None);
+    }
+
+    #[test]
+    fn in_range_positive_ticks() {
+        assert_matches(20_117, 19_441, 21_013, None);
+    }
+
+    #[test]
+    fn below_range_is_all_a() {
+        assert_matches(-25_001, -18_001, -16_999, None);
+        let sqrt_price: u128 = tick_index_to_sqrt_price(-25_001).into();
+        let (_, b) = exact_amounts(LIQUIDITY, sqrt_price, -18_001, -16_999, None, None).unwrap();
+        assert_eq!(b, 0);
+    }
+
+    #[test]
+    fn above_range_is_all_b() {
+        assert_matches(-9_999, -18_001, -16_999, None);
+        let sqrt_price: u128 = tick_index_to_sqrt_price(-9_999).into();
+        let (a, _) = exact_amounts(LIQUIDITY, sqrt_price, -18_001, -16_999, None, None).unwrap();
+        assert_eq!(a, 0);
+    }
+
+    #[test]
+    fn transfer_fee_is_deducted() {
+        let fee = Some(TransferFee { fee_bps: 150, max_fee: u64::MAX });
+        assert_matches(-17_533, -18_001, -16_999, fee);
+    }
+
+    #[test]
+    fn zero_liquidity_is_empty() {
+        let sqrt_price: u128 = tick_index_to_sqrt_price(0).into();
+        assert_eq!(exact_amounts(0, sqrt_price, -11, 13, None, None).unwrap(), (0, 0));
+    }
+}
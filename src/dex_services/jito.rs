@@ -0,0 +1,762 @@
+// src/dex_services/jito.rs
+//
+// Отправка нескольких транзакций одним Jito-бандлом: порядок сохраняется,
+// ложатся все в одном слоте или ни одна, а между ними никто не встанет.
+// Чаевые (JITO_TIP_LAMPORTS) — перевод на случайный tip-аккаунт в последней
+// транзакции бандла. Приоритетная комиссия — как у одиночных транзакций
+// (priority.rs), лимит CU — по simulateBundle (RPC Jito, JITO_RPC_URL; без
+// него — симуляцией каждой транзакции отдельно, где она проходит сама).
+// Симуляция даёт и превью балансов каждой транзакции: оно сверяется с
+// ожиданием по квоте этой транзакции (preview.rs), расхождение — бандл не
+// шлём. Статус бандла опрашиваем до JITO_STATUS_TIMEOUT_SEC.
+// Не подтвердился — ждём, пока истечёт его blockhash (до тех пор он ещё
+// может лечь), и только потом собираем транзакции заново, без чаевых и со
+// свежим blockhash, и шлём по одной. Срочный бандл (выход) перед этим
+// повторяется с повышенной комиссией, как и срочная одиночная отправка.
+//
+// Включается JITO=1. Адрес block engine — JITO_URL (по умолчанию mainnet).
+// Настройки читаются из окружения один раз (`config()`); функции бандлов
+// получают их явно — тесты подставляют свои, с адресом локальной заглушки.
+
+use std::env;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as b64, Engine as _};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    instruction::Instruction,
+    message::AddressLookupTableAccount,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    system_instruction,
+    transaction::VersionedTransaction,
+};
+use std::str::FromStr;
+use tokio::sync::RwLock;
+use tokio::time::{sleep, Duration, Instant};
+
+use crate::database::tx_costs;
+use crate::dex_services::net::http_client;
+use crate::dex_services::preview::{self, BalancePreview, Expect};
+use crate::dex_services::swap::{build_swap_tx, preview_swap, swap_expects};
+use crate::dex_services::{priority, token};
+use crate::utils::utils::{self, Urgency};
+use crate::utils::op;
+
+const DEFAULT_URL: &str = "https://mainnet.block-engine.jito.wtf/api/v1";
+/// чаевые по умолчанию, лампорты
+const DEFAULT_TIP_LAMPORTS: u64 = 10_000;
+/// сколько ждём, пока бандл ляжет, сек
+const DEFAULT_STATUS_TIMEOUT_SEC: u64 = 30;
+const STATUS_POLL: Duration = Duration::from_secs(2);
+/// транзакций в одном бандле
+pub const MAX_BUNDLE_TXS: usize = 5;
+/// лимит CU транзакции бандла, если её не удалось просимулировать
+const BUNDLE_TX_CU: u32 = 400_000;
+/// лимит CU отдельной транзакции с чаевыми
+const TIP_TX_CU: u32 = 10_000;
+
+static TIP_ACCOUNTS: Lazy<RwLock<Vec<Pubkey>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+/// Настройки бандлов.
+#[derive(Debug, Clone)]
+pub struct JitoConfig {
+    /// JITO=1
+    pub enabled:        bool,
+    /// адрес block engine (JITO_URL), без `/` в конце
+    pub url:            String,
+    /// JITO_TIP_LAMPORTS
+    pub tip_lamports:   u64,
+    /// RPC с simulateBundle (JITO_RPC_URL); `None` — пробуем основной
+    pub rpc_url:        Option<String>,
+    /// сколько ждём, пока бандл ляжет (JITO_STATUS_TIMEOUT_SEC)
+    pub status_timeout: Duration,
+}
+
+impl JitoConfig {
+    pub fn from_env() -> Self {
+        JitoConfig {
+            enabled:        env::var("JITO").map(|v| v == "1" || v.eq_ignore_ascii_case("true")).unwrap_or(false),
+            url:            env::var("JITO_URL").unwrap_or_else(|_| DEFAULT_URL.to_string()).trim_end_matches('/').to_string(),
+            tip_lamports:   env::var("JITO_TIP_LAMPORTS").ok().and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_TIP_LAMPORTS),
+            rpc_url:        env::var("JITO_RPC_URL").ok(),
+            status_timeout: Duration::from_secs(
+                env::var("JITO_STATUS_TIMEOUT_SEC").ok().and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_STATUS_TIMEOUT_SEC),
+            ),
+        }
+    }
+}
+
+static CONFIG: Lazy<JitoConfig> = Lazy::new(JitoConfig::from_env);
+
+/// Настройки из окружения (читаются при первом обращении).
+pub fn config() -> &'static JitoConfig {
+    &CONFIG
+}
+
+pub fn enabled() -> bool {
+    config().enabled
+}
+
+/// Транзакция бандла.
+pub enum BundleTx<'a> {
+    /// инструкции и подписанты (первый — плательщик): соберём v0 и подпишем
+    /// сами; `expect` — изменение балансов по квоте, сверяется с симуляцией
+    Instructions { instructions: Vec<Instruction>, signers: Vec<&'a Keypair>, expect: Vec<Expect> },
+    /// свап Jupiter: транзакцию собирает и подписывает Jupiter, поэтому
+    /// строим её при каждой сборке заново — со свежими квотой и blockhash
+    Swap { in_mint: Pubkey, out_mint: Pubkey, amount_atoms: u64, slippage_bps: Option<u16> },
+}
+
+/// Подписанный бандл и до какой высоты блока жив его blockhash.
+pub struct SignedBundle {
+    pub txs:                     Vec<VersionedTransaction>,
+    pub last_valid_block_height: u64,
+}
+
+/// Бандл, который лёг.
+#[derive(Debug, Clone)]
+pub struct BundleOutcome {
+    pub bundle_id:   String,
+    pub landed_slot: u64,
+    pub signatures:  Vec<Signature>,
+}
+
+/// JSON-RPC вызов block engine.
+async fn call(cfg: &JitoConfig, method: &str, params: Value) -> Result<Value> {
+    let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+    let resp: Value = http_client()
+        .post(format!("{}/bundles", cfg.url))
+        .json(&body)
+        .send()
+        .await
+        .with_context(|| format!("jito {method}"))?
+        .json()
+        .await
+        .with_context(|| format!("jito {method}: bad response"))?;
+    if let Some(err) = resp.get("error") {
+        bail!("jito {method}: {err}");
+    }
+    Ok(resp["result"].clone())
+}
+
+/// Tip-аккаунты block engine (кэшируются на время процесса).
+async fn tip_accounts(cfg: &JitoConfig) -> Result<Vec<Pubkey>> {
+    {
+        let cached = TIP_ACCOUNTS.read().await;
+        if !cached.is_empty() {
+            return Ok(cached.clone());
+        }
+    }
+    let list = call(cfg, "getTipAccounts", json!([])).await?
+        .as_array()
+        .ok_or_else(|| anyhow!("jito getTipAccounts: ожидался массив"))?
+        .iter()
+        .filter_map(|v| v.as_str().and_then(|s| Pubkey::from_str(s).ok()))
+        .collect::<Vec<_>>();
+    if list.is_empty() {
+        bail!("jito getTipAccounts: пустой список");
+    }
+    *TIP_ACCOUNTS.write().await = list.clone();
+    Ok(list)
+}
+
+/// Перевод чаевых на случайный tip-аккаунт.
+pub async fn tip_ix(cfg: &JitoConfig, payer: &Pubkey) -> Result<Instruction> {
+    let accounts = tip_accounts(cfg).await?;
+    let to = accounts[rand::random_range(0..accounts.len())];
+    Ok(system_instruction::transfer(payer, &to, cfg.tip_lamports))
+}
+
+/// Отправить подписанные транзакции бандлом и дождаться, пока он ляжет.
+pub async fn send_bundle(cfg: &JitoConfig, txs: &[VersionedTransaction]) -> Result<BundleOutcome> {
+    if txs.is_empty() || txs.len() > MAX_BUNDLE_TXS {
+        bail!("в бандле должно быть от 1 до {MAX_BUNDLE_TXS} транзакций, а не {}", txs.len());
+    }
+    let encoded = txs.iter()
+        .map(|tx| Ok(b64.encode(bincode::serialize(tx)?)))
+        .collect::<Result<Vec<_>>>()?;
+    let bundle_id = call(cfg, "sendBundle", json!([encoded, { "encoding": "base64" }])).await?
+        .as_str()
+        .ok_or_else(|| anyhow!("jito sendBundle: нет id бандла"))?
+        .to_string();
+    log::info!("jito: бандл {bundle_id} ({} tx) отправлен", txs.len());
+
+    let deadline = Instant::now() + cfg.status_timeout;
+    loop {
+        sleep(STATUS_POLL).await;
+        let res = call(cfg, "getInflightBundleStatuses", json!([[bundle_id]])).await?;
+        let st  = &res["value"][0];
+        match st["status"].as_str().unwrap_or("Invalid") {
+            "Landed" => {
+                return Ok(BundleOutcome {
+                    bundle_id,
+                    landed_slot: st["landed_slot"].as_u64().unwrap_or_default(),
+                    signatures:  txs.iter().map(|tx| tx.signatures[0]).collect(),
+                });
+            }
+            "Failed" => bail!("jito: бандл {bundle_id} отклонён"),
+            // Pending, а Invalid — бандл ещё не дошёл до block engine
+            _ if Instant::now() >= deadline => {
+                bail!("jito: бандл {bundle_id} не лёг за {} с", cfg.status_timeout.as_secs())
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Отправить бандл и дождаться, пока он ляжет. Не подтвердился — ждём, пока
+/// истечёт его blockhash, и смотрим ещё раз: ошибка значит, что бандл точно
+/// не лёг и его транзакции можно собирать заново.
+pub async fn land_bundle(cfg: &JitoConfig, rpc: &RpcClient, bundle: &SignedBundle) -> Result<BundleOutcome> {
+    let err = match send_bundle(cfg, &bundle.txs).await {
+        Ok(outcome) => return Ok(outcome),
+        Err(e) => e,
+    };
+    log::warn!("{err:#} — жду, пока истечёт blockhash бандла");
+    while rpc.get_block_height().await.map_err(op("get_block_height"))? <= bundle.last_valid_block_height {
+        sleep(STATUS_POLL).await;
+    }
+    // бандл атомарен: легла первая транзакция — легли все
+    let first = bundle.txs[0].signatures[0];
+    let status = rpc.get_signature_statuses(&[first]).await
+        .map_err(op("get_signature_statuses"))?
+        .value
+        .remove(0);
+    match status {
+        Some(st) if st.err.is_none() => {
+            log::info!("jito: бандл всё же лёг в слоте {}", st.slot);
+            Ok(BundleOutcome {
+                bundle_id:   String::new(),
+                landed_slot: st.slot,
+                signatures:  bundle.txs.iter().map(|tx| tx.signatures[0]).collect(),
+            })
+        }
+        _ => Err(err),
+    }
+}
+
+/// Записать издержки легшего бандла: транзакции и чаевые (они в последней).
+pub async fn track_landed(cfg: &JitoConfig, outcome: &BundleOutcome) {
+    for sig in &outcome.signatures {
+        tx_costs::track(*sig).await;
+    }
+    if let Some(last) = outcome.signatures.last() {
+        tx_costs::track_tip(*last, cfg.tip_lamports).await;
+    }
+}
+
+/// Подписать транзакции бандла одним blockhash; чаевые — в последней
+/// транзакции из инструкций (если последней идёт свап — отдельной).
+/// Цена CU — по writable-аккаунтам каждой транзакции, на попытке `attempt`
+/// повышенная; лимит CU — по симуляции бандла, по ней же превью каждой
+/// транзакции сверяется с её ожиданием. Свапы Jupiter ставят комиссию и
+/// лимит сами, ожидание у них — по квоте.
+pub async fn sign_all(
+    cfg: &JitoConfig,
+    rpc: &RpcClient,
+    txs: &[BundleTx<'_>],
+    tables: &[AddressLookupTableAccount],
+    attempt: usize,
+) -> Result<SignedBundle> {
+    let (recent, last_valid_block_height) = rpc
+        .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
+        .await
+        .map_err(op("get_latest_blockhash"))?;
+    let payer: &Keypair = txs.iter()
+        .find_map(|t| match t {
+            BundleTx::Instructions { signers, .. } => signers.first().copied(),
+            BundleTx::Swap { .. } => None,
+        })
+        .ok_or_else(|| anyhow!("в бандле нет транзакции с плательщиком"))?;
+    let tip = tip_ix(cfg, &payer.pubkey()).await?;
+    let tip_separately = !matches!(txs.last(), Some(BundleTx::Instructions { .. }));
+
+    // ───── 1. Цены CU, ожидания и свапы (собираем один раз: новая сборка — новая квота)
+    let mut prices  = Vec::with_capacity(txs.len());
+    let mut expects = Vec::with_capacity(txs.len());
+    let mut swaps   = Vec::with_capacity(txs.len());
+    for t in txs {
+        match t {
+            BundleTx::Instructions { instructions, expect, .. } => {
+                prices.push(priority::escalated(priority::fee_for(rpc, instructions).await, attempt));
+                expects.push(expect.clone());
+                swaps.push(None);
+            }
+            BundleTx::Swap { in_mint, out_mint, amount_atoms, slippage_bps } => {
+                let (tx, quote) = build_swap_tx(*in_mint, *out_mint, *amount_atoms, *slippage_bps).await?;
+                let input  = (*in_mint, token::mint_info(rpc, in_mint).await?.decimals);
+                let output = (*out_mint, token::mint_info(rpc, out_mint).await?.decimals);
+                prices.push(0);
+                expects.push(swap_expects(input, output, *amount_atoms, &quote));
+                swaps.push(Some(tx));
+            }
+        }
+    }
+    let compile = |limits: &[u32]| -> Result<Vec<VersionedTransaction>> {
+        let mut signed = Vec::with_capacity(txs.len() + 1);
+        for (i, t) in txs.iter().enumerate() {
+            signed.push(match (t, &swaps[i]) {
+                (_, Some(swap)) => swap.clone(),
+                (BundleTx::Instructions { instructions, signers, .. }, None) => {
+                    let mut ixs = priority::budget_ixs(limits[i], prices[i]);
+                    ixs.extend(instructions.iter().cloned());
+                    if i + 1 == txs.len() {
+                        ixs.push(tip.clone());
+                    }
+                    utils::compile_v0(&ixs, signers, tables, recent)?
+                }
+                (BundleTx::Swap { .. }, None) => unreachable!("свапы собраны выше"),
+            });
+        }
+        if tip_separately {
+            let mut ixs = priority::budget_ixs(TIP_TX_CU, 0);
+            ixs.push(tip.clone());
+            signed.push(utils::compile_v0(&ixs, &[payer], tables, recent)?);
+        }
+        Ok(signed)
+    };
+
+    // ───── 2. Симуляция бандла, без неё — каждой транзакции отдельно ──
+    let probe = compile(&vec![priority::MAX_CU; txs.len()])?;
+    let jito_rpc = cfg.rpc_url.clone().map(RpcClient::new);
+    let sims: Vec<Option<(u64, BalancePreview)>> = match preview::simulate_bundle(jito_rpc.as_ref().unwrap_or(rpc), &probe).await? {
+        Some(sim) => sim.units_consumed.into_iter().zip(sim.previews).map(Some).collect(),
+        None => {
+            // транзакция, которая опирается на предыдущие, сама не пройдёт
+            let mut out = Vec::with_capacity(probe.len());
+            for tx in &probe {
+                out.push(preview::simulate(rpc, tx).await.ok().map(|s| (s.units_consumed, s.preview)));
+            }
+            out
+        }
+    };
+
+    // ───── 3. Превью против ожиданий, лимиты CU ───────────────────────
+    for (i, sim) in sims.iter().enumerate() {
+        match sim {
+            Some((_, p)) => {
+                let expect = expects.get(i).cloned().unwrap_or_default();
+                preview::expecting(expect, async { preview::review(p) }).await?;
+            }
+            None => log::warn!("jito: транзакция #{} бандла не просимулирована — без превью", i + 1),
+        }
+    }
+    let limits: Vec<u32> = txs.iter()
+        .zip(&sims)
+        .map(|(_, sim)| sim.as_ref().map_or(BUNDLE_TX_CU, |(units, _)| priority::cu_limit(*units)))
+        .collect();
+
+    Ok(SignedBundle { txs: compile(&limits)?, last_valid_block_height })
+}
+
+/// Транзакции по одной, обычной отправкой: без чаевых, каждая со свежим
+/// blockhash и со сверкой превью со своим ожиданием.
+async fn send_one_by_one(
+    rpc: Arc<RpcClient>,
+    txs: &[BundleTx<'_>],
+    tables: &[AddressLookupTableAccount],
+    urgency: Urgency,
+) -> Result<Vec<Signature>> {
+    let mut sigs = Vec::with_capacity(txs.len());
+    for t in txs {
+        let outcome = match t {
+            BundleTx::Instructions { instructions, signers, expect } => {
+                let send = utils::send_tx(rpc.clone(), instructions.clone(), signers, tables, urgency);
+                preview::expecting(expect.clone(), send).await?
+            }
+            BundleTx::Swap { in_mint, out_mint, amount_atoms, slippage_bps } => {
+                let (tx, quote) = build_swap_tx(*in_mint, *out_mint, *amount_atoms, *slippage_bps).await?;
+                let input  = (*in_mint, token::mint_info(&rpc, in_mint).await?.decimals);
+                let output = (*out_mint, token::mint_info(&rpc, out_mint).await?.decimals);
+                preview_swap(&tx, input, output, *amount_atoms, &quote).await?;
+                utils::send_signed_and_confirm(rpc.clone(), &tx).await?
+            }
+        };
+        sigs.push(outcome.signature);
+    }
+    Ok(sigs)
+}
+
+/// Отправить транзакции одним бандлом; если бандлы выключены или бандл точно
+/// не лёг — те же транзакции по одной, собранные заново. Срочный бандл
+/// сначала повторяем с повышенной комиссией. Подписи — в порядке `txs`
+/// (без отдельной транзакции чаевых).
+pub async fn send_bundle_or_fallback(
+    cfg: &JitoConfig,
+    rpc: Arc<RpcClient>,
+    txs: Vec<BundleTx<'_>>,
+    tables: &[AddressLookupTableAccount],
+    urgency: Urgency,
+) -> Result<Vec<Signature>> {
+    if cfg.enabled {
+        let attempts = match urgency {
+            Urgency::Normal => 1,
+            Urgency::Urgent => priority::urgent_attempts(),
+        };
+        for attempt in 0..attempts {
+            let signed = sign_all(cfg, &rpc, &txs, tables, attempt).await?;
+            match land_bundle(cfg, &rpc, &signed).await {
+                Ok(outcome) => {
+                    log::info!("jito: бандл {} лёг в слоте {}", outcome.bundle_id, outcome.landed_slot);
+                    track_landed(cfg, &outcome).await;
+                    let mut sigs = outcome.signatures;
+                    sigs.truncate(txs.len());
+                    return Ok(sigs);
+                }
+                Err(e) if attempt + 1 < attempts => {
+                    log::warn!("{e:#} — повторяю бандл с повышенной комиссией ({}/{attempts})", attempt + 1);
+                }
+                Err(e) => log::warn!("{e:#} — отправляю транзакции по одной, без чаевых"),
+            }
+        }
+    }
+    send_one_by_one(rpc, &txs, tables, urgency).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::hash::Hash;
+    use std::sync::Mutex;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// настоящий tip-аккаунт mainnet — заглушка отдаёт только его
+    const TIP_ACCOUNT: &str = "96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5";
+    const LAST_VALID: u64 = 1_000;
+    /// столько CU тратит в симуляции бандла каждая транзакция
+    const SIM_UNITS: u64 = 50_000;
+
+    /// Заглушка block engine и Solana RPC на одном порту.
+    struct Stub {
+        /// ответы getInflightBundleStatuses по очереди, последний повторяется
+        statuses:     Vec<&'static str>,
+        block_height: u64,
+        /// лёг ли бандл, когда его blockhash истёк
+        landed_late:  bool,
+        blockhash:    Hash,
+        /// все вызовы: метод и параметры
+        calls:        Vec<(String, Value)>,
+    }
+
+    type Shared = Arc<Mutex<Stub>>;
+
+    fn stub(statuses: Vec<&'static str>) -> Shared {
+        Arc::new(Mutex::new(Stub {
+            statuses,
+            block_height: LAST_VALID + 1,
+            landed_late:  false,
+            blockhash:    Hash::new_unique(),
+            calls:        Vec::new(),
+        }))
+    }
+
+    fn respond(stub: &Shared, req: &Value) -> Value {
+        let method = req["method"].as_str().unwrap_or_default().to_string();
+        let n = req["params"][0].as_array().map_or(0, |a| a.len());
+        let mut st = stub.lock().unwrap();
+        st.calls.push((method.clone(), req["params"].clone()));
+        let ctx = json!({ "slot": 1 });
+        match method.as_str() {
+            "getTipAccounts" => json!([TIP_ACCOUNT]),
+            "sendBundle" => json!("bundle-1"),
+            "getInflightBundleStatuses" => {
+                let status = if st.statuses.len() > 1 { st.statuses.remove(0) } else { st.statuses[0] };
+                let landed = if status == "Landed" { json!(42) } else { Value::Null };
+                json!({ "context": ctx, "value": [{ "bundle_id": "bundle-1", "status": status, "landed_slot": landed }] })
+            }
+            "getLatestBlockhash" => json!({
+                "context": ctx,
+                "value": { "blockhash": st.blockhash.to_string(), "lastValidBlockHeight": LAST_VALID },
+            }),
+            "getBlockHeight" => json!(st.block_height),
+            "getSignatureStatuses" => {
+                let status = if st.landed_late {
+                    json!({ "slot": 40, "confirmations": null, "err": null, "status": { "Ok": null }, "confirmationStatus": "confirmed" })
+                } else {
+                    Value::Null
+                };
+                json!({ "context": ctx, "value": vec![status; n] })
+            }
+            "simulateBundle" => {
+                // аккаунтов в заглушке нет — балансы не меняются
+                let results: Vec<Value> = req["params"][1]["preExecutionAccountsConfigs"]
+                    .as_array()
+                    .map(|l| l.as_slice())
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|cfg| {
+                        let none = vec![Value::Null; cfg["addresses"].as_array().map_or(0, |a| a.len())];
+                        json!({ "err": null, "logs": [], "unitsConsumed": SIM_UNITS, "preExecutionAccounts": none, "postExecutionAccounts": none })
+                    })
+                    .collect();
+                json!({ "context": ctx, "value": { "summary": "succeeded", "transactionResults": results } })
+            }
+            "getRecentPrioritizationFees" => json!([]),
+            "getMultipleAccounts" => json!({ "context": ctx, "value": vec![Value::Null; n] }),
+            // обычная отправка в заглушке не проходит — дальше симуляции не идём
+            "simulateTransaction" => json!({
+                "context": ctx,
+                "value": { "err": "AccountNotFound", "logs": [], "accounts": null, "unitsConsumed": 0 },
+            }),
+            "getVersion" => json!({ "solana-core": "2.2.7", "feature-set": 0 }),
+            _ => Value::Null,
+        }
+    }
+
+    async fn read_body(sock: &mut TcpStream) -> Option<Vec<u8>> {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 4096];
+        loop {
+            let n = sock.read(&mut chunk).await.ok()?;
+            if n == 0 {
+                return None;
+            }
+            buf.extend_from_slice(&chunk[..n]);
+            let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") else { continue };
+            let head = String::from_utf8_lossy(&buf[..end]).to_ascii_lowercase();
+            let len = head.lines()
+                .find_map(|l| l.strip_prefix("content-length:"))
+                .and_then(|v| v.trim().parse::<usize>().ok())
+                .unwrap_or(0);
+            while buf.len() < end + 4 + len {
+                let n = sock.read(&mut chunk).await.ok()?;
+                if n == 0 {
+                    return None;
+                }
+                buf.extend_from_slice(&chunk[..n]);
+            }
+            return Some(buf[end + 4..end + 4 + len].to_vec());
+        }
+    }
+
+    /// Настройки бандлов, направленные на заглушку.
+    fn cfg(url: &str, enabled: bool) -> JitoConfig {
+        JitoConfig {
+            enabled,
+            url:            url.to_string(),
+            tip_lamports:   DEFAULT_TIP_LAMPORTS,
+            rpc_url:        None,
+            status_timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Поднять заглушку. Возвращает её адрес.
+    async fn serve(stub: Shared) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut sock, _)) = listener.accept().await {
+                let stub = stub.clone();
+                tokio::spawn(async move {
+                    let Some(body) = read_body(&mut sock).await else { return };
+                    let req: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+                    let resp = json!({ "jsonrpc": "2.0", "id": req["id"].clone(), "result": respond(&stub, &req) })
+                        .to_string();
+                    let head = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        resp.len()
+                    );
+                    let _ = sock.write_all(head.as_bytes()).await;
+                    let _ = sock.write_all(resp.as_bytes()).await;
+                    let _ = sock.shutdown().await;
+                });
+            }
+        });
+        url
+    }
+
+    fn transfer(payer: &Keypair) -> Instruction {
+        system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1)
+    }
+
+    fn signed_tx(payer: &Keypair) -> VersionedTransaction {
+        utils::compile_v0(&[transfer(payer)], &[payer], &[], Hash::new_unique()).unwrap()
+    }
+
+    fn decode(encoded: &Value) -> VersionedTransaction {
+        bincode::deserialize(&b64.decode(encoded.as_str().unwrap()).unwrap()).unwrap()
+    }
+
+    fn pays_tip(tx: &VersionedTransaction) -> bool {
+        tx.message.static_account_keys().contains(&Pubkey::from_str(TIP_ACCOUNT).unwrap())
+    }
+
+    /// Лимит и цена CU из ComputeBudget-инструкций транзакции.
+    fn budget(tx: &VersionedTransaction) -> (Option<u32>, Option<u64>) {
+        let keys = tx.message.static_account_keys();
+        let (mut limit, mut price) = (None, None);
+        for ix in tx.message.instructions() {
+            if keys[ix.program_id_index as usize] != solana_sdk::compute_budget::id() {
+                continue;
+            }
+            match ix.data[0] {
+                2 => limit = Some(u32::from_le_bytes(ix.data[1..5].try_into().unwrap())),
+                3 => price = Some(u64::from_le_bytes(ix.data[1..9].try_into().unwrap())),
+                _ => {}
+            }
+        }
+        (limit, price)
+    }
+
+    fn calls(stub: &Shared, method: &str) -> Vec<Value> {
+        stub.lock().unwrap().calls.iter().filter(|(m, _)| m == method).map(|(_, p)| p.clone()).collect()
+    }
+
+    #[tokio::test]
+    async fn send_bundle_request_shape() {
+        let st = stub(vec!["Landed"]);
+        let url = serve(st.clone()).await;
+        let payer = Keypair::new();
+        let txs = vec![signed_tx(&payer), signed_tx(&payer)];
+
+        let outcome = send_bundle(&cfg(&url, true), &txs).await.unwrap();
+        assert_eq!(outcome.bundle_id, "bundle-1");
+        assert_eq!(outcome.landed_slot, 42);
+        assert_eq!(outcome.signatures, vec![txs[0].signatures[0], txs[1].signatures[0]]);
+
+        let sent = calls(&st, "sendBundle");
+        assert_eq!(sent.len(), 1);
+        let encoded = sent[0][0].as_array().unwrap();
+        assert_eq!(encoded.len(), 2);
+        for (e, tx) in encoded.iter().zip(&txs) {
+            assert_eq!(decode(e).signatures[0], tx.signatures[0]);
+        }
+        assert_eq!(sent[0][1]["encoding"], "base64");
+        assert_eq!(calls(&st, "getInflightBundleStatuses")[0], json!([["bundle-1"]]));
+    }
+
+    #[tokio::test]
+    async fn send_bundle_waits_through_pending() {
+        let url = serve(stub(vec!["Invalid", "Pending", "Landed"])).await;
+        let outcome = send_bundle(&cfg(&url, true), &[signed_tx(&Keypair::new())]).await.unwrap();
+        assert_eq!(outcome.landed_slot, 42);
+    }
+
+    #[tokio::test]
+    async fn send_bundle_failed() {
+        let url = serve(stub(vec!["Failed"])).await;
+        let err = send_bundle(&cfg(&url, true), &[signed_tx(&Keypair::new())]).await.unwrap_err();
+        assert!(err.to_string().contains("отклонён"), "{err:#}");
+    }
+
+    #[tokio::test]
+    async fn send_bundle_pending_until_timeout() {
+        let st = stub(vec!["Pending"]);
+        let url = serve(st.clone()).await;
+        let cfg = JitoConfig { status_timeout: Duration::from_secs(1), ..cfg(&url, true) };
+        let err = send_bundle(&cfg, &[signed_tx(&Keypair::new())]).await.unwrap_err();
+        assert!(err.to_string().contains("не лёг"), "{err:#}");
+        assert!(!calls(&st, "getInflightBundleStatuses").is_empty());
+    }
+
+    #[tokio::test]
+    async fn send_bundle_rejects_oversized() {
+        let st = stub(vec!["Landed"]);
+        let url = serve(st.clone()).await;
+        let payer = Keypair::new();
+        let txs: Vec<_> = (0..=MAX_BUNDLE_TXS).map(|_| signed_tx(&payer)).collect();
+        assert!(send_bundle(&cfg(&url, true), &txs).await.is_err());
+        assert!(calls(&st, "sendBundle").is_empty());
+    }
+
+    #[tokio::test]
+    async fn sign_all_tips_in_last_tx() {
+        let url = serve(stub(vec!["Landed"])).await;
+        let rpc = RpcClient::new(url.clone());
+        let payer = Keypair::new();
+        let txs = vec![
+            BundleTx::Instructions { instructions: vec![transfer(&payer)], signers: vec![&payer], expect: Vec::new() },
+            BundleTx::Instructions { instructions: vec![transfer(&payer)], signers: vec![&payer], expect: Vec::new() },
+        ];
+        let signed = sign_all(&cfg(&url, true), &rpc, &txs, &[], 0).await.unwrap();
+        assert_eq!(signed.txs.len(), 2);
+        assert_eq!(signed.last_valid_block_height, LAST_VALID);
+        assert!(!pays_tip(&signed.txs[0]));
+        assert!(pays_tip(&signed.txs[1]));
+        // лимит — по симуляции бандла, цена — по комиссиям сети (здесь минимальная)
+        for tx in &signed.txs {
+            assert_eq!(budget(tx), (Some(priority::cu_limit(SIM_UNITS)), Some(1_000)));
+        }
+    }
+
+    #[tokio::test]
+    async fn sign_all_escalates_price() {
+        let url = serve(stub(vec!["Landed"])).await;
+        let rpc = RpcClient::new(url.clone());
+        let payer = Keypair::new();
+        let txs = vec![BundleTx::Instructions { instructions: vec![transfer(&payer)], signers: vec![&payer], expect: Vec::new() }];
+        let signed = sign_all(&cfg(&url, true), &rpc, &txs, &[], 2).await.unwrap();
+        assert_eq!(budget(&signed.txs[0]).1, Some(priority::escalated(1_000, 2)));
+    }
+
+    #[tokio::test]
+    async fn land_bundle_picks_up_late_landing() {
+        let st = stub(vec!["Failed"]);
+        st.lock().unwrap().landed_late = true;
+        let url = serve(st.clone()).await;
+        let rpc = RpcClient::new(url.clone());
+        let tx = signed_tx(&Keypair::new());
+        let bundle = SignedBundle { txs: vec![tx.clone()], last_valid_block_height: LAST_VALID };
+
+        let outcome = land_bundle(&cfg(&url, true), &rpc, &bundle).await.unwrap();
+        assert_eq!(outcome.landed_slot, 40);
+        assert_eq!(outcome.signatures, vec![tx.signatures[0]]);
+        assert!(!calls(&st, "getBlockHeight").is_empty());
+    }
+
+    #[tokio::test]
+    async fn land_bundle_fails_after_expiry() {
+        let st = stub(vec!["Failed"]);
+        let url = serve(st.clone()).await;
+        let rpc = RpcClient::new(url.clone());
+        let bundle = SignedBundle { txs: vec![signed_tx(&Keypair::new())], last_valid_block_height: LAST_VALID };
+        assert!(land_bundle(&cfg(&url, true), &rpc, &bundle).await.is_err());
+        assert_eq!(calls(&st, "getSignatureStatuses").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn fallback_rebuilds_without_tip() {
+        let st = stub(vec!["Failed"]);
+        let url = serve(st.clone()).await;
+        let rpc = Arc::new(RpcClient::new(url.clone()));
+        let payer = Keypair::new();
+        let txs = vec![BundleTx::Instructions { instructions: vec![transfer(&payer)], signers: vec![&payer], expect: Vec::new() }];
+
+        // заглушка не пропускает обычную отправку дальше симуляции
+        let res = send_bundle_or_fallback(&cfg(&url, true), rpc, txs, &[], Urgency::Normal).await;
+        assert!(res.is_err());
+
+        let bundle = calls(&st, "sendBundle");
+        assert_eq!(bundle.len(), 1);
+        assert!(pays_tip(&decode(&bundle[0][0][0])));
+
+        // бандл не лёг — транзакция собрана заново, без чаевых и с новым blockhash
+        let simulated = calls(&st, "simulateTransaction");
+        assert_eq!(simulated.len(), 1);
+        let fallback = decode(&simulated[0][0]);
+        assert!(!pays_tip(&fallback));
+        let order: Vec<String> = st.lock().unwrap().calls.iter().map(|(m, _)| m.clone()).collect();
+        let expired = order.iter().position(|m| m == "getBlockHeight").unwrap();
+        let resent  = order.iter().position(|m| m == "simulateTransaction").unwrap();
+        assert!(expired < resent);
+    }
+
+    #[tokio::test]
+    async fn disabled_goes_straight_to_fallback() {
+        let st = stub(vec!["Landed"]);
+        let url = serve(st.clone()).await;
+        let rpc = Arc::new(RpcClient::new(url.clone()));
+        let payer = Keypair::new();
+        let txs = vec![BundleTx::Instructions { instructions: vec![transfer(&payer)], signers: vec![&payer], expect: Vec::new() }];
+
+        assert!(send_bundle_or_fallback(&cfg(&url, false), rpc, txs, &[], Urgency::Normal).await.is_err());
+        assert!(calls(&st, "sendBundle").is_empty());
+        assert_eq!(calls(&st, "simulateTransaction").len(), 1);
+    }
+}
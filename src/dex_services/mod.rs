@@ -2,4 +2,16 @@ pub mod get_info;
 pub mod net;
 pub mod swap;
 pub mod wirlpool;
-pub mod raydium;
\ No newline at end of file
+pub mod raydium;
+pub mod executor;
+pub mod bundle;
+pub mod venue;
+pub mod token;
+pub mod discovery;
+pub mod alt;
+pub mod multi_open;
+pub mod jito;
+pub mod reopen;
+pub mod priority;
+pub mod preview;
+pub mod tx_sender;
@@ -0,0 +1,425 @@
+// src/dex_services/multi_open.rs
+//
+// Атомарное открытие нескольких диапазонов Whirlpool одной v0-транзакцией.
+// Квоты всех ног считаются по одному состоянию пула, недостающие токены
+// докупаются разом, а инструкции всех ног (плюс tick-array-и и wSOL)
+// уходят одной транзакцией с таблицей адресов пула (alt.rs) — открываются
+// либо все диапазоны по одной цене, либо ни один.
+// Если транзакция не влезает в пакет даже с таблицей — ноги раскладываются
+// по нескольким транзакциям и уходят одним Jito-бандлом (jito.rs); без
+// бандлов такое открытие не делаем вовсе — по очереди оно не атомарно.
+
+use std::ops::Range;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+use orca_whirlpools::{set_whirlpools_config_address, WhirlpoolsConfigInput};
+use orca_whirlpools_client::{
+    get_bundled_position_address, get_position_address, get_tick_array_address,
+    InitializeTickArrayBuilder, OpenPositionWithTokenExtensionsBuilder, Whirlpool,
+};
+use orca_whirlpools_core::{get_tick_array_start_tick_index, sqrt_price_to_price, IncreaseLiquidityQuote, U128};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    hash::Hash,
+    instruction::Instruction,
+    message::AddressLookupTableAccount,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction,
+};
+use spl_associated_token_account::get_associated_token_address;
+
+use crate::database::pool_settings;
+use crate::dex_services::alt;
+use crate::dex_services::jito::{self, BundleTx};
+use crate::dex_services::preview::{self, Expect};
+use crate::dex_services::priority;
+use crate::dex_services::bundle::{bundle_with_free_slots, deposit_quote, increase_ix, open_bundled_ix, RESERVE_LAMPORTS};
+use crate::dex_services::token::{self, MintInfo};
+use crate::dex_services::wirlpool::{gap_b_for, nearest_valid_ticks, prepare_tick_arrays, rebalance_before_open};
+use crate::params::{OVR, WALLET_MUTEX, WSOL};
+use crate::types::{OpenPositionResult, PoolConfig};
+use crate::utils::{op, utils};
+
+/// update-authority метаданных NFT позиций Whirlpool (Token-2022)
+const METADATA_UPDATE_AUTH: &str = "3axbTs2z5GBy6usVbNVoqEgZMng3vZvMnAoX29BFfwhr";
+
+/// Один диапазон атомарного открытия.
+#[derive(Debug, Clone)]
+pub struct LegRequest {
+    pub lower_price: f64,
+    pub upper_price: f64,
+    /// депозит в единицах токена B
+    pub deposit:     f64,
+}
+
+/// Во что открываем ногу: слот бандла или отдельный NFT позиции.
+enum Slot {
+    Bundled { bundle_mint: Pubkey, index: u16 },
+    Nft(Keypair),
+}
+
+/// Ноги, посчитанные по одному состоянию пула. Из них собираются
+/// инструкции одной транзакции или отдельных транзакций бандла.
+pub(crate) struct PreparedOpen {
+    owner:          Pubkey,
+    whirl_pk:       Pubkey,
+    whirl:          Whirlpool,
+    mint_a:         MintInfo,
+    mint_b:         MintInfo,
+    dec_a:          u8,
+    dec_b:          u8,
+    legs:           Vec<PlannedLeg>,
+    /// стартовые тики tick-array-ев, которые надо создать
+    missing_arrays: Vec<i32>,
+    /// рента за них, лампорты
+    init_lamports:  u64,
+}
+
+struct PlannedLeg {
+    tick_l: i32,
+    tick_u: i32,
+    quote:  IncreaseLiquidityQuote,
+    slot:   Slot,
+}
+
+impl PreparedOpen {
+    /// Тики и квоты всех ног по текущему состоянию пула, слоты под позиции.
+    pub async fn new(
+        rpc: &RpcClient,
+        wallet: &Keypair,
+        pool: &PoolConfig,
+        legs: &[LegRequest],
+        slippage: u16,
+    ) -> Result<Self> {
+        let owner    = wallet.pubkey();
+        let whirl_pk = Pubkey::from_str(&pool.pool_address)?;
+        let whirl    = Whirlpool::from_bytes(&rpc.get_account(&whirl_pk).await?.data)?;
+        let dec_a    = pool.decimal_a as u8;
+        let dec_b    = pool.decimal_b as u8;
+        let mint_a   = token::mint_info(rpc, &whirl.token_mint_a).await?;
+        let mint_b   = token::mint_info(rpc, &whirl.token_mint_b).await?;
+
+        let bundled = pool_settings::get_pool_settings(&pool.pool_address)
+            .await?
+            .map_or(false, |s| s.bundle);
+        let mut slots: Vec<Slot> = if bundled {
+            let (bundle_mint, indexes) = bundle_with_free_slots(rpc, wallet, legs.len()).await?;
+            indexes.into_iter().map(|index| Slot::Bundled { bundle_mint, index }).collect()
+        } else {
+            legs.iter().map(|_| Slot::Nft(Keypair::new())).collect()
+        };
+
+        let mut planned = Vec::with_capacity(legs.len());
+        let mut missing_arrays: Vec<i32> = Vec::new();
+        let mut init_lamports = 0u64;
+        for leg in legs {
+            let (tick_l, tick_u) = nearest_valid_ticks(
+                leg.lower_price, leg.upper_price, whirl.tick_spacing as i32, dec_a, dec_b,
+            );
+            let ta = prepare_tick_arrays(rpc, whirl_pk, whirl.tick_spacing, tick_l, tick_u).await?;
+            // соседние ноги часто делят tick-array — ренту считаем один раз
+            for start in &ta.missing {
+                if !missing_arrays.contains(start) {
+                    missing_arrays.push(*start);
+                    init_lamports += ta.init_lamports / ta.missing.len() as u64;
+                }
+            }
+            let quote = deposit_quote(&whirl, ta.tick_l, ta.tick_u, leg.deposit, slippage, &mint_a, &mint_b, dec_a, dec_b)?;
+            planned.push(PlannedLeg { tick_l: ta.tick_l, tick_u: ta.tick_u, quote, slot: slots.remove(0) });
+        }
+
+        Ok(Self { owner, whirl_pk, whirl, mint_a, mint_b, dec_a, dec_b, legs: planned, missing_arrays, init_lamports })
+    }
+
+    pub fn max_a(&self) -> u64 {
+        self.legs.iter().map(|l| l.quote.token_max_a).sum()
+    }
+
+    pub fn max_b(&self) -> u64 {
+        self.legs.iter().map(|l| l.quote.token_max_b).sum()
+    }
+
+    /// Инструкции ног `range`: ATA, wSOL на эти ноги, открытие и депозит.
+    /// `with_tick_arrays` — ещё и создание недостающих tick-array-ев.
+    pub fn instructions(&self, range: Range<usize>, with_tick_arrays: bool) -> Result<Vec<Instruction>> {
+        let owner    = self.owner;
+        let whirl_pk = self.whirl_pk;
+        let whirl    = &self.whirl;
+        let legs     = &self.legs[range];
+
+        let mut ixs: Vec<Instruction> = Vec::new();
+        if with_tick_arrays {
+            for start in &self.missing_arrays {
+                ixs.push(
+                    InitializeTickArrayBuilder::new()
+                        .whirlpool(whirl_pk)
+                        .funder(owner)
+                        .tick_array(get_tick_array_address(&whirl_pk, *start)?.0)
+                        .start_tick_index(*start)
+                        .instruction(),
+                );
+            }
+        }
+        // wSOL заворачиваем один раз на все ноги
+        let max_a: u64 = legs.iter().map(|l| l.quote.token_max_a).sum();
+        let max_b: u64 = legs.iter().map(|l| l.quote.token_max_b).sum();
+        let mut wrapped: Vec<Pubkey> = Vec::new();
+        for (mint, info, max) in [(whirl.token_mint_a, self.mint_a, max_a), (whirl.token_mint_b, self.mint_b, max_b)] {
+            ixs.push(token::create_ata_ix(&owner, &owner, &mint, &info.program));
+            if mint.to_string() == WSOL {
+                let ata = token::ata(&owner, &mint, &info.program);
+                ixs.push(system_instruction::transfer(&owner, &ata, max));
+                ixs.push(spl_token::instruction::sync_native(&spl_token::id(), &ata)?);
+                wrapped.push(ata);
+            }
+        }
+
+        for leg in legs {
+            let (position, position_ta) = match &leg.slot {
+                Slot::Bundled { bundle_mint, index } => {
+                    ixs.push(open_bundled_ix(owner, whirl_pk, *bundle_mint, *index, leg.tick_l, leg.tick_u)?);
+                    (get_bundled_position_address(bundle_mint, *index as u8)?.0, get_associated_token_address(&owner, bundle_mint))
+                }
+                Slot::Nft(mint_kp) => {
+                    let mint        = mint_kp.pubkey();
+                    let position    = get_position_address(&mint)?.0;
+                    let position_ta = token::ata(&owner, &mint, &spl_token_2022::ID);
+                    ixs.push(
+                        OpenPositionWithTokenExtensionsBuilder::new()
+                            .funder(owner)
+                            .owner(owner)
+                            .position(position)
+                            .position_mint(mint)
+                            .position_token_account(position_ta)
+                            .whirlpool(whirl_pk)
+                            .token2022_program(spl_token_2022::ID)
+                            .metadata_update_auth(Pubkey::from_str(METADATA_UPDATE_AUTH)?)
+                            .tick_lower_index(leg.tick_l)
+                            .tick_upper_index(leg.tick_u)
+                            .with_token_metadata_extension(true)
+                            .instruction(),
+                    );
+                    (position, position_ta)
+                }
+            };
+            let arrays = [
+                get_tick_array_address(&whirl_pk, get_tick_array_start_tick_index(leg.tick_l, whirl.tick_spacing))?.0,
+                get_tick_array_address(&whirl_pk, get_tick_array_start_tick_index(leg.tick_u, whirl.tick_spacing))?.0,
+            ];
+            ixs.push(increase_ix(owner, whirl_pk, whirl, &self.mint_a, &self.mint_b, position, position_ta, arrays, &leg.quote));
+        }
+        for ata in &wrapped {
+            // остаток wSOL обратно в SOL
+            ixs.push(spl_token::instruction::close_account(&spl_token::id(), ata, &owner, &owner, &[])?);
+        }
+        Ok(ixs)
+    }
+
+    /// Сколько ноги `range` спишут по квотам — для сверки с симуляцией.
+    pub fn expects(&self, range: Range<usize>) -> Vec<Expect> {
+        let legs = &self.legs[range];
+        let est_a: u64 = legs.iter().map(|l| l.quote.token_est_a).sum();
+        let est_b: u64 = legs.iter().map(|l| l.quote.token_est_b).sum();
+        vec![
+            Expect { mint: self.whirl.token_mint_a, decimals: self.dec_a, atoms: -(est_a as i128) },
+            Expect { mint: self.whirl.token_mint_b, decimals: self.dec_b, atoms: -(est_b as i128) },
+        ]
+    }
+
+    /// Кроме кошелька, транзакцию ног `range` подписывают NFT новых позиций.
+    pub fn signers(&self, range: Range<usize>) -> Vec<&Keypair> {
+        self.legs[range].iter()
+            .filter_map(|l| match &l.slot {
+                Slot::Nft(kp) => Some(kp),
+                Slot::Bundled { .. } => None,
+            })
+            .collect()
+    }
+
+    /// Результаты открытия в порядке ног (подпись проставляет вызывающий).
+    pub fn results(&self) -> Result<Vec<OpenPositionResult>> {
+        self.legs.iter()
+            .map(|l| {
+                let key = match &l.slot {
+                    // у позиции бандла своего NFT нет — ключом служит её адрес
+                    Slot::Bundled { bundle_mint, index } => get_bundled_position_address(bundle_mint, *index as u8)?.0,
+                    Slot::Nft(kp) => kp.pubkey(),
+                };
+                Ok(OpenPositionResult {
+                    position_mint: key,
+                    amount_wsol:   l.quote.token_max_a as f64 / 10f64.powi(self.dec_a as i32),
+                    amount_usdc:   l.quote.token_max_b as f64 / 10f64.powi(self.dec_b as i32),
+                    signature:     None,
+                })
+            })
+            .collect()
+    }
+
+    /// Постоянные аккаунты пула для его таблицы адресов: всё, что трогают
+    /// ноги, плюс tick-array-и под тики `extra_ticks` (закрываемой позиции).
+    pub fn stable_accounts(&self, extra_ticks: &[i32]) -> Result<Vec<Pubkey>> {
+        let mut ticks: Vec<i32> = self.legs.iter().flat_map(|l| [l.tick_l, l.tick_u]).collect();
+        ticks.extend_from_slice(extra_ticks);
+        let mut out = alt::whirlpool_accounts(&self.whirl_pk, &self.whirl, &ticks)?;
+        for program in [self.mint_a.program, self.mint_b.program] {
+            if !out.contains(&program) {
+                out.push(program);
+            }
+        }
+        Ok(out)
+    }
+
+    /// Минты пула (A, B) и их децималы.
+    pub fn mints(&self) -> ((Pubkey, u8), (Pubkey, u8)) {
+        ((self.whirl.token_mint_a, self.dec_a), (self.whirl.token_mint_b, self.dec_b))
+    }
+
+    /// Цена A в B по тому состоянию пула, по которому считались квоты.
+    pub fn price_a_in_b(&self) -> f64 {
+        sqrt_price_to_price(U128::from(self.whirl.sqrt_price), self.dec_a, self.dec_b)
+    }
+
+    /// Свободные остатки кошелька в атомах (A, B); из SOL вычтены резерв
+    /// и рента tick-array-ев.
+    pub async fn free_atoms(&self, rpc: &RpcClient) -> Result<(u64, u64)> {
+        let mut out = [0u64; 2];
+        for (i, (mint, info)) in [(self.whirl.token_mint_a, self.mint_a), (self.whirl.token_mint_b, self.mint_b)].into_iter().enumerate() {
+            out[i] = if mint.to_string() == WSOL {
+                rpc.get_balance(&self.owner).await?.saturating_sub(RESERVE_LAMPORTS + self.init_lamports)
+            } else {
+                rpc.get_token_account_balance(&token::ata(&self.owner, &mint, &info.program)).await
+                    .ok()
+                    .and_then(|r| r.amount.parse::<u64>().ok())
+                    .unwrap_or(0)
+            };
+        }
+        Ok((out[0], out[1]))
+    }
+
+    /// Докупить недостающее на все ноги разом (резерв + рента tick-array-ев).
+    pub async fn top_up(&self, rpc: &RpcClient, pool: &PoolConfig) -> Result<()> {
+        let need_sol  = self.max_a() as f64 / 10f64.powi(self.dec_a as i32) * OVR;
+        let need_tokb = self.max_b() as f64 / 10f64.powi(self.dec_b as i32) * OVR;
+        let (free_a, free_b) = self.free_atoms(rpc).await?;
+        let mut sol_free  = free_a as f64 / 10f64.powi(self.dec_a as i32);
+        let mut tokb_free = free_b as f64 / 10f64.powi(self.dec_b as i32);
+        rebalance_before_open(
+            rpc, &self.owner, pool, need_sol, need_tokb, self.price_a_in_b(), gap_b_for(pool), self.dec_b,
+            &mut sol_free, &mut tokb_free,
+        )
+        .await
+    }
+}
+
+/// Разложить ноги по транзакциям: подряд, сколько влезает в пакет, если
+/// адреса из `lookup` уйдут в таблицу. Tick-array-и создаёт первая
+/// транзакция, в каждой закладываем место под ComputeBudget и чаевые Jito.
+fn pack_legs(
+    prepared: &PreparedOpen,
+    wallet: &Keypair,
+    n_legs: usize,
+    lookup: &[Pubkey],
+) -> Result<Vec<Range<usize>>> {
+    let probe_table = AddressLookupTableAccount { key: Pubkey::new_unique(), addresses: lookup.to_vec() };
+    let fits = |range: Range<usize>| -> Result<bool> {
+        let mut ixs = priority::budget_ixs(priority::MAX_CU, 0);
+        ixs.extend(prepared.instructions(range.clone(), range.start == 0)?);
+        ixs.push(system_instruction::transfer(&wallet.pubkey(), &Pubkey::new_unique(), 0));
+        let mut signers: Vec<&Keypair> = vec![wallet];
+        signers.extend(prepared.signers(range));
+        Ok(utils::fits_packet(&utils::compile_v0(&ixs, &signers, &[probe_table.clone()], Hash::default())?))
+    };
+
+    let mut groups: Vec<Range<usize>> = Vec::new();
+    let mut start = 0;
+    while start < n_legs {
+        if !fits(start..start + 1)? {
+            return Err(anyhow!("нога #{} не влезает даже в отдельную транзакцию", start + 1));
+        }
+        let mut end = start + 1;
+        while end < n_legs && fits(start..end + 1)? {
+            end += 1;
+        }
+        groups.push(start..end);
+        start = end;
+    }
+    Ok(groups)
+}
+
+/// Открыть все `legs` пула разом — либо все, либо ни одной. Результаты — в
+/// порядке `legs`. Влезают в одну транзакцию — одной транзакцией; нет —
+/// раскладываем по нескольким и шлём Jito-бандлом. Без Jito или если
+/// транзакций больше, чем берёт бандл, — ошибка: по частям уже не атомарно.
+pub async fn open_legs_atomic(
+    pool: &PoolConfig,
+    legs: &[LegRequest],
+    slippage: u16,
+) -> Result<Vec<OpenPositionResult>> {
+    set_whirlpools_config_address(WhirlpoolsConfigInput::SolanaMainnet)
+        .map_err(|e| anyhow!("SDK config failed: {e}"))?;
+    let rpc    = utils::init_rpc();
+    let _guard = WALLET_MUTEX.lock().await;
+    let wallet = utils::load_wallet()?;
+
+    // ───── 1. Квоты всех ног по одному состоянию пула ─────────────────
+    let prepared = PreparedOpen::new(&rpc, &wallet, pool, legs, slippage).await?;
+    let stable = prepared.stable_accounts(&[])?;
+
+    // ───── 2. Сколько транзакций нужно, если аккаунты пула уйдут в таблицу
+    let groups = pack_legs(&prepared, &wallet, legs.len(), &stable)?;
+    if groups.len() > 1 {
+        if !jito::enabled() {
+            bail!("{}: {} ног не влезают в одну транзакцию, а без Jito по частям не атомарно", pool.name, legs.len());
+        }
+        if groups.len() > jito::MAX_BUNDLE_TXS {
+            bail!("{}: {} ног — {} транзакций, в бандл влезает {}", pool.name, legs.len(), groups.len(), jito::MAX_BUNDLE_TXS);
+        }
+    }
+
+    // ───── 3. Докупаем недостающее на все ноги разом ──────────────────
+    prepared.top_up(&rpc, pool).await?;
+
+    // ───── 4. Одна транзакция или бандл ───────────────────────────────
+    let table = alt::ensure_pool_table(&rpc, &wallet, &pool.pool_address, &stable).await?;
+    let mut txs = Vec::with_capacity(groups.len());
+    for range in &groups {
+        let mut signers: Vec<&Keypair> = vec![&wallet];
+        signers.extend(prepared.signers(range.clone()));
+        txs.push((prepared.instructions(range.clone(), range.start == 0)?, signers, prepared.expects(range.clone())));
+    }
+    // подпись транзакции, в которую попала каждая группа ног
+    let signatures = if txs.len() == 1 {
+        let (ixs, signers, expect) = txs.remove(0);
+        let sig = preview::expecting(expect, utils::send_v0_and_confirm(rpc.clone(), ixs, &signers, &[table]))
+            .await
+            .map_err(op("open_legs_atomic"))?
+            .signature;
+        log::info!("{}: {} диапазона открыты одной транзакцией {sig}", pool.name, legs.len());
+        vec![sig]
+    } else {
+        let txs: Vec<BundleTx> = txs.into_iter()
+            .map(|(instructions, signers, expect)| BundleTx::Instructions { instructions, signers, expect })
+            .collect();
+        // без отката на одиночную отправку: по одной — уже не атомарно
+        let signed = jito::sign_all(jito::config(), &rpc, &txs, &[table], 0).await?;
+        let outcome = jito::land_bundle(jito::config(), &rpc, &signed)
+            .await
+            .map_err(op("open_legs_atomic (bundle)"))?;
+        jito::track_landed(jito::config(), &outcome).await;
+        log::info!(
+            "{}: {} диапазона открыты бандлом {} из {} транзакций",
+            pool.name, legs.len(), outcome.bundle_id, groups.len()
+        );
+        outcome.signatures
+    };
+
+    let mut results = prepared.results()?;
+    for (range, sig) in groups.iter().zip(&signatures) {
+        for r in &mut results[range.clone()] {
+            r.signature = Some(*sig);
+        }
+    }
+    Ok(results)
+}
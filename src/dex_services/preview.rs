@@ -0,0 +1,391 @@
+// src/dex_services/preview.rs
+//
+// Предпросмотр изменения балансов перед отправкой. Транзакция симулируется
+// с запросом состояния её writable-аккаунтов, разница до/после сводится в
+// строку «−1.23 SOL, +184.2 USDC, rent −0.002 SOL»: SOL — вместе с wSOL и
+// без ренты, рента — созданные (−) и закрытые (+) аккаунты.
+// Если вызывающий задал ожидаемые суммы (`expecting`), а симуляция
+// расходится с ними больше PREVIEW_MAX_DEVIATION_PCT процентов, транзакция
+// не отправляется. Превью пишутся в лог и собираются `collect` — команды
+// Telegram прикладывают их к ответу.
+// Бандл симулируется целиком (`simulate_bundle`): его транзакции опираются
+// друг на друга и по отдельности не проходят. Превью — своё у каждой
+// транзакции бандла, по состоянию аккаунтов до и после неё.
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use anyhow::{bail, Result};
+use base64::{engine::general_purpose::STANDARD as b64, Engine as _};
+use serde_json::{json, Value};
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig};
+use solana_client::rpc_request::RpcRequest;
+use solana_sdk::{
+    account::Account,
+    commitment_config::CommitmentConfig,
+    message::VersionedMessage,
+    pubkey::Pubkey,
+    transaction::VersionedTransaction,
+};
+
+use crate::dex_services::{alt, token};
+use crate::params::{JITOSOL, MSOL, RAY, USDC, USDT, WBTC, WETH, WSOL};
+use crate::utils::op;
+
+/// длина SPL-токен-аккаунта без расширений
+const TOKEN_ACCOUNT_LEN: usize = 165;
+/// тип аккаунта Token-2022 (байт после базовой части): 2 — токен-аккаунт
+const ACCOUNT_TYPE_ACCOUNT: u8 = 2;
+/// getMultipleAccounts отдаёт не больше 100 аккаунтов за раз
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+/// запас по SOL на комиссию сети, лампорты
+const FEE_SLACK_LAMPORTS: i128 = 100_000;
+
+fn max_deviation_pct() -> f64 {
+    env::var("PREVIEW_MAX_DEVIATION_PCT").ok().and_then(|v| v.parse().ok()).unwrap_or(3.0)
+}
+
+/// Ожидаемое изменение баланса: + приход, − расход (в атомах).
+/// SOL задаётся минтом wSOL.
+#[derive(Debug, Clone)]
+pub struct Expect {
+    pub mint:     Pubkey,
+    pub decimals: u8,
+    pub atoms:    i128,
+}
+
+/// Изменение балансов кошелька по симуляции.
+#[derive(Debug, Clone, Default)]
+pub struct BalancePreview {
+    /// SOL вместе с wSOL, без ренты, лампорты
+    pub sol:    i128,
+    /// прочие токены: минт, децималы, изменение в атомах
+    pub tokens: Vec<(Pubkey, u8, i128)>,
+    /// рента: + вернулась, − заплачена, лампорты
+    pub rent:   i128,
+}
+
+impl BalancePreview {
+    fn delta(&self, mint: &Pubkey) -> i128 {
+        if mint.to_string() == WSOL {
+            return self.sol;
+        }
+        self.tokens.iter().find(|(m, ..)| m == mint).map_or(0, |(.., d)| *d)
+    }
+}
+
+impl fmt::Display for BalancePreview {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts: Vec<String> = Vec::new();
+        if self.sol != 0 {
+            parts.push(format!("{} SOL", signed(self.sol, 9)));
+        }
+        for (mint, dec, d) in &self.tokens {
+            parts.push(format!("{} {}", signed(*d, *dec), symbol(mint)));
+        }
+        if self.rent != 0 {
+            parts.push(format!("rent {} SOL", signed(self.rent, 9)));
+        }
+        if parts.is_empty() {
+            return write!(f, "балансы не меняются");
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// Итог симуляции.
+pub struct Simulation {
+    pub units_consumed: u64,
+    pub preview:        BalancePreview,
+}
+
+/// Итог симуляции бандла — по его транзакциям.
+pub struct BundleSimulation {
+    pub units_consumed: Vec<u64>,
+    pub previews:       Vec<BalancePreview>,
+}
+
+tokio::task_local! {
+    static EXPECTED: Vec<Expect>;
+    static COLLECTED: Mutex<Vec<String>>;
+}
+
+/// Выполнить `fut`, сверяя транзакции внутри с ожидаемыми суммами.
+pub async fn expecting<F: Future>(expect: Vec<Expect>, fut: F) -> F::Output {
+    EXPECTED.scope(expect, fut).await
+}
+
+/// Выполнить `fut` и вернуть превью всех его транзакций.
+pub async fn collect<F: Future>(fut: F) -> (F::Output, Vec<String>) {
+    COLLECTED
+        .scope(Mutex::new(Vec::new()), async move {
+            let out = fut.await;
+            let list = COLLECTED.with(|c| std::mem::take(&mut *c.lock().unwrap()));
+            (out, list)
+        })
+        .await
+}
+
+/// Превью для ответа в Telegram (пусто — транзакций не было).
+pub fn format_collected(list: &[String]) -> String {
+    match list {
+        []    => String::new(),
+        [one] => format!("\n🔎 Симуляция: {one}"),
+        many  => format!("\n🔎 Симуляция:\n• {}", many.join("\n• ")),
+    }
+}
+
+/// Симулировать `tx` и посчитать изменение балансов плательщика.
+/// Ошибку программы возвращаем с логами — по ним вызывающие узнают коды
+/// вроде 0x1782, как и по ответу preflight.
+pub async fn simulate(rpc: &RpcClient, tx: &VersionedTransaction) -> Result<Simulation> {
+    let owner = tx.message.static_account_keys()[0];
+    let keys  = writable_keys(rpc, &tx.message).await?;
+
+    let mut pre: Vec<Option<Account>> = Vec::with_capacity(keys.len());
+    for chunk in keys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        pre.extend(rpc.get_multiple_accounts(chunk).await.map_err(op("get_multiple_accounts"))?);
+    }
+
+    let cfg = RpcSimulateTransactionConfig {
+        sig_verify: false,
+        replace_recent_blockhash: true,
+        commitment: Some(CommitmentConfig::processed()),
+        accounts: Some(RpcSimulateTransactionAccountsConfig {
+            encoding:  Some(UiAccountEncoding::Base64),
+            addresses: keys.iter().map(|k| k.to_string()).collect(),
+        }),
+        ..Default::default()
+    };
+    let sim = rpc.simulate_transaction_with_config(tx, cfg).await
+        .map_err(op("simulate transaction"))?
+        .value;
+    if let Some(err) = sim.err {
+        let logs = sim.logs.unwrap_or_default();
+        bail!("simulation failed: {err:?}; {}", logs.join(" | "));
+    }
+    let post: Vec<Option<Account>> = sim.accounts
+        .unwrap_or_default()
+        .into_iter()
+        .map(|a| a.and_then(|ui| ui.decode()))
+        .collect();
+    if post.len() != keys.len() {
+        bail!("simulation: вернулось {} аккаунтов из {}", post.len(), keys.len());
+    }
+
+    Ok(Simulation {
+        units_consumed: sim.units_consumed.unwrap_or_default(),
+        preview:        diff(rpc, owner, &keys, &pre, &post).await?,
+    })
+}
+
+/// Симулировать транзакции бандла подряд, каждую поверх предыдущих.
+/// simulateBundle есть только у RPC Jito: `None` — этот RPC его не знает.
+/// Ошибка — бандл в сети не пройдёт.
+pub async fn simulate_bundle(rpc: &RpcClient, txs: &[VersionedTransaction]) -> Result<Option<BundleSimulation>> {
+    let encoded = txs.iter()
+        .map(|tx| Ok(b64.encode(bincode::serialize(tx)?)))
+        .collect::<Result<Vec<_>>>()?;
+    let mut keys = Vec::with_capacity(txs.len());
+    for tx in txs {
+        keys.push(writable_keys(rpc, &tx.message).await?);
+    }
+    // состояние writable-аккаунтов до и после каждой транзакции
+    let accounts: Vec<Value> = keys.iter()
+        .map(|k| json!({ "addresses": k.iter().map(|p| p.to_string()).collect::<Vec<_>>(), "encoding": "base64" }))
+        .collect();
+    let params = json!([
+        { "encodedTransactions": encoded },
+        {
+            "transactionEncoding": "base64",
+            "skipSigVerify": true,
+            "replaceRecentBlockhash": true,
+            "preExecutionAccountsConfigs": accounts,
+            "postExecutionAccountsConfigs": accounts,
+        },
+    ]);
+    let res: Value = match rpc.send(RpcRequest::Custom { method: "simulateBundle" }, params).await {
+        Ok(v) => v,
+        Err(e) => {
+            log::debug!("simulateBundle: {e}");
+            return Ok(None);
+        }
+    };
+    let value   = &res["value"];
+    let results = value["transactionResults"].as_array().cloned().unwrap_or_default();
+    if let Some(failed) = value["summary"].get("failed") {
+        let logs: Vec<&str> = results.last()
+            .and_then(|r| r["logs"].as_array())
+            .map(|l| l.iter().filter_map(|s| s.as_str()).collect())
+            .unwrap_or_default();
+        bail!("bundle simulation failed: {}; {}", failed["error"], logs.join(" | "));
+    }
+    if results.len() != txs.len() {
+        bail!("simulateBundle: вернулось {} результатов из {}", results.len(), txs.len());
+    }
+
+    let mut out = BundleSimulation { units_consumed: Vec::new(), previews: Vec::new() };
+    for ((tx, keys), r) in txs.iter().zip(&keys).zip(&results) {
+        let pre  = ui_accounts(&r["preExecutionAccounts"]);
+        let post = ui_accounts(&r["postExecutionAccounts"]);
+        if pre.len() != keys.len() || post.len() != keys.len() {
+            bail!("simulateBundle: вернулось {}/{} аккаунтов из {}", pre.len(), post.len(), keys.len());
+        }
+        out.units_consumed.push(r["unitsConsumed"].as_u64().unwrap_or_default());
+        out.previews.push(diff(rpc, tx.message.static_account_keys()[0], keys, &pre, &post).await?);
+    }
+    Ok(Some(out))
+}
+
+/// Аккаунты из ответа simulateBundle (`null` — аккаунта нет).
+fn ui_accounts(list: &Value) -> Vec<Option<Account>> {
+    list.as_array()
+        .map(|l| {
+            l.iter()
+                .map(|a| serde_json::from_value::<UiAccount>(a.clone()).ok().and_then(|ui| ui.decode()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Сверить превью с ожиданием из `expecting`, записать в лог и в `collect`.
+/// Расхождение больше порога — ошибка, транзакцию не отправляем.
+pub fn review(preview: &BalancePreview) -> Result<()> {
+    log::info!("preview: {preview}");
+    let expected = EXPECTED.try_with(|e| e.clone()).unwrap_or_default();
+    let pct = max_deviation_pct();
+    for e in &expected {
+        let actual = preview.delta(&e.mint);
+        let slack  = if e.mint.to_string() == WSOL { FEE_SLACK_LAMPORTS } else { 0 };
+        let allowed = (e.atoms.unsigned_abs() as f64 * pct / 100.0) as i128 + slack;
+        if (actual - e.atoms).abs() > allowed {
+            bail!(
+                "🛑 симуляция расходится с ожиданием по {}: ждали {}, выходит {} ({preview}) — не отправляю",
+                symbol(&e.mint), signed(e.atoms, e.decimals), signed(actual, e.decimals)
+            );
+        }
+    }
+    let _ = COLLECTED.try_with(|c| c.lock().unwrap().push(preview.to_string()));
+    Ok(())
+}
+
+/// Writable-аккаунты сообщения, включая взятые из таблиц адресов.
+async fn writable_keys(rpc: &RpcClient, msg: &VersionedMessage) -> Result<Vec<Pubkey>> {
+    let header = msg.header();
+    let statics = msg.static_account_keys();
+    let n_signed = header.num_required_signatures as usize;
+    let mut keys: Vec<Pubkey> = statics.iter()
+        .enumerate()
+        .filter(|(i, _)| {
+            if *i < n_signed {
+                *i < n_signed - header.num_readonly_signed_accounts as usize
+            } else {
+                *i < statics.len() - header.num_readonly_unsigned_accounts as usize
+            }
+        })
+        .map(|(_, k)| *k)
+        .collect();
+    for lookup in msg.address_table_lookups().unwrap_or_default() {
+        let table = alt::fetch(rpc, lookup.account_key).await?;
+        keys.extend(lookup.writable_indexes.iter().filter_map(|i| table.addresses.get(*i as usize).copied()));
+    }
+    Ok(keys)
+}
+
+/// Минт, владелец и количество токен-аккаунта (SPL Token или Token-2022).
+fn token_fields(acc: &Account) -> Option<(Pubkey, Pubkey, u64)> {
+    if acc.owner != spl_token::id() && acc.owner != spl_token_2022::ID {
+        return None;
+    }
+    let d = &acc.data;
+    if d.len() < TOKEN_ACCOUNT_LEN || (d.len() > TOKEN_ACCOUNT_LEN && d[TOKEN_ACCOUNT_LEN] != ACCOUNT_TYPE_ACCOUNT) {
+        return None;
+    }
+    let mint   = Pubkey::try_from(&d[0..32]).ok()?;
+    let owner  = Pubkey::try_from(&d[32..64]).ok()?;
+    let amount = u64::from_le_bytes(d[64..72].try_into().ok()?);
+    Some((mint, owner, amount))
+}
+
+async fn diff(
+    rpc: &RpcClient,
+    owner: Pubkey,
+    keys: &[Pubkey],
+    pre: &[Option<Account>],
+    post: &[Option<Account>],
+) -> Result<BalancePreview> {
+    let wsol = Pubkey::from_str(WSOL)?;
+    let mut out = BalancePreview::default();
+    let mut tokens: HashMap<Pubkey, i128> = HashMap::new();
+
+    for ((key, pre), post) in keys.iter().zip(pre).zip(post) {
+        let pre_l  = pre.as_ref().map_or(0, |a| a.lamports) as i128;
+        let post_l = post.as_ref().map_or(0, |a| a.lamports) as i128;
+        if *key == owner {
+            out.sol += post_l - pre_l;
+            continue;
+        }
+
+        let pre_t  = pre.as_ref().and_then(token_fields);
+        let post_t = post.as_ref().and_then(token_fields);
+        // лампорты wSOL-аккаунта сверх ренты — это обёрнутый SOL
+        let (mut wrapped_pre, mut wrapped_post) = (0i128, 0i128);
+        if let Some((mint, tok_owner, _)) = pre_t.or(post_t) {
+            if tok_owner == owner {
+                let a_pre  = pre_t.map_or(0, |t| t.2) as i128;
+                let a_post = post_t.map_or(0, |t| t.2) as i128;
+                if mint == wsol {
+                    out.sol += a_post - a_pre;
+                    (wrapped_pre, wrapped_post) = (a_pre, a_post);
+                } else {
+                    *tokens.entry(mint).or_default() += a_post - a_pre;
+                }
+            }
+        }
+
+        // рента — только у созданных и закрытых аккаунтов
+        if (pre_l == 0) != (post_l == 0) {
+            out.rent -= (post_l - wrapped_post) - (pre_l - wrapped_pre);
+        }
+    }
+    // рента уже сидит в балансе кошелька — показываем её отдельно
+    out.sol -= out.rent;
+
+    for (mint, d) in tokens {
+        if d != 0 {
+            let dec = token::mint_info(rpc, &mint).await?.decimals;
+            out.tokens.push((mint, dec, d));
+        }
+    }
+    out.tokens.sort_by_key(|(m, ..)| m.to_string());
+    Ok(out)
+}
+
+/// «+184.2» / «−1.23»: без хвостовых нулей.
+fn signed(atoms: i128, decimals: u8) -> String {
+    let v = atoms.unsigned_abs() as f64 / 10f64.powi(decimals as i32);
+    let s = format!("{v:.6}");
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    format!("{}{s}", if atoms < 0 { "−" } else { "+" })
+}
+
+fn symbol(mint: &Pubkey) -> String {
+    let m = mint.to_string();
+    match m.as_str() {
+        WSOL    => "SOL".into(),
+        USDC    => "USDC".into(),
+        USDT    => "USDT".into(),
+        RAY     => "RAY".into(),
+        WETH    => "WETH".into(),
+        WBTC    => "WBTC".into(),
+        JITOSOL => "JitoSOL".into(),
+        MSOL    => "mSOL".into(),
+        _       => format!("{}…", &m[..4]),
+    }
+}
@@ -0,0 +1,125 @@
+// src/dex_services/priority.rs
+//
+// Приоритетная комиссия и лимит CU транзакций бота.
+// Комиссия (микролампорты за CU) — перцентиль PRIORITY_FEE_PERCENTILE
+// (по умолчанию 75) из getRecentPrioritizationFees по writable-аккаунтам
+// транзакции, в границах PRIORITY_FEE_MIN / PRIORITY_FEE_MAX.
+// Итог за транзакцию (цена × лимит CU) не больше PRIORITY_FEE_MAX_LAMPORTS:
+// при большом лимите цену урезаем, чтобы повышения комиссии на повторах
+// не съели больше, чем стоит сама операция.
+// Лимит CU — по симуляции: потрачено × CU_MARGIN.
+// Срочные отправки (выходы) на каждом повторе умножают комиссию на
+// PRIORITY_FEE_ESCALATE, повторов — PRIORITY_URGENT_ATTEMPTS.
+
+use std::env;
+
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction,
+    instruction::Instruction,
+    pubkey::Pubkey,
+};
+
+/// потолок CU на транзакцию
+pub const MAX_CU: u32 = 1_400_000;
+/// меньше не ставим, даже если симуляция потратила меньше
+const MIN_CU: u32 = 10_000;
+/// getRecentPrioritizationFees принимает не больше 128 аккаунтов
+const MAX_FEE_ACCOUNTS: usize = 128;
+
+fn env_f64(key: &str, default: f64) -> f64 {
+    env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+fn env_u64(key: &str, default: u64) -> u64 {
+    env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+fn percentile() -> f64 {
+    env_f64("PRIORITY_FEE_PERCENTILE", 75.0).clamp(0.0, 100.0)
+}
+
+fn min_fee() -> u64 {
+    env_u64("PRIORITY_FEE_MIN", 1_000)
+}
+
+fn max_fee() -> u64 {
+    env_u64("PRIORITY_FEE_MAX", 500_000)
+}
+
+/// потолок приоритетной комиссии за транзакцию, лампорты
+fn max_fee_lamports() -> u64 {
+    env_u64("PRIORITY_FEE_MAX_LAMPORTS", 100_000)
+}
+
+fn cu_margin() -> f64 {
+    env_f64("CU_MARGIN", 1.15).max(1.0)
+}
+
+/// Сколько раз срочная отправка переподписывается с повышенной комиссией.
+pub fn urgent_attempts() -> usize {
+    env_u64("PRIORITY_URGENT_ATTEMPTS", 3).max(1) as usize
+}
+
+/// Writable-аккаунты инструкций — за них и идёт конкуренция в слоте.
+pub fn writable_accounts(ixs: &[Instruction]) -> Vec<Pubkey> {
+    let mut out: Vec<Pubkey> = Vec::new();
+    for a in ixs.iter().flat_map(|ix| ix.accounts.iter()).filter(|a| a.is_writable) {
+        if !out.contains(&a.pubkey) {
+            out.push(a.pubkey);
+        }
+    }
+    out.truncate(MAX_FEE_ACCOUNTS);
+    out
+}
+
+/// Комиссия за CU для транзакции с инструкциями `ixs`.
+/// Без ответа RPC — минимальная, отправку из-за этого не срываем.
+pub async fn fee_for(rpc: &RpcClient, ixs: &[Instruction]) -> u64 {
+    let accounts = writable_accounts(ixs);
+    let mut fees: Vec<u64> = match rpc.get_recent_prioritization_fees(&accounts).await {
+        Ok(list) => list.into_iter().map(|f| f.prioritization_fee).collect(),
+        Err(e) => {
+            log::warn!("getRecentPrioritizationFees: {e} — беру минимальную комиссию");
+            return min_fee();
+        }
+    };
+    if fees.is_empty() {
+        return min_fee();
+    }
+    fees.sort_unstable();
+    let idx = ((fees.len() - 1) as f64 * percentile() / 100.0).round() as usize;
+    fees[idx].max(min_fee()).min(max_fee())
+}
+
+/// Комиссия повтора `attempt` (0 — первая отправка).
+pub fn escalated(fee: u64, attempt: usize) -> u64 {
+    let k = env_f64("PRIORITY_FEE_ESCALATE", 2.0).max(1.0).powi(attempt as i32);
+    ((fee as f64 * k) as u64).min(max_fee().max(fee))
+}
+
+/// Цена за CU, при которой транзакция с лимитом `cu_limit` заплатит не
+/// больше PRIORITY_FEE_MAX_LAMPORTS.
+pub fn capped(cu_limit: u32, micro_lamports: u64) -> u64 {
+    let cap = (max_fee_lamports() as u128 * 1_000_000 / cu_limit.max(1) as u128) as u64;
+    if micro_lamports > cap {
+        log::debug!("приоритетная комиссия {micro_lamports} µlamports/CU × {cu_limit} CU выше потолка — беру {cap}");
+    }
+    micro_lamports.min(cap)
+}
+
+/// ComputeBudget-инструкции: лимит CU и цена за CU (с потолком `capped`).
+pub fn budget_ixs(cu_limit: u32, micro_lamports: u64) -> Vec<Instruction> {
+    vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(cu_limit),
+        ComputeBudgetInstruction::set_compute_unit_price(capped(cu_limit, micro_lamports)),
+    ]
+}
+
+/// Лимит CU по тому, сколько потратила симуляция (preview.rs).
+pub fn cu_limit(units_consumed: u64) -> u32 {
+    if units_consumed == 0 {
+        return MAX_CU;
+    }
+    ((units_consumed as f64 * cu_margin()).ceil() as u32).clamp(MIN_CU, MAX_CU)
+}
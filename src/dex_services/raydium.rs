@@ -9,7 +9,6 @@ use solana_sdk::{
     instruction::Instruction,
     signature::{Keypair, Signer},
     system_program,
-    transaction::Transaction,
 };
 use anyhow::Context;
 use crate::database::triggers;
@@ -19,6 +18,7 @@ use anchor_lang::AccountDeserialize;
 use anchor_lang::{InstructionData, ToAccountMetas};
 use anyhow::{anyhow, Result};
 use raydium_amm_v3::states::POSITION_SEED;
+use raydium_amm_v3::states::tick_array::TickArrayState;
 use raydium_amm_v3::{
     accounts, instruction,
     states::{
@@ -32,9 +32,16 @@ use spl_associated_token_account::get_associated_token_address;
 use spl_token;
 
 use crate::{
+    database::positions::find_position_index_by_nft,
+    dex_services::{
+        get_info::{compute_amounts, exact_amounts},
+        preview::{self, Expect},
+        token::{self, MintInfo},
+        wirlpool::HarvestSummary,
+    },
     params::*,
-    types::{OpenPositionResult, PoolConfig},
-    utils::utils,
+    types::{OpenPositionResult, PoolConfig, PoolPositionInfo},
+    utils::{get_sol_price_usd, utils},
 };
 
 // Размер одного tick-array в Raydium (константа программы)
@@ -225,6 +232,40 @@ pub async fn open_with_funds_check_clmm(
         &RAYDIUM_CLMM_PROGRAM_ID,
     );
 
+    // ATA владельца под token0 / token1 (раньше сюда ошибочно шли сами минты)
+    let mint0 = token::mint_info(&rpc, &pool_state.token_mint_0).await?;
+    let mint1 = token::mint_info(&rpc, &pool_state.token_mint_1).await?;
+    let ata0  = token::ata(&wallet_pk, &pool_state.token_mint_0, &mint0.program);
+    let ata1  = token::ata(&wallet_pk, &pool_state.token_mint_1, &mint1.program);
+    let wrap_sol = pool_state.token_mint_0.to_string() == WSOL;
+
+    // при transfer-fee в хранилище дойдёт меньше отправленного — поднимаем максимумы
+    let amount_0_max = mint0.gross_for(amount_0_max);
+    let amount_1_max = mint1.gross_for(amount_1_max);
+
+    // сколько по расчёту уйдёт из кошелька — сверяем с симуляцией
+    let (est0, est1) = match base_flag {
+        Some(true)  => (amount_0_max, 0),
+        Some(false) => (0, amount_1_max),
+        None => {
+            let (e0, e1) = exact_amounts(liquidity, sqrt_cur, tick_l, tick_u, None, None)?;
+            (mint0.gross_for(e0), mint1.gross_for(e1))
+        }
+    };
+    let expect = vec![
+        Expect { mint: pool_state.token_mint_0, decimals: dec0, atoms: -(est0 as i128) },
+        Expect { mint: pool_state.token_mint_1, decimals: dec1, atoms: -(est1 as i128) },
+    ];
+
+    let mut instructions: Vec<Instruction> = Vec::new();
+    for (mint, info) in [(pool_state.token_mint_0, mint0), (pool_state.token_mint_1, mint1)] {
+        instructions.push(token::create_ata_ix(&wallet_pk, &wallet_pk, &mint, &info.program));
+    }
+    if wrap_sol {
+        instructions.push(solana_sdk::system_instruction::transfer(&wallet_pk, &ata0, amount_0_max));
+        instructions.push(spl_token::instruction::sync_native(&spl_token::id(), &ata0)?);
+    }
+
     // ───────── 7. Сборка инструкции OpenPositionV2 ────────────────────────
     let ix_data = instruction::OpenPositionV2 {
         liquidity,
@@ -249,8 +290,8 @@ pub async fn open_with_funds_check_clmm(
         tick_array_lower:         tick_array_lower_pk,
         tick_array_upper:         tick_array_upper_pk,
         personal_position:        personal_position_pk,
-        token_account_0:          Pubkey::from_str(&pool.mint_a)?,
-        token_account_1:          Pubkey::from_str(&pool.mint_b)?,
+        token_account_0:          ata0,
+        token_account_1:          ata1,
         token_vault_0:            pool_state.token_vault_0,
         token_vault_1:            pool_state.token_vault_1,
         rent:                     anchor_lang::solana_program::sysvar::rent::ID,
@@ -270,20 +311,24 @@ pub async fn open_with_funds_check_clmm(
     };
 
     // ───────── 8. Отправка транзакции ─────────────────────────────────────
-    let mut tx = Transaction::new_with_payer(&[ix.clone()], Some(&wallet_pk));
-    let latest = rpc.get_latest_blockhash().await?;
-    tx.sign(&[&wallet, &position_nft], latest);
-
-    let instructions = vec![ix];
+    instructions.push(ix);
+    if wrap_sol {
+        instructions.push(spl_token::instruction::close_account(
+            &spl_token::id(), &ata0, &wallet_pk, &wallet_pk, &[],
+        )?);
+    }
     let signers: Vec<&Keypair> = vec![&wallet, &position_nft];
     
-    utils::send_and_confirm(rpc.clone(), instructions, &signers).await?;
+    let signature = preview::expecting(expect, utils::send_and_confirm(rpc.clone(), instructions, &signers))
+        .await?
+        .signature;
 
     // ───────── 9. Результат ───────────────────────────────────────────────
     Ok(OpenPositionResult {
         position_mint: position_nft.pubkey(),
         amount_wsol:   (amount_0_max as f64) / 10f64.powi(dec0 as i32),
         amount_usdc:   (amount_1_max as f64) / 10f64.powi(dec1 as i32),
+        signature:     Some(signature),
     })
 }
 
@@ -365,28 +410,12 @@ pub async fn list_positions_for_owner_clmm(
 /// Закрывает персональную позицию Raydium-CLMM.
 ///
 /// * `position_pk` – PDA personal_position (seed = POSITION_SEED + nft_mint);
-/// * `min_amount0` / `min_amount1` – минимально допустимые суммы вывода (слиппедж).
+/// * `slippage_bps` – минимальные суммы вывода — квота минус слиппедж,
+///   как в `decrease_liquidity_clmm`.
 pub async fn close_clmm_position(
     position_pk: Pubkey,
-    min_amount0: u64,
-    min_amount1: u64,
+    slippage_bps: u16,
 ) -> anyhow::Result<()> {
-    use anchor_lang::AccountDeserialize;
-    use raydium_amm_v3::{
-        accounts, instruction,
-        states::{personal_position::PersonalPositionState, pool::PoolState},
-        ID as RAYDIUM_CLMM_PROGRAM_ID,
-    };
-    use solana_sdk::{
-        instruction::Instruction,
-        signature::{Keypair, Signer},
-        system_program,
-    };
-    use spl_associated_token_account::{
-        get_associated_token_address, instruction::create_associated_token_account,
-    };
-    use spl_token;
-
     // ───────── 0. RPC / кошелёк ────────────────────────────────────────────
     let rpc       = utils::init_rpc();
     let wallet    = utils::load_wallet()?;
@@ -405,87 +434,35 @@ pub async fn close_clmm_position(
         .map_err(|e| anyhow!("decode PoolState: {e}"))?;
 
     // ───────── 2. PDA / ATA ────────────────────────────────────────────────
-    // protocol_position = seed [POSITION_SEED, pool, tick_l, tick_u]
-    let (protocol_position_pk, _) = Pubkey::find_program_address(
-        &[
-            POSITION_SEED.as_bytes(),
-            pool_pk.as_ref(),
-            &pos_state.tick_lower_index.to_be_bytes(),
-            &pos_state.tick_upper_index.to_be_bytes(),
-        ],
-        &RAYDIUM_CLMM_PROGRAM_ID,
-    );
-
-    // tick-array PDA (для DecreaseLiquidity)
-    const TICK_ARRAY_SIZE: i32 = 60;
-    let array_start = |t: i32| {
-        let step = pool_state.tick_spacing as i32 * TICK_ARRAY_SIZE;
-        (t / step) * step
-    };
-    let lower_start = array_start(pos_state.tick_lower_index);
-    let upper_start = array_start(pos_state.tick_upper_index);
-
-    let (tick_array_lower_pk, _) = Pubkey::find_program_address(
-        &[b"tick_array", pool_pk.as_ref(), &lower_start.to_be_bytes()],
-        &RAYDIUM_CLMM_PROGRAM_ID,
-    );
-    let (tick_array_upper_pk, _) = Pubkey::find_program_address(
-        &[b"tick_array", pool_pk.as_ref(), &upper_start.to_be_bytes()],
-        &RAYDIUM_CLMM_PROGRAM_ID,
-    );
-
-    // NFT mint и ATA владельца
+    let mint0 = token::mint_info(&rpc, &pool_state.token_mint_0).await?;
+    let mint1 = token::mint_info(&rpc, &pool_state.token_mint_1).await?;
+    let acc   = liquidity_accounts(&wallet_pk, &pool_pk, &pool_state, &pos_state, &mint0, &mint1);
     let nft_mint    = pos_state.nft_mint;
-    let nft_account = get_associated_token_address(&wallet_pk, &nft_mint);
-
-    // recipient ATA для токен-0 / токен-1
-    let ata0 = get_associated_token_address(&wallet_pk, &pool_state.token_mint_0);
-    let ata1 = get_associated_token_address(&wallet_pk, &pool_state.token_mint_1);
+    let nft_account = acc.nft_account;
 
-    // создаём недостающие ATA (nft_account/ata0/ata1)
-    let mut instructions: Vec<Instruction> = Vec::new();
-    for (ata, mint) in [
-        (nft_account, nft_mint),
-        (ata0, pool_state.token_mint_0),
-        (ata1, pool_state.token_mint_1),
-    ] {
-        if rpc.get_account(&ata).await.is_err() {
-            instructions.push(create_associated_token_account(
-                &wallet_pk,
-                &wallet_pk,
-                &mint,
-                &spl_token::id(),
-            ));
-        }
-    }
+    // создаём недостающие ATA под токены пула (idempotent)
+    let mut instructions: Vec<Instruction> = vec![
+        token::create_ata_ix(&wallet_pk, &wallet_pk, &pool_state.token_mint_0, &mint0.program),
+        token::create_ata_ix(&wallet_pk, &wallet_pk, &pool_state.token_mint_1, &mint1.program),
+    ];
 
     // ───────── 3-A. DecreaseLiquidity (если есть ликвидность) ──────────────
+    // вместе с ликвидностью приходят и комиссии — столько и ждём в симуляции
+    let (mut est0, mut est1) = (0u64, 0u64);
     if pos_state.liquidity > 0 {
-        let dec_ix = Instruction {
-            program_id: RAYDIUM_CLMM_PROGRAM_ID,
-            accounts: accounts::DecreaseLiquidity {
-                nft_owner:                 wallet_pk,
-                nft_account,                                   // ATA c NFT-позиции
-                personal_position:         position_pk,
-                pool_state:                pool_pk,
-                protocol_position:         protocol_position_pk,
-                token_vault_0:             pool_state.token_vault_0,
-                token_vault_1:             pool_state.token_vault_1,
-                tick_array_lower:          tick_array_lower_pk,
-                tick_array_upper:          tick_array_upper_pk,
-                recipient_token_account_0: ata0,
-                recipient_token_account_1: ata1,
-                token_program:             spl_token::ID,
-            }
-            .to_account_metas(None),
-            data: instruction::DecreaseLiquidity {
-                liquidity:    pos_state.liquidity,
-                amount_0_min: min_amount0,
-                amount_1_min: min_amount1,
-            }
-            .data(),
-        };
-        instructions.push(dec_ix);
+        let (out0, out1) = exact_amounts(
+            pos_state.liquidity, pool_state.sqrt_price_x64, pos_state.tick_lower_index, pos_state.tick_upper_index,
+            mint0.orca_fee(), mint1.orca_fee(),
+        )?;
+        let (fee0, fee1) = pending_fees_clmm(&rpc, &pool_pk, &pool_state, &pos_state).await?;
+        est0 = out0 + mint0.net(fee0);
+        est1 = out1 + mint1.net(fee1);
+        // минимумы — по сумме, которая дойдёт до кошелька (квота уже за вычетом transfer-fee)
+        let keep = 1.0 - slippage_bps as f64 / 10_000.0;
+        instructions.push(decrease_ix(
+            wallet_pk, position_pk, pool_pk, &pool_state, &acc,
+            pos_state.liquidity, (out0 as f64 * keep) as u64, (out1 as f64 * keep) as u64,
+        ));
     }
 
     // ───────── 3-B. ClosePosition ──────────────────────────────────────────
@@ -503,16 +480,28 @@ pub async fn close_clmm_position(
         data: instruction::ClosePosition {}.data(),
     };
     instructions.push(close_ix);
+    if pool_state.token_mint_0.to_string() == WSOL {
+        // выведенный wSOL обратно в SOL
+        instructions.push(spl_token::instruction::close_account(
+            &spl_token::id(), &acc.ata0, &wallet_pk, &wallet_pk, &[],
+        )?);
+    }
 
     // ───────── 4. Отправляем транзакцию ────────────────────────────────────
-    utils::send_and_confirm(rpc, instructions, &[&wallet]).await
+    let expect = vec![
+        Expect { mint: pool_state.token_mint_0, decimals: mint0.decimals, atoms: est0 as i128 },
+        Expect { mint: pool_state.token_mint_1, decimals: mint1.decimals, atoms: est1 as i128 },
+    ];
+    preview::expecting(expect, utils::send_urgent(rpc, instructions, &[&wallet])).await?;
+    Ok(())
 }
 
 
 
 /// Закрывает *все* открытые позиции текущего кошелька.
+/// * `slippage_bps` – допуск к минимальным суммам вывода;
 /// * `pool` – необязательный Pubkey пула-фильтр (None → все пулы).
-pub async fn close_all_positions_clmm(pool: Option<Pubkey>) -> anyhow::Result<()> {
+pub async fn close_all_positions_clmm(slippage_bps: u16, pool: Option<Pubkey>) -> anyhow::Result<()> {
     use tokio::time::{sleep, Duration};
 
     // 0. «Флажок» и список позиций
@@ -530,7 +519,7 @@ pub async fn close_all_positions_clmm(pool: Option<Pubkey>) -> anyhow::Result<()
     let mut failed: Vec<Pubkey> = Vec::new();
     for (i, (pk, _)) in positions.iter().enumerate() {
         log::debug!("Closing {}/{} position={pk}", i + 1, positions.len());
-        if let Err(e) = close_clmm_position(*pk, 0, 0).await {
+        if let Err(e) = close_clmm_position(*pk, slippage_bps).await {
             log::error!("  ❌ first-pass failed: {e}");
             failed.push(*pk);
         } else {
@@ -550,7 +539,7 @@ pub async fn close_all_positions_clmm(pool: Option<Pubkey>) -> anyhow::Result<()
     log::warn!("Retrying {} failed positions …", failed.len());
     for (j, pk) in failed.iter().enumerate() {
         log::debug!("Retry {}/{} position={pk}", j + 1, failed.len());
-        if let Err(e) = close_clmm_position(*pk, 0, 0).await {
+        if let Err(e) = close_clmm_position(*pk, slippage_bps).await {
             log::error!("  ❌ second-pass failed: {e}");
         } else {
             log::debug!("  ✅ closed on retry");
@@ -562,3 +551,403 @@ pub async fn close_all_positions_clmm(pool: Option<Pubkey>) -> anyhow::Result<()
     triggers::closing_switcher(false, None).await?;
     Ok(())
 }
+
+
+// ─────────────────────────────────────────────────────────────────────────────
+//  Чтение позиций, частичное изменение ликвидности и сбор комиссий
+//  (то, что для Whirlpool делают get_info.rs и wirlpool.rs)
+// ─────────────────────────────────────────────────────────────────────────────
+
+const Q64: f64 = 18_446_744_073_709_551_616.0;
+
+/// Старт tick-array, в котором лежит `tick` (с округлением вниз и для отрицательных).
+fn tick_array_start(tick: i32, spacing: u16) -> i32 {
+    let step = spacing as i32 * TICK_ARRAY_SIZE;
+    tick.div_euclid(step) * step
+}
+
+fn tick_array_pda(pool_pk: &Pubkey, start: i32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"tick_array", pool_pk.as_ref(), &start.to_be_bytes()],
+        &RAYDIUM_CLMM_PROGRAM_ID,
+    ).0
+}
+
+fn protocol_position_pda(pool_pk: &Pubkey, tick_l: i32, tick_u: i32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[POSITION_SEED.as_bytes(), pool_pk.as_ref(), &tick_l.to_be_bytes(), &tick_u.to_be_bytes()],
+        &RAYDIUM_CLMM_PROGRAM_ID,
+    ).0
+}
+
+/// PDA personal_position по NFT позиции.
+pub fn personal_position_address(nft_mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[POSITION_SEED.as_bytes(), nft_mint.as_ref()], &RAYDIUM_CLMM_PROGRAM_ID).0
+}
+
+async fn load_pool_state(rpc: &RpcClient, pool_pk: &Pubkey) -> Result<PoolState> {
+    let data = rpc.get_account(pool_pk).await?.data;
+    PoolState::try_deserialize(&mut data.as_ref()).map_err(|e| anyhow!("decode PoolState: {e}"))
+}
+
+async fn load_personal(rpc: &RpcClient, position_pk: &Pubkey) -> Result<PersonalPositionState> {
+    let data = rpc.get_account(position_pk).await?.data;
+    PersonalPositionState::try_deserialize(&mut data.as_ref())
+        .map_err(|e| anyhow!("decode PersonalPositionState: {e}"))
+}
+
+/// Текущая цена пула: token1 за 1 token0.
+pub async fn clmm_price(pool_pk: &Pubkey) -> Result<f64> {
+    let ps = load_pool_state(&utils::init_rpc(), pool_pk).await?;
+    Ok(sqrt_price_to_price(ps.sqrt_price_x64, ps.mint_decimals_0, ps.mint_decimals_1))
+}
+
+/// Накопленные комиссии позиции в атомах (token0, token1) — off-chain,
+/// по fee_growth пула и граничных тиков, без транзакции.
+pub async fn pending_fees_clmm(
+    rpc: &RpcClient,
+    pool_pk: &Pubkey,
+    ps: &PoolState,
+    pos: &PersonalPositionState,
+) -> Result<(u64, u64)> {
+    let starts = [pos.tick_lower_index, pos.tick_upper_index].map(|t| tick_array_start(t, ps.tick_spacing));
+    let accs = rpc.get_multiple_accounts(&starts.map(|s| tick_array_pda(pool_pk, s))).await?;
+
+    let mut outside = [(0u128, 0u128); 2];
+    for (i, acc) in accs.into_iter().enumerate() {
+        let acc = acc.ok_or_else(|| anyhow!("tick array {} не инициализирован", starts[i]))?;
+        let ta  = TickArrayState::try_deserialize(&mut acc.data.as_ref())
+            .map_err(|e| anyhow!("decode TickArrayState: {e}"))?;
+        let tick = if i == 0 { pos.tick_lower_index } else { pos.tick_upper_index };
+        let idx  = ((tick - starts[i]) / ps.tick_spacing as i32) as usize;
+        let t    = ta.ticks[idx];
+        outside[i] = (t.fee_growth_outside_0_x64, t.fee_growth_outside_1_x64);
+    }
+
+    let inside = |global: u128, lo_out: u128, up_out: u128| {
+        let below = if ps.tick_current >= pos.tick_lower_index { lo_out } else { global.wrapping_sub(lo_out) };
+        let above = if ps.tick_current <  pos.tick_upper_index { up_out } else { global.wrapping_sub(up_out) };
+        global.wrapping_sub(below).wrapping_sub(above)
+    };
+    let in_0 = inside(ps.fee_growth_global_0_x64, outside[0].0, outside[1].0);
+    let in_1 = inside(ps.fee_growth_global_1_x64, outside[0].1, outside[1].1);
+
+    let owed = |growth: u128, last: u128, owed: u64| {
+        owed + (pos.liquidity as f64 * growth.wrapping_sub(last) as f64 / Q64) as u64
+    };
+    Ok((
+        owed(in_0, pos.fee_growth_inside_0_last_x64, pos.token_fees_owed_0),
+        owed(in_1, pos.fee_growth_inside_1_last_x64, pos.token_fees_owed_1),
+    ))
+}
+
+/// Аналог `fetch_pool_position_info` для позиции Raydium CLMM (`position_pk` — personal_position).
+pub async fn fetch_clmm_position_info(pool_cfg: &PoolConfig, position_pk: &Pubkey) -> Result<PoolPositionInfo> {
+    let rpc     = utils::init_rpc();
+    let pool_pk = Pubkey::from_str(&pool_cfg.pool_address)?;
+    let ps      = load_pool_state(&rpc, &pool_pk).await?;
+    let pos     = load_personal(&rpc, position_pk).await?;
+    let (dec0, dec1) = (ps.mint_decimals_0, ps.mint_decimals_1);
+
+    let price_ab      = sqrt_price_to_price(ps.sqrt_price_x64, dec0, dec1);
+    let disp_invert   = !pool_cfg.name.starts_with("SOL/");
+    let display_price = if disp_invert { 1.0 / price_ab } else { price_ab };
+
+    let sol_usd  = get_sol_price_usd(WSOL, true).await.unwrap_or(0.0);
+    let tokb_usd = if pool_cfg.mint_b == USDC || pool_cfg.mint_b == USDT {
+        1.0
+    } else {
+        sol_usd / price_ab.max(1e-12)
+    };
+
+    // комиссии (в кошелёк дойдут за вычетом transfer-fee)
+    let (fee0, fee1) = pending_fees_clmm(&rpc, &pool_pk, &ps, &pos).await?;
+    let (m0, m1)     = pool_mints(&rpc, &ps).await?;
+    let pending_a = m0.net(fee0) as f64 / 10f64.powi(dec0 as i32);
+    let pending_b = m1.net(fee1) as f64 / 10f64.powi(dec1 as i32);
+
+    // состав позиции: столько придёт при выводе (transfer-fee удерживается и тут)
+    let (raw_a, raw_b) = exact_amounts(
+        pos.liquidity, ps.sqrt_price_x64, pos.tick_lower_index, pos.tick_upper_index,
+        m0.orca_fee(), m1.orca_fee(),
+    )?;
+    let amount_a = raw_a as f64 / 10f64.powi(dec0 as i32);
+    let amount_b = raw_b as f64 / 10f64.powi(dec1 as i32);
+    let value_a  = amount_a * sol_usd;
+    let value_b  = amount_b * tokb_usd;
+    let total    = value_a + value_b;
+    let (pct_a, pct_b) = if total > 0.0 {
+        let a = value_a / total * 100.0;
+        (a, 100.0 - a)
+    } else {
+        (0.0, 0.0)
+    };
+
+    // диапазон
+    let lo_raw = sqrt_price_to_price(
+        raydium_amm_v3::libraries::tick_math::get_sqrt_price_at_tick(pos.tick_lower_index).map_err(|e| anyhow!("{e}"))?,
+        dec0, dec1,
+    );
+    let hi_raw = sqrt_price_to_price(
+        raydium_amm_v3::libraries::tick_math::get_sqrt_price_at_tick(pos.tick_upper_index).map_err(|e| anyhow!("{e}"))?,
+        dec0, dec1,
+    );
+    let (lower_price, upper_price) = if disp_invert { (1.0 / hi_raw, 1.0 / lo_raw) } else { (lo_raw, hi_raw) };
+
+    Ok(PoolPositionInfo {
+        pending_a,
+        pending_b,
+        pending_a_usd: pending_a * sol_usd,
+        sum:           pending_a * sol_usd + pending_b * tokb_usd,
+        amount_a,
+        amount_b,
+        value_a,
+        value_b,
+        pct_a,
+        pct_b,
+        current_price: display_price,
+        lower_price,
+        upper_price,
+        pct_down: (display_price - lower_price) / display_price * 100.0,
+        pct_up:   (upper_price - display_price) / display_price * 100.0,
+        index:    find_position_index_by_nft(&pos.nft_mint.to_string()).await?.unwrap_or_default(),
+        // награды Raydium пока не считаем
+        rewards:     Vec::new(),
+        rewards_usd: 0.0,
+    })
+}
+
+/// Общие аккаунты DecreaseLiquidityV2 / IncreaseLiquidityV2.
+struct LiquidityAccounts {
+    nft_account:       Pubkey,
+    protocol_position: Pubkey,
+    tick_array_lower:  Pubkey,
+    tick_array_upper:  Pubkey,
+    ata0:              Pubkey,
+    ata1:              Pubkey,
+}
+
+fn liquidity_accounts(
+    owner: &Pubkey,
+    pool_pk: &Pubkey,
+    ps: &PoolState,
+    pos: &PersonalPositionState,
+    mint0: &MintInfo,
+    mint1: &MintInfo,
+) -> LiquidityAccounts {
+    LiquidityAccounts {
+        nft_account:       get_associated_token_address(owner, &pos.nft_mint),
+        protocol_position: protocol_position_pda(pool_pk, pos.tick_lower_index, pos.tick_upper_index),
+        tick_array_lower:  tick_array_pda(pool_pk, tick_array_start(pos.tick_lower_index, ps.tick_spacing)),
+        tick_array_upper:  tick_array_pda(pool_pk, tick_array_start(pos.tick_upper_index, ps.tick_spacing)),
+        ata0:              token::ata(owner, &ps.token_mint_0, &mint0.program),
+        ata1:              token::ata(owner, &ps.token_mint_1, &mint1.program),
+    }
+}
+
+/// Минты обоих токенов пула (программа и transfer-fee).
+async fn pool_mints(rpc: &RpcClient, ps: &PoolState) -> Result<(MintInfo, MintInfo)> {
+    Ok((
+        token::mint_info(rpc, &ps.token_mint_0).await?,
+        token::mint_info(rpc, &ps.token_mint_1).await?,
+    ))
+}
+
+fn decrease_ix(
+    owner: Pubkey,
+    position_pk: Pubkey,
+    pool_pk: Pubkey,
+    ps: &PoolState,
+    acc: &LiquidityAccounts,
+    liquidity: u128,
+    min0: u64,
+    min1: u64,
+) -> Instruction {
+    Instruction {
+        program_id: RAYDIUM_CLMM_PROGRAM_ID,
+        // V2 — переводит и Token-2022 (с учётом transfer-fee)
+        accounts: accounts::DecreaseLiquidityV2 {
+            nft_owner:                 owner,
+            nft_account:               acc.nft_account,
+            personal_position:         position_pk,
+            pool_state:                pool_pk,
+            protocol_position:         acc.protocol_position,
+            token_vault_0:             ps.token_vault_0,
+            token_vault_1:             ps.token_vault_1,
+            tick_array_lower:          acc.tick_array_lower,
+            tick_array_upper:          acc.tick_array_upper,
+            recipient_token_account_0: acc.ata0,
+            recipient_token_account_1: acc.ata1,
+            token_program:             spl_token::ID,
+            token_program_2022:        spl_token_2022::ID,
+            memo_program:              spl_memo::ID,
+            vault_0_mint:              ps.token_mint_0,
+            vault_1_mint:              ps.token_mint_1,
+        }
+        .to_account_metas(None),
+        data: instruction::DecreaseLiquidityV2 { liquidity, amount_0_min: min0, amount_1_min: min1 }.data(),
+    }
+}
+
+/// Вывести `pct` % ликвидности позиции (комиссии приходят вместе с ней).
+pub async fn decrease_liquidity_clmm(nft_mint: Pubkey, pct: f64, slippage_bps: u16) -> Result<()> {
+    if !(0.0 < pct && pct <= 100.0) {
+        anyhow::bail!("pct must be within (0;100]");
+    }
+    let rpc         = utils::init_rpc();
+    let wallet      = utils::load_wallet()?;
+    let owner       = wallet.pubkey();
+    let position_pk = personal_position_address(&nft_mint);
+    let pos         = load_personal(&rpc, &position_pk).await?;
+    let ps          = load_pool_state(&rpc, &pos.pool_id).await?;
+    let (m0, m1)    = pool_mints(&rpc, &ps).await?;
+    let acc         = liquidity_accounts(&owner, &pos.pool_id, &ps, &pos, &m0, &m1);
+
+    let liquidity = ((pos.liquidity as f64) * pct / 100.0) as u128;
+    if liquidity == 0 {
+        anyhow::bail!("Position has zero liquidity");
+    }
+    // минимумы — по сумме, которая дойдёт до кошелька (квота уже за вычетом transfer-fee)
+    let (est0, est1) = exact_amounts(
+        liquidity, ps.sqrt_price_x64, pos.tick_lower_index, pos.tick_upper_index,
+        m0.orca_fee(), m1.orca_fee(),
+    )?;
+    let keep = 1.0 - slippage_bps as f64 / 10_000.0;
+    let ix = decrease_ix(
+        owner, position_pk, pos.pool_id, &ps, &acc, liquidity,
+        (est0 as f64 * keep) as u64, (est1 as f64 * keep) as u64,
+    );
+    let mut ixs = vec![
+        token::create_ata_ix(&owner, &owner, &ps.token_mint_0, &m0.program),
+        token::create_ata_ix(&owner, &owner, &ps.token_mint_1, &m1.program),
+        ix,
+    ];
+    if ps.token_mint_0.to_string() == WSOL {
+        ixs.push(spl_token::instruction::close_account(&spl_token::id(), &acc.ata0, &owner, &owner, &[])?);
+    }
+    utils::send_and_confirm(rpc, ixs, &[&wallet]).await?;
+    Ok(())
+}
+
+/// Собрать комиссии: DecreaseLiquidity с нулевой ликвидностью.
+/// Возвращает собранное в «целых» единицах токенов.
+pub async fn harvest_clmm_position(nft_mint: Pubkey, pool: &PoolConfig) -> Result<HarvestSummary> {
+    let rpc         = utils::init_rpc();
+    let wallet      = utils::load_wallet()?;
+    let owner       = wallet.pubkey();
+    let position_pk = personal_position_address(&nft_mint);
+    let pos         = load_personal(&rpc, &position_pk).await?;
+    let ps          = load_pool_state(&rpc, &pos.pool_id).await?;
+    let (m0, m1)    = pool_mints(&rpc, &ps).await?;
+    let acc         = liquidity_accounts(&owner, &pos.pool_id, &ps, &pos, &m0, &m1);
+
+    let (fee0, fee1) = pending_fees_clmm(&rpc, &pos.pool_id, &ps, &pos).await?;
+    if fee0 == 0 && fee1 == 0 {
+        return Err(anyhow!("No fees to collect for position {}", nft_mint));
+    }
+    let mut ixs = vec![
+        token::create_ata_ix(&owner, &owner, &ps.token_mint_0, &m0.program),
+        token::create_ata_ix(&owner, &owner, &ps.token_mint_1, &m1.program),
+        decrease_ix(owner, position_pk, pos.pool_id, &ps, &acc, 0, 0, 0),
+    ];
+    if ps.token_mint_0.to_string() == WSOL {
+        ixs.push(spl_token::instruction::close_account(&spl_token::id(), &acc.ata0, &owner, &owner, &[])?);
+    }
+    utils::send_and_confirm(rpc, ixs, &[&wallet]).await?;
+
+    // в кошелёк пришло за вычетом transfer-fee
+    let amount_a = m0.net(fee0) as f64 / 10f64.powi(ps.mint_decimals_0 as i32);
+    let amount_b = m1.net(fee1) as f64 / 10f64.powi(ps.mint_decimals_1 as i32);
+    let price    = sqrt_price_to_price(ps.sqrt_price_x64, ps.mint_decimals_0, ps.mint_decimals_1);
+    let sol_usd  = get_sol_price_usd(WSOL, true).await?;
+    let tokb_usd = if pool.mint_b == USDC || pool.mint_b == USDT { 1.0 } else { sol_usd / price.max(1e-12) };
+    Ok(HarvestSummary {
+        amount_a,
+        amount_b,
+        price_a_in_usd: sol_usd,
+        total_usd:      amount_a * sol_usd + amount_b * tokb_usd,
+    })
+}
+
+/// Доложить в позицию ликвидность на `usd_budget` (токены должны быть в кошельке;
+/// SOL оборачивается в wSOL на время транзакции).
+pub async fn increase_liquidity_clmm(
+    nft_mint: Pubkey,
+    usd_budget: f64,
+    pool: &PoolConfig,
+    slippage_bps: u16,
+) -> Result<()> {
+    let rpc         = utils::init_rpc();
+    let _guard      = WALLET_MUTEX.lock().await;
+    let wallet      = utils::load_wallet()?;
+    let owner       = wallet.pubkey();
+    let position_pk = personal_position_address(&nft_mint);
+    let pos         = load_personal(&rpc, &position_pk).await?;
+    let ps          = load_pool_state(&rpc, &pos.pool_id).await?;
+    let (m0, m1)    = pool_mints(&rpc, &ps).await?;
+    let acc         = liquidity_accounts(&owner, &pos.pool_id, &ps, &pos, &m0, &m1);
+
+    let price    = sqrt_price_to_price(ps.sqrt_price_x64, ps.mint_decimals_0, ps.mint_decimals_1);
+    let sol_usd  = get_sol_price_usd(WSOL, true).await?;
+    let tokb_usd = if pool.mint_b == USDC || pool.mint_b == USDT { 1.0 } else { sol_usd / price.max(1e-12) };
+
+    // стоимость единицы ликвидности в USD → сколько ликвидности даёт бюджет
+    let sqrt_p = ps.sqrt_price_x64 as f64 / Q64;
+    let sqrt_l = 1.0001_f64.powf(pos.tick_lower_index as f64 / 2.0);
+    let sqrt_u = 1.0001_f64.powf(pos.tick_upper_index as f64 / 2.0);
+    let (unit0, unit1) = compute_amounts(1.0, sqrt_p, sqrt_l, sqrt_u);
+    let unit_usd = unit0 / 10f64.powi(ps.mint_decimals_0 as i32) * sol_usd
+                 + unit1 / 10f64.powi(ps.mint_decimals_1 as i32) * tokb_usd;
+    if unit_usd <= 0.0 {
+        anyhow::bail!("cannot size liquidity for position {}", nft_mint);
+    }
+    let liquidity = (usd_budget / unit_usd).floor();
+    let (need0, need1) = compute_amounts(liquidity, sqrt_p, sqrt_l, sqrt_u);
+    let ovr  = 1.0 + slippage_bps as f64 / 10_000.0;
+    // в хранилище должно дойти need*, transfer-fee сверху
+    let max0 = m0.gross_for((need0 * ovr) as u64 + 1);
+    let max1 = m1.gross_for((need1 * ovr) as u64 + 1);
+
+    let wrap_sol = ps.token_mint_0.to_string() == WSOL;
+    let mut ixs = vec![
+        token::create_ata_ix(&owner, &owner, &ps.token_mint_0, &m0.program),
+        token::create_ata_ix(&owner, &owner, &ps.token_mint_1, &m1.program),
+    ];
+    if wrap_sol {
+        ixs.push(solana_sdk::system_instruction::transfer(&owner, &acc.ata0, max0));
+        ixs.push(spl_token::instruction::sync_native(&spl_token::id(), &acc.ata0)?);
+    }
+    ixs.push(Instruction {
+        program_id: RAYDIUM_CLMM_PROGRAM_ID,
+        accounts: accounts::IncreaseLiquidityV2 {
+            nft_owner:          owner,
+            nft_account:        acc.nft_account,
+            pool_state:         pos.pool_id,
+            protocol_position:  acc.protocol_position,
+            personal_position:  position_pk,
+            tick_array_lower:   acc.tick_array_lower,
+            tick_array_upper:   acc.tick_array_upper,
+            token_account_0:    acc.ata0,
+            token_account_1:    acc.ata1,
+            token_vault_0:      ps.token_vault_0,
+            token_vault_1:      ps.token_vault_1,
+            token_program:      spl_token::ID,
+            token_program_2022: spl_token_2022::ID,
+            vault_0_mint:       ps.token_mint_0,
+            vault_1_mint:       ps.token_mint_1,
+        }
+        .to_account_metas(None),
+        data: instruction::IncreaseLiquidityV2 {
+            liquidity:    liquidity as u128,
+            amount_0_max: max0,
+            amount_1_max: max1,
+            base_flag:    None,
+        }
+        .data(),
+    });
+    if wrap_sol {
+        ixs.push(spl_token::instruction::close_account(&spl_token::id(), &acc.ata0, &owner, &owner, &[])?);
+    }
+    utils::send_and_confirm(rpc, ixs, &[&wallet]).await?;
+    Ok(())
+}
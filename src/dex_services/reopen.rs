@@ -0,0 +1,137 @@
+// src/dex_services/reopen.rs
+//
+// Перецентрирование ноги Whirlpool одним бандлом: закрыть старую позицию,
+// при необходимости свапнуть через Jupiter и открыть новую — три транзакции
+// уходят Jito-бандлом (jito.rs) подряд в одном слоте, и между закрытием и
+// открытием цену никто не сдвинет. Открытие считаем с учётом того, что
+// вернёт закрытие (минимум по квоте + комиссии), свап покрывает разницу.
+// Закрытие и открытие сверяются с симуляцией по своим квотам, свап — по
+// квоте Jupiter. Если бандл точно не лёг — те же транзакции, собранные
+// заново (без чаевых, со свежим blockhash), уходят по одной.
+
+use anyhow::{anyhow, Result};
+use orca_whirlpools::{close_position_instructions, set_whirlpools_config_address, ClosePositionInstruction, WhirlpoolsConfigInput};
+use orca_whirlpools_client::{get_position_address, Position};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+
+use crate::dex_services::{alt, bundle};
+use crate::dex_services::jito::{self, BundleTx};
+use crate::dex_services::multi_open::{LegRequest, PreparedOpen};
+use crate::dex_services::preview::Expect;
+use crate::dex_services::swap::MIN_SWAP_ATOMS;
+use crate::params::{OVR, WALLET_MUTEX};
+use crate::types::{OpenPositionResult, PoolConfig};
+use crate::utils::op;
+use crate::utils::utils::{self, Urgency};
+
+/// запас на проскальзывание свапа
+const SWAP_BUFFER: f64 = 1.02;
+/// проскальзывание свапа внутри бандла, bps
+const SWAP_SLIPPAGE_BPS: u16 = 100;
+
+/// Закрыть `position_mint` и открыть на его средства `[price_low; price_high]`
+/// (сырые цены «B per A», депозит — в токене B). `None` — одной связкой не
+/// выйдет (позиция из бандла или не хватает средств даже со свапом), пусть
+/// вызывающий закроет и откроет по отдельности.
+pub async fn reopen_position(
+    position_mint: Pubkey,
+    price_low: f64,
+    price_high: f64,
+    deposit: f64,
+    pool: &PoolConfig,
+    slippage: u16,
+) -> Result<Option<OpenPositionResult>> {
+    set_whirlpools_config_address(WhirlpoolsConfigInput::SolanaMainnet)
+        .map_err(op("set_whirlpools_config_address"))?;
+    let rpc    = utils::init_rpc();
+    let _guard = WALLET_MUTEX.lock().await;
+    let wallet = utils::load_wallet()?;
+    let owner  = wallet.pubkey();
+
+    // позиции бандла закрываются своими инструкциями — их по-старому
+    if bundle::resolve(&rpc, position_mint).await?.is_some() {
+        return Ok(None);
+    }
+
+    // ───── 1. Закрытие и что оно вернёт ───────────────────────────────
+    let ClosePositionInstruction {
+        instructions: close_ixs,
+        additional_signers,
+        quote,
+        fees_quote,
+        ..
+    } = close_position_instructions(&rpc, position_mint, Some(slippage), Some(owner))
+        .await
+        .map_err(|e| anyhow!("close_position_instructions: {e}"))?;
+    let position = Position::from_bytes(&rpc.get_account(&get_position_address(&position_mint)?.0).await?.data)?;
+    let back_a = quote.token_min_a + fees_quote.fee_owed_a;
+    let back_b = quote.token_min_b + fees_quote.fee_owed_b;
+    let est_a  = quote.token_est_a + fees_quote.fee_owed_a;
+    let est_b  = quote.token_est_b + fees_quote.fee_owed_b;
+
+    // ───── 2. Новая нога по текущему состоянию пула ───────────────────
+    let leg = LegRequest { lower_price: price_low, upper_price: price_high, deposit };
+    let prepared = PreparedOpen::new(&rpc, &wallet, pool, &[leg], slippage).await?;
+    let ((mint_a, dec_a), (mint_b, dec_b)) = prepared.mints();
+    let price = prepared.price_a_in_b();
+    let (free_a, free_b) = prepared.free_atoms(&rpc).await?;
+    let have_a = free_a + back_a;
+    let have_b = free_b + back_b;
+    let need_a = (prepared.max_a() as f64 * OVR).ceil() as u64;
+    let need_b = (prepared.max_b() as f64 * OVR).ceil() as u64;
+
+    // ───── 3. Свап на недостающее ─────────────────────────────────────
+    let scale = |dec: u8| 10f64.powi(dec as i32);
+    let swap = if need_a > have_a {
+        // докупаем A за B
+        let b_in = ((need_a - have_a) as f64 / scale(dec_a) * price * SWAP_BUFFER * scale(dec_b)).ceil() as u64;
+        if have_b < need_b + b_in {
+            log::info!("{}: на перецентрирование не хватает {} (B)", pool.name, (need_b + b_in - have_b) as f64 / scale(dec_b));
+            return Ok(None);
+        }
+        Some((mint_b, mint_a, b_in.max(MIN_SWAP_ATOMS)))
+    } else if need_b > have_b {
+        // докупаем B за A
+        let a_in = ((need_b - have_b) as f64 / scale(dec_b) / price * SWAP_BUFFER * scale(dec_a)).ceil() as u64;
+        if have_a < need_a + a_in {
+            log::info!("{}: на перецентрирование не хватает {} (A)", pool.name, (need_a + a_in - have_a) as f64 / scale(dec_a));
+            return Ok(None);
+        }
+        Some((mint_a, mint_b, a_in.max(MIN_SWAP_ATOMS)))
+    } else {
+        None
+    };
+
+    // ───── 4. Транзакции бандла: close → swap → open ──────────────────
+    let open_ixs = prepared.instructions(0..1, true)?;
+    let stable = prepared.stable_accounts(&[position.tick_lower_index, position.tick_upper_index])?;
+    let table = alt::ensure_pool_table(&rpc, &wallet, &pool.pool_address, &stable).await?;
+
+    let mut close_signers: Vec<&Keypair> = vec![&wallet];
+    close_signers.extend(additional_signers.iter());
+    let mut open_signers: Vec<&Keypair> = vec![&wallet];
+    open_signers.extend(prepared.signers(0..1));
+
+    let close_expect = vec![
+        Expect { mint: mint_a, decimals: dec_a, atoms: est_a as i128 },
+        Expect { mint: mint_b, decimals: dec_b, atoms: est_b as i128 },
+    ];
+    let mut txs = vec![BundleTx::Instructions { instructions: close_ixs, signers: close_signers, expect: close_expect }];
+    if let Some((sell, buy, atoms)) = swap {
+        txs.push(BundleTx::Swap { in_mint: sell, out_mint: buy, amount_atoms: atoms, slippage_bps: Some(SWAP_SLIPPAGE_BPS) });
+    }
+    txs.push(BundleTx::Instructions { instructions: open_ixs, signers: open_signers, expect: prepared.expects(0..1) });
+
+    // перецентрирование — выход из старого диапазона: срочно
+    let sigs = jito::send_bundle_or_fallback(jito::config(), rpc.clone(), txs, &[table], Urgency::Urgent)
+        .await
+        .map_err(op("reopen_position"))?;
+    log::info!("{}: {position_mint} перецентрирована, открытие {}", pool.name, sigs[sigs.len() - 1]);
+
+    let mut res = prepared.results()?.remove(0);
+    res.signature = sigs.last().copied();
+    Ok(Some(res))
+}
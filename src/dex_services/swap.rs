@@ -13,16 +13,19 @@ use solana_client::{
 };
 
 use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
-use solana_sdk::transaction::Transaction;
 use std::env;
 use crate::utils;
 use solana_sdk::{
     pubkey::Pubkey,
-    signature::{read_keypair_file, Keypair},
+    signature::{read_keypair_file, Keypair, Signature},
 };
 use solana_sdk::transaction::VersionedTransaction;
-use spl_associated_token_account::get_associated_token_address;
 use crate::dex_services::net::http_client;
+use crate::dex_services::preview::{self, Expect};
+use crate::dex_services::priority;
+use crate::dex_services::token;
+use crate::dex_services::tx_sender::TxSender;
+use crate::database::tx_costs;
 
 pub const MIN_SWAP_ATOMS: u64  = 10_000;   // ≈ 0.00001 token
 
@@ -66,14 +69,15 @@ pub async fn execute_swap_tokens(
 
 
     let wsol_pubkey = Pubkey::from_str(&WSOL)?;
-    ensure_ata_sync(&rpc, &payer, &wallet, &wsol_pubkey)?;
+    ensure_ata(&rpc, &payer, &wallet, &wsol_pubkey).await?;
 
 
     let get_bal = |mint: &Pubkey, dec: u8| -> Result<f64> {
         if mint.to_string() == WSOL {
             Ok(rpc.get_balance(&wallet)? as f64 / 1e9)
         } else {
-            let ata = get_associated_token_address(&wallet, mint);
+            let program = token::mint_info_blocking(&rpc, mint)?.program;
+            let ata = token::ata(&wallet, mint, &program);
             let ui  = rpc.get_token_account_balance(&ata).ok();
             Ok(ui
                 .and_then(|b| b.amount.parse::<u64>().ok())
@@ -86,10 +90,15 @@ pub async fn execute_swap_tokens(
         return Ok(SwapResult {
             balance_sell: get_bal(&in_mint , in_dec )?,
             balance_buy : get_bal(&out_mint, out_dec)?,
+            signature:    None,
         });
     }
 
 
+    // лимит CU Jupiter считает симуляцией сам, цену CU даём свою — с потолком
+    // на худший лимит, заранее он неизвестен
+    let priority_fee = priority::capped(priority::MAX_CU, priority::fee_for(&utils::utils::init_rpc(), &[]).await);
+
     // ─── 3. две попытки:  50 bps  → 150 bps ─────────────────────────────
     let mut retry = 0;
     for slippage_bps in [40_u16, 120_u16, 500_u16] {
@@ -103,10 +112,12 @@ pub async fn execute_swap_tokens(
 
         // 3.2 build swap (v0)
         let swap_req = serde_json::json!({
-            "quoteResponse": quote,
+            "quoteResponse": quote.clone(),
             "userPublicKey": wallet.to_string(),
             "wrapAndUnwrapSol": true,
-            "asLegacyTransaction": false
+            "asLegacyTransaction": false,
+            "dynamicComputeUnitLimit": true,
+            "computeUnitPriceMicroLamports": priority_fee
         });
         let swap_json: serde_json::Value = http
             .post("https://quote-api.jup.ag/v6/swap")
@@ -130,14 +141,23 @@ pub async fn execute_swap_tokens(
             vtx.signatures[0] = sig;
         }
 
-        // 3.4 отправка
-        match rpc.send_and_confirm_transaction(&vtx) {
+        // 3.4 симуляция: превью балансов и сверка с квотой
+        let checked = preview_swap(&vtx, (in_mint, in_dec), (out_mint, out_dec), amount_atoms, &quote).await;
+
+        // 3.5 отправка
+        let sent = match checked {
+            Ok(()) => tx_costs::scoped(None, "swap", TxSender::new(utils::utils::init_rpc()).send_signed(&vtx))
+                .await
+                .map(|o| o.signature),
+            Err(e) => Err(e),
+        };
+        match sent {
             Ok(sig) => {
                 println!("Swap OK: {sig}");
                 sleep(Duration::from_millis(500)).await;
                 let bal_in  = get_bal(&in_mint , in_dec )?;
                 let bal_out = get_bal(&out_mint, out_dec)?;
-                return Ok(SwapResult { balance_sell: bal_in, balance_buy: bal_out });
+                return Ok(SwapResult { balance_sell: bal_in, balance_buy: bal_out, signature: Some(sig) });
             }
             Err(e) if RETRYABLE.iter().any(|tag| e.to_string().contains(tag)) && retry < MAX_RETRY => {
                 tokio::time::sleep(Duration::from_millis(400)).await;   // ждём следующий слот
@@ -161,7 +181,7 @@ pub async fn execute_swap_tokens(
                 }
                 let bal_in  = get_bal(&in_mint , in_dec )?;
                 let bal_out = get_bal(&out_mint, out_dec)?;
-                return Ok(SwapResult { balance_sell: bal_in, balance_buy: bal_out });
+                return Ok(SwapResult { balance_sell: bal_in, balance_buy: bal_out, signature: None });
 
             }
 
@@ -170,7 +190,7 @@ pub async fn execute_swap_tokens(
                 println!("Jupiter virtual-ATA error (игнорируем): {e}");
                 let bal_in  = get_bal(&in_mint , in_dec )?;
                 let bal_out = get_bal(&out_mint, out_dec)?;
-                return Ok(SwapResult { balance_sell: bal_in, balance_buy: bal_out });
+                return Ok(SwapResult { balance_sell: bal_in, balance_buy: bal_out, signature: None });
             }
 
             Err(e) if retry < 2 => {
@@ -191,31 +211,59 @@ pub async fn execute_swap_tokens(
 pub struct SwapResult {
     pub balance_sell: f64,
     pub balance_buy:  f64,
+    /// подпись свапа (`None` — свапа не было, он дробился или бумажный режим)
+    pub signature:    Option<Signature>,
 }
 
 
 async fn swap_once_wrap(sell_mint: &str, buy_mint: &str, amount: f64) -> Result<()> {
-    use base64::{engine::general_purpose::STANDARD as b64, Engine as _};
-
     let (in_pub,  in_dec)  = mint_pub_dec(sell_mint)?;
-    let (out_pub, _out_de) = mint_pub_dec(buy_mint)?;
-    let payer              = utils::utils::load_wallet()?;
+    let (out_pub, out_dec) = mint_pub_dec(buy_mint)?;
     let amount_atoms       = ((amount * 10f64.powi(in_dec as i32)).ceil()) as u64;
     if amount_atoms < MIN_SWAP_ATOMS { bail!("слишком маленькая сумма для свопа") }
 
+    let (vtx, quote) = build_swap_tx(in_pub, out_pub, amount_atoms, None).await?;
+    preview_swap(&vtx, (in_pub, in_dec), (out_pub, out_dec), amount_atoms, &quote).await?;
+    tx_costs::scoped(None, "swap", TxSender::new(utils::utils::init_rpc()).send_signed(&vtx))
+        .await
+        .map_err(|e| anyhow!("swap_once: {e}"))?;
+    Ok(())
+}
+
+/// Подписанная кошельком транзакция свапа Jupiter (v0) и её квота, без
+/// отправки — её можно отправить самому или положить в бандл (jito.rs).
+/// `slippage_bps = None` — проскальзывание по умолчанию Jupiter.
+pub async fn build_swap_tx(
+    in_mint: Pubkey,
+    out_mint: Pubkey,
+    amount_atoms: u64,
+    slippage_bps: Option<u16>,
+) -> Result<(VersionedTransaction, Value)> {
+    use base64::{engine::general_purpose::STANDARD as b64, Engine as _};
+
+    let payer = utils::utils::load_wallet()?;
+
     // 1) Quote ----------------------------------------------------------------
-    let url = format!(
+    let mut url = format!(
         "https://quote-api.jup.ag/v6/quote?inputMint={}&outputMint={}&amount={}",
-        in_pub, out_pub, amount_atoms
+        in_mint, out_mint, amount_atoms
     );
+    if let Some(bps) = slippage_bps {
+        url.push_str(&format!("&slippageBps={bps}"));
+    }
     let quote: serde_json::Value = http_client().get(&url).send().await?.json().await?;
 
     // 2) SwapTx ----------------------------------------------------------------
     let swap_req = serde_json::json!({
-        "quoteResponse": quote,
+        "quoteResponse": quote.clone(),
         "userPublicKey": payer.pubkey().to_string(),
         "wrapAndUnwrapSol": true,
-        "asLegacyTransaction": false
+        "asLegacyTransaction": false,
+        "dynamicComputeUnitLimit": true,
+        "computeUnitPriceMicroLamports": priority::capped(
+            priority::MAX_CU,
+            priority::fee_for(&utils::utils::init_rpc(), &[]).await,
+        )
     });
     let swap_json: serde_json::Value = http_client()
         .post("https://quote-api.jup.ag/v6/swap")
@@ -227,16 +275,40 @@ async fn swap_once_wrap(sell_mint: &str, buy_mint: &str, amount: f64) -> Result<
     let tx_b64 = swap_json["swapTransaction"]
         .as_str()
         .ok_or_else(|| anyhow!("swapTransaction missing"))?;
+
+    // 3) Sign ------------------------------------------------------------------
     let mut vtx: VersionedTransaction = bincode::deserialize(&b64.decode(tx_b64)?)?;
     let msg  = vtx.message.serialize();
     let sig  = payer.sign_message(&msg);
     if vtx.signatures.is_empty() { vtx.signatures.push(sig) } else { vtx.signatures[0] = sig }
+    Ok((vtx, quote))
+}
 
-    // 3) Send ------------------------------------------------------------------
-    RpcClient::new(RPC_URL.to_string())
-        .send_and_confirm_transaction(&vtx)
-        .map_err(|e| anyhow!("swap_once: {e}"))?;
-    Ok(())
+/// Чего ждём от свапа по квоте: списание `amount_in`, приход `outAmount`.
+pub fn swap_expects(
+    (in_mint, in_dec): (Pubkey, u8),
+    (out_mint, out_dec): (Pubkey, u8),
+    amount_in: u64,
+    quote: &Value,
+) -> Vec<Expect> {
+    let mut expect = vec![Expect { mint: in_mint, decimals: in_dec, atoms: -(amount_in as i128) }];
+    if let Some(out) = quote["outAmount"].as_str().and_then(|s| s.parse::<i128>().ok()) {
+        expect.push(Expect { mint: out_mint, decimals: out_dec, atoms: out });
+    }
+    expect
+}
+
+/// Симуляция свапа: превью балансов и сверка с квотой (`swap_expects`).
+/// Расхождение больше порога — ошибка.
+pub async fn preview_swap(
+    vtx: &VersionedTransaction,
+    input: (Pubkey, u8),
+    output: (Pubkey, u8),
+    amount_in: u64,
+    quote: &Value,
+) -> Result<()> {
+    let sim = preview::simulate(&utils::utils::init_rpc(), vtx).await?;
+    preview::expecting(swap_expects(input, output, amount_in, quote), async { preview::review(&sim.preview) }).await
 }
 
 fn mint_pub_dec(symbol: &str) -> Result<(Pubkey,u8)> {
@@ -247,34 +319,34 @@ fn mint_pub_dec(symbol: &str) -> Result<(Pubkey,u8)> {
         WETH => (Pubkey::from_str(WETH)?, 8),
         WBTC => (Pubkey::from_str(WBTC)?, 8),
         USDT => (Pubkey::from_str(USDT)?, 6),
-        _    => bail!("mint {symbol} не поддерживается"),
+        // прочие минты (в т.ч. Token-2022) — децималы из самого минта
+        _    => {
+            let mint = Pubkey::from_str(symbol)
+                .map_err(|_| anyhow!("mint {symbol} не поддерживается"))?;
+            let info = token::mint_info_blocking(&RpcClient::new(RPC_URL.to_string()), &mint)?;
+            (mint, info.decimals)
+        }
     })
 }
 
-fn ensure_ata_sync(
+async fn ensure_ata(
     rpc: &RpcClient,
     payer: &Keypair,
     owner: &Pubkey,
     mint: &Pubkey,
 ) -> anyhow::Result<()> {
-    let ata = get_associated_token_address(owner, mint);
+    let program = token::mint_info_blocking(rpc, mint)?.program;
+    let ata = token::ata(owner, mint, &program);
     if rpc.get_account(&ata).is_err() {
         // создаём idempotent-версию ATA — безопасно, если уже существует
         let ix = create_associated_token_account_idempotent(
             &payer.pubkey(), // funding_address (payer)
             owner,           // wallet_address (owner)
             mint,            // mint
-            &spl_token::id(),// **программа ассоц. токен аккаунта**
-        );
-        let recent = rpc.get_latest_blockhash()
-            .map_err(|e| anyhow::anyhow!("get_latest_blockhash failed: {}", e))?;
-        let tx = Transaction::new_signed_with_payer(
-            &[ix],
-            Some(&payer.pubkey()),
-            &[payer],
-            recent,
+            &program,        // программа минта (SPL Token или Token-2022)
         );
-        rpc.send_and_confirm_transaction(&tx)
+        tx_costs::scoped(None, "ata", utils::utils::send_and_confirm(utils::utils::init_rpc(), vec![ix], &[payer]))
+            .await
             .map_err(|e| anyhow::anyhow!("create ATA failed: {}", e))?;
     }
     Ok(())
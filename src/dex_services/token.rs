@@ -0,0 +1,134 @@
+// src/dex_services/token.rs
+//
+// Минты SPL Token и Token-2022: программа минта, ATA под эту программу
+// и комиссия за перевод (расширение transfer-fee). ATA и суммы депозита /
+// вывода считаем отсюда, а не от `spl_token::id()`.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Result};
+use once_cell::sync::Lazy;
+use orca_whirlpools_core::TransferFee;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use spl_token::solana_program::program_pack::Pack;
+use spl_token_2022::extension::transfer_fee::TransferFeeConfig;
+use spl_token_2022::extension::{BaseStateWithExtensions, StateWithExtensions};
+use tokio::sync::RwLock;
+
+/// комиссия transfer-fee меняется не чаще раза в эпоху
+const MINT_TTL: Duration = Duration::from_secs(600);
+
+static MINTS: Lazy<RwLock<HashMap<Pubkey, (MintInfo, Instant)>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Что нужно знать о минте для переводов.
+#[derive(Debug, Clone, Copy)]
+pub struct MintInfo {
+    /// `spl_token` или `spl_token_2022`
+    pub program:  Pubkey,
+    pub decimals: u8,
+    /// комиссия за перевод текущей эпохи (0 — расширения нет)
+    pub fee_bps:  u16,
+    pub max_fee:  u64,
+}
+
+impl MintInfo {
+    pub fn is_2022(&self) -> bool {
+        self.program == spl_token_2022::id()
+    }
+
+    /// Комиссия, которую удержит перевод `amount` атомов.
+    pub fn fee(&self, amount: u64) -> u64 {
+        if self.fee_bps == 0 || amount == 0 {
+            return 0;
+        }
+        let fee = (amount as u128 * self.fee_bps as u128).div_ceil(10_000) as u64;
+        fee.min(self.max_fee)
+    }
+
+    /// Сколько дойдёт до получателя при переводе `gross`.
+    pub fn net(&self, gross: u64) -> u64 {
+        gross - self.fee(gross)
+    }
+
+    /// Сколько перевести, чтобы до получателя дошло `net`.
+    pub fn gross_for(&self, net: u64) -> u64 {
+        if self.fee_bps == 0 || net == 0 {
+            return net;
+        }
+        if self.fee_bps >= 10_000 {
+            return net.saturating_add(self.max_fee);
+        }
+        let raw = (net as u128 * 10_000).div_ceil(10_000 - self.fee_bps as u128) as u64;
+        if raw - net >= self.max_fee { net + self.max_fee } else { raw }
+    }
+
+    /// Комиссия в виде, который принимают квоты `orca_whirlpools_core`.
+    pub fn orca_fee(&self) -> Option<TransferFee> {
+        (self.fee_bps > 0).then(|| TransferFee::new_with_max(self.fee_bps, self.max_fee))
+    }
+}
+
+/// Разобрать аккаунт минта (`owner` — программа-владелец аккаунта).
+pub fn parse_mint(owner: &Pubkey, data: &[u8], epoch: u64) -> Result<MintInfo> {
+    if *owner == spl_token::id() {
+        let m = spl_token::state::Mint::unpack(&data[..spl_token::state::Mint::LEN])?;
+        return Ok(MintInfo { program: *owner, decimals: m.decimals, fee_bps: 0, max_fee: 0 });
+    }
+    if *owner != spl_token_2022::id() {
+        bail!("аккаунт не минт: владелец {owner}");
+    }
+    let st = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(data)
+        .map_err(|e| anyhow!("decode Token-2022 mint: {e}"))?;
+    let (fee_bps, max_fee) = match st.get_extension::<TransferFeeConfig>() {
+        Ok(cfg) => {
+            let f = cfg.get_epoch_fee(epoch);
+            (u16::from(f.transfer_fee_basis_points), u64::from(f.maximum_fee))
+        }
+        Err(_) => (0, 0),
+    };
+    Ok(MintInfo { program: *owner, decimals: st.base.decimals, fee_bps, max_fee })
+}
+
+/// Минт с кэшем на `MINT_TTL`.
+pub async fn mint_info(rpc: &RpcClient, mint: &Pubkey) -> Result<MintInfo> {
+    if let Some((info, at)) = MINTS.read().await.get(mint) {
+        if at.elapsed() < MINT_TTL {
+            return Ok(*info);
+        }
+    }
+    let acc = rpc.get_account(mint).await
+        .map_err(|e| anyhow!("get mint {mint}: {e}"))?;
+    // эпоха нужна только для комиссии Token-2022
+    let epoch = if acc.owner == spl_token_2022::id() { rpc.get_epoch_info().await?.epoch } else { 0 };
+    let info  = parse_mint(&acc.owner, &acc.data, epoch)?;
+    MINTS.write().await.insert(*mint, (info, Instant::now()));
+    Ok(info)
+}
+
+/// Минт через блокирующий клиент (для синхронного пути свапа), без кэша.
+pub fn mint_info_blocking(rpc: &solana_client::rpc_client::RpcClient, mint: &Pubkey) -> Result<MintInfo> {
+    let acc = rpc.get_account(mint)
+        .map_err(|e| anyhow!("get mint {mint}: {e}"))?;
+    let epoch = if acc.owner == spl_token_2022::id() { rpc.get_epoch_info()?.epoch } else { 0 };
+    parse_mint(&acc.owner, &acc.data, epoch)
+}
+
+/// ATA владельца под программу минта.
+pub fn ata(owner: &Pubkey, mint: &Pubkey, program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(owner, mint, program)
+}
+
+/// ATA владельца для `mint` (программа берётся из самого минта).
+pub async fn owner_ata(rpc: &RpcClient, owner: &Pubkey, mint: &Pubkey) -> Result<Pubkey> {
+    Ok(ata(owner, mint, &mint_info(rpc, mint).await?.program))
+}
+
+/// Идемпотентное создание ATA под программу минта.
+pub fn create_ata_ix(payer: &Pubkey, owner: &Pubkey, mint: &Pubkey, program: &Pubkey) -> Instruction {
+    create_associated_token_account_idempotent(payer, owner, mint, program)
+}
@@ -0,0 +1,247 @@
+// src/dex_services/tx_sender.rs
+//
+// Единая отправка транзакций. Подписанная транзакция рассылается повторно
+// (раз в REBROADCAST_INTERVAL), пока не ляжет или высота блока не уйдёт за
+// last_valid_block_height её blockhash.
+// Подтверждение ждём по signatureSubscribe; опрос getSignatureStatuses на
+// каждом повторе страхует от потерянного уведомления и упавшего веб-сокета.
+// Истёкший blockhash означает, что эта подпись уже не ляжет, — тогда
+// собранную из инструкций транзакцию можно безопасно переподписать со
+// свежим blockhash (готовую, вроде свапа Jupiter, — нет).
+// Итог — `TxOutcome` (слот, комиссия, потраченные CU), отказ программы —
+// `TxFailure` с кодом ошибки.
+
+use std::fmt;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use futures::StreamExt;
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcSendTransactionConfig, RpcSignatureSubscribeConfig};
+use solana_client::rpc_response::RpcSignatureResult;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    signature::Signature,
+    transaction::VersionedTransaction,
+};
+use tokio::time::{interval, sleep, Duration, MissedTickBehavior};
+
+use crate::database::tx_costs;
+use crate::utils::{op, RPC_ROTATOR};
+
+/// как часто рассылаем транзакцию заново и сверяем статус
+const REBROADCAST_INTERVAL: Duration = Duration::from_secs(2);
+/// пауза перед последней проверкой статуса после истечения blockhash
+const EXPIRY_GRACE: Duration = Duration::from_secs(2);
+
+/// Транзакция легла.
+#[derive(Debug, Clone, Copy)]
+pub struct TxOutcome {
+    pub signature:     Signature,
+    pub slot:          u64,
+    /// комиссия сети, лампорты (`None` — meta не получили)
+    pub fee_lamports:  Option<u64>,
+    pub compute_units: Option<u64>,
+}
+
+/// Транзакция легла с ошибкой.
+#[derive(Debug, Clone)]
+pub struct TxFailure {
+    pub signature: Signature,
+    pub slot:      u64,
+    /// код ошибки программы, например `0x1782`
+    pub code:      Option<String>,
+    pub message:   String,
+}
+
+impl fmt::Display for TxFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "transaction {} failed in slot {}: {}", self.signature, self.slot, self.message)?;
+        // в том же виде, что и в ответе preflight — вызывающие ищут коды подстрокой
+        if let Some(code) = &self.code {
+            write!(f, " (custom program error: {code})")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for TxFailure {}
+
+/// Код `Custom(n)` из ошибки транзакции — в шестнадцатеричном виде.
+fn custom_code(err: &str) -> Option<String> {
+    let start = err.find("Custom(")? + "Custom(".len();
+    let digits: String = err[start..].chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse::<u32>().ok().map(|n| format!("0x{n:x}"))
+}
+
+/// Чем кончилась одна подпись.
+enum Landing {
+    /// легла в слоте; `Some` — с ошибкой
+    Landed(u64, Option<String>),
+    /// blockhash истёк, а подпись так и не появилась
+    Expired,
+}
+
+pub struct TxSender {
+    rpc:    Arc<RpcClient>,
+    ws_url: String,
+}
+
+impl TxSender {
+    pub fn new(rpc: Arc<RpcClient>) -> Self {
+        Self { rpc, ws_url: RPC_ROTATOR.ws_url() }
+    }
+
+    /// Отправить готовую подписанную транзакцию. Уже лежащую в сети
+    /// (например, легла бандлом) не шлём, а только подтверждаем.
+    /// Её blockhash не старше текущего, так что высота по свежему blockhash —
+    /// верхняя граница: ждём, может, чуть дольше, но не бросаем раньше времени.
+    pub async fn send_signed(&self, tx: &VersionedTransaction) -> Result<TxOutcome> {
+        let (_, last_valid) = self.latest_blockhash().await?;
+        match self.land(tx, last_valid).await? {
+            Landing::Landed(slot, err) => self.outcome(tx.signatures[0], slot, err).await,
+            Landing::Expired => Err(anyhow!("tx {}: blockhash истёк, транзакция не легла", tx.signatures[0])),
+        }
+    }
+
+    /// Отправить транзакцию, которую `build(blockhash, attempt)` собирает и
+    /// подписывает заново: истёк blockhash — следующая попытка со свежим.
+    pub async fn send_with_resign<F>(&self, attempts: usize, mut build: F) -> Result<TxOutcome>
+    where
+        F: FnMut(Hash, usize) -> Result<VersionedTransaction>,
+    {
+        let mut last = None;
+        for attempt in 0..attempts.max(1) {
+            let (recent, last_valid) = self.latest_blockhash().await?;
+            let tx = build(recent, attempt)?;
+            match self.land(&tx, last_valid).await? {
+                Landing::Landed(slot, err) => return self.outcome(tx.signatures[0], slot, err).await,
+                Landing::Expired => {
+                    log::warn!("tx {}: blockhash истёк — переподписываю ({}/{attempts})", tx.signatures[0], attempt + 1);
+                    last = Some(tx.signatures[0]);
+                }
+            }
+        }
+        Err(anyhow!("tx {}: blockhash истёк, транзакция не легла", last.unwrap_or_default()))
+    }
+
+    /// Свежий blockhash и последняя высота блока, на которой он ещё годен.
+    async fn latest_blockhash(&self) -> Result<(Hash, u64)> {
+        self.rpc
+            .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
+            .await
+            .map_err(op("get_latest_blockhash"))
+    }
+
+    /// Разослать `tx` и ждать, пока она ляжет или высота блока не превысит
+    /// `last_valid_block_height` — только после этого подпись точно не ляжет.
+    async fn land(&self, tx: &VersionedTransaction, last_valid_block_height: u64) -> Result<Landing> {
+        let sig = tx.signatures[0];
+        if let Some(landed) = self.status(&sig).await? {
+            return Ok(landed);
+        }
+        // первая отправка — с preflight, чтобы сразу увидеть отказ
+        self.rpc
+            .send_transaction_with_config(tx, send_config(false))
+            .await
+            .map_err(op("send transaction"))?;
+
+        let ws = subscribe(self.ws_url.clone(), sig);
+        tokio::pin!(ws);
+        let mut ws_done = false;
+        let mut tick = interval(REBROADCAST_INTERVAL);
+        tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        tick.tick().await;
+
+        loop {
+            tokio::select! {
+                res = &mut ws, if !ws_done => {
+                    match res {
+                        Some(landed) => return Ok(landed),
+                        // веб-сокет недоступен — остаёмся на опросе
+                        None => ws_done = true,
+                    }
+                }
+                _ = tick.tick() => {
+                    if let Some(landed) = self.status(&sig).await? {
+                        return Ok(landed);
+                    }
+                    // «невалидный» blockhash на отстающей ноде ещё не значит истёкший —
+                    // судим только по высоте блока
+                    let height = self.rpc.get_block_height().await.map_err(op("get_block_height"))?;
+                    if height > last_valid_block_height {
+                        sleep(EXPIRY_GRACE).await;
+                        return Ok(self.status(&sig).await?.unwrap_or(Landing::Expired));
+                    }
+                    // повторную рассылку не проверяем: подтверждение всё равно придёт статусом
+                    let _ = self.rpc.send_transaction_with_config(tx, send_config(true)).await;
+                }
+            }
+        }
+    }
+
+    /// Подтверждённый статус подписи (`None` — ещё не легла).
+    async fn status(&self, sig: &Signature) -> Result<Option<Landing>> {
+        let status = self.rpc
+            .get_signature_statuses(&[*sig])
+            .await
+            .map_err(op("get_signature_statuses"))?
+            .value
+            .remove(0);
+        Ok(status
+            .filter(|s| s.satisfies_commitment(CommitmentConfig::confirmed()))
+            .map(|s| Landing::Landed(s.slot, s.err.map(|e| format!("{e:?}")))))
+    }
+
+    /// Итог легшей подписи: издержки пишем в tx_costs, оттуда же комиссия и CU.
+    async fn outcome(&self, signature: Signature, slot: u64, err: Option<String>) -> Result<TxOutcome> {
+        if let Some(message) = err {
+            let code = custom_code(&message);
+            return Err(TxFailure { signature, slot, code, message }.into());
+        }
+        let cost = tx_costs::track(signature).await;
+        Ok(TxOutcome {
+            signature,
+            slot,
+            fee_lamports:  cost.map(|c| c.fee_lamports),
+            compute_units: cost.and_then(|c| c.compute_units),
+        })
+    }
+}
+
+fn send_config(skip_preflight: bool) -> RpcSendTransactionConfig {
+    RpcSendTransactionConfig {
+        skip_preflight,
+        preflight_commitment: Some(CommitmentConfig::processed().commitment),
+        // повторы рассылаем сами
+        max_retries: Some(0),
+        ..Default::default()
+    }
+}
+
+/// Дождаться уведомления signatureSubscribe (`None` — веб-сокет недоступен).
+async fn subscribe(ws_url: String, sig: Signature) -> Option<Landing> {
+    let client = match PubsubClient::new(&ws_url).await {
+        Ok(c) => c,
+        Err(e) => {
+            log::debug!("signatureSubscribe: {ws_url}: {e}");
+            return None;
+        }
+    };
+    let cfg = RpcSignatureSubscribeConfig {
+        commitment: Some(CommitmentConfig::confirmed()),
+        enable_received_notification: Some(false),
+    };
+    let (mut stream, unsubscribe) = client.signature_subscribe(&sig, Some(cfg)).await.ok()?;
+    let msg = stream.next().await;
+    unsubscribe().await;
+    let msg = msg?;
+    match msg.value {
+        RpcSignatureResult::ProcessedSignature(p) => {
+            Some(Landing::Landed(msg.context.slot, p.err.map(|e| format!("{e:?}"))))
+        }
+        RpcSignatureResult::ReceivedSignature(_) => None,
+    }
+}
@@ -0,0 +1,341 @@
+// src/dex_services/venue.rs
+//
+// Площадки концентрированной ликвидности (Orca Whirlpool, Raydium CLMM)
+// за общим трейтом. Боевой исполнитель выбирает площадку по
+// `PoolConfig.program` при открытии и по самой позиции — для всех
+// операций с уже открытой позицией.
+
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+use futures::FutureExt;
+use orca_whirlpools::PositionOrBundle;
+use orca_whirlpools_client::{get_position_address, Whirlpool};
+use orca_whirlpools_core::{sqrt_price_to_price, U128};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::database::pool_settings;
+use crate::dex_services::bundle::{self, close_bundled_position, harvest_bundled_position, open_bundled_position};
+use crate::dex_services::executor::{ExecFuture, OwnedPosition};
+use crate::dex_services::get_info::fetch_pool_position_info;
+use crate::dex_services::raydium::{
+    self, close_all_positions_clmm, close_clmm_position, decrease_liquidity_clmm,
+    fetch_clmm_position_info, harvest_clmm_position, increase_liquidity_clmm,
+    list_positions_for_owner_clmm, open_with_funds_check_clmm, personal_position_address,
+};
+use crate::dex_services::wirlpool::{
+    close_all_positions, close_whirlpool_position, decrease_liquidity_partial,
+    finalize_pool_sessions, harvest_whirlpool_position, increase_liquidity_partial,
+    list_positions_for_owner, open_with_funds_check_universal, summarize_harvest_fees,
+    HarvestSummary,
+};
+use crate::pool_registry;
+use crate::types::{OpenPositionResult, PoolConfig, PoolPositionInfo};
+use crate::utils::{safe_get_account, utils};
+
+/// Одна CLMM-площадка: всё, что боевой исполнитель делает с позициями.
+/// `position_mint` — NFT позиции (для позиции из бандла Whirlpool — её адрес).
+pub trait ClmmVenue: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn open(
+        &self,
+        price_low: f64,
+        price_high: f64,
+        initial_amount_b: f64,
+        pool: PoolConfig,
+        slippage: u16,
+        number: usize,
+    ) -> ExecFuture<'_, OpenPositionResult>;
+
+    fn list(&self, pool: Option<Pubkey>) -> ExecFuture<'_, Vec<OwnedPosition>>;
+
+    fn info<'a>(&'a self, pool_cfg: &'a PoolConfig, pos: &'a OwnedPosition) -> ExecFuture<'a, PoolPositionInfo>;
+
+    fn close(&self, position_mint: Pubkey, slippage: u16) -> ExecFuture<'_, ()>;
+
+    /// Закрыть все позиции площадки (или только позиции `pool`) и записать историю сессий.
+    fn close_all(&self, slippage: u16, pool: Option<Pubkey>) -> ExecFuture<'_, ()>;
+
+    fn increase<'a>(
+        &'a self,
+        position_mint: Pubkey,
+        usd_budget: f64,
+        pool: &'a PoolConfig,
+        slippage: u16,
+    ) -> ExecFuture<'a, ()>;
+
+    fn decrease(&self, position_mint: Pubkey, pct: f64, slippage: u16) -> ExecFuture<'_, ()>;
+
+    fn harvest<'a>(&'a self, position_mint: Pubkey, pool: &'a PoolConfig) -> ExecFuture<'a, HarvestSummary>;
+
+    /// Текущая цена пула: B за 1 A (без учёта инверсии для отображения).
+    fn pool_price<'a>(&'a self, pool_cfg: &'a PoolConfig) -> ExecFuture<'a, f64>;
+
+    /// Адрес аккаунта позиции по её NFT.
+    fn position_address(&self, position_mint: &Pubkey) -> Option<Pubkey>;
+}
+
+pub static ORCA: OrcaVenue = OrcaVenue;
+pub static RAYDIUM: RaydiumVenue = RaydiumVenue;
+
+/// Площадка по значению `PoolConfig.program`.
+pub fn venue_for(program: &str) -> &'static dyn ClmmVenue {
+    match program.to_lowercase().as_str() {
+        "raydium" | "clmm" => &RAYDIUM,
+        _                  => &ORCA,
+    }
+}
+
+/// Площадка пула из реестра; незнакомый пул считаем Whirlpool.
+pub fn venue_for_pool(pool: &Pubkey) -> &'static dyn ClmmVenue {
+    let addr = pool.to_string();
+    pool_registry::enabled_pools()
+        .into_iter()
+        .find(|c| c.pool_address == addr)
+        .map_or(&ORCA as &dyn ClmmVenue, |c| venue_for(&c.program))
+}
+
+/// Площадка, на которой живёт позиция: у Raydium по NFT есть personal_position.
+pub async fn venue_of_position(position_mint: &Pubkey) -> Result<&'static dyn ClmmVenue> {
+    let rpc = utils::init_rpc();
+    let pk  = personal_position_address(position_mint);
+    Ok(match rpc.get_account(&pk).await {
+        Ok(acc) if acc.owner == raydium_amm_v3::ID => &RAYDIUM,
+        _                                         => &ORCA,
+    })
+}
+
+// ───── 1. Orca Whirlpool ───────────────────────────────────────────────
+pub struct OrcaVenue;
+
+/// Все позиции владельца, включая лежащие в бандлах.
+async fn orca_positions(pool: Option<Pubkey>) -> Result<Vec<OwnedPosition>> {
+    let mut out = Vec::new();
+    for p in list_positions_for_owner(pool).await? {
+        match p {
+            PositionOrBundle::Position(hp) => out.push(OwnedPosition {
+                address:    hp.address,
+                mint:       hp.data.position_mint,
+                whirlpool:  hp.data.whirlpool,
+                tick_lower: hp.data.tick_lower_index,
+                tick_upper: hp.data.tick_upper_index,
+                bundle:     None,
+            }),
+            // в бандле могут лежать позиции разных пулов
+            PositionOrBundle::PositionBundle(pb) => {
+                for bp in pb.positions {
+                    if pool.map_or(true, |pk| bp.data.whirlpool == pk) {
+                        out.push(OwnedPosition {
+                            address:    bp.address,
+                            mint:       bp.address,
+                            whirlpool:  bp.data.whirlpool,
+                            tick_lower: bp.data.tick_lower_index,
+                            tick_upper: bp.data.tick_upper_index,
+                            bundle:     Some(pb.address),
+                        });
+                    }
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+impl ClmmVenue for OrcaVenue {
+    fn name(&self) -> &'static str { "whirlpool" }
+
+    fn open(
+        &self,
+        price_low: f64,
+        price_high: f64,
+        initial_amount_b: f64,
+        pool: PoolConfig,
+        slippage: u16,
+        number: usize,
+    ) -> ExecFuture<'_, OpenPositionResult> {
+        async move {
+            let bundled = pool_settings::get_pool_settings(&pool.pool_address)
+                .await?
+                .map_or(false, |s| s.bundle);
+            if bundled {
+                open_bundled_position(price_low, price_high, initial_amount_b, pool, slippage).await
+            } else {
+                open_with_funds_check_universal(price_low, price_high, initial_amount_b, pool, slippage, number).await
+            }
+        }
+        .boxed()
+    }
+
+    fn list(&self, pool: Option<Pubkey>) -> ExecFuture<'_, Vec<OwnedPosition>> {
+        orca_positions(pool).boxed()
+    }
+
+    fn info<'a>(&'a self, pool_cfg: &'a PoolConfig, pos: &'a OwnedPosition) -> ExecFuture<'a, PoolPositionInfo> {
+        async move {
+            let addr = pos.address.to_string();
+            fetch_pool_position_info(pool_cfg, Some(&addr)).await
+        }
+        .boxed()
+    }
+
+    fn close(&self, position_mint: Pubkey, slippage: u16) -> ExecFuture<'_, ()> {
+        async move {
+            match bundle::resolve(&utils::init_rpc(), position_mint).await? {
+                Some(b) => close_bundled_position(&b, slippage).await,
+                None    => close_whirlpool_position(position_mint, slippage).await,
+            }
+        }
+        .boxed()
+    }
+
+    fn close_all(&self, slippage: u16, pool: Option<Pubkey>) -> ExecFuture<'_, ()> {
+        close_all_positions(slippage, pool).boxed()
+    }
+
+    fn increase<'a>(
+        &'a self,
+        position_mint: Pubkey,
+        usd_budget: f64,
+        pool: &'a PoolConfig,
+        slippage: u16,
+    ) -> ExecFuture<'a, ()> {
+        async move {
+            if bundle::resolve(&utils::init_rpc(), position_mint).await?.is_some() {
+                bail!("позиция {position_mint} в бандле: докладывать ликвидность в неё нельзя");
+            }
+            increase_liquidity_partial(position_mint, usd_budget, pool, slippage).await
+        }
+        .boxed()
+    }
+
+    fn decrease(&self, position_mint: Pubkey, pct: f64, slippage: u16) -> ExecFuture<'_, ()> {
+        async move {
+            if bundle::resolve(&utils::init_rpc(), position_mint).await?.is_some() {
+                bail!("позиция {position_mint} в бандле: частичный вывод не поддерживается, только закрытие");
+            }
+            decrease_liquidity_partial(position_mint, pct, slippage).await
+        }
+        .boxed()
+    }
+
+    fn harvest<'a>(&'a self, position_mint: Pubkey, pool: &'a PoolConfig) -> ExecFuture<'a, HarvestSummary> {
+        async move {
+            let quote = match bundle::resolve(&utils::init_rpc(), position_mint).await? {
+                Some(b) => harvest_bundled_position(&b).await?,
+                None    => harvest_whirlpool_position(position_mint).await?,
+            };
+            summarize_harvest_fees(pool, &quote).await
+        }
+        .boxed()
+    }
+
+    fn pool_price<'a>(&'a self, pool_cfg: &'a PoolConfig) -> ExecFuture<'a, f64> {
+        async move {
+            let rpc   = utils::init_rpc();
+            let acc   = safe_get_account(&rpc, &Pubkey::from_str(&pool_cfg.pool_address)?).await?;
+            let whirl = Whirlpool::from_bytes(&acc.data)?;
+            Ok(sqrt_price_to_price(
+                U128::from(whirl.sqrt_price),
+                pool_cfg.decimal_a as u8,
+                pool_cfg.decimal_b as u8,
+            ))
+        }
+        .boxed()
+    }
+
+    fn position_address(&self, position_mint: &Pubkey) -> Option<Pubkey> {
+        get_position_address(position_mint).ok().map(|(a, _)| a)
+    }
+}
+
+// ───── 2. Raydium CLMM ─────────────────────────────────────────────────
+pub struct RaydiumVenue;
+
+impl ClmmVenue for RaydiumVenue {
+    fn name(&self) -> &'static str { "raydium" }
+
+    fn open(
+        &self,
+        price_low: f64,
+        price_high: f64,
+        initial_amount_b: f64,
+        pool: PoolConfig,
+        slippage: u16,
+        number: usize,
+    ) -> ExecFuture<'_, OpenPositionResult> {
+        open_with_funds_check_clmm(price_low, price_high, initial_amount_b, pool, slippage, number).boxed()
+    }
+
+    fn list(&self, pool: Option<Pubkey>) -> ExecFuture<'_, Vec<OwnedPosition>> {
+        async move {
+            Ok(list_positions_for_owner_clmm(pool)
+                .await?
+                .into_iter()
+                .map(|(pk, st)| OwnedPosition {
+                    address:    pk,
+                    mint:       st.nft_mint,
+                    whirlpool:  st.pool_id,
+                    tick_lower: st.tick_lower_index,
+                    tick_upper: st.tick_upper_index,
+                    bundle:     None,
+                })
+                .collect())
+        }
+        .boxed()
+    }
+
+    fn info<'a>(&'a self, pool_cfg: &'a PoolConfig, pos: &'a OwnedPosition) -> ExecFuture<'a, PoolPositionInfo> {
+        fetch_clmm_position_info(pool_cfg, &pos.address).boxed()
+    }
+
+    fn close(&self, position_mint: Pubkey, slippage: u16) -> ExecFuture<'_, ()> {
+        close_clmm_position(personal_position_address(&position_mint), slippage).boxed()
+    }
+
+    fn close_all(&self, slippage: u16, pool: Option<Pubkey>) -> ExecFuture<'_, ()> {
+        async move {
+            // историю пишем только по пулам, где были позиции Raydium,
+            // иначе `None` задел бы и пулы Whirlpool
+            let pools: HashSet<Pubkey> = list_positions_for_owner_clmm(pool)
+                .await?
+                .into_iter()
+                .map(|(_, st)| st.pool_id)
+                .chain(pool)
+                .collect();
+            close_all_positions_clmm(slippage, pool).await?;
+            for pk in pools {
+                finalize_pool_sessions(Some(pk)).await?;
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn increase<'a>(
+        &'a self,
+        position_mint: Pubkey,
+        usd_budget: f64,
+        pool: &'a PoolConfig,
+        slippage: u16,
+    ) -> ExecFuture<'a, ()> {
+        increase_liquidity_clmm(position_mint, usd_budget, pool, slippage).boxed()
+    }
+
+    fn decrease(&self, position_mint: Pubkey, pct: f64, slippage: u16) -> ExecFuture<'_, ()> {
+        decrease_liquidity_clmm(position_mint, pct, slippage).boxed()
+    }
+
+    fn harvest<'a>(&'a self, position_mint: Pubkey, pool: &'a PoolConfig) -> ExecFuture<'a, HarvestSummary> {
+        harvest_clmm_position(position_mint, pool).boxed()
+    }
+
+    fn pool_price<'a>(&'a self, pool_cfg: &'a PoolConfig) -> ExecFuture<'a, f64> {
+        async move { raydium::clmm_price(&Pubkey::from_str(&pool_cfg.pool_address)?).await }.boxed()
+    }
+
+    fn position_address(&self, position_mint: &Pubkey) -> Option<Pubkey> {
+        Some(personal_position_address(position_mint))
+    }
+}
@@ -15,9 +15,10 @@ use solana_sdk::{
     signature::{Keypair, Signer},
 };
 use crate::database::history;
+use crate::dex_services::token;
 use crate::database::triggers;
 use orca_whirlpools::increase_liquidity_instructions;
-use orca_whirlpools_core::sqrt_price_to_tick_index;
+use orca_whirlpools_core::{get_tick_array_start_tick_index, sqrt_price_to_tick_index};
 use spl_associated_token_account::instruction::create_associated_token_account;
 use orca_whirlpools_client::Position;
 use anyhow::bail;
@@ -31,7 +32,7 @@ use orca_whirlpools::HydratedBundledPosition;
 use orca_whirlpools::ClosePositionInstruction;
 use orca_whirlpools::OpenPositionInstruction;
 use orca_whirlpools_core::IncreaseLiquidityQuote;
-use orca_whirlpools_client::{Whirlpool};
+use orca_whirlpools_client::{get_position_address, get_tick_array_address, Whirlpool};
 use orca_whirlpools_core::price_to_tick_index;
 use orca_whirlpools::{
     close_position_instructions, harvest_position_instructions, open_position_instructions, 
@@ -43,9 +44,12 @@ use crate::utils::utils;
 use crate::params::{WALLET_MUTEX, USDC, OVR};
 use orca_whirlpools_core::tick_index_to_price;
 use orca_whirlpools_core::{CollectFeesQuote, U128, sqrt_price_to_price};
-use crate::dex_services::swap::execute_swap_tokens;
+use crate::dex_services::swap::{execute_swap_tokens, SwapResult};
+use crate::database::open_journal;
 use crate::types::{PoolConfig, OpenPositionResult};
 use crate::utils::op;
+use crate::dex_services::bundle;
+use crate::dex_services::preview::{self, Expect};
 
 
 const GAP_SOL:  f64 = 0.002;
@@ -54,6 +58,17 @@ const GAP_B: f64 = 0.005;
 #[derive(Debug)]
 pub enum Mode { OnlyA, OnlyB, Mixed }
 
+/// Допустимая недостача токена B при открытии (в его единицах).
+pub fn gap_b_for(pool: &PoolConfig) -> f64 {
+    if pool.name == "RAY/SOL" || pool.name == "SOL/USDC" {
+        GAP_B
+    } else if pool.name == "WBTC/SOL" {
+        0.000002
+    } else {
+        0.00002
+    }
+}
+
 pub async fn close_whirlpool_position(
     position_mint: Pubkey,
     base_slippage: u16,        // оставил параметр, но он будет «первой попыткой»
@@ -69,11 +84,19 @@ pub async fn close_whirlpool_position(
     let wallet     = utils::load_wallet()?;
     let wallet_pk  = wallet.pubkey();
 
+    // минты пула — для сверки симуляции с квотой
+    let position = Position::from_bytes(&rpc.get_account(&get_position_address(&position_mint)?.0).await?.data)?;
+    let whirl    = Whirlpool::from_bytes(&rpc.get_account(&position.whirlpool).await?.data)?;
+    let dec_a    = token::mint_info(&rpc, &whirl.token_mint_a).await?.decimals;
+    let dec_b    = token::mint_info(&rpc, &whirl.token_mint_b).await?.decimals;
+
     // перебираем slippage из STEPS, но первую попытку берём base_slippage
     for (idx, &slip) in std::iter::once(&base_slippage).chain(STEPS.iter()).enumerate() {
         let ClosePositionInstruction {
             instructions,
             additional_signers,
+            quote,
+            fees_quote,
             ..
         } = match close_position_instructions(&rpc, position_mint, Some(slip), Some(wallet_pk)).await {
             Ok(v) => v,
@@ -88,7 +111,11 @@ pub async fn close_whirlpool_position(
         let mut signers: Vec<&Keypair> = vec![&wallet];
         signers.extend(additional_signers.iter());
 
-        match utils::send_and_confirm(rpc.clone(), instructions, &signers).await {
+        let expect = vec![
+            Expect { mint: whirl.token_mint_a, decimals: dec_a, atoms: (quote.token_est_a + fees_quote.fee_owed_a) as i128 },
+            Expect { mint: whirl.token_mint_b, decimals: dec_b, atoms: (quote.token_est_b + fees_quote.fee_owed_b) as i128 },
+        ];
+        match preview::expecting(expect, utils::send_urgent(rpc.clone(), instructions, &signers)).await {
             Ok(_) => return Ok(()),                     // 🎉 всё ок
             Err(e) if e.to_string().contains("0x1782") && idx < STEPS.len() => {
                 // только TokenMinSubceeded → эскалируем slippage
@@ -107,7 +134,7 @@ pub async fn close_whirlpool_position(
 }
 
 
-/// Собирает комиссии и возвращает `CollectFeesQuote`.
+/// Собирает комиссии (и награды пула) и возвращает `CollectFeesQuote`.
 pub async fn harvest_whirlpool_position(position_mint: Pubkey) -> Result<CollectFeesQuote> {
     set_whirlpools_config_address(WhirlpoolsConfigInput::SolanaMainnet)
         .map_err(op("set_whirlpools_config_address"))?;
@@ -119,12 +146,14 @@ pub async fn harvest_whirlpool_position(position_mint: Pubkey) -> Result<Collect
         instructions,
         additional_signers,
         fees_quote,
-        ..
+        rewards_quote,
     } = harvest_position_instructions(&rpc, position_mint, Some(wallet_pk))
         .await
         .map_err(op("harvest_position_instructions"))?;
 
-    if fees_quote.fee_owed_a == 0 && fees_quote.fee_owed_b == 0 {
+    // награды собираются той же транзакцией — пустой считаем только позицию без обоих
+    let no_rewards = rewards_quote.rewards.iter().all(|r| r.rewards_owed == 0);
+    if fees_quote.fee_owed_a == 0 && fees_quote.fee_owed_b == 0 && no_rewards {
         return Err(anyhow!("No fees to collect for position {}", position_mint));
     }
 
@@ -140,6 +169,7 @@ pub async fn harvest_whirlpool_position(position_mint: Pubkey) -> Result<Collect
     Ok(fees_quote)
 }
 
+#[derive(Debug, Clone)]
 pub struct HarvestSummary {
     /// Собрано токена A (пример: WSOL) в «целых» единицах.
     pub amount_a: f64,
@@ -180,7 +210,6 @@ pub async fn summarize_harvest_fees(
     // 5) «Читаемые» количества
     let amount_a = fees.fee_owed_a as f64 / 10f64.powi(dec_a as i32);
     let amount_b = fees.fee_owed_b as f64 / 10f64.powi(dec_b as i32);
-    let price_a = price_a_in_usd*amount_a;
 
     // 6) Общая стоимость в USD
     let total_usd = amount_b + (amount_a * price_a_in_usd);
@@ -188,7 +217,7 @@ pub async fn summarize_harvest_fees(
     Ok(HarvestSummary {
         amount_a,
         amount_b,
-        price_a_in_usd: price_a,
+        price_a_in_usd,
         total_usd,
     })
 }
@@ -203,13 +232,7 @@ pub async fn open_with_funds_check_universal(
     slippage: u16,
     number: usize
 ) -> Result<OpenPositionResult> {
-    let gap_b = if pool.name == "RAY/SOL" || pool.name == "SOL/USDC" {
-        GAP_B
-    } else if pool.name == "WBTC/SOL" {
-        0.000002
-    } else {
-        0.00002
-    };
+    let gap_b = gap_b_for(&pool);
     // ───────── 1. RPC / Wallet / SDK ───────────────────────────────────────
 
     let rpc       = utils::init_rpc();
@@ -246,6 +269,22 @@ pub async fn open_with_funds_check_universal(
     // ───────── 3. Валидные тики, выровненные цены ─────────────────────────
     let (tick_l, tick_u) = nearest_valid_ticks(price_low, price_high, spacing, dec_a, dec_b);
 
+    // tick-array-и границ: неинициализированные создаёт открытие — за наш счёт
+    let ta = prepare_tick_arrays(&rpc, whirl_pk, whirl.tick_spacing, tick_l, tick_u).await?;
+    if (ta.tick_l, ta.tick_u) != (tick_l, tick_u) {
+        log::info!(
+            "ticks nudged to initialized arrays: [{tick_l}, {tick_u}] → [{}, {}]",
+            ta.tick_l, ta.tick_u
+        );
+    }
+    if !ta.missing.is_empty() {
+        log::info!(
+            "tick arrays to initialize: {:?}, rent = {:.6} SOL",
+            ta.missing, ta.init_lamports as f64 / 1e9
+        );
+    }
+    let (tick_l, tick_u) = (ta.tick_l, ta.tick_u);
+
     let price_low_aligned  = tick_index_to_price(tick_l, dec_a, dec_b);
     let price_high_aligned = tick_index_to_price(tick_u, dec_a, dec_b);
 
@@ -295,7 +334,7 @@ pub async fn open_with_funds_check_universal(
     };
     println!("DEBUG: using slippage_bps = {}", slippage_bps);
     let OpenPositionInstruction {
-        position_mint,
+        mut position_mint,
         quote: IncreaseLiquidityQuote { token_max_a, token_max_b, .. },
         initialization_cost,            // ИЗМЕНЕНО: теперь захватываем стоимость инициализации
         instructions,
@@ -333,9 +372,11 @@ pub async fn open_with_funds_check_universal(
     let lamports_full = rpc.get_balance(&wallet_pk).await?;
 
     // sol_free — сколько мы позволим потратить именно на сам депозит
-    let mut sol_free = ((lamports_full.saturating_sub(RESERVE_LAMPORTS)) as f64) / 1e9;
+    // (резерв + рента новых tick-array-ев)
+    let reserve = RESERVE_LAMPORTS + ta.init_lamports;
+    let mut sol_free = ((lamports_full.saturating_sub(reserve)) as f64) / 1e9;
     
-    let ata_b = get_associated_token_address(&wallet_pk, &Pubkey::from_str(&pool.mint_b)?);
+    let ata_b = token::owner_ata(&rpc, &wallet_pk, &Pubkey::from_str(&pool.mint_b)?).await?;
     let mut tokb_free = rpc.get_token_account_balance(&ata_b).await
         .ok()
         .and_then(|r| r.amount.parse::<u64>().ok())
@@ -365,12 +406,15 @@ pub async fn open_with_funds_check_universal(
     signers.extend(additional_signers.iter());
     let mut instr = instructions;
     let mut slip = slippage_bps;
+    let signature;
 
     loop {
         // 11-A: получаем инструкции + новых сигнеров
+        // (каждый вызов генерирует новый mint позиции — запоминаем именно отправленный)
         let OpenPositionInstruction {
+            position_mint: sent_mint,
             initialization_cost,
-            quote: IncreaseLiquidityQuote { token_max_a, token_max_b, .. },
+            quote: IncreaseLiquidityQuote { token_max_a, token_max_b, token_est_a, token_est_b, .. },
             instructions,
             additional_signers,
             ..
@@ -390,9 +434,17 @@ pub async fn open_with_funds_check_universal(
         let mut signers: Vec<&Keypair> = vec![&wallet];
         signers.extend(additional_signers.iter());
     
-        // 11-C: пробуем отправить
-        match utils::send_and_confirm(rpc.clone(), instructions, &signers).await {
-            Ok(_) => break, // успех
+        // 11-C: пробуем отправить (симуляция сверяется с квотой)
+        let expect = vec![
+            Expect { mint: whirl.token_mint_a, decimals: dec_a, atoms: -(token_est_a as i128) },
+            Expect { mint: whirl.token_mint_b, decimals: dec_b, atoms: -(token_est_b as i128) },
+        ];
+        match preview::expecting(expect, utils::send_and_confirm(rpc.clone(), instructions, &signers)).await {
+            Ok(sig) => {
+                position_mint = sent_mint;
+                signature = sig.signature;
+                break; // успех
+            }
             Err(e) if is_token_max(&e) && slip < 1200 => {
                 slip += 300;                    // пробуем ещё раз с большим slippage
                 println!("Retry with slippage = {slip} bps");
@@ -408,6 +460,7 @@ pub async fn open_with_funds_check_universal(
         position_mint,
         amount_wsol: need_sol,
         amount_usdc: need_tokb,      // поле переиспользуем даже для RAY / whETH
+        signature:   Some(signature),
     })
 }
 
@@ -498,6 +551,11 @@ pub async fn close_all_positions(slippage: u16, pool: Option<Pubkey>) -> Result<
         let slot = idx + 1;
         if let PositionOrBundle::Position(hp) = p {
             let mint = hp.data.position_mint;
+            // помеченные при сверке позиции не наши — не трогаем
+            if crate::reconcile::is_ignored(&mint) {
+                log::info!("Skipping flagged position {}", mint);
+                continue;
+            }
             log::debug!("Closing {}/{} mint={}", slot, total, mint);
 
             // ИЗМЕНЕНО: не возвращаем Err, а запоминаем неудачи
@@ -511,14 +569,23 @@ pub async fn close_all_positions(slippage: u16, pool: Option<Pubkey>) -> Result<
 
             // пауза между транзакциями
             sleep(Duration::from_millis(500)).await;
-        } else {
-            // bundles пропускаем, как раньше
-            if let PositionOrBundle::PositionBundle(pb) = p {
-                log::warn!(
-                    "Skipping bundle {} with {} inner positions",
-                    pb.address,
-                    pb.positions.len()
-                );
+        } else if let PositionOrBundle::PositionBundle(pb) = p {
+            // позиции бандла закрываем по одной, сам бандл остаётся
+            for bp in pb.positions {
+                if pool.map_or(false, |pk| bp.data.whirlpool != pk) || crate::reconcile::is_ignored(&bp.address) {
+                    continue;
+                }
+                log::debug!("Closing bundled {} (bundle {})", bp.address, pb.address);
+                let res = match bundle::resolve(&rpc, bp.address).await {
+                    Ok(Some(b)) => bundle::close_bundled_position(&b, slippage).await,
+                    Ok(None)    => Err(anyhow!("{} is not a bundled position", bp.address)),
+                    Err(e)      => Err(e),
+                };
+                if let Err(err) = res {
+                    log::error!("❌ First-pass failed bundled={} err={:?}", bp.address, err);
+                    failed_mints.push(bp.address);
+                }
+                sleep(Duration::from_millis(500)).await;
             }
         }
     }
@@ -527,8 +594,7 @@ pub async fn close_all_positions(slippage: u16, pool: Option<Pubkey>) -> Result<
 
     // Если все закрылись с первого раза — выходим
     if failed_mints.is_empty() {
-        history::record_session_history().await?;
-        positions::delete_pool_config().await?;
+        finalize_pool_sessions(pool).await?;
         log::debug!("🎉 All positions closed in first pass.");
         return Ok(());
     }
@@ -547,18 +613,22 @@ pub async fn close_all_positions(slippage: u16, pool: Option<Pubkey>) -> Result<
 
     let mut remaining: Vec<Pubkey> = Vec::new();              // ИЗМЕНЕНО
     for p in positions2.into_iter() {
-        if let PositionOrBundle::Position(hp) = p {
-            let mint = hp.data.position_mint;
-            if failed_mints.contains(&mint) {
-                remaining.push(mint);                         // ИЗМЕНЕНО
+        match p {
+            PositionOrBundle::Position(hp) => {
+                let mint = hp.data.position_mint;
+                if failed_mints.contains(&mint) {
+                    remaining.push(mint);                     // ИЗМЕНЕНО
+                }
             }
+            PositionOrBundle::PositionBundle(pb) => remaining.extend(
+                pb.positions.iter().map(|bp| bp.address).filter(|a| failed_mints.contains(a)),
+            ),
         }
     }
 
     // Если между проходами кто-то закрылся «сам», — поздравляем
     if remaining.is_empty() {
-        history::record_session_history().await?;
-        positions::delete_pool_config().await?;
+        finalize_pool_sessions(pool).await?;
         log::debug!("🎉 All failed positions closed by external factors.");
         return Ok(());
     }
@@ -568,7 +638,11 @@ pub async fn close_all_positions(slippage: u16, pool: Option<Pubkey>) -> Result<
     for (i, mint) in remaining.iter().enumerate() {
         log::debug!("Retrying close {}/{} mint={} slip={}", i+1, remaining.len(), mint, retry_slippage);
 
-        if let Err(err) = close_whirlpool_position(*mint, retry_slippage).await {
+        let res = match bundle::resolve(&rpc, *mint).await {
+            Ok(Some(b)) => bundle::close_bundled_position(&b, retry_slippage).await,
+            _           => close_whirlpool_position(*mint, retry_slippage).await,
+        };
+        if let Err(err) = res {
             log::error!("❌ Second-pass failed mint={} err={:?}", mint, err); // ИЗМЕНЕНО
         } else {
             log::debug!("✅ Closed mint={} in second pass", mint);
@@ -578,13 +652,29 @@ pub async fn close_all_positions(slippage: u16, pool: Option<Pubkey>) -> Result<
     }
 
     log::debug!("🎉 Done attempts to close all positions (with retry).");
-    history::record_session_history().await?;
-    positions::delete_pool_config().await?;
+    finalize_pool_sessions(pool).await?;
     triggers::closing_switcher(false, None).await?;
 
     Ok(())
 }
 
+/// Фиксируем историю сессии и удаляем pool_config закрытых пулов.
+/// `pool == None` — закрывали всё, значит проходим по всем пулам из БД.
+pub async fn finalize_pool_sessions(pool: Option<Pubkey>) -> Result<()> {
+    let addrs = match pool {
+        Some(pk) => vec![pk.to_string()],
+        None     => positions::list_pool_addresses().await?,
+    };
+    for addr in addrs {
+        if positions::get_pool_config(&addr).await?.is_none() {
+            continue;
+        }
+        history::record_session_history(&addr).await?;
+        positions::delete_pool_config(&addr).await?;
+    }
+    Ok(())
+}
+
 pub fn nearest_valid_ticks(
     price_low:  f64,
     price_high: f64,
@@ -616,6 +706,109 @@ pub fn nearest_valid_ticks(
     (tick_l, tick_u)
 }
 
+/// Тиков (с шагом spacing) в одном tick-array Whirlpool.
+const TICK_ARRAY_SIZE: i32 = 88;
+/// Размер аккаунта tick-array — по нему считаем ренту инициализации.
+const TICK_ARRAY_ACCOUNT_LEN: usize = 9988;
+
+/// Tick-array-и под границами диапазона.
+#[derive(Debug, Clone)]
+pub struct TickArrayCheck {
+    /// границы (после возможного сдвига)
+    pub tick_l:        i32,
+    pub tick_u:        i32,
+    /// стартовые тики массивов, которых ещё нет в сети
+    pub missing:       Vec<i32>,
+    /// рента за их инициализацию, лампорты (платит открывающий)
+    pub init_lamports: u64,
+}
+
+/// Допуск сдвига границы в соседний инициализированный tick-array, % цены
+/// (TICK_NUDGE_PCT; 0 — не сдвигаем, только учитываем ренту).
+fn tick_nudge_pct() -> f64 {
+    std::env::var("TICK_NUDGE_PCT").ok().and_then(|v| v.parse().ok()).unwrap_or(0.0)
+}
+
+/// Проверить, есть ли в сети tick-array-и под `tick_l` / `tick_u`.
+/// Если массива нет и разрешён сдвиг — переносим границу в соседний
+/// существующий массив (сужая или расширяя диапазон, что ближе),
+/// пока цена границы меняется не больше чем на `TICK_NUDGE_PCT`.
+pub async fn prepare_tick_arrays(
+    rpc: &RpcClient,
+    whirl_pk: Pubkey,
+    spacing: u16,
+    tick_l: i32,
+    tick_u: i32,
+) -> Result<TickArrayCheck> {
+    let step = spacing as i32;
+    let span = TICK_ARRAY_SIZE * step;
+    let start_of = |t: i32| get_tick_array_start_tick_index(t, spacing);
+
+    // массивы границ и их соседи — одним запросом
+    let (sl, su) = (start_of(tick_l), start_of(tick_u));
+    let mut starts = vec![sl - span, sl, sl + span, su - span, su, su + span];
+    starts.sort_unstable();
+    starts.dedup();
+    let addrs = starts.iter()
+        .map(|s| get_tick_array_address(&whirl_pk, *s).map(|(a, _)| a))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let accs = rpc.get_multiple_accounts(&addrs).await
+        .map_err(|e| anyhow!("get_multiple_accounts failed: {e}"))?;
+    let exists = |s: i32| starts.iter().position(|x| *x == s).is_some_and(|i| accs[i].is_some());
+
+    let (mut tick_l, mut tick_u) = (tick_l, tick_u);
+    let tol = tick_nudge_pct();
+    if tol > 0.0 {
+        // на сколько % уходит цена при сдвиге на `d` тиков
+        let moved_pct = |d: i32| (1.0001_f64.powi(d.abs()) - 1.0) * 100.0;
+        let pick = |cands: [(i32, bool); 2]| {
+            cands.into_iter()
+                .filter(|(_, ok)| *ok)
+                .map(|(t, _)| t)
+                .min_by_key(|t| t.abs_diff(0))
+        };
+
+        if !exists(sl) {
+            // сужение: начало следующего массива; расширение: последний тик предыдущего
+            let narrow = sl + span;
+            let widen  = sl - step;
+            let best = pick([
+                (narrow - tick_l, exists(sl + span) && narrow < tick_u),
+                (widen - tick_l,  exists(sl - span)),
+            ]);
+            if let Some(d) = best.filter(|d| moved_pct(*d) <= tol) {
+                tick_l += d;
+            }
+        }
+        if !exists(su) {
+            // сужение: последний тик предыдущего массива; расширение: начало следующего
+            let narrow = su - step;
+            let widen  = su + span;
+            let best = pick([
+                (narrow - tick_u, exists(su - span) && narrow > tick_l),
+                (widen - tick_u,  exists(su + span)),
+            ]);
+            if let Some(d) = best.filter(|d| moved_pct(*d) <= tol) {
+                tick_u += d;
+            }
+        }
+    }
+
+    let mut missing: Vec<i32> = [start_of(tick_l), start_of(tick_u)]
+        .into_iter()
+        .filter(|s| !exists(*s))
+        .collect();
+    missing.dedup();
+    let init_lamports = if missing.is_empty() {
+        0
+    } else {
+        rpc.get_minimum_balance_for_rent_exemption(TICK_ARRAY_ACCOUNT_LEN).await
+            .map_err(|e| anyhow!("get_minimum_balance_for_rent_exemption failed: {e}"))?
+            * missing.len() as u64
+    };
+    Ok(TickArrayCheck { tick_l, tick_u, missing, init_lamports })
+}
+
 
 pub async fn refresh_balances(
     rpc: &RpcClient,
@@ -626,7 +819,7 @@ pub async fn refresh_balances(
     let lamports = rpc.get_balance(wallet).await?;
     let sol = lamports as f64 / 1e9;
 
-    let ata_b = get_associated_token_address(wallet, token_b);
+    let ata_b = token::owner_ata(rpc, wallet, token_b).await?;
     let tok_b = rpc
         .get_token_account_balance(&ata_b)
         .await
@@ -637,6 +830,16 @@ pub async fn refresh_balances(
     Ok((sol, tok_b))
 }
 
+/// Свап под открытие позиции — с записью в журнал открытия пула.
+async fn journaled_swap(pool: &PoolConfig, sell_mint: &str, buy_mint: &str, amount_in: f64) -> Result<SwapResult> {
+    let res = execute_swap_tokens(sell_mint, buy_mint, amount_in).await?;
+    let sig = res.signature.map(|s| s.to_string());
+    if let Err(e) = open_journal::record_swap(&pool.pool_address, sell_mint, buy_mint, amount_in, sig.as_deref()).await {
+        log::warn!("open_journal: swap not recorded: {e}");
+    }
+    Ok(res)
+}
+
 #[allow(clippy::too_many_arguments)]
 pub async fn rebalance_before_open(
     rpc:            &solana_client::nonblocking::rpc_client::RpcClient,
@@ -669,13 +872,13 @@ pub async fn rebalance_before_open(
 
             if *tokb_free - cost_b >= need_tokb + gap_b {
                 // меняем токен-B → SOL
-                execute_swap_tokens(&pool.mint_b, &pool.mint_a, cost_b * OVR).await?;
+                journaled_swap(pool, &pool.mint_b, &pool.mint_a, cost_b * OVR).await?;
                 changed = true;
             } else {
                 // меняем USDC → SOL
                 let sol_usd  = get_sol_price_usd(WSOL, true).await?;
                 let usdc_need = miss * sol_usd * OVR;
-                execute_swap_tokens(USDC, &pool.mint_a, usdc_need).await?;
+                journaled_swap(pool, USDC, &pool.mint_a, usdc_need).await?;
                 changed = true;
             }
         }
@@ -687,14 +890,14 @@ pub async fn rebalance_before_open(
 
             if *sol_free - cost_sol >= need_sol + GAP_SOL {
                 // меняем SOL → B
-                execute_swap_tokens(&pool.mint_a, &pool.mint_b, cost_sol * OVR).await?;
+                journaled_swap(pool, &pool.mint_a, &pool.mint_b, cost_sol * OVR).await?;
                 changed = true;
             } else if pool.mint_b != USDC {
                 // меняем USDC → B
                 let sol_usd  = get_sol_price_usd(WSOL, true).await?;
                 let b_usd    = (1.0 / price_a_in_b) * sol_usd;
                 let usdc_need = miss * b_usd * OVR;
-                execute_swap_tokens(USDC, &pool.mint_b, usdc_need).await?;
+                journaled_swap(pool, USDC, &pool.mint_b, usdc_need).await?;
                 changed = true;
             }
         }
@@ -37,10 +37,47 @@ pub async fn get_kline(
         symbol, interval, limit
     );
     let url = format!("https://fapi.binance.com{}", endpoint);
+    fetch_klines(&url, symbol).await
+}
+
+/// История 1m/5m/… свечей глубже одного запроса Binance (1500 баров):
+/// листаем назад через `endTime`, результат — по возрастанию времени.
+pub async fn get_kline_history(
+    symbol: &str,
+    bars: usize,
+    interval: u32,
+) -> Result<Vec<Candle>> {
+    const PAGE: usize = 1500;
+
+    let mut out: Vec<Candle> = Vec::with_capacity(bars);
+    let mut end_time: Option<i64> = None;
+    while out.len() < bars {
+        let limit = (bars - out.len()).min(PAGE);
+        let mut url = format!(
+            "https://fapi.binance.com/fapi/v1/klines?symbol={}&interval={}m&limit={}",
+            symbol, interval, limit
+        );
+        if let Some(t) = end_time {
+            url.push_str(&format!("&endTime={}", t));
+        }
+        let mut page = fetch_klines(&url, symbol).await?;
+        if page.is_empty() {
+            break;
+        }
+        page.sort_by_key(|c| c.timestamp);
+        end_time = Some(page[0].timestamp - 1);
+        page.extend(out);
+        out = page;
+        sleep(Duration::from_millis(200)).await;   // не упираемся в rate-limit
+    }
+    Ok(out)
+}
+
+async fn fetch_klines(url: &str, symbol: &str) -> Result<Vec<Candle>> {
     let client = Client::new();
 
     let resp = client
-        .get(&url)
+        .get(url)
         .send()
         .await
         .with_context(|| format!("Не удалось выполнить запрос klines для {}", symbol))?;
@@ -1,127 +1,231 @@
-// src/exchange/engine.rs
-
-use std::{env, sync::Arc};
-use dotenv::dotenv;
-use tokio::{
-    sync::mpsc::{unbounded_channel, UnboundedSender},
-    task,
-    time::{sleep, Duration},
-};
+// src/exchange/hl_engine.rs
+//
+// Дельта-нейтральный хедж на Hyperliquid.
+// Воркер считает суммарную SOL-дельту: SOL во всех LP-позициях
+// (`PoolPositionInfo.amount_a` пулов с SOL в качестве A, а в LST-пулах —
+// и `amount_b` по курсу LST/SOL) плюс SOL в кошельке,
+// и держит шорт SOLUSDT = дельта × HEDGE_RATIO. Шорт подравнивается, только
+// когда расхождение выходит за полосу max(HEDGE_BAND × цель, HEDGE_MIN_SOL).
+// Включается из Telegram (`hedge on|off|status`) или HEDGE_ENABLED=1,
+// пересчитывается по таймеру и по событиям оркестратора (открытие / закрытие).
+// В бумажном режиме ордера не шлются — только пишется, что было бы сделано.
+
+use std::env;
+
 use anyhow::Result;
+use hyperliquid_rust_sdk::truncate_float;
+use once_cell::sync::OnceCell;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio::time::{interval, Duration};
 
-use crate::types::WorkerCommand;
+use crate::dex_services::executor::{self, executor, mode_tag};
 use crate::exchange::hyperliquid::hl::HL;
-use crate::telegram_service::telegram::Telegram;
-
-// pub fn start() -> Result<UnboundedSender<WorkerCommand>> {
-//     // Загрузка .env (HLSECRET, HL_TRADING_ADDRESS, TELEGRAM_API, CHAT_ID)
-//     dotenv().ok();
-
-//     // канал управления воркером
-//     let (tx, mut rx) = unbounded_channel::<WorkerCommand>();
-
-//     // сам воркер
-//     task::spawn(async move {
-//         // 1) Инициализируем HL-клиент
-//         let mut hl = match HL::new_from_env().await {
-//             Ok(c) => c,
-//             Err(e) => {
-//                 eprintln!("[exchange][ERROR] HL init failed: {e}");
-//                 return;
-//             }
-//         };
-
-//         // 2) Инициализируем Telegram-уведомления
-//         let token = env::var("TELEGRAM_API").expect("TELEGRAM_API not set");
-//         let chat_id = env::var("CHAT_ID").expect("CHAT_ID not set");
-//         let tele = Telegram::new(&token, &chat_id);
-
-//         // Состояние воркера
-//         let mut running = false;
-//         let mut entry_px = 0.0;
-//         let mut pos_size = 0.0;
-//         let mut tp_price = 0.0;
-//         let mut sl_price = 0.0;
-
-//         loop {
-//             tokio::select! {
-//                 // Приход команды On/Off
-//                 Some(cmd) = rx.recv() => {
-//                     match cmd {
-//                         WorkerCommand::On(cfg) => {
-//                             // Если уже работаем — сначала закрываем старую позицию
-//                             if running {
-//                                 if let Ok(Some(pos)) = hl.get_position("SOLUSDT").await {
-//                                     let _ = hl.open_market_order("SOLUSDT", "Buy", 0.0, true, pos.size).await;
-//                                 }
-//                             }
-//                             // Открываем новый шорт на cfg.amount/2
-//                             let mut amount = 0.0;
-//                             let amount_sol = cfg.sol_init;
-//                             if let Ok(price) = hl.get_last_price("SOLUSDT").await {
-//                                 amount = amount_sol * price;
-//                                 if let Ok((_, px)) = hl.open_market_order("SOLUSDT", "Sell", amount, false, 0.0).await {
-//                                     entry_px = px;
-//                                     // Узнаём реальный размер
-//                                     if let Ok(Some(pos)) = hl.get_position("SOLUSDT").await {
-//                                         pos_size = pos.size;
-//                                         // Ставим TP и SL
-//                                         let _ = hl.open_tp("SOLUSDT", "Sell", pos_size, entry_px, 0.016).await;
-//                                         sleep(Duration::from_millis(500)).await;
-//                                         let _ = hl.open_sl("SOLUSDT", "Sell", pos_size, entry_px, 0.016).await;
-//                                         running = true;
-//                                     }
-//                                 }
-//                             };
-//                         }
-//                         WorkerCommand::Off => {
-//                             if running {
-//                                 // Принудительно закрываем
-//                                 if let Ok(Some(pos)) = hl.get_position("SOLUSDT").await {
-//                                     let _ = hl.open_market_order("SOLUSDT", "Sell", 0.0, true, pos.size).await;
-//                                     // Шлём остаток в Telegram
-//                                     sleep(Duration::from_millis(1000)).await;
-//                                     if let Ok(Some(pos)) = hl.get_position("SOLUSDT").await {
-//                                         let _ = hl.open_market_order("SOLUSDT", "Sell", 0.0, true, pos.size).await;
-//                                         sleep(Duration::from_millis(1000)).await;
-//                                         if let Ok(bal) = hl.get_balance().await {
-//                                             let msg = format!("🚫 Second try to close. Balance: ${:.2}", bal);
-//                                             let _ = tele.send(&msg).await;
-//                                         }
-//                                     } else {
-//                                         sleep(Duration::from_millis(1000)).await;
-//                                         if let Ok(bal) = hl.get_balance().await {
-//                                             let msg = format!("🚫 Forced close. Balance: ${:.2}", bal);
-//                                             let _ = tele.send(&msg).await;
-//                                         }
-//                                     }
-                                    
-//                                 }
-//                                 running = false;
-//                             }
-//                         }
-//                     }
-//                 },
-
-//                 // Каждые 5 с, если в режиме `On`, проверяем цену
-//                 // _ = sleep(Duration::from_secs(5)), if running => {
-//                 //     if let Ok(price) = hl.get_last_price("SOLUSDT").await {
-//                 //         if price >= tp_price || price <= sl_price {
-//                 //             // Закрываем по тейку или стопу
-//                 //             if let Ok(Some(pos)) = hl.get_position("SOLUSDT").await {
-//                 //                 let _ = hl.open_market_order("SOLUSDT", "Sell", 0.0, true, pos.size).await;
-//                 //                 if let Ok(bal) = hl.get_balance().await {
-//                 //                     let msg = format!("🔔 Auto-closed at {:.2}. Balance: ${:.2}", price, bal);
-//                 //                     let _ = tele.send(&msg).await;
-//                 //                 }
-//                 //             }
-//                 //             running = false;
-//                 //         }
-//                 //     }
-//                 // }
-//             }
-//         }
-//     });
-
-//     Ok(tx)
-// }
+use crate::orchestrator::owned_in_pool;
+use crate::params::{JITOSOL, MSOL, WSOL};
+use crate::pool_registry;
+use crate::telegram_service::tl_engine::ServiceCommand;
+use crate::types::HedgeCommand;
+
+const SYMBOL: &str = "SOLUSDT";
+/// как часто пересчитывать дельту
+const HEDGE_EVERY: Duration = Duration::from_secs(60);
+/// шаг размера SOL на Hyperliquid
+const SZ_DECIMALS: u32 = 2;
+
+static HEDGE_TX: OnceCell<UnboundedSender<HedgeCommand>> = OnceCell::new();
+
+/// Параметры хеджа из окружения.
+#[derive(Debug, Clone, Copy)]
+struct HedgeParams {
+    /// доля дельты, которую закрываем шортом
+    ratio:   f64,
+    /// допустимое расхождение, доля от цели
+    band:    f64,
+    /// допустимое расхождение не меньше, SOL
+    min_sol: f64,
+}
+
+impl HedgeParams {
+    fn from_env() -> Self {
+        let read = |key: &str, default: f64| {
+            env::var(key).ok().and_then(|v| v.parse::<f64>().ok()).unwrap_or(default)
+        };
+        Self {
+            ratio:   read("HEDGE_RATIO", 1.0),
+            band:    read("HEDGE_BAND", 0.10),
+            min_sol: read("HEDGE_MIN_SOL", 0.05),
+        }
+    }
+}
+
+/// Снимок: дельта LP + кошелька и текущий шорт.
+#[derive(Debug, Default, Clone, Copy)]
+struct HedgeState {
+    lp_sol:     f64,
+    wallet_sol: f64,
+    short_sol:  f64,
+}
+
+impl HedgeState {
+    fn delta(&self) -> f64 { self.lp_sol + self.wallet_sol }
+}
+
+/// Запустить воркер (один на процесс).
+pub fn start(tx_tg: UnboundedSender<ServiceCommand>) {
+    let (tx, mut rx) = unbounded_channel::<HedgeCommand>();
+    if HEDGE_TX.set(tx).is_err() {
+        return;
+    }
+    let mut enabled = env::var("HEDGE_ENABLED")
+        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "on"))
+        .unwrap_or(false);
+
+    tokio::spawn(async move {
+        let params = HedgeParams::from_env();
+        let mut hl: Option<HL> = None;
+        let mut last = HedgeState::default();
+        let mut itv = interval(HEDGE_EVERY);
+
+        loop {
+            let cmd = tokio::select! {
+                Some(cmd) = rx.recv() => cmd,
+                _ = itv.tick(), if enabled => HedgeCommand::Sync,
+                else => break,
+            };
+
+            let res = match cmd {
+                HedgeCommand::On => {
+                    enabled = true;
+                    let _ = tx_tg.send(ServiceCommand::SendMessage(format!(
+                        "{}🛡 Хедж включён: ratio {:.2}, полоса {:.0}% / {:.2} SOL",
+                        mode_tag(), params.ratio, params.band * 100.0, params.min_sol
+                    )));
+                    rebalance(&mut hl, &params, &tx_tg).await.map(|s| last = s)
+                }
+                HedgeCommand::Off => {
+                    enabled = false;
+                    close_short(&mut hl, &tx_tg).await.map(|_| last.short_sol = 0.0)
+                }
+                HedgeCommand::Sync if enabled => {
+                    rebalance(&mut hl, &params, &tx_tg).await.map(|s| last = s)
+                }
+                HedgeCommand::Sync => Ok(()),
+                HedgeCommand::Status => {
+                    let _ = tx_tg.send(ServiceCommand::SendMessage(format!(
+                        "{}🛡 Хедж {}: LP {:.3} SOL + кошелёк {:.3} SOL = дельта {:.3}, шорт {:.3}",
+                        mode_tag(), if enabled { "on" } else { "off" },
+                        last.lp_sol, last.wallet_sol, last.delta(), last.short_sol
+                    )));
+                    Ok(())
+                }
+            };
+            if let Err(e) = res {
+                let _ = tx_tg.send(ServiceCommand::SendMessage(format!("❌ Хедж: {e:#}")));
+            }
+        }
+    });
+}
+
+/// Отправить команду воркеру; false — воркер не запущен.
+pub fn send(cmd: HedgeCommand) -> bool {
+    HEDGE_TX.get().map(|tx| tx.send(cmd).is_ok()).unwrap_or(false)
+}
+
+/// Клиент HL создаётся при первой сделке (без ключей бот работает и без хеджа).
+async fn client(hl: &mut Option<HL>) -> Result<&mut HL> {
+    if hl.is_none() {
+        *hl = Some(HL::new_from_env().await?);
+    }
+    Ok(hl.as_mut().expect("HL client initialised above"))
+}
+
+/// SOL во всех LP-позициях пулов, где SOL — токен A. В LST-пулах
+/// (SOL/JitoSOL, SOL/mSOL) нога B — тоже SOL по курсу пула.
+async fn lp_sol() -> Result<f64> {
+    let mut total = 0.0;
+    for cfg in pool_registry::enabled_pools().into_iter().filter(|c| c.mint_a == WSOL) {
+        let lst = cfg.mint_b == JITOSOL || cfg.mint_b == MSOL;
+        for p in owned_in_pool(cfg.pool_address.parse()?).await? {
+            let info = executor().position_info(&cfg, &p).await?;
+            total += info.amount_a;
+            // current_price — LST за 1 SOL
+            if lst && info.current_price > 0.0 {
+                total += info.amount_b / info.current_price;
+            }
+        }
+    }
+    Ok(total)
+}
+
+/// Текущий шорт SOL (лонг — отрицательный шорт).
+async fn current_short(hl: &HL) -> Result<f64> {
+    Ok(match hl.get_position(SYMBOL).await? {
+        Some(p) if p.side == 2 => p.size,
+        Some(p) => -p.size,
+        None => 0.0,
+    })
+}
+
+async fn rebalance(
+    hl: &mut Option<HL>,
+    params: &HedgeParams,
+    tx_tg: &UnboundedSender<ServiceCommand>,
+) -> Result<HedgeState> {
+    let lp_sol     = lp_sol().await?;
+    let wallet_sol = executor().balance(WSOL, 9).await?;
+    let target     = ((lp_sol + wallet_sol) * params.ratio).max(0.0);
+
+    // бумага: шорт не открываем, считаем его равным цели
+    if executor::is_paper() {
+        log::info!("hedge (paper): дельта {:.3} SOL → шорт {:.3}", lp_sol + wallet_sol, target);
+        return Ok(HedgeState { lp_sol, wallet_sol, short_sol: target });
+    }
+
+    let hl    = client(hl).await?;
+    let short = current_short(hl).await?;
+    let diff  = target - short;
+    let band  = (target * params.band).max(params.min_sol);
+    let state = HedgeState { lp_sol, wallet_sol, short_sol: short };
+    if diff.abs() <= band {
+        return Ok(state);
+    }
+
+    let size = truncate_float(diff.abs(), SZ_DECIMALS, false);
+    if size == 0.0 {
+        return Ok(state);
+    }
+    if diff > 0.0 {
+        let px = hl.get_last_price(SYMBOL).await?;
+        hl.open_market_order(SYMBOL, "Sell", size * px, false, 0.0).await?;
+    } else {
+        // уменьшаем только шорт, в лонг не переворачиваемся
+        let size = size.min(short.max(0.0));
+        if size == 0.0 {
+            return Ok(state);
+        }
+        hl.open_market_order(SYMBOL, "Buy", 0.0, true, size).await?;
+    }
+
+    let short_after = current_short(hl).await?;
+    let _ = tx_tg.send(ServiceCommand::SendMessage(format!(
+        "🛡 Хедж: дельта {:.3} SOL (LP {:.3} + кошелёк {:.3}), шорт {:.3} → {:.3}",
+        lp_sol + wallet_sol, lp_sol, wallet_sol, short, short_after
+    )));
+    Ok(HedgeState { short_sol: short_after, ..state })
+}
+
+async fn close_short(hl: &mut Option<HL>, tx_tg: &UnboundedSender<ServiceCommand>) -> Result<()> {
+    if executor::is_paper() {
+        let _ = tx_tg.send(ServiceCommand::SendMessage(format!("{}🛡 Хедж выключен", mode_tag())));
+        return Ok(());
+    }
+    let hl    = client(hl).await?;
+    let short = current_short(hl).await?;
+    if short > 0.0 {
+        hl.open_market_order(SYMBOL, "Buy", 0.0, true, short).await?;
+    }
+    let _ = tx_tg.send(ServiceCommand::SendMessage(format!(
+        "🛡 Хедж выключен, шорт {:.3} SOL закрыт", short.max(0.0)
+    )));
+    Ok(())
+}
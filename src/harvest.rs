@@ -0,0 +1,232 @@
+// src/harvest.rs
+//
+// Сбор комиссий по порогу. Раз в HARVEST_EVERY оркестратор спрашивает,
+// сколько накопилось (`PoolPositionInfo.sum`, USD); если больше, чем
+// HARVEST_MIN_MULT × стоимость транзакций сбора, — собираем со всех позиций пула
+// и либо докладываем в позицию, внутри которой цена (compound),
+// либо переводим в USDC и оставляем в кошельке (reserve). В позицию бандла
+// докладывать нельзя — для неё compound сводится к reserve.
+// Каждый сбор пишется в `fee_harvests` как реализованный доход.
+//
+// Награды пула (`reward_infos`) собираются той же инструкцией harvest и при
+// закрытии позиции; токены наград сразу свапаются в USDC (`swap_rewards`).
+// Собранные награды пишутся в `fee_harvests` вместе с комиссиями и
+// вычитаются из несобранных (`rewards_pending`) — иначе история сессии
+// посчитала бы их дважды.
+
+use std::env;
+use std::time::Duration;
+
+use anyhow::Result;
+use solana_sdk::pubkey::Pubkey;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::database::{history, positions};
+use crate::dex_services::executor::{executor, mode_tag};
+use crate::orchestrator::owned_in_pool;
+use crate::params::{USDC, USDT, WSOL};
+use crate::telegram_service::tl_engine::ServiceCommand;
+use crate::types::{HarvestMode, PoolConfig};
+use crate::dex_services::get_info::whirlpool_reward_mints;
+use crate::dex_services::venue::{venue_for, ClmmVenue, ORCA};
+use crate::utils::{get_sol_price_usd, get_token_price_usd, utils};
+
+/// как часто проверять накопленные комиссии
+pub const HARVEST_EVERY: Duration = Duration::from_secs(10 * 60);
+/// комиссия сети за одну транзакцию, SOL (с приоритетом)
+const HARVEST_TX_FEE_SOL: f64 = 0.000_1;
+/// порог по умолчанию: комиссии должны окупать транзакции сбора в N раз
+const HARVEST_MIN_MULT: f64 = 20.0;
+/// меньше этого (в USD) не свапаем в резерв — съест спред
+const RESERVE_MIN_SWAP_USD: f64 = 1.0;
+
+fn min_mult() -> f64 {
+    env::var("HARVEST_MIN_MULT").ok().and_then(|v| v.parse().ok()).unwrap_or(HARVEST_MIN_MULT)
+}
+
+/// Проверить порог и, если пора, собрать комиссии пула.
+/// `price` — текущая display-цена (для выбора позиции под compound).
+pub async fn maybe_harvest(
+    pool_cfg: &PoolConfig,
+    mode: &HarvestMode,
+    price: f64,
+    tx_tg: &UnboundedSender<ServiceCommand>,
+) -> Result<()> {
+    if *mode == HarvestMode::Off {
+        return Ok(());
+    }
+    let whirl_pk: Pubkey = pool_cfg.pool_address.parse()?;
+
+    // 1) сколько накопилось по позициям
+    let mut infos = Vec::new();
+    for p in owned_in_pool(whirl_pk).await? {
+        let info = executor().position_info(pool_cfg, &p).await?;
+        infos.push((p, info));
+    }
+    // награды приходят той же транзакцией — позиция только с наградами тоже в деле
+    let with_fees: Vec<_> = infos.iter().filter(|(_, i)| i.sum + i.rewards_usd > 0.0).collect();
+    let pending_usd: f64 = with_fees.iter().map(|(_, i)| i.sum + i.rewards_usd).sum();
+
+    // 2) порог: сбор с каждой позиции + compound/свапы резерва
+    let sol_usd   = get_sol_price_usd(WSOL, true).await?;
+    let tx_count  = with_fees.len() as f64 + if *mode == HarvestMode::Compound { 1.0 } else { 2.0 };
+    let threshold = HARVEST_TX_FEE_SOL * sol_usd * tx_count * min_mult();
+    if with_fees.is_empty() || pending_usd < threshold {
+        log::debug!("harvest {}: ${pending_usd:.4} < ${threshold:.4}", pool_cfg.name);
+        return Ok(());
+    }
+
+    // 3) собираем
+    let (mut got_a, mut got_b, mut got_usd, mut rewards_usd) = (0.0, 0.0, 0.0, 0.0);
+    let mut harvested = Vec::new();
+    for (p, info) in &with_fees {
+        match executor().harvest(p.mint, pool_cfg).await {
+            Ok(h) => {
+                got_a   += h.amount_a;
+                got_b   += h.amount_b;
+                got_usd += info.sum;
+                // награды пришли той же транзакцией
+                rewards_usd += info.rewards_usd;
+                harvested.push((p.mint, h, info.sum + info.rewards_usd));
+            }
+            Err(e) => log::warn!("harvest {}: {} не собрана: {e}", pool_cfg.name, p.mint),
+        }
+    }
+    if harvested.is_empty() {
+        return Ok(());
+    }
+
+    // 4) compound в позицию под ценой, иначе — в резерв
+    let target = infos.iter()
+        .find(|(_, i)| price >= i.lower_price && price < i.upper_price)
+        .map(|(p, _)| p);
+    let mut action = "reserve";
+    if *mode == HarvestMode::Compound {
+        match target {
+            // в позицию бандла ликвидность не докладывается — сразу в резерв
+            Some(p) if p.bundle.is_some() => log::info!(
+                "harvest {}: позиция под ценой в бандле, compound невозможен — комиссии в резерв", pool_cfg.name
+            ),
+            Some(p) => match executor().increase_liquidity(p.mint, got_usd, pool_cfg, 300).await {
+                Ok(_)  => action = "compound",
+                Err(e) => log::warn!("harvest {}: compound не удался ({e}), оставляем в резерве", pool_cfg.name),
+            },
+            None => log::info!("harvest {}: цена вне всех позиций, комиссии — в резерв", pool_cfg.name),
+        }
+    }
+    if action == "reserve" {
+        to_usdc_reserve(pool_cfg, got_a, got_b, got_usd).await;
+    }
+
+    for (mint, h, usd) in &harvested {
+        if let Err(e) = history::record_fee_harvest(
+            &pool_cfg.pool_address, &pool_cfg.name, &mint.to_string(), h.amount_a, h.amount_b, *usd, action,
+        ).await {
+            log::warn!("fee_harvests: not recorded: {e}");
+        }
+    }
+    if rewards_usd > 0.0 {
+        if let Err(e) = positions::take_rewards_pending(&pool_cfg.pool_address, rewards_usd).await {
+            log::warn!("rewards_pending: not updated: {e}");
+        }
+        if let Err(e) = swap_rewards(pool_cfg).await {
+            log::warn!("harvest {}: награды не свапнуты в USDC: {e}", pool_cfg.name);
+        }
+    }
+
+    let _ = tx_tg.send(ServiceCommand::SendMessage(format!(
+        "{}🌾 {}: собрано комиссий ≈ ${:.2} с {} поз. → {}",
+        mode_tag(), pool_cfg.name, got_usd, harvested.len(),
+        if action == "compound" { "в позицию под ценой" } else { "в резерв USDC" },
+    )));
+    Ok(())
+}
+
+/// Свести собранные токены в USDC (стейблы не трогаем, мелочь — тоже).
+async fn to_usdc_reserve(pool_cfg: &PoolConfig, got_a: f64, got_b: f64, got_usd: f64) {
+    if got_usd < RESERVE_MIN_SWAP_USD {
+        return;
+    }
+    for (mint, amount) in [(pool_cfg.mint_a.as_str(), got_a), (pool_cfg.mint_b.as_str(), got_b)] {
+        if mint == USDC || mint == USDT || amount <= 0.0 {
+            continue;
+        }
+        if let Err(e) = executor().swap(mint, USDC, amount).await {
+            log::warn!("harvest {}: свап {mint} → USDC не удался: {e}", pool_cfg.name);
+        }
+    }
+}
+
+/// Собрать награды со всех позиций пула, где они есть, и свапнуть их в USDC.
+/// Комиссии приходят той же транзакцией и остаются в кошельке.
+pub async fn claim_rewards(pool_cfg: &PoolConfig, tx_tg: &UnboundedSender<ServiceCommand>) -> Result<()> {
+    let whirl_pk: Pubkey = pool_cfg.pool_address.parse()?;
+    let mut claimed = 0usize;
+    let mut usd     = 0.0;
+    for p in owned_in_pool(whirl_pk).await? {
+        let info = executor().position_info(pool_cfg, &p).await?;
+        if info.rewards.is_empty() {
+            continue;
+        }
+        match executor().harvest(p.mint, pool_cfg).await {
+            Ok(h) => {
+                claimed += 1;
+                usd     += info.rewards_usd;
+                if let Err(e) = history::record_fee_harvest(
+                    &pool_cfg.pool_address, &pool_cfg.name, &p.mint.to_string(),
+                    h.amount_a, h.amount_b, h.total_usd + info.rewards_usd, "reward",
+                ).await {
+                    log::warn!("fee_harvests: not recorded: {e}");
+                }
+            }
+            Err(e) => log::warn!("rewards {}: {} не собрана: {e}", pool_cfg.name, p.mint),
+        }
+    }
+    if claimed == 0 {
+        let _ = tx_tg.send(ServiceCommand::SendMessage(format!(
+            "{}🎁 {}: несобранных наград нет", mode_tag(), pool_cfg.name
+        )));
+        return Ok(());
+    }
+    positions::take_rewards_pending(&pool_cfg.pool_address, usd).await?;
+    let swapped = swap_rewards(pool_cfg).await?;
+    let _ = tx_tg.send(ServiceCommand::SendMessage(format!(
+        "{}🎁 {}: награды ≈ ${:.2} собраны с {} поз., в USDC ≈ ${:.2}",
+        mode_tag(), pool_cfg.name, usd, claimed, swapped
+    )));
+    Ok(())
+}
+
+/// Свапнуть в USDC токены наград пула, лежащие в кошельке
+/// (кроме токенов самого пула и стейблов). Возвращает USD-оценку свапнутого.
+pub async fn swap_rewards(pool_cfg: &PoolConfig) -> Result<f64> {
+    // награды читаем только из Whirlpool
+    if venue_for(&pool_cfg.program).name() != ORCA.name() {
+        return Ok(0.0);
+    }
+    let rpc      = utils::init_rpc();
+    let whirl_pk: Pubkey = pool_cfg.pool_address.parse()?;
+    let whirl    = orca_whirlpools_client::Whirlpool::from_bytes(&rpc.get_account(&whirl_pk).await?.data)?;
+
+    let mut swapped = 0.0;
+    for (_, mint, dec) in whirlpool_reward_mints(&rpc, &whirl).await? {
+        let mint = mint.to_string();
+        if mint == pool_cfg.mint_a || mint == pool_cfg.mint_b || mint == USDC || mint == USDT {
+            continue;
+        }
+        let amount = executor().balance(&mint, dec).await?;
+        if amount <= 0.0 {
+            continue;
+        }
+        let usd = amount * get_token_price_usd(&mint).await.unwrap_or(0.0);
+        if usd < RESERVE_MIN_SWAP_USD {
+            log::debug!("rewards {}: {mint} ≈ ${usd:.4} — мелочь, не свапаем", pool_cfg.name);
+            continue;
+        }
+        match executor().swap(&mint, USDC, amount).await {
+            Ok(_)  => swapped += usd,
+            Err(e) => log::warn!("rewards {}: свап {mint} → USDC не удался: {e}", pool_cfg.name),
+        }
+    }
+    Ok(swapped)
+}
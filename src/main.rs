@@ -5,6 +5,10 @@ mod telegram_service;
 mod dex_services;
 mod exchange;
 mod orchestrator;
+mod pool_registry;
+mod reconcile;
+mod harvest;
+mod migration;
 
 
 pub mod utils;
@@ -15,7 +19,6 @@ pub mod comp_strategy;
 
 // ─── External and standard imports ─────────────────────────────────────────
 use std::{
-    env,
     sync::{
         Arc,
         atomic::{AtomicBool, Ordering},
@@ -24,7 +27,6 @@ use std::{
 use sqlx::Error;
 use tokio::time::Instant;
 use anyhow::{Context, Result};
-use chrono::Utc;
 use dotenv::dotenv;
 use tokio::{
     sync::{mpsc::UnboundedSender, Notify, RwLock},
@@ -34,22 +36,15 @@ use tokio::{
 // ─── Local crate imports ────────────────────────────────────────────────────
 use crate::{
     database::{
-        general_settings, history, positions, triggers::{self, Trigger}
-    }, params::{RANGE, USDC, WSOL}, strategies::limit_order::is_limit_trigger_satisfied, telegram_service::tl_engine::ServiceCommand, types::{PoolConfig, Range}
+        exit_policy, general_settings, history, lookup_tables, open_journal, pool_settings, positions, tx_costs, triggers::{self, Trigger}
+    }, params::RANGE, strategies::{limit_order::is_limit_trigger_satisfied, ranges::{strategy_for, ENTRY_LOOKBACK_1M}}, telegram_service::tl_engine::ServiceCommand, types::{HarvestMode, PoolConfig}
 };
 use crate::exchange::helpers::Candle;
-use crate::exchange::helpers::decide;
-use crate::exchange::helpers::{get_atr, range_coefficient, calculate_price_bounds, Mode};
-use crate::exchange::helpers::get_rsi;
+use crate::dex_services::executor::{executor, mode_tag};
 use crate::comp_strategy::stream_candles;
-use crate::exchange::helpers::convert_timeframe;
-use crate::exchange::helpers::Unzip5;
 
 const POST_CLOSE_RESTART_DELAY:  u64 = 10;
 const RPC_RETRY_DELAY:           u64 = 20;
-const LOOKBACK_1M: usize = 30;    // сколько 1-минуток держим в расчёте
-const ATR_PER: usize     = 14;
-const RSI_PER: usize     = 14;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -59,145 +54,171 @@ async fn main() -> Result<()> {
     let close_notify = Arc::new(tokio::sync::Notify::new());
 
     let (tx_tg, _commander) = telegram_service::tl_engine::start(close_notify.clone());
+    exchange::hl_engine::start(tx_tg.clone());
 
-    let need_new_pos = Arc::new(AtomicBool::new(false)); //true - будут открываться новые при запуске; false - не будут
     let auto_trade = true;
 
-    init_default_triggers(&need_new_pos, auto_trade).await?;
-    let init_wallet_balance = utils::fetch_wallet_balance_info().await?;
+    init_default_triggers().await?;
+    let init_wallet_balance = executor().wallet_balance().await?;
     println!("Wallet Balance: {}", init_wallet_balance);
-    let _ = tx_tg.send(ServiceCommand::SendMessage(init_wallet_balance.to_string()));
-
-    let solusdc_cfg = make_solusdc_config()?;
-    let report_cfgs = vec![solusdc_cfg.clone()];
-
-    tokio::spawn(run_pool_with_restart(
-        solusdc_cfg.clone(), 
-        tx_tg.clone(), need_new_pos.clone(), close_notify.clone(), 1, Some(0.03)
-    ));
-
-    // ─── C)  единый репортёр каждые 5 минут ────────────────────────────
-    let tx_tel = tx_tg.clone();
-    tokio::spawn({
-        let report_cfgs = report_cfgs.clone();   // если нужно
-        let tx_tel      = tx_tel.clone();
-        async move {
-            use tokio::time::{Duration};
-            use std::collections::VecDeque;
-    
-            let mut prev_total   = 0.0;
-            let mut last_profits = VecDeque::<f64>::with_capacity(12); // 12×5 мин = час
-            let mut first_loop = true;
-            let mut counter = 0;
-
-            loop {
-
-                let auto_trade = triggers::get_trigger("auto_trade").await;
-                let pool_report_run = triggers::get_trigger("pool_report_run").await;
-                if pool_report_run.state && !auto_trade.state {
-                    break;
-                } else {
-                    sleep(Duration::from_secs(1)).await;
-                }
+    let _ = tx_tg.send(ServiceCommand::SendMessage(format!("{}{}", mode_tag(), init_wallet_balance)));
+
+    // ─── адреса пулов: прошлые переезды и <POOL_ENV>=auto; сэмплер копит снимки для ранжирования
+    for line in pool_registry::resolve_pool_addresses().await {
+        let _ = tx_tg.send(ServiceCommand::SendMessage(line));
+    }
+    tokio::spawn(dex_services::discovery::run_sampler());
+
+    let pools = pool_registry::enabled_pools();
+    if pools.is_empty() {
+        anyhow::bail!("Ни один пул не задан в окружении (SOLUSDC_POOL, SOLUSDT_POOL, …)");
+    }
+
+    // ─── открытия, прерванные прошлым падением: достроить или откатить
+    orchestrator::recover_unfinished_opens(&tx_tg).await?;
+
+    // ─── каждый пул — свой воркер и свой репортёр ───────────────────────
+    for cfg in pools {
+        init_pool_settings(&cfg).await?;
+
+        // сверка БД с позициями в сети решает, усыновляем или открываемся заново
+        let need_new = match reconcile::reconcile_pool(&cfg).await {
+            Ok(rep) => {
+                let _ = tx_tg.send(ServiceCommand::SendMessage(rep.summary()));
+                rep.need_new
             }
-    
-            loop {
+            Err(e) => {
+                let _ = tx_tg.send(ServiceCommand::SendMessage(
+                    format!("⚠️ {}: сверка позиций не удалась: {e:#}", cfg.name)
+                ));
+                false
+            }
+        };
+        let need_new_pos = Arc::new(AtomicBool::new(need_new)); //true - будут открываться новые при запуске; false - не будут
+        init_pool_triggers(&cfg.name, &need_new_pos, auto_trade).await?;
+
+        // все транзакции воркера и репортёра ложатся в издержки этого пула
+        let pool_addr = Some(cfg.pool_address.clone());
+        tokio::spawn(tx_costs::scoped(pool_addr.clone(), "pool", run_pool_with_restart(
+            cfg.clone(),
+            tx_tg.clone(), need_new_pos.clone(), close_notify.clone()
+        )));
+        tokio::spawn(tx_costs::scoped(pool_addr, "pool", run_pool_reporter(cfg, tx_tg.clone(), init_wallet_balance.total_usd)));
+    }
 
-                let report_info_reset = triggers::get_trigger("report_info_reset").await;
-                let opening = triggers::get_trigger("opening").await;
-                if opening.state {
-                    sleep(Duration::from_secs(1)).await;
-                    continue;
-                }
-                if !report_info_reset.state  {
-                    let mins = match general_settings::get_general_settings().await {
-                        Ok(Some(cfg)) => cfg.info_interval,
-                        _             => 5,
-                    };
-                    sleep(Duration::from_secs((mins as u64) * 60)).await;
-                } 
+    // ─── держим runtime живым
+    futures::future::pending::<()>().await;
 
-                counter+=1;
+    Ok(())
+}
 
-                // 1) собираем отчёты
-                let mut grand_total = 0.0;
-                let mut msg = String::new();
+/// Репортёр одного пула: раз в `info_interval` минут шлёт сводку в Telegram.
+async fn run_pool_reporter(
+    mut cfg:    PoolConfig,
+    tx_tel:     UnboundedSender<ServiceCommand>,
+    init_wallet_balance: f64,
+) {
+    use std::collections::VecDeque;
 
-                let trig = triggers::get_trigger("pool_report_run").await;
+    let mut prev_total   = 0.0;
+    let mut last_profits = VecDeque::<f64>::with_capacity(12); // 12×5 мин = час
+    let mut first_loop = true;
+    let mut counter = 0;
 
-                if !trig.state {
-                    sleep(Duration::from_secs(1)).await;
-                    continue;
-                }
-    
-                for cfg in &report_cfgs {
-                    match orchestrator::build_pool_report(cfg, tx_tg.clone(), init_wallet_balance.total_usd).await {
-                        Ok(rep) => {
-                            grand_total += rep.total;
-                            msg.push_str(&rep.text);
-                        }
-                        Err(e) => msg.push_str(
-                            &format!("⚠️ report for {} failed: {e}\n", cfg.name)),
-                    }
-                }
-                
-    
-                // 2) считаем profit и поддерживаем «кольцевой буфер»
-                let mut profit = 0.0;
-
-                
-                if report_info_reset.state {
-                    first_loop = true;
-                    last_profits.clear();
-                    let _ = triggers::report_info_reset(false, None).await;
-                }
+    loop {
 
-                
-
-                if first_loop {
-                    counter = 0;
-                    prev_total = grand_total;
-                    first_loop = false;
-                } else {
-                    profit = grand_total - prev_total;
-                    prev_total = grand_total;
-        
-                    if last_profits.len() == 12 { last_profits.pop_front(); }
-                    last_profits.push_back(profit);
-                }
+        let auto_trade = triggers::get_pool_trigger("auto_trade", &cfg.name).await;
+        let pool_report_run = triggers::get_pool_trigger("pool_report_run", &cfg.name).await;
+        if pool_report_run.state && !auto_trade.state {
+            break;
+        } else {
+            sleep(Duration::from_secs(1)).await;
+        }
+    }
 
-    
-                let sum_last: f64 = last_profits.iter().sum();
-                let avg_last: f64 = if !last_profits.is_empty() {
-                    sum_last / last_profits.len() as f64
-                } else { 0.0 };
+    loop {
+        if let Some(fresh) = pool_registry::moved(&cfg) {
+            cfg = fresh;
+        }
 
-                let duration = counter * 5;
-    
-                // 3) итоговый блок и отправка
-                msg.push_str(&format!(
-                    "📈 Total: ${:.6}\nΔ5 min: {:+.4}\nΣ1 h (12×5 min): {:+.4} (avg {:+.4}/5 min) dur: {:}",
-                    grand_total, profit, sum_last, avg_last, duration
-                ));
-                let _ = tx_tel.send(ServiceCommand::SendMessage(msg));
+        let report_info_reset = triggers::get_pool_trigger("report_info_reset", &cfg.name).await;
+        let opening = triggers::get_pool_trigger("opening", &cfg.name).await;
+        if opening.state {
+            sleep(Duration::from_secs(1)).await;
+            continue;
+        }
+        if !report_info_reset.state  {
+            let mins = match general_settings::get_general_settings().await {
+                Ok(Some(cfg)) => cfg.info_interval,
+                _             => 5,
+            };
+            sleep(Duration::from_secs((mins as u64) * 60)).await;
+        } 
+
+        counter+=1;
+
+        // 1) собираем отчёт
+        let mut grand_total = 0.0;
+        let mut msg = String::new();
+
+        let trig = triggers::get_pool_trigger("pool_report_run", &cfg.name).await;
+
+        if !trig.state {
+            sleep(Duration::from_secs(1)).await;
+            continue;
+        }
+
+        match orchestrator::build_pool_report(&cfg, tx_tel.clone(), init_wallet_balance).await {
+            Ok(rep) => {
+                grand_total += rep.total;
+                msg.push_str(&rep.text);
             }
-            
+            Err(e) => msg.push_str(
+                &format!("⚠️ report for {} failed: {e}\n", cfg.name)),
         }
-    });
 
-    // ─── держим runtime живым
-    futures::future::pending::<()>().await;
+        // 2) считаем profit и поддерживаем «кольцевой буфер»
+        let mut profit = 0.0;
 
-    Ok(())
+        if report_info_reset.state {
+            first_loop = true;
+            last_profits.clear();
+            let _ = triggers::report_info_reset(&cfg.name, false, None).await;
+        }
+
+        if first_loop {
+            counter = 0;
+            prev_total = grand_total;
+            first_loop = false;
+        } else {
+            profit = grand_total - prev_total;
+            prev_total = grand_total;
+
+            if last_profits.len() == 12 { last_profits.pop_front(); }
+            last_profits.push_back(profit);
+        }
+
+        let sum_last: f64 = last_profits.iter().sum();
+        let avg_last: f64 = if !last_profits.is_empty() {
+            sum_last / last_profits.len() as f64
+        } else { 0.0 };
+
+        let duration = counter * 5;
+
+        // 3) итоговый блок и отправка
+        msg.push_str(&format!(
+            "📈 Total: ${:.6}\nΔ5 min: {:+.4}\nΣ1 h (12×5 min): {:+.4} (avg {:+.4}/5 min) dur: {:}",
+            grand_total, profit, sum_last, avg_last, duration
+        ));
+        let _ = tx_tel.send(ServiceCommand::SendMessage(msg));
+    }
 }
 
 async fn run_pool_with_restart(
-    cfg:        PoolConfig,
+    mut cfg:    PoolConfig,
     tx_tg:      UnboundedSender<ServiceCommand>,
     need_new: Arc<AtomicBool>,
     close_ntf:  Arc<Notify>,
-    min_restart: u64,
-    range: Option<f32>,
 ) -> Result<()> {
     use tokio::time::{sleep, Duration};
 
@@ -206,13 +227,26 @@ async fn run_pool_with_restart(
     let mut last_report = Instant::now() - report_interval;
     
     loop {
-        let auto_trade = triggers::get_trigger("auto_trade").await;
+        // пул мог переехать в другой fee tier — дальше работаем по новому адресу
+        if let Some(fresh) = pool_registry::moved(&cfg) {
+            cfg = fresh;
+            need_new.store(true, Ordering::SeqCst);
+        }
+        let pool_set = pool_settings::get_pool_settings(&cfg.pool_address).await?
+            .context("Pool settings not found in database")?;
+        if !pool_set.enabled {
+            sleep(Duration::from_secs(RPC_RETRY_DELAY)).await;
+            continue;
+        }
+
+        let strategy = strategy_for(&pool_set.strategy);
+        let auto_trade = triggers::get_pool_trigger("auto_trade", &cfg.name).await;
 
         println!("Got: {:?}", auto_trade);
         let limit = is_limit_trigger_satisfied(&tx_tg).await?;
         if limit && auto_trade.state {
             need_new.store(true, Ordering::SeqCst);
-            triggers::auto_trade_switch(false, None).await?;
+            triggers::auto_trade_switch(&cfg.name, false, None).await?;
             triggers::limit_switcher(false, Some(&tx_tg)).await?;
         } else if auto_trade.state == true {
             if candles_arc.is_none() {
@@ -225,23 +259,16 @@ async fn run_pool_with_restart(
                     let guard = candles_arc.as_ref().unwrap().read().await;
                     guard.iter()
                             .rev()
-                            .take(LOOKBACK_1M)
+                            .take(ENTRY_LOOKBACK_1M)
                             .cloned()
                             .collect::<Vec<_>>()
                             .into_iter()
                             .rev()
                             .collect::<Vec<_>>()
                 };
-                if src.len() == LOOKBACK_1M {
-                    let (o,h,l,c,v) = src.iter()
-                        .map(|c| (c.open,c.high,c.low,c.close,c.volume))
-                        .unzip5();
-                    let (o5,h5,l5,c5,v5) = convert_timeframe(&o,&h,&l,&c,&v,5,0);
-                    let atr = get_atr(&o5,&h5,&l5,&c5,&v5,ATR_PER)?;
-                    let atr_last = atr[atr.len()-1];
-                    let (pr_up, pr_low) = calculate_price_bounds(c[c.len()-1]);
-                    println!("pr_up: {:.2} pr_low: {:.2}", pr_up, pr_low);
-                    let centre_kof = range_coefficient(&o,&h,&l,&c, 70, pr_low, pr_up, Mode::Full).unwrap();
+                if src.len() == ENTRY_LOOKBACK_1M {
+                    let gate = strategy.entry_gate(&src)?;
+                    let (atr_last, centre_kof) = (gate.atr, gate.centre);
                     println!("Ждем вход в позицию ATR: {} CNT: {}", &atr_last, &centre_kof);
 
                     if last_report.elapsed() >= report_interval {
@@ -254,7 +281,7 @@ async fn run_pool_with_restart(
                         last_report = Instant::now();
                     }
 
-                    centre_kof > 0.99 && atr_last < 0.40            // ← true / false
+                    gate.ready            // ← true / false
                 } else { 
                     false 
                 }
@@ -267,25 +294,25 @@ async fn run_pool_with_restart(
             } else {
                 need_new.store(true, Ordering::SeqCst);
                 // «решение принято» — стрим больше не нужен
-                triggers::auto_trade_switch(false, None).await?;
+                triggers::auto_trade_switch(&cfg.name, false, None).await?;
                 candles_arc = None;             // ★ drop Arc => ws завершается
             }
         }
-        triggers::report_info_reset(true, Some(&tx_tg.clone())).await?;
+        triggers::report_info_reset(&cfg.name, true, Some(&tx_tg.clone())).await?;
 
         let settings = general_settings::get_general_settings().await?
         .context("General settings not found in database")?;
 
-        let pct: [f64; 4]   = if settings.pct_number == 1 {settings.pct_list_1} else { settings.pct_list_2 };
-
+        let pct: [f64; 4]   = if pool_set.pct_number == 1 {settings.pct_list_1} else { settings.pct_list_2 };
 
-        triggers::opening_switcher(true, Some(&tx_tg)).await?;
 
-        let weights = if RANGE == Range::Two {params::weights_2()} else if RANGE == Range::Three {params::weights_1()} else {params::weights_1()};
+        triggers::opening_switcher(&cfg.name, true, Some(&tx_tg)).await?;
 
-        let res = orchestrator::orchestrator_pool(
-            cfg.clone(), settings.amount, pct, weights, tx_tg.clone(), need_new.clone(), close_ntf.clone(), min_restart, range, settings.compress
-        ).await;
+        // издержки — на текущий адрес пула (после переезда он другой)
+        let res = tx_costs::scoped(Some(cfg.pool_address.clone()), "pool", orchestrator::orchestrator_pool(
+            cfg.clone(), strategy.as_ref(), pool_set.amount, pct, tx_tg.clone(), need_new.clone(), close_ntf.clone(), pool_set.min_restart, pool_set.range, settings.compress,
+            pool_set.recenter, pool_set.harvest.clone()
+        )).await;
 
         match res {
             Ok(()) => sleep(Duration::from_secs(POST_CLOSE_RESTART_DELAY)).await,
@@ -321,44 +348,49 @@ async fn run_pool_with_restart(
     return Ok(());
 }
 
-fn make_solusdc_config() -> Result<PoolConfig> {
-    Ok(PoolConfig {
-        program: "whirlpool".to_string(),
-        name:    "SOL/USDC".to_string(),
-        pool_address: env::var("SOLUSDC_POOL")?,
-        mint_a:  WSOL.to_string(),  decimal_a: 9,
-        mint_b:  USDC.to_string(),  decimal_b: 6,
-        amount:  0.0,
-        position_1: None, position_2: None, position_3: None,
-        date_opened:         Utc::now(),
-        is_closed:           false,
-        commission_collected_1: 0.0,
-        commission_collected_2: 0.0,
-        commission_collected_3: 0.0,
-        total_value_open:    0.0,
-        total_value_current: 0.0,
-        wallet_balance: 0.0
-    })
+// helper-закрывалка
+async fn upsert(name: &str, state: bool) -> Result<()> {
+    let tg = Trigger { name: name.into(), state, position: String::new() };
+    triggers::upsert_trigger(&tg).await?;
+    Ok(())
 }
 
-/// Создаёт или обновляет набор «базовых» триггеров.
-async fn init_default_triggers(need_new_pos: &Arc<AtomicBool>, auto_trade: bool) -> Result<()> {
+/// Создаёт или обновляет глобальные триггеры.
+async fn init_default_triggers() -> Result<()> {
+    upsert("closing",              false        ).await?;
+    upsert("limit",                false        ).await?;
+    Ok(())
+}
+
+/// Создаёт или обновляет триггеры конкретного пула.
+async fn init_pool_triggers(pool: &str, need_new_pos: &Arc<AtomicBool>, auto_trade: bool) -> Result<()> {
     let new_pos_flag = !need_new_pos.load(Ordering::SeqCst);
 
-    // helper-закрывалка
-    async fn upsert(name: &str, state: bool) -> Result<()> {
-        let tg = Trigger { name: name.into(), state, position: String::new() };
-        triggers::upsert_trigger(&tg).await?;
-        Ok(())
-    }
+    upsert(&triggers::pool_trigger("pool_report_run", pool), new_pos_flag).await?;
+    upsert(&triggers::pool_trigger("report_info_reset", pool), true       ).await?;
+    upsert(&triggers::pool_trigger("auto_trade", pool),        auto_trade ).await?;
+    upsert(&triggers::pool_trigger("opening", pool),           false      ).await?;
 
-    upsert("pool_report_run", new_pos_flag).await?;
-    upsert("closing",              false        ).await?;
-    upsert("limit",                false        ).await?;
-    upsert("report_info_reset",         true         ).await?;
-    upsert("auto_trade",         auto_trade         ).await?;
-    upsert("opening",         false         ).await?;
+    Ok(())
+}
 
+/// Заводим настройки пула, если их ещё нет (по умолчанию — из general_settings).
+async fn init_pool_settings(cfg: &PoolConfig) -> Result<()> {
+    let general = general_settings::get_general_settings().await?
+        .context("General settings not found in database")?;
+    pool_settings::ensure_pool_settings(&pool_settings::PoolSettings {
+        pool_address: cfg.pool_address.clone(),
+        name:         cfg.name.clone(),
+        enabled:      true,
+        amount:       general.amount,
+        pct_number:   general.pct_number,
+        min_restart:  1,
+        range:        Some(0.03),
+        strategy:     RANGE,
+        recenter:     false,
+        harvest:      HarvestMode::Off,
+        bundle:       false,
+    }).await?;
     Ok(())
 }
 
@@ -369,5 +401,10 @@ async fn init_database() -> Result<()> {
     history::init_history_module().await?;
     general_settings::init_general_settings_module().await?;
     general_settings::init_settings_from_params().await?;
+    pool_settings::init_pool_settings_module().await?;
+    open_journal::init_open_journal_module().await?;
+    exit_policy::init_exit_policy_module().await?;
+    tx_costs::init_tx_costs_module().await?;
+    lookup_tables::init_lookup_tables_module().await?;
     Ok(())
 }
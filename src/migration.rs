@@ -0,0 +1,152 @@
+// src/migration.rs
+//
+// Переезд ликвидности в более доходный fee tier той же пары.
+// Раз в MIGRATE_EVERY оркестратор сравнивает реализованный доход комиссий
+// на доллар ликвидности в диапазоне (прирост `fee_growth_global` между снимками
+// discovery) у нашего пула и у остальных Whirlpool пары. Если разница APR
+// за MIGRATE_HORIZON_DAYS окупает закрытие, свапы и переоткрытие — позиции
+// закрываются, настройки пула копируются на новый адрес, переезд пишется
+// в `pool_migrations`, а воркер пула открывает ту же стратегию уже там.
+//
+// Включается MIGRATE=1; сравниваются только Whirlpool-пулы (discovery).
+
+use std::env;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::database::exit_policy;
+use crate::database::history::{self, PoolMigration};
+use crate::database::pool_settings::{self, PoolSettings};
+use crate::database::tx_costs;
+use crate::dex_services::discovery::{self, DiscoveredPool};
+use crate::dex_services::executor::mode_tag;
+use crate::params::WSOL;
+use crate::pool_registry;
+use crate::strategies::backtest::{DEFAULT_SWAP_COST, DEFAULT_TX_FEE_SOL};
+use crate::telegram_service::tl_engine::ServiceCommand;
+use crate::types::PoolConfig;
+use crate::utils::get_sol_price_usd;
+
+/// как часто проверять, не пора ли переехать
+pub const MIGRATE_EVERY: Duration = Duration::from_secs(30 * 60);
+/// минимальный разрыв APR (п.п.), ниже которого не переезжаем
+const MIGRATE_MIN_GAP_APR: f64 = 10.0;
+/// за сколько дней разница дохода должна окупить переезд
+const MIGRATE_HORIZON_DAYS: f64 = 7.0;
+/// оценка APR по окну короче этого — шум
+const MIGRATE_MIN_WINDOW_SEC: i64 = 3600;
+/// транзакций на позицию при переезде (закрытие + открытие), если истории издержек нет
+const TX_PER_POSITION: f64 = 2.0;
+
+pub fn enabled() -> bool {
+    env::var("MIGRATE").map(|v| v == "1" || v.eq_ignore_ascii_case("true")).unwrap_or(false)
+}
+
+fn env_f64(key: &str, default: f64) -> f64 {
+    env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+/// Решение о переезде.
+#[derive(Debug, Clone)]
+pub struct MigrationPlan {
+    pub from_apr: f64,
+    pub to:       DiscoveredPool,
+    /// лишний доход за горизонт, USD
+    pub gain_usd: f64,
+    /// оценка стоимости переезда, USD
+    pub cost_usd: f64,
+}
+
+impl MigrationPlan {
+    pub fn describe(&self) -> String {
+        format!(
+            "APR {:.1}% → {:.1}% (ts {}), +${:.2} за {} дн. против издержек ${:.2}",
+            self.from_apr, self.to.fee_apr.unwrap_or(0.0), self.to.tick_spacing,
+            self.gain_usd, env_f64("MIGRATE_HORIZON_DAYS", MIGRATE_HORIZON_DAYS), self.cost_usd,
+        )
+    }
+}
+
+/// Стоит ли пулу `pool_cfg` с капиталом `capital_usd` переехать.
+/// `positions` — сколько позиций придётся закрыть и открыть заново.
+pub async fn evaluate(pool_cfg: &PoolConfig, capital_usd: f64, positions: usize) -> Result<Option<MigrationPlan>> {
+    if pool_cfg.program != "whirlpool" {
+        return Ok(None);
+    }
+    let Some(spec) = pool_registry::find_spec(&pool_cfg.name) else { return Ok(None) };
+
+    let ranked = discovery::rank(spec, false).await?;
+    let Some(current) = ranked.iter().find(|p| p.address.to_string() == pool_cfg.pool_address) else {
+        return Ok(None);
+    };
+    let (Some(from_apr), Some(best)) = (current.fee_apr, ranked.first()) else { return Ok(None) };
+    let Some(to_apr) = best.fee_apr else { return Ok(None) };
+    if best.address == current.address
+        || current.window_sec < MIGRATE_MIN_WINDOW_SEC
+        || best.window_sec < MIGRATE_MIN_WINDOW_SEC
+        || to_apr - from_apr < env_f64("MIGRATE_MIN_GAP_APR", MIGRATE_MIN_GAP_APR)
+    {
+        return Ok(None);
+    }
+
+    let horizon  = env_f64("MIGRATE_HORIZON_DAYS", MIGRATE_HORIZON_DAYS);
+    let gain_usd = (to_apr - from_apr) / 100.0 * capital_usd * horizon / 365.0;
+    let cost_usd = estimate_cost(capital_usd, positions).await?;
+    if gain_usd <= cost_usd {
+        return Ok(None);
+    }
+    Ok(Some(MigrationPlan { from_apr, to: best.clone(), gain_usd, cost_usd }))
+}
+
+/// Издержки переезда: транзакции закрытия и открытия (средние из `tx_costs`,
+/// иначе — оценка по комиссии сети) и ребаланс свапом половины капитала.
+async fn estimate_cost(capital_usd: f64, positions: usize) -> Result<f64> {
+    let n = positions.max(1) as f64;
+    let close = tx_costs::average_usd("close", 20).await?;
+    let open  = tx_costs::average_usd("open", 20).await?;
+    let tx_usd = match (close, open) {
+        (Some(c), Some(o)) => (c + o) * n,
+        _ => {
+            let sol = get_sol_price_usd(WSOL, true).await?;
+            DEFAULT_TX_FEE_SOL * TX_PER_POSITION * n * sol
+        }
+    };
+    Ok(tx_usd + capital_usd * 0.5 * DEFAULT_SWAP_COST)
+}
+
+/// Перевести пул на новый адрес после того, как его позиции закрыты:
+/// копируем настройки и политику выхода, пишем переезд и меняем адрес в реестре.
+pub async fn commit(
+    pool_cfg: &PoolConfig,
+    plan: &MigrationPlan,
+    tx_tg: &UnboundedSender<ServiceCommand>,
+) -> Result<()> {
+    let Some(spec) = pool_registry::find_spec(&pool_cfg.name) else {
+        bail!("{}: пула нет в реестре", pool_cfg.name);
+    };
+    let to = plan.to.address.to_string();
+
+    if let Some(s) = pool_settings::get_pool_settings(&pool_cfg.pool_address).await? {
+        pool_settings::save_pool_settings(&PoolSettings { pool_address: to.clone(), ..s }).await?;
+    }
+    if let Some(p) = exit_policy::get_exit_policy(&pool_cfg.pool_address).await? {
+        exit_policy::set_exit_policy(&to, &p).await?;
+    }
+    history::record_pool_migration(&PoolMigration {
+        pool_name:    pool_cfg.name.clone(),
+        from_address: pool_cfg.pool_address.clone(),
+        to_address:   to.clone(),
+        from_apr:     plan.from_apr,
+        to_apr:       plan.to.fee_apr.unwrap_or(0.0),
+        est_gain_usd: plan.gain_usd,
+        est_cost_usd: plan.cost_usd,
+    }).await?;
+    pool_registry::set_override(spec.pool_env, to.clone());
+
+    let _ = tx_tg.send(ServiceCommand::SendMessage(format!(
+        "{}🚚 {} переехал в {}\n{}", mode_tag(), pool_cfg.name, to, plan.describe()
+    )));
+    Ok(())
+}
@@ -1,14 +1,11 @@
 
-use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::{read_keypair_file, Keypair}, signer::Signer};
 use spl_associated_token_account::get_associated_token_address;
 use std::{str::FromStr, time::Duration};
 use anyhow::Result;
-use orca_whirlpools::PositionOrBundle;
-use crate::{database::triggers::Trigger, exchange::helpers, types::PoolConfig, utils::{calc_bound_prices_struct_for_two, calc_range_allocation_struct_for_two}};
+use crate::{database::triggers::Trigger, exchange::helpers, types::PoolConfig};
 use std::time::Instant;
 use std::sync::atomic::AtomicBool;
-use orca_whirlpools_core::U128;
 use crate::database::positions;
 use anyhow::bail;
 use crate::types::PoolPositionInfo;
@@ -20,42 +17,47 @@ use tokio::time::sleep;
 use std::sync::atomic::Ordering;
 use crate::database::positions::record_position_metrics;
 
-use crate::params::RANGE;
-use crate::types::Range;
-use crate::types::{LiqPosition, Role};
-use crate::utils::{calc_bound_prices_struct, calc_range_allocation_struct};
-use crate::dex_services::wirlpool::{open_with_funds_check_universal, close_all_positions, list_positions_for_owner};
-use crate::dex_services::get_info::fetch_pool_position_info;
+use crate::types::{LiqPosition, Role, RangeAlloc};
+use crate::strategies::ranges::{set_slot, strategy_for_pool, AllocContext, PostClose, RecenterPlan, Strategy};
+use crate::dex_services::executor::{self, executor, mode_tag, OwnedPosition};
+use crate::dex_services::multi_open::LegRequest;
+use crate::dex_services::venue::venue_for;
 use crate::telegram_service::tl_engine::ServiceCommand;
 use tokio::sync::mpsc::UnboundedSender;
-use spl_token::state::Mint;
 use tokio::sync::Notify;
 use crate::database::triggers;
 
-use crate::params::{WALLET_MUTEX, USDC, WSOL};
-use crate::utils::utils;
-use crate::utils::{safe_get_account, swap_excess_to_usdc};
+use crate::params::{WALLET_MUTEX, USDC, USDT, WSOL};
+use crate::utils::swap_excess_to_usdc;
 use std::sync::Arc;
-use spl_token::solana_program::program_pack::Pack;
 use crate::exchange::helpers::get_atr;
 use crate::utils::get_sol_price_usd;
-use crate::dex_services::wirlpool::close_whirlpool_position;
-
-
-fn get_pyth_feed_id(symbol: &str) -> &'static str {
+use crate::pool_registry;
+use crate::reconcile;
+use crate::harvest;
+use crate::migration;
+use crate::types::{HarvestMode, HedgeCommand};
+use crate::exchange::hl_engine;
+use chrono::Utc;
+use orca_whirlpools_core::tick_index_to_price;
+use crate::database::open_journal::{self, JournalLeg, JournalStatus, OpenJournal};
+use crate::database::exit_policy;
+use crate::strategies::exit::{ExitEvent, ExitSide, ExitTracker};
+
+
+fn get_pyth_feed_id(symbol: &str) -> Option<&'static str> {
     match symbol {
-        "SOL/USDC"  => "0xef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d",
-        "SOL/WETH" => "0xde87506dabfadbef89af2d5d796ebae80ddaea240fc7667aa808fce3629cd8fb",
-        _ => panic!("unsupported symbol: {}", symbol),
+        "SOL/WETH" => Some("0xde87506dabfadbef89af2d5d796ebae80ddaea240fc7667aa808fce3629cd8fb"),
+        _ => pool_registry::pyth_feed_id(symbol),
     }
 }
 //-------------------------------- helper -----------------------------------
 async fn close_and_report(
-    rpc: &RpcClient,
     pool_cfg: &PoolConfig,
     whirl_pk: Pubkey,
     tx_tg: &UnboundedSender<ServiceCommand>,
     lower: bool,
+    post: PostClose,
 ) -> Result<()> {
     const MAX_CLOSE_ATTEMPTS: u8 = 4;
     let mut attempt  = 1u8;
@@ -63,9 +65,9 @@ async fn close_and_report(
     // 1) закрываем все позиции ЭТОГО пула
     // ───────── блок закрытия позиций с ретраями ─────────
     loop {
-        match close_all_positions(slippage, Some(whirl_pk)).await {
+        match executor().close_all(slippage, Some(whirl_pk)).await {
             Ok(_) => {
-                let rem = list_positions_for_owner(Some(whirl_pk))
+                let rem = owned_in_pool(whirl_pk)
                     .await
                     .unwrap_or_default();
                 if rem.is_empty() {
@@ -89,40 +91,36 @@ async fn close_and_report(
         sleep(Duration::from_secs(2)).await;
     }
 
-    _ = triggers::pool_report_run(false, &tx_tg).await?;
-    _ = triggers::auto_trade_switch(true, None).await?;
-    _ = triggers::report_info_reset(true, None).await?;
+    _ = triggers::pool_report_run(&pool_cfg.name, false, &tx_tg).await?;
+    _ = triggers::auto_trade_switch(&pool_cfg.name, post.wait_entry, None).await?;
+    _ = triggers::report_info_reset(&pool_cfg.name, true, None).await?;
     tokio::time::sleep(std::time::Duration::from_millis(3000)).await;
-    _ = swap_excess_to_usdc(WSOL, 9, 0.05).await?;
+    if post.swap_to_usdc {
+        _ = swap_excess_to_usdc(WSOL, 9, 0.05).await?;
+    }
+    // закрытие забрало и награды пула — их токены в USDC
+    match harvest::swap_rewards(pool_cfg).await {
+        Ok(usd) if usd > 0.0 => log::info!("{}: награды свапнуты в USDC ≈ ${usd:.2}", pool_cfg.name),
+        Ok(_) => {}
+        Err(e) => log::warn!("{}: свап наград не удался: {e}", pool_cfg.name),
+    }
     if lower {
         let _ = tx_tg.send(ServiceCommand::SendSignal("Signal! Lower breakthrough".to_string()));
     }
+    // дельта LP ушла — хедж подравнивается
+    hl_engine::send(HedgeCommand::Sync);
 
     // 2) Балансы после закрытия
     let _lock   = WALLET_MUTEX.lock().await;              // единый замок
-    let wallet  = utils::load_wallet()?;
-    let wallet_pk = wallet.pubkey();
-
-    let lamports = rpc.get_balance(&wallet_pk).await?;
-    let bal_sol  = lamports as f64 / 1e9;
-
-    let mint_b  = Pubkey::from_str(&pool_cfg.mint_b)?;
-    let ata_b   = get_associated_token_address(&wallet_pk, &mint_b);
-    let bal_b   = rpc.get_token_account_balance(&ata_b)
-        .await?.amount.parse::<f64>()?
-        / 10f64.powi(pool_cfg.decimal_b as i32);
+    let bal_sol = executor().balance(WSOL, 9).await?;
+    let bal_b   = executor().balance(&pool_cfg.mint_b, pool_cfg.decimal_b as u8).await?;
 
     // 3) Конвертируем SOL в эквивалент токена B или USDC
-    let whirl_acct = safe_get_account(&rpc, &whirl_pk).await?;
-    let whirl = orca_whirlpools_client::Whirlpool::from_bytes(&whirl_acct.data)?;
-    let price_ab = orca_whirlpools_core::sqrt_price_to_price(
-        whirl.sqrt_price.into(),
-        pool_cfg.decimal_a as u8,
-        pool_cfg.decimal_b as u8,
-    );                       // price(B per A): сколько B за 1 SOL
-
-    let total_usd = if pool_cfg.mint_b == USDC {
-        bal_b + bal_sol * price_ab            // price_ab == USDC per SOL
+    let price_ab = venue_for(&pool_cfg.program).pool_price(pool_cfg).await?;
+                             // price(B per A): сколько B за 1 SOL
+
+    let total_usd = if pool_cfg.mint_b == USDC || pool_cfg.mint_b == USDT {
+        bal_b + bal_sol * price_ab            // price_ab == USD per SOL
     } else {
         // для RAY/SOL считаем «через SOL»: всё в экв. доллары
         // берём цену SOL-USD из биржи Jupiter (один HTTP-запрос)
@@ -132,8 +130,8 @@ async fn close_and_report(
 
     // 4) отчёт
     let _ = tx_tg.send(ServiceCommand::SendMessage(format!(
-        "🏦 {} закрыт.\n► SOL {:.6}\n► token B {:.6}\n► Всего ≈ ${:.2}",
-        pool_cfg.name, bal_sol, bal_b, total_usd
+        "{}🏦 {} закрыт.\n► SOL {:.6}\n► token B {:.6}\n► Всего ≈ ${:.2}",
+        mode_tag(), pool_cfg.name, bal_sol, bal_b, total_usd
     )));
     Ok(())
 }
@@ -141,15 +139,17 @@ async fn close_and_report(
 
 pub async fn orchestrator_pool(
     mut pool_cfg: PoolConfig,          // шаблон пула
+    strategy: &dyn Strategy,           // как раскладываем ликвидность
     capital_usd: f64,                  // общий размер “кошелька” под пул
     pct_list: [f64; 4],                // как раньше (игнорируется для 1-диапаз.)
-    weights: Vec<f64>, 
     tx_tg: UnboundedSender<ServiceCommand>,
     need_new: Arc<AtomicBool>,
     close_ntf:    Arc<Notify>,
     min_restart: u64,
     range: Option<f32>,
-    compress: bool
+    compress: bool,
+    recenter: bool,                    // перевыставлять только дальнюю ногу
+    harvest: HarvestMode,              // сбор комиссий по порогу
 ) -> Result<()> {
     
     let need_open_new = need_new.load(Ordering::SeqCst);
@@ -157,291 +157,43 @@ pub async fn orchestrator_pool(
     if need_open_new {
         close_existing_owner_positions(&pool_cfg).await?;
     }
-    // ───── 0. RPC / Whirlpool meta ───────────────────────────────────────
-    let rpc       = utils::init_rpc();
+    // ───── 0. RPC / мета пула ────────────────────────────────────────────
     let whirl_pk  = Pubkey::from_str(&pool_cfg.pool_address)?;
-    let whirl_acct = safe_get_account(&rpc, &whirl_pk).await?;
-    let whirl = orca_whirlpools_client::Whirlpool::from_bytes(&whirl_acct.data)?;
-    let dec_a     = Mint::unpack(&rpc.get_account(&whirl.token_mint_a).await?.data)?.decimals;
-    let dec_b     = Mint::unpack(&rpc.get_account(&whirl.token_mint_b).await?.data)?.decimals;
+    let venue     = venue_for(&pool_cfg.program);
+    let dec_a     = pool_cfg.decimal_a as u8;
+    let dec_b     = pool_cfg.decimal_b as u8;
 
 
     // ───── 1. Текущая цена ───────────────────────────────────────────────
-    let mut start_time = Instant::now();
-    let price_raw = orca_whirlpools_core::sqrt_price_to_price(whirl.sqrt_price.into(), dec_a, dec_b);
-    let invert    = RANGE != Range::Three && RANGE != Range::Two;          // false для SOL/USDC, true для RAY/SOL
-    let price_disp = if invert { 1.0 / price_raw } else { price_raw };
-    let mut price = norm_price(price_raw, invert);
+    let price_raw = venue.pool_price(&pool_cfg).await?;
+    let invert    = strategy.invert();          // false для SOL/USDC, true для RAY/SOL
+    let price     = norm_price(price_raw, invert);
 
     // ───── 2. Формируем диапазоны / аллокации  ───────────────────────────
-    let mut upper_exit = 0.0;
-    let mut lower_exit = 0.0;
-    if RANGE == Range::Two && need_open_new {
-
-        let bounds  = calc_bound_prices_struct_for_two(price, &pct_list);
-        let allocs  = calc_range_allocation_struct_for_two(price, &bounds, &weights, capital_usd);  // ✱ ИЗМЕНЕНО: без sort
-        println!("Allocs: {:?}", allocs);
-        
-        let _ = tx_tg.send(ServiceCommand::SendMessage(
-            format!("🔔 Пытаюсь открыть 2 позиции SOL/USDC ({} USDC)…", capital_usd)
-        ));
-        let mut minted: Vec<Role> = Vec::new();   // ✱ ИЗМЕНЕНО: трекаем по роли
-        let mut slippage = 150u16;
-
-        'outer: for round in 1..=2 {              // максимум 3 раунда
-            let mut progress = false;
-
-            // проходим в фиксированном порядке Up→Middle→Down
-            for alloc in &allocs {
-                if minted.contains(&alloc.role) { continue }     // уже открыта
-
-                // сколько USDC вносить
-                let deposit = match alloc.role {
-                    Role::Middle | Role::MiddleSmall => alloc.usdc_amount,
-                    Role::Up | Role::Down => alloc.usdc_equivalent,
-                };
-
-                match open_with_funds_check_universal(
-                    alloc.lower_price,
-                    alloc.upper_price,
-                    deposit,
-                    pool_cfg.clone(),
-                    slippage,
-                    alloc.range_idx,              // ← просто для отладочных логов
-                ).await {
-                    Ok(res) => {
-                        minted.push(alloc.role.clone());  // ✱ ИЗМЕНЕНО
-                        progress = true;
-
-                        let liq = LiqPosition {
-                            role: alloc.role.clone(),
-                            position_address: None,
-                            position_nft:     None,
-                            upper_price: alloc.upper_price,
-                            lower_price: alloc.lower_price,
-                        };
-                        match alloc.role {
-                            Role::MiddleSmall => pool_cfg.position_1 = Some(liq),
-                            Role::Up    => pool_cfg.position_1 = Some(liq),
-                            Role::Middle=> pool_cfg.position_2 = Some(liq),
-                            Role::Down  => pool_cfg.position_3 = Some(liq),
-                        }
-
-                        let _ = tx_tg.send(ServiceCommand::SendMessage(
-                            format!("✅ Открыта {:?} (mint {})", alloc.role, res.position_mint), // ✱ ИЗМЕНЕНО
-                        ));
-                    }
-                    Err(e) => {
-                        let _ = tx_tg.send(ServiceCommand::SendMessage(
-                            format!("⚠️ {:?} не открылась: {e}", alloc.role),                 // ✱ ИЗМЕНЕНО
-                        ));
-                    }
-                }
-
-                tokio::time::sleep(std::time::Duration::from_millis(800)).await;
-            }
-
-            if minted.len() == 2 { break 'outer; }      // всё открыто
-
-            if !progress { slippage += 100; }
-
-            let _ = tx_tg.send(ServiceCommand::SendMessage(
-                format!("🔄 Раунд {round} окончен, открыто {}/2. Slippage = {} bps", minted.len(), slippage)
-            ));
-        }
-
-        // окончательная проверка
-        if minted.len() != 2 {
-            let _ = tx_tg.send(ServiceCommand::SendMessage(
-                format!("❌ За 3 раунда открыто только {}/2. Закрываю то, что было.", minted.len())
-            ));
-            for r in minted {
-                if let Some(pm) = match r {
-                    Role::MiddleSmall    => &pool_cfg.position_1,
-                    Role::Up    => &pool_cfg.position_1,
-                    Role::Middle=> &pool_cfg.position_2,
-                    Role::Down  => &pool_cfg.position_3,
-                }.as_ref().map(|p| &p.position_nft) {
-                    let _ = close_whirlpool_position(Pubkey::from_str(pm.as_ref().unwrap())?, 150u16).await;
-                }
-            }
-            bail!("Не удалось открыть все два диапазона");
-        }
-
-        upper_exit = pool_cfg.position_2.as_ref().unwrap().upper_price;
-        lower_exit = pool_cfg.position_2.as_ref().unwrap().lower_price;
-
-    }
-    else if RANGE == Range::Three && need_open_new {
-        let bounds  = calc_bound_prices_struct(price, &pct_list, compress.clone());
-        let allocs  = calc_range_allocation_struct(price, &bounds, &weights, capital_usd, compress.clone());  // ✱ ИЗМЕНЕНО: без sort
+    if need_open_new {
+        let sol_usd = get_sol_price_usd(WSOL, true).await?;
+        let ctx = AllocContext { price, pct_list, capital_usd, compress, range, sol_usd };
+        let allocs = strategy.allocations(&ctx)?;
         println!("Allocs: {:?}", allocs);
-        let _ = tx_tg.send(ServiceCommand::SendMessage(
-            format!("🔔 Пытаюсь открыть 3 позиции SOL/USDC ({} USDC)…", capital_usd)
-        ));
-
-        let mut minted: Vec<Role> = Vec::new();   // ✱ ИЗМЕНЕНО: трекаем по роли
-        let mut slippage = 150u16;
-
-        'outer: for round in 1..=2 {              // максимум 3 раунда
-            let mut progress = false;
-
-            // проходим в фиксированном порядке Up→Middle→Down
-            for alloc in &allocs {
-                if minted.contains(&alloc.role) { continue }     // уже открыта
-
-                // сколько USDC вносить
-                let deposit: f64 = match alloc.role {
-                    Role::Middle | Role::MiddleSmall => alloc.usdc_amount,
-                    Role::Up | Role::Down => alloc.usdc_equivalent,
-                };
-
-                match open_with_funds_check_universal(
-                    alloc.lower_price,
-                    alloc.upper_price,
-                    deposit,
-                    pool_cfg.clone(),
-                    slippage,
-                    alloc.range_idx,              // ← просто для отладочных логов
-                ).await {
-                    Ok(res) => {
-                        minted.push(alloc.role.clone());  // ✱ ИЗМЕНЕНО
-                        progress = true;
-
-                        let liq = LiqPosition {
-                            role: alloc.role.clone(),
-                            position_address: None,
-                            position_nft:     None,
-                            upper_price: alloc.upper_price,
-                            lower_price: alloc.lower_price,
-                        };
-                        match alloc.role {
-                            Role::MiddleSmall => pool_cfg.position_1 = Some(liq),
-                            Role::Up    => pool_cfg.position_1 = Some(liq),
-                            Role::Middle=> pool_cfg.position_2 = Some(liq),
-                            Role::Down  => pool_cfg.position_3 = Some(liq),
-                        }
-
-                        let _ = tx_tg.send(ServiceCommand::SendMessage(
-                            format!("✅ Открыта {:?} (mint {})", alloc.role, res.position_mint), // ✱ ИЗМЕНЕНО
-                        ));
-                    }
-                    Err(e) => {
-                        let _ = tx_tg.send(ServiceCommand::SendMessage(
-                            format!("⚠️ {:?} не открылась: {e}", alloc.role),                 // ✱ ИЗМЕНЕНО
-                        ));
-                    }
-                }
-
-                tokio::time::sleep(std::time::Duration::from_millis(800)).await;
-            }
-
-            if minted.len() == 3 { break 'outer; }      // всё открыто
-
-            if !progress { slippage += 100; }
-
-            let _ = tx_tg.send(ServiceCommand::SendMessage(
-                format!("🔄 Раунд {round} окончен, открыто {}/3. Slippage = {} bps", minted.len(), slippage)
-            ));
-        }
-
-        // окончательная проверка
-        if minted.len() != 3 {
-            let _ = tx_tg.send(ServiceCommand::SendMessage(
-                format!("❌ За 3 раунда открыто только {}/3. Закрываю то, что было.", minted.len())
-            ));
-            for r in minted {
-                if let Some(pm) = match r {
-                    Role::MiddleSmall    => &pool_cfg.position_1,
-                    Role::Up    => &pool_cfg.position_1,
-                    Role::Middle=> &pool_cfg.position_2,
-                    Role::Down  => &pool_cfg.position_3,
-                }.as_ref().map(|p| &p.position_nft) {
-                    let _ = close_whirlpool_position(Pubkey::from_str(pm.as_ref().unwrap())?, 150u16).await;
-                }
-            }
-            bail!("Не удалось открыть все три диапазона");
-        }
-
-        upper_exit = if pool_cfg.position_1.as_ref().unwrap().upper_price > pool_cfg.position_2.as_ref().unwrap().upper_price { pool_cfg.position_1.as_ref().unwrap().upper_price} else { pool_cfg.position_2.as_ref().unwrap().upper_price };
-        lower_exit = if pool_cfg.position_3.as_ref().unwrap().lower_price < pool_cfg.position_2.as_ref().unwrap().lower_price {pool_cfg.position_3.as_ref().unwrap().lower_price} else {pool_cfg.position_2.as_ref().unwrap().lower_price};
-
-    } else if need_open_new {
-        // ── 1. Текущие границы в “SOL за токен-B” (display) ─────────────────
 
-        let (low_perc, high_perc) = match calculate_range(range) {
-            Some((low, high)) => (low, high),
-            None => {
-                println!("Не удалось вычислить диапазон");
-                return Ok(()); // или return Err(...), если в async fn
-            }
-        };
-        let low_disp  = low_perc * price_disp;
-        let high_disp = high_perc * price_disp;
-        println!("range: {} - {}", low_disp, high_disp);
-
-
-        // ── 2. Цена 1 токена-B в SOL, а затем в USD ─────────────────────────
-        let price_b_in_sol = price_disp;                 // invert всегда true здесь
-        let sol_usd        = get_sol_price_usd(WSOL, true).await?;
-        let tok_b_usd      = price_b_in_sol * sol_usd;   // ETH ≈ 2 400 $, RAY ≈ 2 $
-
-        // ── 3. Сколько B нужно, чтобы занять половину капитала ──────────────
-        let amount_tok_b = (capital_usd / 2.0) / tok_b_usd;
-
-        // — Telegram + DEBUG —
-        let telegram_msg = format!(
-            "🔔 Открываю центр {} [{:.6}; {:.6}], вес ${:.2} amount Tok_b: {:.6}",
-            pool_cfg.name, low_disp, high_disp, capital_usd, amount_tok_b
-        );
-        println!(
-            "DBG: B_in_SOL={:.6}, tok_b_usd={:.2}, amount_tok_b={:.6}",
-            price_b_in_sol, tok_b_usd, amount_tok_b
-        );
-        let _ = tx_tg.send(ServiceCommand::SendMessage(telegram_msg));
-
-        // ── 4. Границы, которые ждёт SDK (B за SOL) ─────────────────────────
-        let low_raw  = 1.0 / high_disp;
-        let high_raw = 1.0 / low_disp;
-
-        // ── 5. Открываем позицию ────────────────────────────────────────────
-        open_with_funds_check_universal(
-            low_raw,
-            high_raw,
-            amount_tok_b,
-            pool_cfg.clone(),
-            150,
-            0
-        ).await?;
-
-
-        pool_cfg.position_2 = Some(LiqPosition {
-            role:          Role::Middle,
-            position_address: None,
-            position_nft:     None,
-            upper_price: high_disp,     // храним и контролируем в display-виде
-            lower_price: low_disp,
-        });
-        upper_exit = high_disp;
-        lower_exit = low_disp;
+        open_allocations(&mut pool_cfg, &allocs, strategy, capital_usd, &tx_tg).await?;
     } else {
         //------------------------------------------------------------------
         //  ✦  Блок, когда позиции уже существуют (need_open_new == false) ✦
         //------------------------------------------------------------------
 
         // 1) получаем все позиции текущего owner-а в данном пуле
-        let list = list_positions_for_owner(Some(whirl_pk)).await?;
+        //    (кроме помеченных при сверке — их бот не трогает)
+        let list = owned_in_pool(whirl_pk).await?;
         if list.is_empty() {
             bail!("need_open_new == false, но в пуле {} нет ни одной позиции", pool_cfg.name);
         }
 
         // 2) собираем информацию и сортируем её по lower_price
         let mut infos = Vec::<crate::types::PoolPositionInfo>::new();
-        for p in list {
-            if let PositionOrBundle::Position(hp) = p {
-                if let Ok(i) = fetch_pool_position_info(&pool_cfg, Some(&hp.address.to_string())).await {
-                    infos.push(i);
-                }
+        for p in &list {
+            if let Ok(i) = executor().position_info(&pool_cfg, p).await {
+                infos.push(i);
             }
         }
         infos.sort_by(|a, b| {
@@ -449,53 +201,11 @@ pub async fn orchestrator_pool(
             b.lower_price.partial_cmp(&a.lower_price).unwrap()
         });
 
-        // 3) заполняем pool_cfg и границы выхода
-        if RANGE == Range::Three {
-            // ожидаем ровно 3 диапазона
-            if infos.len() != 3 {
-                bail!("В пуле {} найдено {} позиций, а должно быть 3", pool_cfg.name, infos.len());
-            }
-
-            pool_cfg.position_1 = Some(LiqPosition {
-                role: Role::Up,
-                position_address: None,
-                position_nft:     None,
-                upper_price: infos[0].upper_price,
-                lower_price: infos[0].lower_price,
-            });
-            pool_cfg.position_2 = Some(LiqPosition {
-                role: Role::Middle,
-                position_address: None,
-                position_nft:     None,
-                upper_price: infos[1].upper_price,
-                lower_price: infos[1].lower_price,
-            });
-            pool_cfg.position_3 = Some(LiqPosition {
-                role: Role::Down,
-                position_address: None,
-                position_nft:     None,
-                upper_price: infos[2].upper_price,
-                lower_price: infos[2].lower_price,
-            });
-
-            // «верхний вылет» контролируем по верхней границе первой (Up) позиции,
-            // «нижний вылет» — по нижней границе третьей (Down), как и раньше
-            upper_exit = if infos[0].upper_price > infos[1].upper_price { infos[0].upper_price } else { infos[1].upper_price };
-            lower_exit = if infos[2].lower_price < infos[1].lower_price { infos[0].lower_price } else { infos[1].lower_price };
-
-
-        } else {
-            // одиночный диапазон (RAY/SOL, WETH/SOL) – берём первую позицию
-            let i = &infos[0];
-            pool_cfg.position_2 = Some(LiqPosition {
-                role: Role::Middle,
-                position_address: None,
-                position_nft:     None,
-                upper_price: i.upper_price,
-                lower_price: i.lower_price,
-            });
-            upper_exit = i.upper_price;
-            lower_exit = i.lower_price;
+        // 3) заполняем pool_cfg по ролям стратегии; набор не подходит —
+        //    в следующий перезапуск открываемся заново, а не крутимся в рестартах
+        if let Err(e) = strategy.adopt_existing(&infos, &mut pool_cfg) {
+            need_new.store(true, Ordering::SeqCst);
+            return Err(e.context("позиции не подходят стратегии, будут открыты заново"));
         }
 
         // 4) информируем пользователя
@@ -504,30 +214,56 @@ pub async fn orchestrator_pool(
             pool_cfg.name, infos.len()
         )));
     }
-    let list = list_positions_for_owner(Some(whirl_pk)).await?;
+
+    // границы выхода задаёт стратегия
+    let (mut upper_exit, mut lower_exit) = strategy
+        .exit_bounds(&pool_cfg)
+        .context("нет позиций для расчёта границ выхода")?;
+
+    let list = owned_in_pool(whirl_pk).await?;
 
     println!("After positions opening");
     if list.len() > 0 {
         need_new.store(true, Ordering::SeqCst);
-    _ = triggers::pool_report_run(true, &tx_tg).await?;
+    _ = triggers::pool_report_run(&pool_cfg.name, true, &tx_tg).await?;
+        hl_engine::send(HedgeCommand::Sync);
     }
-    _ = triggers::opening_switcher(false, Some(&tx_tg)).await?;
+    _ = triggers::opening_switcher(&pool_cfg.name, false, Some(&tx_tg)).await?;
     // ───── 3. Мониторинг ────────────────────────────────────────────────
 
-    // ➊ Price-feed Pyth для базового токена пула
-    //    (подберите ID один раз и храните в cfg, здесь для примера SOL/USD)
-    let feed_id = get_pyth_feed_id(&pool_cfg.name).to_string();
-
-    // канал, в который pyth_ws::subscribe будет писать Option<f64>
-    let mut pyth_rx = subscribe(feed_id)
-        .await
-        .context("pyth subscribe")?;
+    // ➊ Price-feed Pyth для базового токена пула (ID берём из реестра пулов).
+    //    Если фида нет (LST-пулы) — канал-заглушка, работаем только по HTTP.
+    let (_pyth_stub_tx, stub_rx) = tokio::sync::watch::channel::<Option<f64>>(None);
+    let mut pyth_rx = match get_pyth_feed_id(&pool_cfg.name) {
+        // канал, в который pyth_ws::subscribe будет писать Option<f64>
+        Some(feed_id) => subscribe(feed_id.to_string())
+            .await
+            .context("pyth subscribe")?,
+        None => stub_rx,
+    };
 
     // fallback-таймер HTTP (Whirlpool RPC) – раз в 15 с
     let mut http_itv = tokio::time::interval(Duration::from_secs(15));
 
-    let mut out_of_range_since: Option<Instant> = None;
-    let mut wait_before_redeploy = Duration::from_secs(min_restart * 60);
+    // правила выхода: из настроек пула, иначе — политика стратегии;
+    // перечитываем на каждом HTTP-тике, чтобы `exit ...` действовал без перезапуска
+    let policy = exit_policy::get_exit_policy(&pool_cfg.pool_address).await?
+        .unwrap_or_else(|| strategy.exit_policy());
+    let mut tracker = ExitTracker::new(policy, min_restart);
+    let mut recenter_since: Option<Instant> = None;
+
+    // проверка накопленных комиссий (первая — через HARVEST_EVERY после старта)
+    let mut harvest_itv = tokio::time::interval_at(
+        tokio::time::Instant::now() + harvest::HARVEST_EVERY,
+        harvest::HARVEST_EVERY,
+    );
+    let mut last_price = price;
+
+    // переезд в более доходный fee tier (MIGRATE=1)
+    let mut migrate_itv = tokio::time::interval_at(
+        tokio::time::Instant::now() + migration::MIGRATE_EVERY,
+        migration::MIGRATE_EVERY,
+    );
 
     loop {
         tokio::select! {
@@ -542,19 +278,27 @@ pub async fn orchestrator_pool(
             
                 if let Some(p) = price_opt {
                     let price_display = if invert { 1.0 / p } else { p };
+                    last_price = price_display;
             
                     // ➋ теперь guard уже drop-нут, можно safely await
+                    if recenter {
+                        if let Some((u, l)) = maybe_recenter(
+                            strategy, price_display, min_restart, &mut pool_cfg, &tx_tg, whirl_pk, &mut recenter_since,
+                        ).await? {
+                            (upper_exit, lower_exit) = (u, l);
+                            tracker.reset();
+                            continue;
+                        }
+                    }
                     if check_bounds_and_maybe_close(
+                        strategy,
                         price_display,
                         upper_exit,
                         lower_exit,
-                        min_restart,
                         &pool_cfg,
                         &tx_tg,
-                        &rpc,
                         whirl_pk,
-                        &mut out_of_range_since,
-                        &mut wait_before_redeploy,
+                        &mut tracker,
                     ).await? {
                         break;            // позиции закрыты — выходим
                     }
@@ -564,41 +308,83 @@ pub async fn orchestrator_pool(
 
             // ➌ fallback — старая логика через getAccount раз в 15 с
             _ = http_itv.tick() => {
-                let acct = match safe_get_account(&rpc, &whirl_pk).await {
-                    Ok(a) => a,
+                match exit_policy::get_exit_policy(&pool_cfg.pool_address).await {
+                    Ok(p) => {
+                        let p = p.unwrap_or_else(|| strategy.exit_policy());
+                        let text = format!("up: {} / down: {}", p.up.describe(), p.down.describe());
+                        if tracker.set_policy(p) {
+                            let _ = tx_tg.send(ServiceCommand::SendMessage(
+                                format!("🔧 {}: новые правила выхода — {text}", pool_cfg.name)));
+                        }
+                    }
+                    Err(e) => log::warn!("{}: не удалось перечитать правила выхода: {e}", pool_cfg.name),
+                }
+
+                let curr_raw = match venue.pool_price(&pool_cfg).await {
+                    Ok(p) => p,
                     Err(e) => {
                         let _ = tx_tg.send(ServiceCommand::SendMessage(
                             format!("🌐 {}: RPC error ({e}), ждём следующий тик", pool_cfg.name)));
                         continue;
                     }
                 };
-
-                let curr_raw = orca_whirlpools_core::sqrt_price_to_price(
-                    orca_whirlpools_client::Whirlpool::from_bytes(&acct.data)?
-                        .sqrt_price
-                        .into(),
-                    dec_a,
-                    dec_b,
-                );
                 let price_display = norm_price(curr_raw, invert);
+                last_price = price_display;
 
+                if recenter {
+                    if let Some((u, l)) = maybe_recenter(
+                        strategy, price_display, min_restart, &mut pool_cfg, &tx_tg, whirl_pk, &mut recenter_since,
+                    ).await? {
+                        (upper_exit, lower_exit) = (u, l);
+                        tracker.reset();
+                        continue;
+                    }
+                }
                 if check_bounds_and_maybe_close(
+                    strategy,
                     price_display,
                     upper_exit,
                     lower_exit,
-                    min_restart,
                     &pool_cfg,
                     &tx_tg,
-                    &rpc,
                     whirl_pk,
-                    &mut out_of_range_since,
-                    &mut wait_before_redeploy
+                    &mut tracker,
                 ).await? {
                     break;
                 }
             }
 
-            // ➍ внешняя команда «закрыть всё»
+            // ➍ сбор комиссий по порогу
+            _ = harvest_itv.tick() => {
+                if let Err(e) = harvest::maybe_harvest(&pool_cfg, &harvest, last_price, &tx_tg).await {
+                    log::warn!("harvest {}: {e:#}", pool_cfg.name);
+                }
+            }
+
+            // ➎ переезд: закрываем здесь, воркер откроет ту же стратегию в новом пуле
+            _ = migrate_itv.tick(), if migration::enabled() => {
+                let n = owned_in_pool(whirl_pk).await.map(|l| l.len()).unwrap_or(0);
+                let plan = match migration::evaluate(&pool_cfg, capital_usd, n).await {
+                    Ok(Some(plan)) => plan,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        log::warn!("migration {}: {e:#}", pool_cfg.name);
+                        continue;
+                    }
+                };
+                let _ = tx_tg.send(ServiceCommand::SendMessage(format!(
+                    "🚚 {}: переезжаем — {}", pool_cfg.name, plan.describe()
+                )));
+                let post = PostClose { wait_entry: false, swap_to_usdc: false };
+                close_and_report(&pool_cfg, whirl_pk, &tx_tg, false, post).await?;
+                if !owned_in_pool(whirl_pk).await?.is_empty() {
+                    bail!("{}: переезд отменён — не все позиции закрылись", pool_cfg.name);
+                }
+                migration::commit(&pool_cfg, &plan, &tx_tg).await?;
+                break;
+            }
+
+            // ➏ внешняя команда «закрыть всё»
             _ = close_ntf.notified() => {
                 let _ = tx_tg.send(ServiceCommand::SendMessage(
                     format!("🔔 {}: получен сигнал CLOSE ALL — выходим из пула", pool_cfg.name)
@@ -612,6 +398,355 @@ pub async fn orchestrator_pool(
 }
 
 
+/// Сколько USDC вносить в диапазон.
+fn alloc_deposit(alloc: &RangeAlloc) -> f64 {
+    match alloc.role {
+        Role::Middle | Role::MiddleSmall => alloc.usdc_amount,
+        Role::Up | Role::Down => alloc.usdc_equivalent,
+    }
+}
+
+/// Открыть все диапазоны стратегии. Где площадка умеет — разом (одной
+/// транзакцией или бандлом: всё или ничего, по одной цене). Иначе — по
+/// одной: до двух раундов с ростом slippage, а если что-то так и не
+/// открылось — закрываем уже открытое.
+/// Каждый шаг пишется в журнал открытия (см. `recover_unfinished_opens`).
+async fn open_allocations(
+    pool_cfg: &mut PoolConfig,
+    allocs: &[RangeAlloc],
+    strategy: &dyn Strategy,
+    capital_usd: f64,
+    tx_tg: &UnboundedSender<ServiceCommand>,
+) -> Result<()> {
+    let total = allocs.len();
+    let invert = strategy.invert();
+    let _ = tx_tg.send(ServiceCommand::SendMessage(
+        format!("🔔 Пытаюсь открыть {} позиции {} ({} USDC)…", total, pool_cfg.name, capital_usd)
+    ));
+
+    let legs: Vec<JournalLeg> = allocs.iter().map(|a| JournalLeg {
+        role:          a.role.clone(),
+        range_idx:     a.range_idx,
+        lower_price:   a.lower_price,
+        upper_price:   a.upper_price,
+        deposit:       alloc_deposit(a),
+        position_mint: None,
+        signature:     None,
+    }).collect();
+    let journal_id = open_journal::begin(
+        &pool_cfg.pool_address, &pool_cfg.name, &strategy.kind(), capital_usd, &legs,
+    ).await?;
+
+    let mut slippage = 150u16;
+
+    // ───── все ноги разом ─────
+    if total > 1 {
+        let requests: Vec<LegRequest> = allocs.iter().map(|a| LegRequest {
+            lower_price: a.lower_price,
+            upper_price: a.upper_price,
+            deposit:     alloc_deposit(a),
+        }).collect();
+        let mut last_err = None;
+        for _ in 1..=2 {
+            match executor().open_positions_atomic(&requests, pool_cfg, slippage).await {
+                Ok(Some(results)) => {
+                    for (alloc, res) in allocs.iter().zip(&results) {
+                        let sig = res.signature.map(|s| s.to_string());
+                        if let Err(e) = open_journal::record_mint(
+                            journal_id, &alloc.role, &res.position_mint.to_string(), sig.as_deref(),
+                        ).await {
+                            log::warn!("open_journal: mint not recorded: {e}");
+                        }
+                        fill_slot(pool_cfg, alloc, res.position_mint, invert);
+                    }
+                    open_journal::finish(journal_id, JournalStatus::Done).await?;
+                    let _ = tx_tg.send(ServiceCommand::SendMessage(format!(
+                        "✅ {} диапазона {} открыты разом{}",
+                        total, pool_cfg.name,
+                        results.first().and_then(|r| r.signature).map(|s| format!(" ({s})")).unwrap_or_default(),
+                    )));
+                    return Ok(());
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    // открытие атомарно: не прошло — не открылось ничего
+                    let _ = tx_tg.send(ServiceCommand::SendMessage(
+                        format!("⚠️ Атомарное открытие {} не прошло (slippage {slippage} bps): {e}", pool_cfg.name),
+                    ));
+                    // подтверждения не дождались, а транзакция всё же легла —
+                    // повтор открыл бы второй комплект, берём то, что легло
+                    let landed = owned_in_pool(Pubkey::from_str(&pool_cfg.pool_address)?).await
+                        .unwrap_or_default();
+                    if !landed.is_empty() {
+                        return adopt_landed(pool_cfg, allocs, landed, invert, journal_id, tx_tg).await;
+                    }
+                    last_err = Some(e);
+                    slippage += 100;
+                }
+            }
+        }
+        if let Some(e) = last_err {
+            open_journal::finish(journal_id, JournalStatus::RolledBack).await?;
+            bail!("Не удалось открыть диапазоны {} одной транзакцией: {e}", pool_cfg.name);
+        }
+        let _ = tx_tg.send(ServiceCommand::SendMessage(
+            format!("ℹ️ {}: площадка не открывает ноги разом — открываю по одной", pool_cfg.name),
+        ));
+    }
+
+    let mut minted: Vec<(Role, Pubkey)> = Vec::new();   // трекаем по роли
+
+    'outer: for round in 1..=2 {
+        let mut progress = false;
+
+        // проходим в порядке, который задала стратегия
+        for alloc in allocs {
+            if minted.iter().any(|(r, _)| *r == alloc.role) { continue }     // уже открыта
+
+            // сколько USDC вносить
+            let deposit = alloc_deposit(alloc);
+
+            match executor().open_position(
+                alloc.lower_price,
+                alloc.upper_price,
+                deposit,
+                pool_cfg.clone(),
+                slippage,
+                alloc.range_idx,              // ← просто для отладочных логов
+            ).await {
+                Ok(res) => {
+                    let sig = res.signature.map(|s| s.to_string());
+                    if let Err(e) = open_journal::record_mint(
+                        journal_id, &alloc.role, &res.position_mint.to_string(), sig.as_deref(),
+                    ).await {
+                        log::warn!("open_journal: mint not recorded: {e}");
+                    }
+                    minted.push((alloc.role.clone(), res.position_mint));
+                    progress = true;
+
+                    fill_slot(pool_cfg, alloc, res.position_mint, invert);
+
+                    let _ = tx_tg.send(ServiceCommand::SendMessage(
+                        format!("✅ Открыта {:?} (mint {})", alloc.role, res.position_mint),
+                    ));
+                }
+                Err(e) => {
+                    let _ = tx_tg.send(ServiceCommand::SendMessage(
+                        format!("⚠️ {:?} не открылась: {e}", alloc.role),
+                    ));
+                }
+            }
+
+            tokio::time::sleep(std::time::Duration::from_millis(800)).await;
+        }
+
+        if minted.len() == total { break 'outer; }      // всё открыто
+
+        if !progress { slippage += 100; }
+
+        let _ = tx_tg.send(ServiceCommand::SendMessage(
+            format!("🔄 Раунд {round} окончен, открыто {}/{}. Slippage = {} bps", minted.len(), total, slippage)
+        ));
+    }
+
+    // окончательная проверка
+    if minted.len() != total {
+        let _ = tx_tg.send(ServiceCommand::SendMessage(
+            format!("❌ Открыто только {}/{}. Закрываю то, что было.", minted.len(), total)
+        ));
+        for (_, mint) in minted {
+            let _ = executor().close_position(mint, 150u16).await;
+        }
+        open_journal::finish(journal_id, JournalStatus::RolledBack).await?;
+        bail!("Не удалось открыть все диапазоны {}", pool_cfg.name);
+    }
+    open_journal::finish(journal_id, JournalStatus::Done).await?;
+    Ok(())
+}
+
+/// Атомарное открытие легло, хотя отправка вернула ошибку: сопоставляем
+/// позиции пула с диапазонами по границам и заполняем слоты. Не сошлось —
+/// журнал остаётся `Opening`, его разберёт `recover_unfinished_opens`.
+async fn adopt_landed(
+    pool_cfg: &mut PoolConfig,
+    allocs: &[RangeAlloc],
+    mut landed: Vec<OwnedPosition>,
+    invert: bool,
+    journal_id: i64,
+    tx_tg: &UnboundedSender<ServiceCommand>,
+) -> Result<()> {
+    let (dec_a, dec_b) = (pool_cfg.decimal_a as u8, pool_cfg.decimal_b as u8);
+    let mut found: Vec<(&RangeAlloc, Pubkey)> = Vec::with_capacity(allocs.len());
+    for alloc in allocs {
+        if let Some(i) = landed.iter().position(|p| matches_bounds(p, alloc.lower_price, alloc.upper_price, dec_a, dec_b)) {
+            found.push((alloc, landed.remove(i).mint));
+        }
+    }
+    if found.len() != allocs.len() {
+        bail!(
+            "{}: в пуле позиции, не совпадающие с открытием #{journal_id} ({}/{}) — разберётся при перезапуске",
+            pool_cfg.name, found.len(), allocs.len()
+        );
+    }
+    for (alloc, mint) in found {
+        open_journal::record_mint(journal_id, &alloc.role, &mint.to_string(), None).await?;
+        fill_slot(pool_cfg, alloc, mint, invert);
+    }
+    open_journal::finish(journal_id, JournalStatus::Done).await?;
+    let _ = tx_tg.send(ServiceCommand::SendMessage(format!(
+        "✅ {}: транзакция открытия всё же легла — {} диапазона на месте", pool_cfg.name, allocs.len()
+    )));
+    Ok(())
+}
+
+/// Совпадает ли позиция с диапазоном (сырые цены «B per A») с точностью до тиков.
+fn matches_bounds(p: &OwnedPosition, lower_price: f64, upper_price: f64, dec_a: u8, dec_b: u8) -> bool {
+    let lo = tick_index_to_price(p.tick_lower, dec_a, dec_b);
+    let hi = tick_index_to_price(p.tick_upper, dec_a, dec_b);
+    (lo / lower_price - 1.0).abs() < JOURNAL_BOUNDS_TOL
+        && (hi / upper_price - 1.0).abs() < JOURNAL_BOUNDS_TOL
+}
+
+/// Положить открытую позицию в слот: границы в display-виде, mint и адрес позиции.
+fn fill_slot(pool_cfg: &mut PoolConfig, alloc: &RangeAlloc, mint: Pubkey, invert: bool) {
+    let (lower, upper) = if invert {
+        (1.0 / alloc.upper_price, 1.0 / alloc.lower_price)
+    } else {
+        (alloc.lower_price, alloc.upper_price)
+    };
+    set_slot(pool_cfg, LiqPosition {
+        role: alloc.role.clone(),
+        position_address: venue_for(&pool_cfg.program).position_address(&mint).map(|a| a.to_string()),
+        position_nft:     Some(mint.to_string()),
+        upper_price: upper,
+        lower_price: lower,
+    });
+}
+
+/// Журнал старше этого не достраиваем — рынок уже другой, частичное закрываем.
+const RESUME_MAX_AGE_MIN: i64 = 30;
+/// допуск при сопоставлении позиции с диапазоном журнала (выравнивание по тикам)
+const JOURNAL_BOUNDS_TOL: f64 = 0.01;
+
+/// Открытия, прерванные падением процесса: по журналу либо достраиваем
+/// недостающие диапазоны, либо закрываем то, что успело открыться.
+pub async fn recover_unfinished_opens(tx_tg: &UnboundedSender<ServiceCommand>) -> Result<()> {
+    for journal in open_journal::unfinished().await? {
+        if let Err(e) = recover_journal(&journal, tx_tg).await {
+            let _ = tx_tg.send(ServiceCommand::SendMessage(format!(
+                "❌ {}: не удалось восстановить открытие #{}: {e:#}", journal.pool_name, journal.id
+            )));
+        }
+    }
+    Ok(())
+}
+
+async fn recover_journal(j: &OpenJournal, tx_tg: &UnboundedSender<ServiceCommand>) -> Result<()> {
+    // виртуальные позиции живут только в памяти — после рестарта их уже нет
+    if executor::is_paper() {
+        open_journal::finish(j.id, JournalStatus::RolledBack).await?;
+        return Ok(());
+    }
+
+    let pool_cfg = match pool_registry::enabled_pools().into_iter().find(|c| c.pool_address == j.pool_address) {
+        Some(cfg) => cfg,
+        None => pool_registry::find_spec(&j.pool_name)
+            .map(|spec| spec.build(j.pool_address.clone()))
+            .with_context(|| format!("пул {} не найден в реестре", j.pool_name))?,
+    };
+    let (dec_a, dec_b) = (pool_cfg.decimal_a as u8, pool_cfg.decimal_b as u8);
+    let whirl_pk = Pubkey::from_str(&j.pool_address)?;
+    let onchain  = executor().list_positions(Some(whirl_pk)).await?;
+
+    // 1) какие диапазоны реально стоят: по mint-у из журнала, а если mint
+    //    не успел записаться — по границам позиции без записи
+    let mut orphans: Vec<&OwnedPosition> = onchain.iter()
+        .filter(|p| !j.legs.iter().any(|l| l.position_mint.as_deref() == Some(p.mint.to_string().as_str())))
+        .collect();
+    let mut live:    Vec<(JournalLeg, Pubkey)> = Vec::new();
+    let mut missing: Vec<JournalLeg>           = Vec::new();
+    for leg in &j.legs {
+        let found = match &leg.position_mint {
+            Some(m) => onchain.iter().find(|p| p.mint.to_string() == *m).map(|p| p.mint),
+            None => orphans.iter()
+                .position(|p| matches_bounds(p, leg.lower_price, leg.upper_price, dec_a, dec_b))
+                .map(|i| orphans.remove(i).mint),
+        };
+        match found {
+            Some(mint) => {
+                if leg.position_mint.is_none() {
+                    open_journal::record_mint(j.id, &leg.role, &mint.to_string(), None).await?;
+                }
+                live.push((leg.clone(), mint));
+            }
+            None => missing.push(leg.clone()),
+        }
+    }
+
+    if missing.is_empty() {
+        open_journal::finish(j.id, JournalStatus::Done).await?;
+        let _ = tx_tg.send(ServiceCommand::SendMessage(format!(
+            "✅ {}: открытие #{} завершилось до рестарта — все {} диапазона на месте",
+            j.pool_name, j.id, live.len()
+        )));
+        return Ok(());
+    }
+
+    // 2) достраиваем, если план ещё актуален: что-то уже стоит,
+    //    журнал свежий и цена внутри запланированной огибающей
+    let price = venue_for(&pool_cfg.program).pool_price(pool_cfg).await?;
+    let lo = j.legs.iter().map(|l| l.lower_price).fold(f64::MAX, f64::min);
+    let hi = j.legs.iter().map(|l| l.upper_price).fold(f64::MIN, f64::max);
+    let fresh = Utc::now() - j.created_at < chrono::Duration::minutes(RESUME_MAX_AGE_MIN);
+
+    if !live.is_empty() && fresh && price > lo && price < hi {
+        let _ = tx_tg.send(ServiceCommand::SendMessage(format!(
+            "🔁 {}: открытие #{} прервано ({}/{}), достраиваю недостающие диапазоны…",
+            j.pool_name, j.id, live.len(), j.legs.len()
+        )));
+        let mut complete = true;
+        for leg in &missing {
+            match executor().open_position(
+                leg.lower_price, leg.upper_price, leg.deposit, pool_cfg.clone(), 150u16, leg.range_idx,
+            ).await {
+                Ok(res) => {
+                    let sig = res.signature.map(|s| s.to_string());
+                    open_journal::record_mint(j.id, &leg.role, &res.position_mint.to_string(), sig.as_deref()).await?;
+                    live.push((leg.clone(), res.position_mint));
+                }
+                Err(e) => {
+                    let _ = tx_tg.send(ServiceCommand::SendMessage(
+                        format!("⚠️ {:?} не открылась: {e}", leg.role),
+                    ));
+                    complete = false;
+                    break;
+                }
+            }
+        }
+        if complete {
+            open_journal::finish(j.id, JournalStatus::Done).await?;
+            let _ = tx_tg.send(ServiceCommand::SendMessage(format!(
+                "✅ {}: открытие #{} достроено", j.pool_name, j.id
+            )));
+            return Ok(());
+        }
+    }
+
+    // 3) иначе откатываем: закрываем всё, что успело открыться
+    for (leg, mint) in &live {
+        if let Err(e) = executor().close_position(*mint, 400u16).await {
+            let _ = tx_tg.send(ServiceCommand::SendMessage(
+                format!("⚠️ {:?} ({mint}) не закрылась: {e}", leg.role),
+            ));
+        }
+    }
+    open_journal::finish(j.id, JournalStatus::RolledBack).await?;
+    let _ = tx_tg.send(ServiceCommand::SendMessage(format!(
+        "↩️ {}: открытие #{} откатено, закрыто позиций: {}", j.pool_name, j.id, live.len()
+    )));
+    Ok(())
+}
+
 #[inline]
 fn norm_price(raw: f64, invert: bool) -> f64 {
     if invert { 1.0 / raw } else { raw }
@@ -623,29 +758,26 @@ pub struct PoolReport {
 // reporter.rs
 pub async fn build_pool_report(cfg: &PoolConfig, tx_tg: UnboundedSender<ServiceCommand>, init_wallet_balance: f64) -> Result<PoolReport> {
     let closing: triggers::Trigger  = triggers::get_trigger("closing").await;
-    // 1. RPC / Whirlpool
-    let rpc      = utils::init_rpc();
+    // 1. Пул
     let whirl_pk = Pubkey::from_str(&cfg.pool_address)?;
-    let whirl_ac = safe_get_account(&rpc, &whirl_pk).await?;
-    let whirl    = orca_whirlpools_client::Whirlpool::from_bytes(&whirl_ac.data)?;
 
     // 2. Текущая цена
-    let raw = orca_whirlpools_core::sqrt_price_to_price(U128::from(whirl.sqrt_price),
-                                  cfg.decimal_a as u8, cfg.decimal_b as u8);
+    let raw = venue_for(&cfg.program).pool_price(cfg).await?;
     let price_disp = if cfg.name.starts_with("SOL/") { raw } else { 1.0 / raw };
 
-    // 3. Все позиции владельца
-    let list = list_positions_for_owner(Some(whirl_pk)).await?;
+    // 3. Позиции владельца (без игнорируемых)
+    let list = owned_in_pool(whirl_pk).await?;
     if list.is_empty() {
         return Ok(PoolReport{
-            text:  format!("📊 {} — позиций нет.\n", cfg.name),
+            text:  format!("{}📊 {} — позиций нет.\n", mode_tag(), cfg.name),
             total: 0.0,
         });
     }
-    if ((list.len() < 3 && RANGE == Range::Three) || (list.len() < 2 && RANGE == Range::Two)) && closing.state == false {
-        _ = close_all_positions(250, None).await?;
+    let strategy = strategy_for_pool(&cfg.pool_address).await?;
+    if list.len() < strategy.roles().len() && closing.state == false {
+        _ = executor().close_all(250, Some(whirl_pk)).await?;
         _ = swap_excess_to_usdc(WSOL, 9, 0.05).await?;
-        let _ = tx_tg.send(ServiceCommand::SendSignal("Signal! list.len() < 3 && closing.state == false".to_string()));
+        let _ = tx_tg.send(ServiceCommand::SendSignal(format!("Signal! {}: list.len() < 3 && closing.state == false", cfg.name)));
     }
     let candels_1m = get_kline("SOLUSDT", 250, 1).await?;
 
@@ -673,34 +805,34 @@ pub async fn build_pool_report(cfg: &PoolConfig, tx_tg: UnboundedSender<ServiceC
 
     // 4. Информация по позициям
     let mut infos = Vec::new();
-    for p in list {
-        if let PositionOrBundle::Position(hp) = p {
-            if let Ok(i) = fetch_pool_position_info(cfg, Some(&hp.address.to_string())).await {
-                infos.push(i);
-            }
+    for p in &list {
+        if let Ok(i) = executor().position_info(cfg, p).await {
+            infos.push(i);
         }
     }
-    if crate::params::RANGE == Range::Two{
-        infos.sort_by(|a, b| a.index.partial_cmp(&b.index).unwrap());
-    } else {
-        infos.sort_by(|a, b| b.lower_price.partial_cmp(&a.lower_price).unwrap());
-    }
+    infos.sort_by(|a, b| b.lower_price.partial_cmp(&a.lower_price).unwrap());
 
     // 5. Формируем текст и суммируем total
     let icons = ["🍏","🍊","🍎"];
-    let mut txt   = format!("📊 {} — Price {:.6}\n", cfg.name, price_disp);
-    let mut total = 0.0;
-    let mut tv    = 0.0;
+    let mut txt   = format!("{}📊 {} — Price {:.6}\n", mode_tag(), cfg.name, price_disp);
+    let mut total   = 0.0;
+    let mut tv      = 0.0;
+    let mut rewards = 0.0;
 
     for (idx, i) in infos.iter().enumerate() {
-        let l = if cfg.name == "SOL/USDT" { 1.0/i.upper_price } else { i.lower_price };
-        let u = if cfg.name == "SOL/USDT" { 1.0/i.lower_price } else { i.upper_price };
+        let l = i.lower_price;
+        let u = i.upper_price;
         let mark = if price_disp > l && price_disp < u {
             icons.get(idx).unwrap_or(&"✅")
         } else { "----" };
-        txt.push_str(&format!("{mark}P{}: R[{:.4}–{:.4}], ${:.4}\n", idx+1, l, u, i.sum));
-        total += i.sum;
-        tv    += i.value_a + i.value_b;
+        let reward = if i.rewards_usd > 0.0 { format!(" +🎁${:.4}", i.rewards_usd) } else { String::new() };
+        txt.push_str(&format!("{mark}P{}: R[{:.4}–{:.4}], ${:.4}{reward}\n", idx+1, l, u, i.sum));
+        total   += i.sum + i.rewards_usd;
+        tv      += i.value_a + i.value_b;
+        rewards += i.rewards_usd;
+    }
+    if rewards > 0.0 {
+        txt.push_str(&format!("🎁 Награды: ${:.4}\n", rewards));
     }
     txt.push_str(&format!("\n"));
 
@@ -717,12 +849,12 @@ pub async fn build_pool_report(cfg: &PoolConfig, tx_tg: UnboundedSender<ServiceC
     let comm3 = infos.get(2).map(|i| i.sum).unwrap_or(0.0);
     let total_current = tv;
 
-    if let Err(e) = record_position_metrics(&db_cfg, comm1, comm2, comm3, total_current, init_wallet_balance).await {
+    if let Err(e) = record_position_metrics(&db_cfg, comm1, comm2, comm3, total_current, init_wallet_balance, rewards).await {
         log::error!("Не удалось сохранить метрики для {}: {}", cfg.name, e);
     }
 
     let mut init_value = 0.0;
-    match positions::get_pool_config().await {
+    match positions::get_pool_config(&cfg.pool_address).await {
         Ok(Some(ps))  => {
             init_value = ps.total_value_open;
         }, // выходим, если триггер включён
@@ -743,13 +875,17 @@ pub async fn build_pool_report(cfg: &PoolConfig, tx_tg: UnboundedSender<ServiceC
     Ok(PoolReport { text: txt, total })
 }
 
+/// Позиции owner-а в пуле, кроме помеченных при сверке.
+pub async fn owned_in_pool(whirl_pk: Pubkey) -> Result<Vec<OwnedPosition>> {
+    let list = executor().list_positions(Some(whirl_pk)).await?;
+    Ok(list.into_iter().filter(|p| !reconcile::is_ignored(&p.mint)).collect())
+}
+
 async fn close_existing_owner_positions(pool: &PoolConfig) -> anyhow::Result<()> {
-    let list = list_positions_for_owner(Some(Pubkey::from_str(&pool.pool_address)?)).await?;
+    let list = owned_in_pool(Pubkey::from_str(&pool.pool_address)?).await?;
     for p in list {
-        if let PositionOrBundle::Position(pos) = p {
-            // закрываем любую позицию owner-а в этом пуле
-            let _ = close_whirlpool_position(pos.data.position_mint, 150.0 as u16).await;
-        }
+        // закрываем любую позицию owner-а в этом пуле
+        let _ = executor().close_position(p.mint, 150.0 as u16).await;
     }
     Ok(())
 }
@@ -766,70 +902,200 @@ pub fn calculate_range(range: Option<f32>) -> Option<(f64, f64)>{
     Some((1.0-half as f64, 1.0+half as f64))
 }
 
+/// Частичное перецентрирование: цена ушла из центральной ноги, но ещё внутри
+/// огибающей. Ждём `min_restart` минут (вернулась — сбрасываем), потом
+/// переносим дальнюю ногу на другую сторону цены. Возвращает новые границы выхода.
+async fn maybe_recenter(
+    strategy: &dyn Strategy,
+    price: f64,
+    min_restart: u64,
+    pool_cfg: &mut PoolConfig,
+    tx_tg: &UnboundedSender<ServiceCommand>,
+    whirl_pk: Pubkey,
+    since: &mut Option<Instant>,
+) -> Result<Option<(f64, f64)>> {
+    let Some(plan) = strategy.recenter(pool_cfg, price) else {
+        *since = None;
+        return Ok(None);
+    };
+    let t0 = *since.get_or_insert_with(Instant::now);
+    if t0.elapsed() < Duration::from_secs(min_restart * 60) {
+        return Ok(None);
+    }
+    *since = None;
+
+    // на время перецентрирования пул «открывается» — как при первом открытии
+    triggers::opening_switcher(&pool_cfg.name, true, Some(tx_tg)).await?;
+    let res = recenter_leg(strategy, pool_cfg, &plan, price, tx_tg, whirl_pk).await;
+    triggers::opening_switcher(&pool_cfg.name, false, Some(tx_tg)).await?;
+    res?;
+    let bounds = strategy
+        .exit_bounds(pool_cfg)
+        .context("нет позиций для расчёта границ выхода")?;
+    Ok(Some(bounds))
+}
+
+/// Закрыть ногу `plan.close_role` и заминтить на её средства новую.
+/// Диапазон позиции Whirlpool менять нельзя, поэтому нога не «сдвигается»,
+/// а перевыставляется: close → open (недостающий токен докупает open).
+async fn recenter_leg(
+    strategy: &dyn Strategy,
+    pool_cfg: &mut PoolConfig,
+    plan: &RecenterPlan,
+    price: f64,
+    tx_tg: &UnboundedSender<ServiceCommand>,
+    whirl_pk: Pubkey,
+) -> Result<()> {
+    let invert = strategy.invert();
+    let slot = [&pool_cfg.position_1, &pool_cfg.position_2, &pool_cfg.position_3]
+        .into_iter()
+        .flatten()
+        .find(|p| p.role == plan.close_role)
+        .cloned()
+        .with_context(|| format!("в {} нет ноги {:?}", pool_cfg.name, plan.close_role))?;
+
+    // 1) находим позицию ноги: по mint-у, для старых записей — по границам
+    let owned = owned_in_pool(whirl_pk).await?;
+    let pos = owned.iter()
+        .find(|p| match &slot.position_nft {
+            Some(nft) => p.mint.to_string() == *nft,
+            None      => reconcile::bounds_match(p, &slot, pool_cfg, invert),
+        })
+        .with_context(|| format!("позиция {:?} не найдена в сети", plan.close_role))?;
+
+    // 2) сколько в ней сейчас (в токене B) — столько и вносим в новую ногу
+    let info = executor().position_info(pool_cfg, pos).await?;
+    let deposit = info.amount_b + info.amount_a * info.current_price;
+
+    let _ = tx_tg.send(ServiceCommand::SendMessage(format!(
+        "🎯 {}: цена {:.6} ушла из центра — перецентрирование: {:?} → {:?} [{:.6}; {:.6}]",
+        pool_cfg.name, price, plan.close_role, plan.new_role, plan.new_lower, plan.new_upper
+    )));
+
+    // SDK ждёт сырые цены «B per A»
+    let (raw_lower, raw_upper) = if invert {
+        (1.0 / plan.new_upper, 1.0 / plan.new_lower)
+    } else {
+        (plan.new_lower, plan.new_upper)
+    };
+
+    // закрытие, свап и открытие одним бандлом, если получится
+    let reopened = match executor().reopen_position(pos.mint, raw_lower, raw_upper, deposit, pool_cfg, 150u16).await {
+        Ok(r) => r,
+        Err(e) => {
+            log::warn!("{}: перецентрирование бандлом не удалось: {e:#}", pool_cfg.name);
+            None
+        }
+    };
+    // при отправке по одной закрытие могло лечь, а открытие — нет
+    let still_open = reopened.is_none()
+        && owned_in_pool(whirl_pk).await?.iter().any(|p| p.mint == pos.mint);
+    if still_open {
+        executor().close_position(pos.mint, 400u16).await
+            .with_context(|| format!("не удалось закрыть {:?}", plan.close_role))?;
+    }
+
+    // 3) роли оставшихся ног сдвигаются, новая нога встаёт на освободившееся место
+    let remaining: Vec<LiqPosition> = [&pool_cfg.position_1, &pool_cfg.position_2, &pool_cfg.position_3]
+        .into_iter()
+        .flatten()
+        .filter(|p| p.role != plan.close_role)
+        .cloned()
+        .collect();
+    pool_cfg.position_1 = None;
+    pool_cfg.position_2 = None;
+    pool_cfg.position_3 = None;
+    for mut liq in remaining {
+        if let Some((_, to)) = plan.relabel.iter().find(|(from, _)| *from == liq.role) {
+            liq.role = to.clone();
+        }
+        set_slot(pool_cfg, liq);
+    }
+
+    // 4) минтим новую ногу, если её ещё не открыл бандл
+    let alloc = RangeAlloc {
+        role:            plan.new_role.clone(),
+        range_idx:       plan.new_role.slot() as usize - 1,
+        usdc_amount:     deposit,
+        sol_amount:      0.0,
+        usdc_equivalent: deposit,
+        upper_price:     raw_upper,
+        lower_price:     raw_lower,
+    };
+    let res = match reopened {
+        Some(r) => Ok(r),
+        None => executor().open_position(
+            alloc.lower_price, alloc.upper_price, deposit, pool_cfg.clone(), 150u16, alloc.range_idx,
+        ).await,
+    };
+
+    // БД отражает реальное состояние и при неудаче открытия
+    if let Ok(r) = &res {
+        fill_slot(pool_cfg, &alloc, r.position_mint, invert);
+    }
+    positions::update_slots(pool_cfg).await?;
+
+    let res = res.with_context(|| format!("не удалось открыть новую ногу {:?}", plan.new_role))?;
+    let _ = tx_tg.send(ServiceCommand::SendMessage(format!(
+        "{}✅ {}: перецентрирование завершено, новая {:?} (mint {})",
+        mode_tag(), pool_cfg.name, plan.new_role, res.position_mint
+    )));
+    Ok(())
+}
+
 async fn check_bounds_and_maybe_close(
+    strategy: &dyn Strategy,
     price: f64,
     upper_exit: f64,
     lower_exit: f64,
-    min_restart: u64,
     pool_cfg: &PoolConfig,
     tx_tg: &UnboundedSender<ServiceCommand>,
-    rpc: &RpcClient,
     whirl_pk: Pubkey,
-    out_of_range_since: &mut Option<Instant>,
-    wait_before_redeploy: &mut Duration,
+    tracker: &mut ExitTracker,
 ) -> Result<bool> {
-    // 3.2 допустимое отклонение
-
-    let out_of_range = price > upper_exit
-        || price < lower_exit;
-
-    if out_of_range {
-        // если цена ушла ниже — немедленно выполняем redeploy
-        let lower = price < lower_exit*1.007;
-        println!("lower: {} price: {:.2} lower_exit: {:.2}", lower, price, lower_exit);
-        if lower {
-            *wait_before_redeploy = Duration::from_secs(0);
-        }
+    // 3.2 решение принимает политика выхода пула
+    let Some(d) = tracker.observe(Utc::now().timestamp_millis(), price, upper_exit, lower_exit) else {
+        return Ok(false);
+    };
+    if let Err(e) = exit_policy::log_decision(
+        &pool_cfg.pool_address, &pool_cfg.name, d.side.as_str(), d.event.as_str(), d.price, d.ref_price, &d.reason,
+    ).await {
+        log::warn!("exit_decisions: not recorded: {e}");
+    }
 
-        match out_of_range_since {
-            // первый выход — запускаем таймер
-            None => {
-                *out_of_range_since = Some(Instant::now());
-                let _ = tx_tg.send(ServiceCommand::SendMessage(format!(
-                    "⚠️ {}: price {:.6} вышла за [{:.6}; {:.6}]. Ждём {:.2} мин",
-                    pool_cfg.name, price, lower_exit, upper_exit, min_restart as f64
-                )));
-                // не выходим из цикла сразу, ждём
-                Ok(false)
-            }
-            // таймер уже запущен — проверяем, не пора ли перезапускать
-            Some(t0) if t0.elapsed() >= *wait_before_redeploy => {
-                let _ = tx_tg.send(ServiceCommand::SendMessage(format!(
-                    "⏰ {}: прошло {:.2} мин, цена {:.6} всё ещё вне диапазона — перевыставляем позиции",
-                    pool_cfg.name, min_restart as f64, price
-                )));
-                // пытаемся закрыть
-                if let Err(e) = close_and_report(rpc, pool_cfg, whirl_pk, tx_tg, lower).await {
-                    let _ = tx_tg.send(ServiceCommand::SendMessage(
-                        format!("❌ Ошибка при закрытии {}: {:?}", pool_cfg.name, e),
-                    ));
-                }
-                // сигнализируем, что нужно break
-                Ok(true)
-            }
-            // ещё не время
-            _ => Ok(false),
+    match d.event {
+        // первый выход — ждём, что скажет политика
+        ExitEvent::Armed => {
+            let _ = tx_tg.send(ServiceCommand::SendMessage(format!(
+                "⚠️ {}: price {:.6} вышла за [{:.6}; {:.6}]. Ждём ({})",
+                pool_cfg.name, price, lower_exit, upper_exit, d.reason
+            )));
+            Ok(false)
         }
-    } else {
-        // вернулись в диапазон — сбрасываем таймер
-        if out_of_range_since.is_some() {
+        // вернулись в диапазон — ожидание сброшено
+        ExitEvent::Reset => {
             let _ = tx_tg.send(ServiceCommand::SendMessage(format!(
                 "✅ {}: price {:.6} снова в диапазоне, продолжаем работу",
                 pool_cfg.name, price
             )));
-            *out_of_range_since = None;
+            Ok(false)
+        }
+        ExitEvent::Exit => {
+            let lower = d.side == ExitSide::Down;
+            let _ = tx_tg.send(ServiceCommand::SendMessage(format!(
+                "⏰ {}: {} — цена {:.6}, перевыставляем позиции",
+                pool_cfg.name, d.reason, price
+            )));
+            // пытаемся закрыть
+            let post = strategy.post_close(lower);
+            if let Err(e) = close_and_report(pool_cfg, whirl_pk, tx_tg, lower, post).await {
+                let _ = tx_tg.send(ServiceCommand::SendMessage(
+                    format!("❌ Ошибка при закрытии {}: {:?}", pool_cfg.name, e),
+                ));
+            }
+            // сигнализируем, что нужно break
+            Ok(true)
         }
-        Ok(false)
     }
 }
 
@@ -0,0 +1,195 @@
+// src/pool_registry.rs
+//
+// Реестр пулов, которые бот ведёт параллельно.
+// Каждый пул включается, только если задана его переменная окружения
+// с адресом пула — так в .env можно держать хоть один пул, хоть все.
+// Площадка задаётся `<POOL_ENV>_PROGRAM` (`whirlpool` по умолчанию или `raydium`);
+// адрес в `<POOL_ENV>` тогда должен быть адресом пула этой площадки.
+// `<POOL_ENV>=auto` — адрес Whirlpool выбирает discovery при старте
+// (лучший по оценке APR комиссий среди всех fee tier пары).
+// Переезд пула (migration.rs) перекрывает адрес из окружения и переживает рестарт.
+
+use std::collections::HashMap;
+use std::env;
+use std::sync::RwLock;
+use chrono::Utc;
+use anyhow::{bail, Result};
+use once_cell::sync::Lazy;
+
+use crate::database::history;
+use crate::dex_services::discovery;
+
+use crate::params::{WSOL, USDC, USDT, JITOSOL, MSOL};
+use crate::types::PoolConfig;
+
+/// Описание пула в реестре.
+#[derive(Debug, Clone)]
+pub struct PoolSpec {
+    pub name:      &'static str,
+    /// переменная окружения с адресом пула
+    pub pool_env:  &'static str,
+    pub mint_a:    &'static str,
+    pub mint_b:    &'static str,
+    pub decimal_a: u16,
+    pub decimal_b: u16,
+    /// Pyth feed для мониторинга цены; `None` — только HTTP-фоллбек
+    pub pyth_feed: Option<&'static str>,
+}
+
+const SOL_USD_FEED: &str = "0xef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d";
+
+/// Все известные боту пулы (token A всегда SOL — так его упорядочивает Whirlpool).
+pub const POOL_SPECS: &[PoolSpec] = &[
+    PoolSpec {
+        name: "SOL/USDC", pool_env: "SOLUSDC_POOL",
+        mint_a: WSOL, mint_b: USDC, decimal_a: 9, decimal_b: 6,
+        pyth_feed: Some(SOL_USD_FEED),
+    },
+    PoolSpec {
+        name: "SOL/USDT", pool_env: "SOLUSDT_POOL",
+        mint_a: WSOL, mint_b: USDT, decimal_a: 9, decimal_b: 6,
+        pyth_feed: Some(SOL_USD_FEED),
+    },
+    // LST-пулы: цена пула — это курс LST к SOL, SOL/USD-фид тут не подходит
+    PoolSpec {
+        name: "SOL/JitoSOL", pool_env: "SOLJITOSOL_POOL",
+        mint_a: WSOL, mint_b: JITOSOL, decimal_a: 9, decimal_b: 9,
+        pyth_feed: None,
+    },
+    PoolSpec {
+        name: "SOL/mSOL", pool_env: "SOLMSOL_POOL",
+        mint_a: WSOL, mint_b: MSOL, decimal_a: 9, decimal_b: 9,
+        pyth_feed: None,
+    },
+];
+
+impl PoolSpec {
+    /// Шаблон `PoolConfig` для пула (адрес берётся из окружения).
+    pub fn to_config(&self) -> Result<PoolConfig> {
+        let addr = env::var(self.pool_env)?;
+        // выбранный discovery или переехавший пул — всегда Whirlpool
+        if let Some(addr) = OVERRIDES.read().unwrap().get(self.pool_env).cloned() {
+            return Ok(self.build(addr));
+        }
+        if is_auto(&addr) {
+            bail!("{}=auto: пул ещё не выбран", self.pool_env);
+        }
+        let mut cfg = self.build(addr);
+        if let Ok(program) = env::var(format!("{}_PROGRAM", self.pool_env)) {
+            cfg.program = program.trim().to_lowercase();
+        }
+        Ok(cfg)
+    }
+
+    /// `PoolConfig` с заданным адресом (для бэктеста адрес не важен).
+    pub fn build(&self, pool_address: String) -> PoolConfig {
+        PoolConfig {
+            program: "whirlpool".to_string(),
+            name:    self.name.to_string(),
+            pool_address,
+            mint_a:  self.mint_a.to_string(),  decimal_a: self.decimal_a,
+            mint_b:  self.mint_b.to_string(),  decimal_b: self.decimal_b,
+            amount:  0.0,
+            position_1: None, position_2: None, position_3: None,
+            date_opened:         Utc::now(),
+            is_closed:           false,
+            commission_collected_1: 0.0,
+            commission_collected_2: 0.0,
+            commission_collected_3: 0.0,
+            total_value_open:    0.0,
+            total_value_current: 0.0,
+            wallet_balance: 0.0
+        }
+    }
+}
+
+/// Адреса, перекрывающие окружение: выбор discovery для `<POOL_ENV>=auto`
+/// и переезды пулов.
+static OVERRIDES: Lazy<RwLock<HashMap<&'static str, String>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+fn is_auto(addr: &str) -> bool {
+    addr.trim().eq_ignore_ascii_case("auto")
+}
+
+/// Перевести пул на другой адрес (переезд в другой fee tier).
+pub fn set_override(pool_env: &'static str, pool_address: String) {
+    OVERRIDES.write().unwrap().insert(pool_env, pool_address);
+}
+
+/// Актуальный `PoolConfig` пула, если его адрес сменился с момента `cfg`.
+pub fn moved(cfg: &PoolConfig) -> Option<PoolConfig> {
+    find_spec(&cfg.name)
+        .and_then(|s| s.to_config().ok())
+        .filter(|fresh| fresh.pool_address != cfg.pool_address)
+}
+
+/// Адреса пулов при старте (до `enabled_pools`): прошлые переезды из истории,
+/// затем выбор для `<POOL_ENV>=auto`. Возвращает строки для отчёта.
+pub async fn resolve_pool_addresses() -> Vec<String> {
+    let mut report = Vec::new();
+    for spec in POOL_SPECS {
+        let Ok(env_addr) = env::var(spec.pool_env) else { continue };
+        let auto = is_auto(&env_addr);
+
+        // переезд в силе, пока адрес в окружении не поменяли руками
+        match history::list_pool_migrations(spec.name).await {
+            Ok(moves) => {
+                if let Some(last) = moves.last() {
+                    if auto || moves.iter().any(|m| m.from_address == env_addr.trim()) {
+                        set_override(spec.pool_env, last.to_address.clone());
+                        report.push(format!("🚚 {}: пул после переезда {}", spec.name, last.to_address));
+                        continue;
+                    }
+                }
+            }
+            Err(e) => report.push(format!("❌ {}: история переездов: {e}", spec.name)),
+        }
+
+        if !auto {
+            continue;
+        }
+        match discovery::rank(spec, true).await {
+            Ok(ranked) => match ranked.first() {
+                Some(best) => {
+                    set_override(spec.pool_env, best.address.to_string());
+                    report.push(format!("🔎 {}: выбран {}", spec.name, best.describe()));
+                }
+                None => report.push(format!("⚠️ {}: Whirlpool-пулов пары не найдено", spec.name)),
+            },
+            Err(e) => report.push(format!("❌ {}: discovery: {e:#}", spec.name)),
+        }
+    }
+    report
+}
+
+/// Пулы, для которых в окружении задан адрес.
+pub fn enabled_pools() -> Vec<PoolConfig> {
+    POOL_SPECS
+        .iter()
+        .filter_map(|s| s.to_config().ok())
+        .collect()
+}
+
+/// Найти пул по имени (`SOL/USDC`) или адресу.
+pub fn find_pool(key: &str) -> Result<PoolConfig> {
+    for cfg in enabled_pools() {
+        if cfg.name.eq_ignore_ascii_case(key) || cfg.pool_address == key {
+            return Ok(cfg);
+        }
+    }
+    bail!("pool `{}` не найден в реестре", key)
+}
+
+/// Описание пула по имени (независимо от того, задан ли адрес в окружении).
+pub fn find_spec(name: &str) -> Option<&'static PoolSpec> {
+    POOL_SPECS.iter().find(|s| s.name.eq_ignore_ascii_case(name))
+}
+
+/// Pyth feed для пула по имени.
+pub fn pyth_feed_id(name: &str) -> Option<&'static str> {
+    POOL_SPECS
+        .iter()
+        .find(|s| s.name == name)
+        .and_then(|s| s.pyth_feed)
+}
@@ -0,0 +1,216 @@
+// src/reconcile.rs
+//
+// Сверка при старте: позиции в сети (включая бандлы) против записи пула
+// в `pool_configs`. Каждая позиция получает статус known / orphaned / missing,
+// дальше по политике ORPHAN_POLICY (adopt | close | flag, по умолчанию flag)
+// её берём в работу, закрываем или помечаем, и шлём сводку в Telegram.
+// Помеченные позиции бот больше не трогает: не усыновляет и не закрывает.
+
+use std::collections::HashSet;
+use std::env;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use orca_whirlpools_core::tick_index_to_price;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::database::positions;
+use crate::dex_services::executor::{executor, OwnedPosition};
+use crate::strategies::ranges::strategy_for_pool;
+use crate::types::{LiqPosition, PoolConfig};
+
+/// допуск при сопоставлении позиции со слотом по границам (выравнивание по тикам)
+const BOUNDS_TOL: f64 = 0.01;
+
+/// Что делать с позицией, о которой в БД ничего нет.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrphanPolicy {
+    Adopt,
+    Close,
+    Flag,
+}
+
+impl OrphanPolicy {
+    pub fn from_env() -> Self {
+        match env::var("ORPHAN_POLICY").unwrap_or_default().to_lowercase().as_str() {
+            "adopt" => OrphanPolicy::Adopt,
+            "close" => OrphanPolicy::Close,
+            _       => OrphanPolicy::Flag,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PositionState {
+    /// есть и в сети, и в БД
+    Known,
+    /// есть в сети, нет в БД
+    Orphaned,
+    /// есть в БД, нет в сети
+    Missing,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReconAction {
+    Adopted,
+    Closed,
+    Flagged,
+    /// запись в БД больше не актуальна
+    Dropped,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReconItem {
+    pub label:  String,
+    pub state:  PositionState,
+    pub action: ReconAction,
+}
+
+#[derive(Debug)]
+pub struct ReconReport {
+    pub pool_name: String,
+    pub items:     Vec<ReconItem>,
+    /// true — набор позиций не совпадает со стратегией, открываемся заново
+    pub need_new:  bool,
+}
+
+impl ReconReport {
+    fn count(&self, state: PositionState) -> usize {
+        self.items.iter().filter(|i| i.state == state).count()
+    }
+
+    /// Текст сводки для Telegram.
+    pub fn summary(&self) -> String {
+        let mut msg = format!(
+            "🧾 Сверка {}: known {}, orphaned {}, missing {}",
+            self.pool_name,
+            self.count(PositionState::Known),
+            self.count(PositionState::Orphaned),
+            self.count(PositionState::Missing),
+        );
+        for i in &self.items {
+            msg.push_str(&format!("\n• {} — {:?} → {:?}", i.label, i.state, i.action));
+        }
+        if self.need_new {
+            msg.push_str("\n🔄 набор позиций неполный — будет открыт заново");
+        }
+        msg
+    }
+}
+
+/// Позиции, которые бот не трогает (помечены при сверке).
+static IGNORED: Lazy<Mutex<HashSet<Pubkey>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+pub fn is_ignored(mint: &Pubkey) -> bool {
+    IGNORED.lock().map(|s| s.contains(mint)).unwrap_or(false)
+}
+
+fn ignore(mint: Pubkey) {
+    if let Ok(mut s) = IGNORED.lock() {
+        s.insert(mint);
+    }
+}
+
+/// Сверить один пул и применить политику.
+pub async fn reconcile_pool(cfg: &PoolConfig) -> Result<ReconReport> {
+    let whirl_pk = Pubkey::from_str(&cfg.pool_address)?;
+    let policy   = OrphanPolicy::from_env();
+    let strategy = strategy_for_pool(&cfg.pool_address).await?;
+    let invert   = strategy.invert();
+
+    // позиции из бандлов приходят вместе с обычными (ключ — адрес позиции)
+    let onchain = executor().list_positions(Some(whirl_pk)).await?;
+
+    // слоты из БД (только если сессия не закрыта)
+    let db_cfg = positions::get_pool_config(&cfg.pool_address).await?.filter(|c| !c.is_closed);
+    let slots: Vec<LiqPosition> = db_cfg
+        .as_ref()
+        .map(|c| [c.position_1.clone(), c.position_2.clone(), c.position_3.clone()]
+            .into_iter()
+            .flatten()
+            .collect())
+        .unwrap_or_default();
+
+    let mut items = Vec::new();
+    let mut used  = vec![false; onchain.len()];
+
+    // 1) слоты БД: по mint-у, а для старых записей без mint — по границам
+    for slot in &slots {
+        let idx = onchain.iter().enumerate().position(|(i, p)| {
+            !used[i] && match &slot.position_nft {
+                Some(nft) => p.mint.to_string() == *nft,
+                None      => bounds_match(p, slot, cfg, invert),
+            }
+        });
+        match idx {
+            Some(i) => {
+                used[i] = true;
+                items.push(ReconItem {
+                    label:  format!("{:?} {}", slot.role, onchain[i].mint),
+                    state:  PositionState::Known,
+                    action: ReconAction::Adopted,
+                });
+            }
+            None => items.push(ReconItem {
+                label:  format!("{:?} [{:.6}; {:.6}]", slot.role, slot.lower_price, slot.upper_price),
+                state:  PositionState::Missing,
+                action: ReconAction::Dropped,
+            }),
+        }
+    }
+    let known = used.iter().filter(|u| **u).count();
+
+    // 2) позиции без записи в БД — по политике
+    let mut adopted = 0usize;
+    for p in onchain.iter().zip(&used).filter(|(_, u)| !**u).map(|(p, _)| p) {
+        let action = match policy {
+            OrphanPolicy::Adopt => {
+                adopted += 1;
+                ReconAction::Adopted
+            }
+            OrphanPolicy::Close => match executor().close_position(p.mint, 400u16).await {
+                Ok(_) => ReconAction::Closed,
+                Err(e) => {
+                    log::warn!("reconcile: close {} failed: {e}", p.mint);
+                    ignore(p.mint);
+                    ReconAction::Flagged
+                }
+            },
+            OrphanPolicy::Flag => {
+                ignore(p.mint);
+                ReconAction::Flagged
+            }
+        };
+        items.push(ReconItem {
+            label:  match p.bundle {
+                Some(b) => format!("bundle {b} / {}", p.mint),
+                None    => p.mint.to_string(),
+            },
+            state:  PositionState::Orphaned,
+            action,
+        });
+    }
+
+    // 3) в сети от сессии ничего не осталось — закрываем запись
+    let missing = slots.len() - known;
+    if db_cfg.is_some() && known == 0 {
+        positions::close_pool_config(&cfg.pool_address).await?;
+    }
+
+    let working  = known + adopted;
+    let need_new = working > 0 && (working != strategy.roles().len() || missing > 0);
+
+    Ok(ReconReport { pool_name: cfg.name.clone(), items, need_new })
+}
+
+/// Границы позиции совпадают со слотом (слоты хранятся в display-виде).
+pub fn bounds_match(p: &OwnedPosition, slot: &LiqPosition, cfg: &PoolConfig, invert: bool) -> bool {
+    let (dec_a, dec_b) = (cfg.decimal_a as u8, cfg.decimal_b as u8);
+    let lo = tick_index_to_price(p.tick_lower, dec_a, dec_b);
+    let hi = tick_index_to_price(p.tick_upper, dec_a, dec_b);
+    let (lo, hi) = if invert { (1.0 / hi, 1.0 / lo) } else { (lo, hi) };
+    (lo / slot.lower_price - 1.0).abs() < BOUNDS_TOL
+        && (hi / slot.upper_price - 1.0).abs() < BOUNDS_TOL
+}
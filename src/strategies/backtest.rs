@@ -0,0 +1,265 @@
+// src/strategies/backtest.rs
+//
+// Бэктест стратегий диапазонов на исторических 1m-свечах.
+// Прогоняет те же шаги, что и живой бот: `entry_gate` (как в run_pool_with_restart),
+// `allocations` стратегии, выход по границам через тот же `ExitTracker`,
+// что и check_bounds_and_maybe_close, и `post_close`.
+// Позиции оцениваются той же математикой ликвидности, что и `compute_amounts`.
+// Результат — сессии в форме `SessionHistory` и их сводка.
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::database::exit_policy::ExitPolicy;
+use crate::database::history::{summarize_sessions, ProfitBasis, SessionHistory, SessionStatistics};
+use crate::dex_services::get_info::{compute_amounts, liquidity_for_deposit};
+use crate::exchange::helpers::Candle;
+use crate::params::{USDC, USDT};
+use crate::pool_registry;
+use crate::strategies::exit::{ExitEvent, ExitSide, ExitTracker};
+use crate::strategies::ranges::{set_slot, AllocContext, Strategy, ENTRY_LOOKBACK_1M};
+use crate::types::{LiqPosition, Role};
+
+const MINUTES_PER_YEAR: f64 = 525_600.0;
+/// спред + проскальзывание свапа по умолчанию
+pub const DEFAULT_SWAP_COST:  f64 = 0.001;
+/// комиссия сети за одну транзакцию, SOL
+pub const DEFAULT_TX_FEE_SOL: f64 = 0.000_05;
+
+/// Как начисляются комиссии позиции, пока цена внутри её диапазона.
+#[derive(Debug, Clone)]
+pub enum FeeModel {
+    /// фиксированная доходность, % годовых от стоимости позиции
+    Apr(f64),
+    /// объём пула в USD по барам (та же длина, что и свечи);
+    /// доля позиции в комиссиях ≈ стоимость позиции / TVL пула
+    Volume { volume_usd: Vec<f64>, fee_rate: f64, pool_tvl_usd: f64 },
+}
+
+#[derive(Debug, Clone)]
+pub struct BacktestConfig {
+    /// имя пула из реестра (SOL/USDC, SOL/USDT)
+    pub pool_name:   String,
+    pub capital_usd: f64,
+    pub pct_list:    [f64; 4],
+    pub compress:    bool,
+    pub range:       Option<f32>,
+    /// ожидание вне диапазона перед закрытием, минуты
+    pub min_restart: u64,
+    /// правила выхода (TWAP и подтверждения считаются по закрытиям 1m-свечей)
+    pub exit:        ExitPolicy,
+    pub fees:        FeeModel,
+    /// спред + проскальзывание свапа (доля от суммы)
+    pub swap_cost:   f64,
+    /// стоимость одной транзакции в USD (открытие / закрытие позиции)
+    pub tx_cost_usd: f64,
+}
+
+#[derive(Debug)]
+pub struct BacktestReport {
+    pub sessions: Vec<SessionHistory>,
+    pub stats:    SessionStatistics,
+}
+
+/// Одна виртуальная позиция сессии (цены — «B per A», человеческие единицы).
+struct SimPosition {
+    liquidity: f64,
+    sqrt_l:    f64,
+    sqrt_u:    f64,
+    fees:      f64,
+}
+
+impl SimPosition {
+    fn value(&self, price: f64) -> (f64, f64) {
+        compute_amounts(self.liquidity, price.sqrt(), self.sqrt_l, self.sqrt_u)
+    }
+
+    fn in_range(&self, price: f64) -> bool {
+        let s = price.sqrt();
+        s >= self.sqrt_l && s < self.sqrt_u
+    }
+}
+
+struct SimSession {
+    opened_at:  i64,
+    sum_open:   f64,
+    costs:      f64,
+    positions:  Vec<SimPosition>,
+    upper_exit: f64,
+    lower_exit: f64,
+    tracker:    ExitTracker,
+}
+
+/// Прогнать 1m-свечи (по возрастанию времени) через стратегию.
+pub fn run_backtest(
+    strategy: &dyn Strategy,
+    candles: &[Candle],
+    bt: &BacktestConfig,
+) -> Result<BacktestReport> {
+    let spec = pool_registry::find_spec(&bt.pool_name)
+        .ok_or_else(|| anyhow!("пул {} не найден в реестре", bt.pool_name))?;
+    if !(spec.mint_b == USDC || spec.mint_b == USDT) || strategy.invert() {
+        bail!("бэктест поддерживает только пулы SOL/стейбл без инверсии цены");
+    }
+    if let FeeModel::Volume { volume_usd, .. } = &bt.fees {
+        if volume_usd.len() != candles.len() {
+            bail!("ряд объёмов ({}) не совпадает со свечами ({})", volume_usd.len(), candles.len());
+        }
+    }
+    if candles.len() <= ENTRY_LOOKBACK_1M {
+        bail!("слишком мало свечей: {}", candles.len());
+    }
+
+    let mut sessions: Vec<SessionHistory> = Vec::new();
+    let mut session: Option<SimSession> = None;
+    let mut wait_entry = true;
+
+    for i in ENTRY_LOOKBACK_1M..candles.len() {
+        let bar   = &candles[i];
+        let price = bar.close;
+
+        // ───── 1. Нет позиций — ждём входа или сразу перевыставляемся ─────
+        let Some(s) = session.as_mut() else {
+            if wait_entry {
+                let window = &candles[i + 1 - ENTRY_LOOKBACK_1M..=i];
+                let ready = strategy.entry_gate(window).map(|g| g.ready).unwrap_or(false);
+                if !ready { continue; }
+            }
+            session = Some(open_session(strategy, spec, bar, bt)?);
+            continue;
+        };
+
+        // ───── 2. Комиссии за бар ─────────────────────────────────────────
+        let dt_min = (bar.timestamp - candles[i - 1].timestamp) as f64 / 60_000.0;
+        for p in s.positions.iter_mut().filter(|p| p.in_range(price)) {
+            let (a, b) = p.value(price);
+            let value  = a * price + b;
+            p.fees += match &bt.fees {
+                FeeModel::Apr(apr) => value * apr / 100.0 * dt_min / MINUTES_PER_YEAR,
+                FeeModel::Volume { volume_usd, fee_rate, pool_tvl_usd } => {
+                    volume_usd[i] * fee_rate * (value / pool_tvl_usd.max(1.0))
+                }
+            };
+        }
+
+        // ───── 3. Выход — как в check_bounds_and_maybe_close ──────────────
+        let exit = s.tracker
+            .observe(bar.timestamp, price, s.upper_exit, s.lower_exit)
+            .filter(|d| d.event == ExitEvent::Exit);
+        if let Some(d) = exit {
+            let post = strategy.post_close(d.side == ExitSide::Down);
+            if let Some(closed) = session.take() {
+                sessions.push(close_session(closed, bar, post.swap_to_usdc, bt));
+            }
+            wait_entry = post.wait_entry;
+        }
+    }
+
+    // незакрытая сессия — оцениваем по последней свече
+    if let Some(s) = session.take() {
+        sessions.push(close_session(s, candles.last().unwrap(), false, bt));
+    }
+
+    let period_start = ts_to_dt(candles[0].timestamp)?;
+    let period_end   = ts_to_dt(candles[candles.len() - 1].timestamp)?;
+    let stats = summarize_sessions(&sessions, period_start, period_end);
+    Ok(BacktestReport { sessions, stats })
+}
+
+fn open_session(
+    strategy: &dyn Strategy,
+    spec: &pool_registry::PoolSpec,
+    bar: &Candle,
+    bt: &BacktestConfig,
+) -> Result<SimSession> {
+    let price = bar.close;
+    let ctx = AllocContext {
+        price,
+        pct_list:    bt.pct_list,
+        capital_usd: bt.capital_usd,
+        compress:    bt.compress,
+        range:       bt.range,
+        sol_usd:     price,
+    };
+    let allocs = strategy.allocations(&ctx)?;
+
+    let mut cfg = spec.build(String::new());
+    let mut positions = Vec::with_capacity(allocs.len());
+    let mut sum_open = 0.0;
+    let mut costs    = 0.0;
+    for alloc in &allocs {
+        // сколько вносится — как в open_allocations
+        let deposit = match alloc.role {
+            Role::Middle | Role::MiddleSmall => alloc.usdc_amount,
+            Role::Up | Role::Down => alloc.usdc_equivalent,
+        };
+        let (sqrt_l, sqrt_u) = (alloc.lower_price.sqrt(), alloc.upper_price.sqrt());
+        let liquidity = liquidity_for_deposit(deposit / price, deposit, price.sqrt(), sqrt_l, sqrt_u);
+        let pos = SimPosition { liquidity, sqrt_l, sqrt_u, fees: 0.0 };
+
+        let (a, b) = pos.value(price);
+        sum_open += a * price + b;
+        // SOL-часть докупается свапом, плюс транзакция открытия
+        costs += a * price * bt.swap_cost + bt.tx_cost_usd;
+        positions.push(pos);
+
+        set_slot(&mut cfg, LiqPosition {
+            role: alloc.role.clone(),
+            position_address: None,
+            position_nft:     None,
+            upper_price: alloc.upper_price,
+            lower_price: alloc.lower_price,
+        });
+    }
+
+    let (upper_exit, lower_exit) = strategy
+        .exit_bounds(&cfg)
+        .ok_or_else(|| anyhow!("нет позиций для расчёта границ выхода"))?;
+
+    Ok(SimSession {
+        opened_at: bar.timestamp,
+        sum_open,
+        costs,
+        positions,
+        upper_exit,
+        lower_exit,
+        tracker: ExitTracker::new(bt.exit.clone(), bt.min_restart),
+    })
+}
+
+fn close_session(s: SimSession, bar: &Candle, swap_to_usdc: bool, bt: &BacktestConfig) -> SessionHistory {
+    let price = bar.close;
+    let mut value = 0.0;
+    let mut costs = s.costs;
+    for p in &s.positions {
+        let (a, b) = p.value(price);
+        value += a * price + b;
+        costs += bt.tx_cost_usd;
+        if swap_to_usdc {
+            costs += a * price * bt.swap_cost;
+        }
+    }
+
+    SessionHistory {
+        id:          0,
+        date_opened: ts_to_dt(s.opened_at).unwrap_or_default(),
+        date_closed: ts_to_dt(bar.timestamp).unwrap_or_default(),
+        pool_name:   bt.pool_name.clone(),
+        range_lower: s.lower_exit,
+        range_upper: s.upper_exit,
+        sum_open:    s.sum_open,
+        sum_close:   value,
+        commissions: s.positions.iter().map(|p| p.fees).sum(),
+        // модельные издержки (сеть + свапы) вычитаются в summarize_sessions
+        tx_fees:     costs,
+        rent_net:    0.0,
+        paper:       true,
+        basis:       ProfitBasis::Positions,
+    }
+}
+
+fn ts_to_dt(ts_ms: i64) -> Result<DateTime<Utc>> {
+    Utc.timestamp_millis_opt(ts_ms)
+        .single()
+        .ok_or_else(|| anyhow!("некорректный timestamp {ts_ms}"))
+}
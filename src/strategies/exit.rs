@@ -0,0 +1,176 @@
+// src/strategies/exit.rs
+//
+// Решение «закрываться или ждать» по потоку цен и политике выхода пула.
+// Один и тот же трекер кормят тики Pyth/RPC в оркестраторе и свечи в бэктесте,
+// поэтому время — в миллисекундах, а не `Instant`.
+
+use std::collections::VecDeque;
+
+use crate::database::exit_policy::{ExitPolicy, ExitRule};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExitSide {
+    Up,
+    Down,
+}
+
+impl ExitSide {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ExitSide::Up   => "up",
+            ExitSide::Down => "down",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExitEvent {
+    /// цена вышла — запускаем ожидание
+    Armed,
+    /// цена вернулась — ожидание сброшено
+    Reset,
+    /// условия выполнены — закрываемся
+    Exit,
+}
+
+impl ExitEvent {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ExitEvent::Armed => "armed",
+            ExitEvent::Reset => "reset",
+            ExitEvent::Exit  => "exit",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExitDecision {
+    pub side:      ExitSide,
+    pub event:     ExitEvent,
+    pub price:     f64,
+    /// цена, по которой принималось решение (TWAP или текущая)
+    pub ref_price: f64,
+    pub reason:    String,
+}
+
+pub struct ExitTracker {
+    policy:      ExitPolicy,
+    min_restart: u64,
+    /// (ts_ms, price) за самое длинное окно TWAP
+    window:      VecDeque<(i64, f64)>,
+    armed:       Option<(ExitSide, i64)>,
+    confirmed:   u32,
+}
+
+impl ExitTracker {
+    pub fn new(policy: ExitPolicy, min_restart: u64) -> Self {
+        ExitTracker { policy, min_restart, window: VecDeque::new(), armed: None, confirmed: 0 }
+    }
+
+    fn rule(&self, side: ExitSide) -> &ExitRule {
+        match side {
+            ExitSide::Up   => &self.policy.up,
+            ExitSide::Down => &self.policy.down,
+        }
+    }
+
+    fn twap(&self, now_ms: i64, sec: u64) -> Option<f64> {
+        let from = now_ms - sec as i64 * 1000;
+        let (sum, n) = self.window.iter()
+            .filter(|(ts, _)| *ts >= from)
+            .fold((0.0, 0usize), |(s, n), (_, p)| (s + p, n + 1));
+        (n > 0).then(|| sum / n as f64)
+    }
+
+    /// Новый тик цены. Возвращает решение, если что-то изменилось.
+    pub fn observe(&mut self, now_ms: i64, price: f64, upper_exit: f64, lower_exit: f64) -> Option<ExitDecision> {
+        let keep = self.policy.up.twap_sec.max(self.policy.down.twap_sec) as i64 * 1000;
+        self.window.push_back((now_ms, price));
+        while self.window.front().is_some_and(|(ts, _)| *ts < now_ms - keep) {
+            self.window.pop_front();
+        }
+
+        // сторону определяет текущая цена
+        let side = if price > upper_exit {
+            ExitSide::Up
+        } else if price < lower_exit {
+            ExitSide::Down
+        } else {
+            let (side, _) = self.armed.take()?;
+            self.confirmed = 0;
+            return Some(ExitDecision {
+                side, event: ExitEvent::Reset, price, ref_price: price,
+                reason: "цена вернулась в диапазон".into(),
+            });
+        };
+        let rule = self.rule(side).clone();
+        let bound = match side { ExitSide::Up => upper_exit, ExitSide::Down => lower_exit };
+        let beyond = |p: f64| match side { ExitSide::Up => p > upper_exit, ExitSide::Down => p < lower_exit };
+
+        let newly_armed = !matches!(self.armed, Some((s, _)) if s == side);
+        if newly_armed {
+            self.armed = Some((side, now_ms));
+            self.confirmed = 0;
+        }
+        let t0 = self.armed.map(|(_, t)| t).unwrap_or(now_ms);
+
+        // 1) сильный пробой — без ожидания
+        let overshoot = (price / bound - 1.0).abs() * 100.0;
+        if rule.overshoot_pct > 0.0 && overshoot >= rule.overshoot_pct {
+            self.armed = None;
+            return Some(ExitDecision {
+                side, event: ExitEvent::Exit, price, ref_price: price,
+                reason: format!("пробой {:.2}% ≥ {:.2}%", overshoot, rule.overshoot_pct),
+            });
+        }
+
+        // 2) подтверждения по TWAP (или по текущей цене)
+        let ref_price = if rule.twap_sec > 0 {
+            self.twap(now_ms, rule.twap_sec).unwrap_or(price)
+        } else {
+            price
+        };
+        if beyond(ref_price) {
+            self.confirmed += 1;
+        } else {
+            self.confirmed = 0;
+        }
+
+        // 3) время вне диапазона
+        let wait_ms = rule.wait_min.unwrap_or(self.min_restart) as i64 * 60_000;
+        let elapsed = now_ms - t0;
+        if elapsed >= wait_ms && self.confirmed >= rule.confirmations.max(1) {
+            self.armed = None;
+            return Some(ExitDecision {
+                side, event: ExitEvent::Exit, price, ref_price,
+                reason: format!(
+                    "вне диапазона {:.1} мин, подтверждений {}{}",
+                    elapsed as f64 / 60_000.0,
+                    self.confirmed,
+                    if rule.twap_sec > 0 { format!(", TWAP {}s {:.6}", rule.twap_sec, ref_price) } else { String::new() },
+                ),
+            });
+        }
+
+        newly_armed.then(|| ExitDecision {
+            side, event: ExitEvent::Armed, price, ref_price,
+            reason: format!("вышла за {:.6}, правило: {}", bound, rule.describe()),
+        })
+    }
+
+    /// Политику поменяли на ходу: подменяем правила, начатое ожидание не
+    /// сбрасываем. `true` — политика действительно другая.
+    pub fn set_policy(&mut self, policy: ExitPolicy) -> bool {
+        if policy == self.policy {
+            return false;
+        }
+        self.policy = policy;
+        true
+    }
+
+    /// Границы сменились (перецентрирование) — начинаем с чистого листа.
+    pub fn reset(&mut self) {
+        self.armed = None;
+        self.confirmed = 0;
+    }
+}
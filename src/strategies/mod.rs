@@ -1 +1,4 @@
-pub mod limit_order;
\ No newline at end of file
+pub mod backtest;
+pub mod exit;
+pub mod limit_order;
+pub mod ranges;
\ No newline at end of file
@@ -0,0 +1,281 @@
+// src/strategies/ranges.rs
+//
+// Стратегии раскладки ликвидности по диапазонам.
+// Оркестратор берёт стратегию пула из `pool_settings` и спрашивает у неё
+// аллокации, условия входа, границы выхода и что делать после закрытия.
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::database::exit_policy::ExitPolicy;
+use crate::database::pool_settings;
+use crate::exchange::helpers::{
+    calculate_price_bounds, convert_timeframe, get_atr, range_coefficient, Candle, Mode, Unzip5,
+};
+use crate::orchestrator::calculate_range;
+use crate::params;
+use crate::types::{LiqPosition, PoolConfig, PoolPositionInfo, Range, RangeAlloc, Role};
+use crate::utils::{
+    calc_bound_prices_struct, calc_bound_prices_struct_for_two,
+    calc_range_allocation_struct, calc_range_allocation_struct_for_two,
+};
+
+/// сколько 1-минуток смотрит `entry_gate`
+pub const ENTRY_LOOKBACK_1M: usize = 30;
+const ENTRY_ATR_PER:    usize = 14;
+const ENTRY_MAX_ATR:    f64   = 0.40;
+const ENTRY_MIN_CENTRE: f64   = 0.99;
+
+/// Частичное перецентрирование: какую ногу закрыть и куда её перевыставить.
+#[derive(Debug, Clone)]
+pub struct RecenterPlan {
+    /// нога, целиком ушедшая в один токен
+    pub close_role: Role,
+    /// новая нога по другую сторону цены (display-цены)
+    pub new_role:   Role,
+    pub new_lower:  f64,
+    pub new_upper:  f64,
+    /// оставшиеся ноги меняют роли: (было, стало)
+    pub relabel:    Vec<(Role, Role)>,
+}
+
+/// Всё, что нужно стратегии, чтобы разложить капитал.
+#[derive(Debug, Clone)]
+pub struct AllocContext {
+    /// цена в том виде, в котором её видит стратегия (см. `Strategy::invert`)
+    pub price:       f64,
+    pub pct_list:    [f64; 4],
+    pub capital_usd: f64,
+    pub compress:    bool,
+    /// ширина одиночного диапазона (доля от цены)
+    pub range:       Option<f32>,
+    pub sol_usd:     f64,
+}
+
+/// Результат проверки условий входа.
+#[derive(Debug, Clone, Copy)]
+pub struct EntryGate {
+    pub ready:  bool,
+    pub atr:    f64,
+    pub centre: f64,
+}
+
+/// Что делать после закрытия позиций.
+#[derive(Debug, Clone, Copy)]
+pub struct PostClose {
+    /// true — снова ждать условий входа (auto_trade = on), false — сразу перевыставить
+    pub wait_entry:   bool,
+    /// свести излишек SOL в USDC
+    pub swap_to_usdc: bool,
+}
+
+pub trait Strategy: Send + Sync {
+    fn kind(&self) -> Range;
+
+    /// Роли диапазонов в том порядке, в каком они лежат по цене (сверху вниз).
+    fn roles(&self) -> &'static [Role];
+
+    /// true — работаем в «перевёрнутой» цене (1 / B-per-A), как RAY/SOL.
+    fn invert(&self) -> bool { false }
+
+    fn weights(&self) -> Vec<f64> { params::weights_1() }
+
+    /// Диапазоны и суммы депозитов; цены — в сырой форме «B per A» для SDK.
+    fn allocations(&self, ctx: &AllocContext) -> Result<Vec<RangeAlloc>>;
+
+    /// Условия входа по 1-минуткам (ATR на 5m + доля свечей внутри будущего диапазона).
+    fn entry_gate(&self, candles_1m: &[Candle]) -> Result<EntryGate> {
+        let (o,h,l,c,v) = candles_1m.iter()
+            .map(|c| (c.open,c.high,c.low,c.close,c.volume))
+            .unzip5();
+        let (o5,h5,l5,c5,v5) = convert_timeframe(&o,&h,&l,&c,&v,5,0);
+        let atr = get_atr(&o5,&h5,&l5,&c5,&v5,ENTRY_ATR_PER)?;
+        let atr_last = *atr.last().ok_or_else(|| anyhow!("ATR: пустой ряд"))?;
+        let last_close = *c.last().ok_or_else(|| anyhow!("нет свечей"))?;
+        let (pr_up, pr_low) = calculate_price_bounds(last_close);
+        let centre = range_coefficient(&o,&h,&l,&c, 70, pr_low, pr_up, Mode::Full)
+            .map_err(|e| anyhow!("range_coefficient: {e:?}"))?;
+
+        Ok(EntryGate {
+            ready: centre > ENTRY_MIN_CENTRE && atr_last < ENTRY_MAX_ATR,
+            atr: atr_last,
+            centre,
+        })
+    }
+
+    /// Границы выхода (display-цены): по умолчанию — огибающая всех диапазонов.
+    fn exit_bounds(&self, cfg: &PoolConfig) -> Option<(f64, f64)> {
+        let slots = [&cfg.position_1, &cfg.position_2, &cfg.position_3];
+        let filled: Vec<&LiqPosition> = slots.iter().filter_map(|p| p.as_ref()).collect();
+        if filled.is_empty() {
+            return None;
+        }
+        let upper = filled.iter().map(|p| p.upper_price).fold(f64::MIN, f64::max);
+        let lower = filled.iter().map(|p| p.lower_price).fold(f64::MAX, f64::min);
+        Some((upper, lower))
+    }
+
+    /// Правила выхода, если для пула они не заданы в `exit_policies`.
+    fn exit_policy(&self) -> ExitPolicy { ExitPolicy::default() }
+
+    fn post_close(&self, _lower_breakout: bool) -> PostClose {
+        PostClose { wait_entry: true, swap_to_usdc: true }
+    }
+
+    /// Цена ушла из центральной ноги, но ещё внутри огибающей — можно
+    /// перевыставить только дальнюю ногу. `None` — только полный выход.
+    fn recenter(&self, _cfg: &PoolConfig, _price: f64) -> Option<RecenterPlan> { None }
+
+    /// Восстановить `position_N` из уже открытых позиций
+    /// (`infos` отсортированы по lower_price сверху вниз).
+    fn adopt_existing(&self, infos: &[PoolPositionInfo], cfg: &mut PoolConfig) -> Result<()> {
+        let roles = self.roles();
+        if infos.len() != roles.len() {
+            bail!("В пуле {} найдено {} позиций, а должно быть {}", cfg.name, infos.len(), roles.len());
+        }
+        for (role, info) in roles.iter().zip(infos) {
+            set_slot(cfg, LiqPosition {
+                role: role.clone(),
+                position_address: None,
+                position_nft:     None,
+                upper_price: info.upper_price,
+                lower_price: info.lower_price,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Положить позицию в слот, соответствующий её роли.
+pub fn set_slot(cfg: &mut PoolConfig, liq: LiqPosition) {
+    match liq.role.slot() {
+        1 => cfg.position_1 = Some(liq),
+        2 => cfg.position_2 = Some(liq),
+        _ => cfg.position_3 = Some(liq),
+    }
+}
+
+// ───── 1. Три диапазона: Up / Middle / Down ────────────────────────────
+pub struct ThreeRanges;
+
+impl Strategy for ThreeRanges {
+    fn kind(&self) -> Range { Range::Three }
+
+    fn roles(&self) -> &'static [Role] { &[Role::Up, Role::Middle, Role::Down] }
+
+    fn allocations(&self, ctx: &AllocContext) -> Result<Vec<RangeAlloc>> {
+        let bounds = calc_bound_prices_struct(ctx.price, &ctx.pct_list, ctx.compress);
+        Ok(calc_range_allocation_struct(ctx.price, &bounds, &self.weights(), ctx.capital_usd, ctx.compress))
+    }
+
+    fn recenter(&self, cfg: &PoolConfig, price: f64) -> Option<RecenterPlan> {
+        let (up, mid, down) = (cfg.position_1.as_ref()?, cfg.position_2.as_ref()?, cfg.position_3.as_ref()?);
+
+        // цена должна быть внутри крайней ноги (при compress между ногами бывают зазоры)
+        if price < mid.lower_price && price < down.upper_price && price >= down.lower_price {
+            // ушли вниз: Up целиком в SOL, а новой ноге под ценой нужен только USDC —
+            // переносим Up под Down той же ширины (в %), недостающий USDC докупит свап
+            // перецентрирования (депозит — вся стоимость ноги в USDC)
+            let k = down.upper_price / down.lower_price;
+            Some(RecenterPlan {
+                close_role: Role::Up,
+                new_role:   Role::Down,
+                new_lower:  down.lower_price / k,
+                new_upper:  down.lower_price,
+                relabel:    vec![(Role::Middle, Role::Up), (Role::Down, Role::Middle)],
+            })
+        } else if price > mid.upper_price && price > up.lower_price && price <= up.upper_price {
+            // ушли вверх: Down целиком в USDC, а ноге над ценой нужен только SOL —
+            // переносим Down над Up, SOL докупит тот же свап
+            let k = up.upper_price / up.lower_price;
+            Some(RecenterPlan {
+                close_role: Role::Down,
+                new_role:   Role::Up,
+                new_lower:  up.upper_price,
+                new_upper:  up.upper_price * k,
+                relabel:    vec![(Role::Middle, Role::Down), (Role::Up, Role::Middle)],
+            })
+        } else {
+            None
+        }
+    }
+}
+
+// ───── 2. Два вложенных центральных диапазона ──────────────────────────
+pub struct TwoRanges;
+
+impl Strategy for TwoRanges {
+    fn kind(&self) -> Range { Range::Two }
+
+    fn roles(&self) -> &'static [Role] { &[Role::MiddleSmall, Role::Middle] }
+
+    fn weights(&self) -> Vec<f64> { params::weights_2() }
+
+    fn allocations(&self, ctx: &AllocContext) -> Result<Vec<RangeAlloc>> {
+        let bounds = calc_bound_prices_struct_for_two(ctx.price, &ctx.pct_list);
+        Ok(calc_range_allocation_struct_for_two(ctx.price, &bounds, &self.weights(), ctx.capital_usd))
+    }
+}
+
+// ───── 3. Один симметричный диапазон (RAY/SOL, WETH/SOL) ───────────────
+pub struct SingleRange;
+
+impl Strategy for SingleRange {
+    fn kind(&self) -> Range { Range::One }
+
+    fn roles(&self) -> &'static [Role] { &[Role::Middle] }
+
+    fn invert(&self) -> bool { true }
+
+    fn allocations(&self, ctx: &AllocContext) -> Result<Vec<RangeAlloc>> {
+        let (low_perc, high_perc) = calculate_range(ctx.range)
+            .ok_or_else(|| anyhow!("Не удалось вычислить диапазон"))?;
+        // границы в «SOL за токен-B» (display)
+        let low_disp  = low_perc * ctx.price;
+        let high_disp = high_perc * ctx.price;
+
+        // цена токена-B в USD и сколько B нужно на половину капитала
+        let tok_b_usd    = ctx.price * ctx.sol_usd;
+        let amount_tok_b = (ctx.capital_usd / 2.0) / tok_b_usd;
+
+        Ok(vec![RangeAlloc {
+            role: Role::Middle,
+            range_idx: 0,
+            usdc_amount: amount_tok_b,
+            sol_amount:  0.0,
+            usdc_equivalent: ctx.capital_usd,
+            // SDK ждёт «B за SOL»
+            upper_price: 1.0 / low_disp,
+            lower_price: 1.0 / high_disp,
+        }])
+    }
+
+    fn adopt_existing(&self, infos: &[PoolPositionInfo], cfg: &mut PoolConfig) -> Result<()> {
+        // берём первую позицию, остальные (если вдруг есть) игнорируем
+        let i = infos.first()
+            .ok_or_else(|| anyhow!("В пуле {} нет позиций", cfg.name))?;
+        cfg.position_2 = Some(LiqPosition {
+            role: Role::Middle,
+            position_address: None,
+            position_nft:     None,
+            upper_price: i.upper_price,
+            lower_price: i.lower_price,
+        });
+        Ok(())
+    }
+}
+
+pub fn strategy_for(kind: &Range) -> Box<dyn Strategy> {
+    match kind {
+        Range::Three => Box::new(ThreeRanges),
+        Range::Two   => Box::new(TwoRanges),
+        Range::One   => Box::new(SingleRange),
+    }
+}
+
+/// Стратегия пула из его настроек в БД (по умолчанию — `params::RANGE`).
+pub async fn strategy_for_pool(pool_address: &str) -> Result<Box<dyn Strategy>> {
+    let kind = pool_settings::get_pool_settings(pool_address).await?
+        .map(|s| s.strategy)
+        .unwrap_or(params::RANGE);
+    Ok(strategy_for(&kind))
+}
@@ -3,33 +3,29 @@
 use tokio::sync::mpsc::UnboundedSender;
 use crate::telegram_service::commands::Commander;
 use crate::telegram_service::tl_engine::ServiceCommand;
-use crate::params::{WETH, WBTC, WSOL, USDC};
+use crate::params::{WETH, WBTC, WSOL, USDC, USDT};
 use tokio::sync::Notify;
-use chrono::Utc;
-use crate::dex_services::{
-    wirlpool::{close_all_positions, list_positions_for_owner
-    },
-};
-use crate::strategies::limit_order;
-use crate::types::PoolConfig;
-use crate::dex_services::swap::execute_swap_tokens;
-use crate::dex_services::get_info::get_sol_price_usd;
-use spl_associated_token_account::get_associated_token_address;
+use crate::dex_services::executor::{executor, mode_tag, OwnedPosition};
+use crate::strategies::{backtest, limit_order, ranges::{strategy_for, strategy_for_pool}};
+use crate::types::{HarvestMode, HedgeCommand, PoolConfig, Range};
+use crate::exchange::hl_engine;
 use solana_sdk::pubkey::Pubkey;
 use crate::database::general_settings::{update_pct_list_2, update_pct_number};
 use orca_whirlpools_core::tick_index_to_price;
-use orca_tx_sender::Signer;
 use crate::database::triggers;
-use orca_whirlpools::PositionOrBundle;
 use crate::utils::{self, sweep_dust_to_usdc};
 use std::time::Duration;
-use std::str::FromStr;
 use std::sync::Arc;
 use crate::database::general_settings::update_info_interval;
 use crate::database::general_settings::{update_amount, get_general_settings};
-use crate::utils::utils::{init_rpc, load_wallet};
 use crate::database::triggers::Trigger;
-use crate:: dex_services::wirlpool::{increase_liquidity_partial, decrease_liquidity_partial, refresh_balances};
+use crate::database::pool_settings;
+use crate::database::exit_policy::{self, ExitPolicy, ExitRule};
+use crate::pool_registry;
+use crate::dex_services::discovery;
+use crate::dex_services::preview;
+use crate::harvest;
+use crate::orchestrator::owned_in_pool;
 
 /// Регистрация всех телеграм-команд
 pub fn register_commands(commander: Arc<Commander>, tx: UnboundedSender<ServiceCommand>, close_ntf:  Arc<Notify>) {
@@ -107,26 +103,42 @@ pub fn register_commands(commander: Arc<Commander>, tx: UnboundedSender<ServiceC
         }
     });
 
-    commander.add_command(&["auto", "off"], {
+    let auto_off_help = "[--pool] — выключить auto_trade (без параметра — во всех пулах)";
+    commander.add_command_with_help(&["auto", "off"], auto_off_help, {
         let tx = Arc::clone(&tx);
-        move |_params| {
+        move |params| {
             let tx = Arc::clone(&tx);
             async move {
-                // Выключаем флаг в БД
-                let mut t = Trigger {
-                    name: "auto_trade".into(),
-                    state: false,
-                    position: "opening".into(),
+                // Выключаем флаг в БД для каждого пула
+                let pools = match target_pools(params.first()) {
+                    Ok(p) => p,
+                    Err(e) => { let _ = tx.send(ServiceCommand::SendMessage(format!("❌ {e}"))); return; }
                 };
-                match triggers::upsert_trigger(&t).await {
-                    Ok(_) => {
+                for cfg in pools {
+                    if let Err(e) = triggers::auto_trade_switch(&cfg.name, false, Some(&tx)).await {
                         let _ = tx.send(ServiceCommand::SendMessage(
-                            "✅ Trigger `auto_trade` off".into(),
+                            format!("❌ Failed to off trigger [{}]: {}", cfg.name, e),
                         ));
                     }
-                    Err(e) => {
+                }
+            }
+        }
+    });
+
+    let auto_on_help = "[--pool] — включить auto_trade (без параметра — во всех пулах)";
+    commander.add_command_with_help(&["auto", "on"], auto_on_help, {
+        let tx = Arc::clone(&tx);
+        move |params| {
+            let tx = Arc::clone(&tx);
+            async move {
+                let pools = match target_pools(params.first()) {
+                    Ok(p) => p,
+                    Err(e) => { let _ = tx.send(ServiceCommand::SendMessage(format!("❌ {e}"))); return; }
+                };
+                for cfg in pools {
+                    if let Err(e) = triggers::auto_trade_switch(&cfg.name, true, Some(&tx)).await {
                         let _ = tx.send(ServiceCommand::SendMessage(
-                            format!("❌ Failed to on trigger: {}", e),
+                            format!("❌ Failed to enable trigger [{}]: {}", cfg.name, e),
                         ));
                     }
                 }
@@ -134,32 +146,338 @@ pub fn register_commands(commander: Arc<Commander>, tx: UnboundedSender<ServiceC
         }
     });
 
-    commander.add_command(&["auto", "on"], {
+    // ─────────── Команда pools — реестр пулов и их состояние ─────────────
+    let pools_help = "список пулов: статус, amount, pct_number, стратегия, auto_trade";
+    commander.add_command_with_help(&["pools"], pools_help, {
         let tx = Arc::clone(&tx);
         move |_params| {
             let tx = Arc::clone(&tx);
             async move {
-                let mut t = Trigger {
-                    name: "auto_trade".into(),
-                    state: true,
-                    position: "opening".into(),
+                let mut msg = String::from("🗂 Пулы:\n");
+                for cfg in pool_registry::enabled_pools() {
+                    let auto = triggers::get_pool_trigger("auto_trade", &cfg.name).await;
+                    match pool_settings::get_pool_settings(&cfg.pool_address).await {
+                        Ok(Some(ps)) => msg.push_str(&format!(
+                            "{} {} — ${:.2}, pct {}, {}, auto {}\n",
+                            if ps.enabled { "🟢" } else { "⚪" },
+                            cfg.name, ps.amount, ps.pct_number, ps.strategy.as_str(), auto.state
+                        )),
+                        Ok(None) => msg.push_str(&format!("⚠️ {} — нет настроек\n", cfg.name)),
+                        Err(e)   => msg.push_str(&format!("❌ {} — {}\n", cfg.name, e)),
+                    }
+                }
+                let _ = tx.send(ServiceCommand::SendMessage(msg));
+            }
+        }
+    });
+
+    let rank_help = "[--pool] — пулы пары по всем fee tier Whirlpool, по оценке APR комиссий";
+    commander.add_command_with_help(&["pools", "rank"], rank_help, {
+        let tx = Arc::clone(&tx);
+        move |params| {
+            let tx = Arc::clone(&tx);
+            async move {
+                let specs: Vec<&pool_registry::PoolSpec> = match params.first() {
+                    Some(name) => match pool_registry::find_spec(name) {
+                        Some(s) => vec![s],
+                        None => {
+                            let _ = tx.send(ServiceCommand::SendMessage(format!("❌ pool `{name}` не найден в реестре")));
+                            return;
+                        }
+                    },
+                    None => pool_registry::POOL_SPECS.iter().collect(),
                 };
-                match triggers::upsert_trigger(&t).await {
-                    Ok(_) => {
+                let active: Vec<String> = pool_registry::enabled_pools().into_iter().map(|c| c.pool_address).collect();
+                for spec in specs {
+                    let mut msg = format!("🏆 {} (±{}%):\n", spec.name, discovery::REF_RANGE_PCT);
+                    match discovery::rank(spec, true).await {
+                        Ok(ranked) if ranked.is_empty() => msg.push_str("пулов не найдено\n"),
+                        Ok(ranked) => {
+                            for (i, p) in ranked.iter().enumerate() {
+                                let mark = if active.contains(&p.address.to_string()) { " ◀" } else { "" };
+                                msg.push_str(&format!("{}. {}{}\n", i + 1, p.describe(), mark));
+                            }
+                        }
+                        Err(e) => msg.push_str(&format!("❌ {e:#}\n")),
+                    }
+                    let _ = tx.send(ServiceCommand::SendMessage(msg));
+                }
+            }
+        }
+    });
+
+    let pool_on_help = "--pool — включить пул";
+    commander.add_command_with_help(&["pool", "on"], pool_on_help, {
+        let tx = Arc::clone(&tx);
+        move |params| {
+            let tx = Arc::clone(&tx);
+            async move { set_pool_enabled(&tx, params.first(), true).await; }
+        }
+    });
+
+    let strategy_help = "<three|two|one> [--pool] — стратегия пула (со следующего открытия)";
+    commander.add_command_with_help(&["strategy"], strategy_help, {
+        let tx = Arc::clone(&tx);
+        move |params| {
+            let tx = Arc::clone(&tx);
+            async move {
+                let Some(kind) = params.first().and_then(|p| p.parse::<Range>().ok()) else {
+                    let _ = tx.send(ServiceCommand::SendMessage(
+                        "❌ Usage: strategy <three|two|one> [pool]".into()
+                    ));
+                    return;
+                };
+                let pools = match target_pools(params.get(1)) {
+                    Ok(p) => p,
+                    Err(e) => { let _ = tx.send(ServiceCommand::SendMessage(format!("❌ {e}"))); return; }
+                };
+                for cfg in pools {
+                    match pool_settings::update_pool_strategy(&cfg.pool_address, &kind).await {
+                        Ok(_) => { let _ = tx.send(ServiceCommand::SendMessage(
+                            format!("✅ {}: strategy = {}", cfg.name, kind.as_str()))); }
+                        Err(e) => { let _ = tx.send(ServiceCommand::SendMessage(
+                            format!("❌ {}: {}", cfg.name, e))); }
+                    }
+                }
+            }
+        }
+    });
+
+    let recenter_help = "<on|off> [--pool] — перевыставлять только дальнюю ногу вместо полного выхода";
+    commander.add_command_with_help(&["recenter"], recenter_help, {
+        let tx = Arc::clone(&tx);
+        move |params| {
+            let tx = Arc::clone(&tx);
+            async move {
+                let on = match params.first().map(|s| s.as_str()) {
+                    Some("on")  => true,
+                    Some("off") => false,
+                    _ => {
                         let _ = tx.send(ServiceCommand::SendMessage(
-                            "✅ Trigger `auto_trade` on".into(),
+                            "❌ Usage: recenter <on|off> [pool]".into()
                         ));
+                        return;
                     }
-                    Err(e) => {
+                };
+                let pools = match target_pools(params.get(1)) {
+                    Ok(p) => p,
+                    Err(e) => { let _ = tx.send(ServiceCommand::SendMessage(format!("❌ {e}"))); return; }
+                };
+                for cfg in pools {
+                    match pool_settings::update_pool_recenter(&cfg.pool_address, on).await {
+                        Ok(_) => { let _ = tx.send(ServiceCommand::SendMessage(
+                            format!("✅ {}: recenter = {}", cfg.name, if on { "on" } else { "off" }))); }
+                        Err(e) => { let _ = tx.send(ServiceCommand::SendMessage(
+                            format!("❌ {}: {}", cfg.name, e))); }
+                    }
+                }
+            }
+        }
+    });
+
+    let bundle_help = "<on|off> [--pool] — открывать диапазоны в одном position bundle (со следующего открытия)";
+    commander.add_command_with_help(&["bundle"], bundle_help, {
+        let tx = Arc::clone(&tx);
+        move |params| {
+            let tx = Arc::clone(&tx);
+            async move {
+                let on = match params.first().map(|s| s.as_str()) {
+                    Some("on")  => true,
+                    Some("off") => false,
+                    _ => {
                         let _ = tx.send(ServiceCommand::SendMessage(
-                            format!("❌ Failed to enable trigger: {}", e),
+                            "❌ Usage: bundle <on|off> [pool]".into()
                         ));
+                        return;
+                    }
+                };
+                let pools = match target_pools(params.get(1)) {
+                    Ok(p) => p,
+                    Err(e) => { let _ = tx.send(ServiceCommand::SendMessage(format!("❌ {e}"))); return; }
+                };
+                for cfg in pools {
+                    match pool_settings::update_pool_bundle(&cfg.pool_address, on).await {
+                        Ok(_) => { let _ = tx.send(ServiceCommand::SendMessage(
+                            format!("✅ {}: bundle = {}", cfg.name, if on { "on" } else { "off" }))); }
+                        Err(e) => { let _ = tx.send(ServiceCommand::SendMessage(
+                            format!("❌ {}: {}", cfg.name, e))); }
+                    }
+                }
+            }
+        }
+    });
+
+    for (word, cmd, help) in [
+        ("on",     HedgeCommand::On,     "— включить дельта-хедж SOL на Hyperliquid"),
+        ("off",    HedgeCommand::Off,    "— закрыть шорт и выключить хедж"),
+        ("status", HedgeCommand::Status, "— дельта LP + кошелька и текущий шорт"),
+    ] {
+        commander.add_command_with_help(&["hedge", word], help, {
+            let tx = Arc::clone(&tx);
+            move |_params| {
+                let tx = Arc::clone(&tx);
+                let cmd = cmd.clone();
+                async move {
+                    if !hl_engine::send(cmd) {
+                        let _ = tx.send(ServiceCommand::SendMessage("❌ Хедж-воркер не запущен".into()));
+                    }
+                }
+            }
+        });
+    }
+
+    let harvest_help = "<off|compound|reserve> [--pool] — сбор комиссий по порогу (со следующего запуска пула)";
+    commander.add_command_with_help(&["harvest"], harvest_help, {
+        let tx = Arc::clone(&tx);
+        move |params| {
+            let tx = Arc::clone(&tx);
+            async move {
+                let Some(mode) = params.first().and_then(|p| p.parse::<HarvestMode>().ok()) else {
+                    let _ = tx.send(ServiceCommand::SendMessage(
+                        "❌ Usage: harvest <off|compound|reserve> [pool]".into()
+                    ));
+                    return;
+                };
+                let pools = match target_pools(params.get(1)) {
+                    Ok(p) => p,
+                    Err(e) => { let _ = tx.send(ServiceCommand::SendMessage(format!("❌ {e}"))); return; }
+                };
+                for cfg in pools {
+                    match pool_settings::update_pool_harvest(&cfg.pool_address, &mode).await {
+                        Ok(_) => { let _ = tx.send(ServiceCommand::SendMessage(
+                            format!("✅ {}: harvest = {}", cfg.name, mode.as_str()))); }
+                        Err(e) => { let _ = tx.send(ServiceCommand::SendMessage(
+                            format!("❌ {}: {}", cfg.name, e))); }
+                    }
+                }
+            }
+        }
+    });
+
+    let rewards_help = "[--pool] — несобранные награды (emissions) по позициям";
+    commander.add_command_with_help(&["rewards"], rewards_help, {
+        let tx = Arc::clone(&tx);
+        move |params| {
+            let tx = Arc::clone(&tx);
+            async move {
+                let pools = match target_pools(params.first()) {
+                    Ok(p) => p,
+                    Err(e) => { let _ = tx.send(ServiceCommand::SendMessage(format!("❌ {e}"))); return; }
+                };
+                for cfg in pools {
+                    let whirl_pk: Pubkey = match cfg.pool_address.parse() {
+                        Ok(pk) => pk,
+                        Err(e) => { let _ = tx.send(ServiceCommand::SendMessage(format!("❌ {}: {e}", cfg.name))); continue; }
+                    };
+                    let list = match owned_in_pool(whirl_pk).await {
+                        Ok(l) => l,
+                        Err(e) => { let _ = tx.send(ServiceCommand::SendMessage(format!("❌ {}: {e}", cfg.name))); continue; }
+                    };
+                    let mut txt   = format!("{}🎁 {} — награды\n", mode_tag(), cfg.name);
+                    let mut total = 0.0;
+                    for p in &list {
+                        match executor().position_info(&cfg, p).await {
+                            Ok(i) => {
+                                for r in &i.rewards {
+                                    txt.push_str(&format!("P{} {}…: {:.6} (${:.4})\n", i.index, &r.mint[..6], r.amount, r.usd));
+                                }
+                                total += i.rewards_usd;
+                            }
+                            Err(e) => txt.push_str(&format!("⚠️ {}: {e}\n", p.mint)),
+                        }
+                    }
+                    txt.push_str(&format!("Итого: ${:.4}", total));
+                    let _ = tx.send(ServiceCommand::SendMessage(txt));
+                }
+            }
+        }
+    });
+
+    let rewards_claim_help = "[--pool] — собрать награды и свапнуть их в USDC";
+    commander.add_command_with_help(&["rewards", "claim"], rewards_claim_help, {
+        let tx = Arc::clone(&tx);
+        move |params| {
+            let tx = Arc::clone(&tx);
+            async move {
+                let pools = match target_pools(params.first()) {
+                    Ok(p) => p,
+                    Err(e) => { let _ = tx.send(ServiceCommand::SendMessage(format!("❌ {e}"))); return; }
+                };
+                for cfg in pools {
+                    if let Err(e) = harvest::claim_rewards(&cfg, &tx).await {
+                        let _ = tx.send(ServiceCommand::SendMessage(format!("❌ {}: {e}", cfg.name)));
                     }
                 }
             }
         }
     });
 
+    let exit_show_help = "[--pool] — правила выхода из диапазона";
+    commander.add_command_with_help(&["exit", "show"], exit_show_help, {
+        let tx = Arc::clone(&tx);
+        move |params| {
+            let tx = Arc::clone(&tx);
+            async move {
+                let pools = match target_pools(params.first()) {
+                    Ok(p) => p,
+                    Err(e) => { let _ = tx.send(ServiceCommand::SendMessage(format!("❌ {e}"))); return; }
+                };
+                for cfg in pools {
+                    let (p, custom) = match pool_exit_policy(&cfg.pool_address).await {
+                        Ok(v) => v,
+                        Err(e) => { let _ = tx.send(ServiceCommand::SendMessage(format!("❌ {}: {e}", cfg.name))); continue; }
+                    };
+                    let _ = tx.send(ServiceCommand::SendMessage(format!(
+                        "🚪 {}{}\n⬆️ {}\n⬇️ {}",
+                        cfg.name, if custom { "" } else { " (по умолчанию)" }, p.up.describe(), p.down.describe()
+                    )));
+                }
+            }
+        }
+    });
+
+    let exit_rule_help = "--<wait|twap|confirm|overshoot> --<value> [--pool] — правило выхода вверх/вниз";
+    for side in ["up", "down"] {
+        commander.add_command_with_help(&["exit", side], exit_rule_help, {
+            let tx = Arc::clone(&tx);
+            move |params| {
+                let tx = Arc::clone(&tx);
+                async move { set_exit_rule(&tx, side, &params).await; }
+            }
+        });
+    }
+
+    let exit_reset_help = "[--pool] — вернуть правила выхода стратегии";
+    commander.add_command_with_help(&["exit", "reset"], exit_reset_help, {
+        let tx = Arc::clone(&tx);
+        move |params| {
+            let tx = Arc::clone(&tx);
+            async move {
+                let pools = match target_pools(params.first()) {
+                    Ok(p) => p,
+                    Err(e) => { let _ = tx.send(ServiceCommand::SendMessage(format!("❌ {e}"))); return; }
+                };
+                for cfg in pools {
+                    match exit_policy::delete_exit_policy(&cfg.pool_address).await {
+                        Ok(_) => { let _ = tx.send(ServiceCommand::SendMessage(
+                            format!("✅ {}: правила выхода сброшены", cfg.name))); }
+                        Err(e) => { let _ = tx.send(ServiceCommand::SendMessage(
+                            format!("❌ {}: {}", cfg.name, e))); }
+                    }
+                }
+            }
+        }
+    });
+
+    let pool_off_help = "--pool — выключить пул (открытые позиции не трогаются)";
+    commander.add_command_with_help(&["pool", "off"], pool_off_help, {
+        let tx = Arc::clone(&tx);
+        move |params| {
+            let tx = Arc::clone(&tx);
+            async move { set_pool_enabled(&tx, params.first(), false).await; }
+        }
+    });
+
     {
         let tx = Arc::clone(&tx);
         commander.add_command(&["limit", "off"], {
@@ -223,18 +541,7 @@ pub fn register_commands(commander: Arc<Commander>, tx: UnboundedSender<ServiceC
                 };
 
                 // 2) проверяем баланс SOL
-                let rpc = init_rpc();
-                let wallet = match load_wallet() {
-                    Ok(w) => w,
-                    Err(e) => {
-                        let _ = tx.send(ServiceCommand::SendMessage(
-                            format!("❌ Wallet load failed: {}", e)
-                        ));
-                        return;
-                    }
-                };
-                let pk = wallet.pubkey();
-                let lam = match rpc.get_balance(&pk).await {
+                let sol_bal = match executor().balance(WSOL, 9).await {
                     Ok(b) => b,
                     Err(e) => {
                         let _ = tx.send(ServiceCommand::SendMessage(
@@ -243,7 +550,6 @@ pub fn register_commands(commander: Arc<Commander>, tx: UnboundedSender<ServiceC
                         return;
                     }
                 };
-                let sol_bal = lam as f64 / 1e9;
                 if sol_bal < amount {
                     let _ = tx.send(ServiceCommand::SendMessage(
                         format!("❌ Insufficient SOL: have {:.6}, need {:.6}", sol_bal, amount)
@@ -252,20 +558,22 @@ pub fn register_commands(commander: Arc<Commander>, tx: UnboundedSender<ServiceC
                 }
 
                 // 3) выполняем swap
-                match execute_swap_tokens(WSOL, USDC, amount).await {
+                let (res, previews) = preview::collect(executor().swap(WSOL, USDC, amount)).await;
+                let sim = preview::format_collected(&previews);
+                match res {
                     Ok(res) => {
                         let _ = tx.send(ServiceCommand::SendMessage(
                             format!(
-                                "✅ Swapped {:.6} SOL → USDC\n\
+                                "{}✅ Swapped {:.6} SOL → USDC\n\
                                  New SOL balance: {:.6}\n\
-                                 New USDC balance: {:.6}",
-                                amount, res.balance_sell, res.balance_buy
+                                 New USDC balance: {:.6}{}",
+                                mode_tag(), amount, res.balance_sell, res.balance_buy, sim
                             )
                         ));
                     }
                     Err(e) => {
                         let _ = tx.send(ServiceCommand::SendMessage(
-                            format!("❌ Swap failed: {}", e)
+                            format!("❌ Swap failed: {}{}", e, sim)
                         ));
                     }
                 }
@@ -298,23 +606,7 @@ pub fn register_commands(commander: Arc<Commander>, tx: UnboundedSender<ServiceC
                 };
 
                 // 2) проверяем баланс USDC
-                let rpc = init_rpc();
-                let wallet = match load_wallet() {
-                    Ok(w) => w,
-                    Err(e) => {
-                        let _ = tx.send(ServiceCommand::SendMessage(
-                            format!("❌ Wallet load failed: {}", e)
-                        ));
-                        return;
-                    }
-                };
-                let pk = wallet.pubkey();
-                let mint = Pubkey::from_str(USDC).unwrap();
-                let ata = get_associated_token_address(&pk, &mint);
-                let usdc_bal = match rpc.get_token_account_balance(&ata).await {
-                    Ok(bal) => bal.amount.parse::<f64>().unwrap_or(0.0) / 1e6,
-                    Err(_)    => 0.0,
-                };
+                let usdc_bal = executor().balance(USDC, 6).await.unwrap_or(0.0);
                 if usdc_bal < amount {
                     let _ = tx.send(ServiceCommand::SendMessage(
                         format!("❌ Insufficient USDC: have {:.6}, need {:.6}", usdc_bal, amount)
@@ -323,20 +615,22 @@ pub fn register_commands(commander: Arc<Commander>, tx: UnboundedSender<ServiceC
                 }
 
                 // 3) выполняем swap
-                match execute_swap_tokens(USDC, WSOL, amount).await {
+                let (res, previews) = preview::collect(executor().swap(USDC, WSOL, amount)).await;
+                let sim = preview::format_collected(&previews);
+                match res {
                     Ok(res) => {
                         let _ = tx.send(ServiceCommand::SendMessage(
                             format!(
-                                "✅ Swapped {:.6} USDC → SOL\n\
+                                "{}✅ Swapped {:.6} USDC → SOL\n\
                                  New USDC balance: {:.6}\n\
-                                 New SOL balance: {:.6}",
-                                amount, res.balance_sell, res.balance_buy
+                                 New SOL balance: {:.6}{}",
+                                mode_tag(), amount, res.balance_sell, res.balance_buy, sim
                             )
                         ));
                     }
                     Err(e) => {
                         let _ = tx.send(ServiceCommand::SendMessage(
-                            format!("❌ Swap failed: {}", e)
+                            format!("❌ Swap failed: {}{}", e, sim)
                         ));
                     }
                 }
@@ -383,23 +677,22 @@ pub fn register_commands(commander: Arc<Commander>, tx: UnboundedSender<ServiceC
             let tx = Arc::clone(&tx);
             let close_ntf = close_ntf.clone(); 
             async move {
-                let _ = triggers::auto_trade_switch(true, Some(&tx)).await;
-                let _ =triggers::opening_switcher(true, Some(&tx)).await;
+                for cfg in pool_registry::enabled_pools() {
+                    let _ = triggers::auto_trade_switch(&cfg.name, true, Some(&tx)).await;
+                    let _ = triggers::opening_switcher(&cfg.name, true, Some(&tx)).await;
+                }
                 // мгновенно информируем пользователя
                 let _ = tx.send(ServiceCommand::SendMessage(
                     "🔒 Начинаем закрывать ВСЕ позиции…".into(),
                 ));
-                let mut t = Trigger {
-                    name: "auto_trade".into(),
-                    state: true,
-                    position: "opening".into(),
-                };
     
                 // всё тяжёлое – в фоне
                 let tx_bg = Arc::clone(&tx);
                 tokio::spawn(async move {
-                    if let Err(err) = close_all_positions(300, None).await {
-                        let t = triggers::auto_trade_switch(true, Some(&tx)).await;
+                    if let Err(err) = executor().close_all(300, None).await {
+                        for cfg in pool_registry::enabled_pools() {
+                            let _ = triggers::auto_trade_switch(&cfg.name, true, Some(&tx)).await;
+                        }
                         let _ = tx_bg.send(ServiceCommand::SendMessage(
                             format!("❌ Ошибка при закрытии позиций: {err:?}"),
                         ));
@@ -413,7 +706,7 @@ pub fn register_commands(commander: Arc<Commander>, tx: UnboundedSender<ServiceC
                     tokio::time::sleep(Duration::from_secs(2)).await;
                     close_ntf.notify_waiters();
     
-                    match list_positions_for_owner(None).await {
+                    match executor().list_positions(None).await {
                         Ok(positions) if positions.is_empty() => {
                             tokio::time::sleep(Duration::from_secs(10)).await;
                             match utils::swap_excess_to_usdc(WSOL, 9, 0.10).await {
@@ -426,21 +719,16 @@ pub fn register_commands(commander: Arc<Commander>, tx: UnboundedSender<ServiceC
                             ));
 
 
-                            let t = triggers::auto_trade_switch(true, Some(&tx)).await;
+                            for cfg in pool_registry::enabled_pools() {
+                                let _ = triggers::auto_trade_switch(&cfg.name, true, Some(&tx)).await;
+                            }
                             tokio::time::sleep(Duration::from_secs(10)).await;
 
                         }
                         Ok(positions) => {
                             let mut msg = String::from("⚠️ Остались незакрытые позиции:\n");
                             for p in positions {
-                                match p {
-                                    PositionOrBundle::Position(hp) =>
-                                        msg.push_str(&format!("- mint: {}\n",
-                                            hp.data.position_mint)),
-                                    PositionOrBundle::PositionBundle(pb) =>
-                                        msg.push_str(&format!("- bundle account: {}\n",
-                                            pb.address)),
-                                }
+                                msg.push_str(&format!("- mint: {}\n", p.mint));
                             }
                             let _ = tx_bg.send(ServiceCommand::SendMessage(msg));
                         }
@@ -473,21 +761,23 @@ pub fn register_commands(commander: Arc<Commander>, tx: UnboundedSender<ServiceC
                 // всё тяжёлое – в фоне
                 let tx_bg = Arc::clone(&tx);
                 tokio::spawn(async move {
-                    if let Err(err) = close_all_positions(300, None).await {
+                    let (res, previews) = preview::collect(executor().close_all(300, None)).await;
+                    let sim = preview::format_collected(&previews);
+                    if let Err(err) = res {
                         let _ = tx_bg.send(ServiceCommand::SendMessage(
-                            format!("❌ Ошибка при закрытии позиций: {err:?}"),
+                            format!("❌ Ошибка при закрытии позиций: {err:?}{sim}"),
                         ));
                         return;
                     }
     
                     let _ = tx_bg.send(ServiceCommand::SendMessage(
-                        "✅ Запросы отправлены, ждём подтверждений…".into(),
+                        format!("✅ Запросы отправлены, ждём подтверждений…{sim}"),
                     ));
     
                     tokio::time::sleep(Duration::from_secs(2)).await;
                     close_ntf.notify_waiters();
     
-                    match list_positions_for_owner(None).await {
+                    match executor().list_positions(None).await {
                         Ok(positions) if positions.is_empty() => {
                             tokio::time::sleep(Duration::from_secs(10)).await;
                             match utils::swap_excess_to_usdc(WSOL, 9, 0.10).await {
@@ -503,14 +793,7 @@ pub fn register_commands(commander: Arc<Commander>, tx: UnboundedSender<ServiceC
                         Ok(positions) => {
                             let mut msg = String::from("⚠️ Остались незакрытые позиции:\n");
                             for p in positions {
-                                match p {
-                                    PositionOrBundle::Position(hp) =>
-                                        msg.push_str(&format!("- mint: {}\n",
-                                            hp.data.position_mint)),
-                                    PositionOrBundle::PositionBundle(pb) =>
-                                        msg.push_str(&format!("- bundle account: {}\n",
-                                            pb.address)),
-                                }
+                                msg.push_str(&format!("- mint: {}\n", p.mint));
                             }
                             let _ = tx_bg.send(ServiceCommand::SendMessage(msg));
                         }
@@ -541,37 +824,30 @@ pub fn register_commands(commander: Arc<Commander>, tx: UnboundedSender<ServiceC
                 // тяжёлую работу + завершение — в фоне
                 let tx_bg = Arc::clone(&tx);
                 tokio::spawn(async move {
-                    if let Err(err) = close_all_positions(300, None).await {
+                    if let Err(err) = executor().close_all(300, None).await {
                         let _ = tx_bg.send(ServiceCommand::SendMessage(
                             format!("❌ Ошибка при закрытии позиций: {err:?}"),
                         ));
                     } else {
-                        let rpc = &utils::utils::init_rpc();
-                        let payer = match utils::utils::load_wallet() {
-                            Ok(kp) => kp,
-                            Err(e) => {
-                                let _ = tx_bg.send(ServiceCommand::SendMessage(
-                                    format!("❌ Не удалось загрузить кошелёк: {e}"),
-                                ));
-                                return; // прекращаем работу в этом фоне
-                            }
-                        };
-                        let wallet: Pubkey  = payer.pubkey();
                         const TOKENS: [(&str, u8); 2] = [
                             (WSOL, 9),
                             (USDC, 6),
                         ];
-                        let balances = match utils::balances_for_mints(&rpc, &wallet, &TOKENS).await {
-                            Ok(v) => v,
-                            Err(e) => {
-                                let _ = tx_bg.send(ServiceCommand::SendMessage(
-                                    format!("❌ Ошибка при получении балансов: {e}"),
-                                ));
-                                return;
+                        let mut balances = Vec::new();
+                        for (mint, dec) in TOKENS {
+                            match executor().balance(mint, dec).await {
+                                Ok(bal) if bal > 0.0 => balances.push((mint, dec, bal)),
+                                Ok(_) => {}
+                                Err(e) => {
+                                    let _ = tx_bg.send(ServiceCommand::SendMessage(
+                                        format!("❌ Ошибка при получении балансов: {e}"),
+                                    ));
+                                    return;
+                                }
                             }
-                        };
+                        }
 
-                        let mut report = String::from("✅ Позиции закрыты. Текущие балансы:\n");
+                        let mut report = format!("{}✅ Позиции закрыты. Текущие балансы:\n", mode_tag());
                         if balances.is_empty() {
                             report.push_str("  — все остатки равны нулю.\n");
                         } else {
@@ -642,13 +918,14 @@ pub fn register_commands(commander: Arc<Commander>, tx: UnboundedSender<ServiceC
         move |_params| {
             let tx = Arc::clone(&tx);
             async move {
-                match utils::fetch_wallet_balance_info().await {
+                match executor().wallet_balance().await {
                     Ok(info) => {
                         let msg = format!(
-                            "🏦 Баланс кошелька:\n\
+                            "{}🏦 Баланс кошелька:\n\
                              ► SOL: {:.4} (~${:.2})\n\
                              ► USDC: {:.4} (~${:.2})\n\
                              ► Всего: ≈ ${:.2}",
+                            mode_tag(),
                             info.sol_balance, info.sol_in_usd,
                             info.usdc_balance, info.usdc_in_usd,
                             info.total_usd
@@ -678,7 +955,7 @@ pub fn register_commands(commander: Arc<Commander>, tx: UnboundedSender<ServiceC
                         // 2) вычисляем новый номер
                         let new = if cfg.pct_number == 1 { 2 } else { 1 };
                         // 3) сохраняем
-                        if let Err(e) = update_pct_number(new).await {
+                        if let Err(e) = set_pct_number_everywhere(new).await {
                             let _ = tx.send(ServiceCommand::SendMessage(
                                 format!("❌ Failed to update pct_number: {}", e)
                             ));
@@ -740,7 +1017,7 @@ pub fn register_commands(commander: Arc<Commander>, tx: UnboundedSender<ServiceC
                     return;
                 }
                 // переключаем active pct_number = 2
-                if let Err(e) = update_pct_number(2).await {
+                if let Err(e) = set_pct_number_everywhere(2).await {
                     let _ = tx.send(ServiceCommand::SendMessage(
                         format!("❌ Failed to set pct_number: {}", e)
                     ));
@@ -755,16 +1032,16 @@ pub fn register_commands(commander: Arc<Commander>, tx: UnboundedSender<ServiceC
     });
 
     // ─────────── Команда amount <float> ────────────────────────────────
-    let amount_help = "<float> — установить базовый amount";
+    let amount_help = "<float> [--pool] — установить amount (без пула — базовый и во всех пулах)";
     commander.add_command_with_help(&["amount"], amount_help, {
         let tx = Arc::clone(&tx);
         move |params| {
             let tx = Arc::clone(&tx);
             async move {
-                // проверяем, что ровно один аргумент
-                if params.len() != 1 {
+                // проверяем, что один аргумент (+ опционально пул)
+                if params.is_empty() || params.len() > 2 {
                     let _ = tx.send(ServiceCommand::SendMessage(
-                        "❌ Usage: amount <float>".into()
+                        "❌ Usage: amount <float> [pool]".into()
                     ));
                     return;
                 }
@@ -778,31 +1055,46 @@ pub fn register_commands(commander: Arc<Commander>, tx: UnboundedSender<ServiceC
                         return;
                     }
                 };
+                let pools = match target_pools(params.get(1)) {
+                    Ok(p) => p,
+                    Err(e) => { let _ = tx.send(ServiceCommand::SendMessage(format!("❌ {e}"))); return; }
+                };
                 // сохраняем в БД
-                if let Err(e) = update_amount(val).await {
-                    let _ = tx.send(ServiceCommand::SendMessage(
-                        format!("❌ Не удалось обновить amount: {}", e)
-                    ));
-                    return;
+                if params.len() == 1 {
+                    if let Err(e) = update_amount(val).await {
+                        let _ = tx.send(ServiceCommand::SendMessage(
+                            format!("❌ Не удалось обновить amount: {}", e)
+                        ));
+                        return;
+                    }
+                }
+                for cfg in &pools {
+                    if let Err(e) = pool_settings::update_pool_amount(&cfg.pool_address, val).await {
+                        let _ = tx.send(ServiceCommand::SendMessage(
+                            format!("❌ Не удалось обновить amount [{}]: {}", cfg.name, e)
+                        ));
+                        return;
+                    }
                 }
+                let names: Vec<&str> = pools.iter().map(|c| c.name.as_str()).collect();
                 let _ = tx.send(ServiceCommand::SendMessage(
-                    format!("✅ amount успешно установлен в {}", val)
+                    format!("✅ amount успешно установлен в {} ({})", val, names.join(", "))
                 ));
             }
         }
     });
 
     // ─────────── Команда mliq <pos> <pct> — вывести часть ликвидности ─────────────
-    let mliq_help = "<int> — номер позиции (1–3), <float> — % ликвидности для вывода";
+    let mliq_help = "<int> — номер позиции (1–3), <float> — % ликвидности для вывода, [pool]";
     commander.add_command_with_help(&["mliq"], mliq_help, {
         let tx = Arc::clone(&tx);
         move |params| {
             let tx = Arc::clone(&tx);
             async move {
                 // 1) Проверяем аргументы
-                if params.len() != 2 {
+                if !(2..=3).contains(&params.len()) {
                     let _ = tx.send(ServiceCommand::SendMessage(
-                        "❌ Usage: mliq <position_index> <percent>".into()
+                        "❌ Usage: mliq <position_index> <percent> [pool]".into()
                     ));
                     return;
                 }
@@ -824,54 +1116,43 @@ pub fn register_commands(commander: Arc<Commander>, tx: UnboundedSender<ServiceC
                         return;
                     }
                 };
-                // 2) Загружаем RPC и позиции
-                let rpc = init_rpc();
-                let list = match list_positions_for_owner(None).await {
-                    Ok(v) if !v.is_empty() => v,
-                    Ok(_) => {
-                        let _ = tx.send(ServiceCommand::SendMessage(
-                            "⚠️ У вас нет открытых позиций.".into()
-                        ));
-                        return;
-                    }
+                // 2) Позиции пула сверху вниз
+                let (cfg, list) = match pool_positions(params.get(2)).await {
+                    Ok(v) => v,
                     Err(e) => {
-                        let _ = tx.send(ServiceCommand::SendMessage(
-                            format!("❌ Не удалось получить позиции: {}", e)
-                        ));
+                        let _ = tx.send(ServiceCommand::SendMessage(format!("❌ {e}")));
                         return;
                     }
                 };
-                // 3) Собираем вектор (mint, upper_price) и сортируем
-                let mut info = Vec::new();
-                for p in &list {
-                    if let PositionOrBundle::Position(hp) = p {
-                        let up = tick_index_to_price(
-                            hp.data.tick_upper_index,
-                            /* dec_a = */ 9,
-                            /* dec_b = */ 6,
-                        );
-                        info.push((hp.data.position_mint, up));
-                    }
-                }
-                info.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-                if pos_idx > info.len() {
+                if pos_idx > list.len() {
                     let _ = tx.send(ServiceCommand::SendMessage(
-                        format!("❌ Всего {} позиций, позиция {} отсутствует", info.len(), pos_idx)
+                        format!("❌ Всего {} позиций, позиция {} отсутствует", list.len(), pos_idx)
                     ));
                     return;
                 }
-                let mint = info[pos_idx - 1].0;
+                let pos  = &list[pos_idx - 1];
+                let mint = pos.mint;
+                // 3) сколько придёт — по точной квоте вывода
+                let estimate = match withdraw_quote(&cfg, pos, pct).await {
+                    Ok((a, b)) => format!(" (≈ {a:.6} / {b:.6} {})", cfg.name),
+                    Err(e) => {
+                        log::warn!("mliq: квота вывода не посчитана: {e:#}");
+                        String::new()
+                    }
+                };
                 let _ = tx.send(ServiceCommand::SendMessage(
-                    format!("🔄 Снимаю {:.2}% ликвидности из позиции {}", pct, pos_idx)
+                    format!("🔄 Снимаю {:.2}% ликвидности из позиции {}{}", pct, pos_idx, estimate)
                 ));
                 // 4) Вызываем decrease_liquidity_partial
-                if let Err(e) = decrease_liquidity_partial(mint, pct, 500).await {
+                let (res, previews) = preview::collect(executor().decrease_liquidity(mint, pct, 500)).await;
+                let sim = preview::format_collected(&previews);
+                if let Err(e) = res {
                     let _ = tx.send(ServiceCommand::SendMessage(
-                        format!("❌ Не удалось снять ликвидность: {}", e)
+                        format!("❌ Не удалось снять ликвидность: {}{}", e, sim)
                     ));
                 } else {
                     let _ = tx.send(ServiceCommand::SendMessage(
-                        "✅ Ликвидность снята".into()
+                        format!("✅ Ликвидность снята{}", sim)
                     ));
                 }
             }
@@ -879,16 +1160,16 @@ pub fn register_commands(commander: Arc<Commander>, tx: UnboundedSender<ServiceC
     });
     
     // ─────────── Команда pliq <pos> <usd> — добавить ликвидность на сумму в USD ────────────
-    let pliq_help = "<int> — номер позиции (1–3), <float> — сумма в USD для добавления ликвидности";
+    let pliq_help = "<int> — номер позиции (1–3), <float> — сумма в USD для добавления ликвидности, [pool]";
     commander.add_command_with_help(&["pliq"], pliq_help, {
         let tx = Arc::clone(&tx);
         move |params| {
             let tx = Arc::clone(&tx);
             async move {
                 // 1) Разбор аргументов ──────────────────────────────────────
-                if params.len() != 2 {
+                if !(2..=3).contains(&params.len()) {
                     let _ = tx.send(ServiceCommand::SendMessage(
-                        "❌ Usage: pliq <position_index> <usd_amount>".into()
+                        "❌ Usage: pliq <position_index> <usd_amount> [pool]".into()
                     ));
                     return;
                 }
@@ -911,79 +1192,38 @@ pub fn register_commands(commander: Arc<Commander>, tx: UnboundedSender<ServiceC
                     }
                 };
 
-                // 2) Список позиций владельца ───────────────────────────────
-                let list = match list_positions_for_owner(None).await {
-                    Ok(v) if !v.is_empty() => v,
-                    Ok(_) => {
-                        let _ = tx.send(ServiceCommand::SendMessage(
-                            "⚠️ У вас нет открытых позиций.".into()
-                        ));
-                        return;
-                    }
+                // 2) Позиции пула «сверху → вниз» и нужная из них ─────────────
+                let (pool_cfg, list) = match pool_positions(params.get(2)).await {
+                    Ok(v) => v,
                     Err(e) => {
-                        let _ = tx.send(ServiceCommand::SendMessage(
-                            format!("❌ Failed to fetch positions: {e}")
-                        ));
+                        let _ = tx.send(ServiceCommand::SendMessage(format!("❌ {e}")));
                         return;
                     }
                 };
-
-                // 3) Сортируем «сверху → вниз» по upper-price и берём нужную позицию
-                let mut info = Vec::<(Pubkey, f64)>::new();
-                for p in &list {
-                    if let PositionOrBundle::Position(hp) = p {
-                        let up = tick_index_to_price(hp.data.tick_upper_index, 9, 6);
-                        info.push((hp.data.position_mint, up));
-                    }
-                }
-                info.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-
-                if pos_idx > info.len() {
+                if pos_idx > list.len() {
                     let _ = tx.send(ServiceCommand::SendMessage(
-                        format!("❌ Only {} positions; index {} is out of range", info.len(), pos_idx)
+                        format!("❌ Only {} positions; index {} is out of range", list.len(), pos_idx)
                     ));
                     return;
                 }
-                let mint = info[pos_idx - 1].0;
+                let mint = list[pos_idx - 1].mint;
 
-                // 4) Быстрая проверка, хватает ли общего капитала под указанный бюджет
-                let rpc    = init_rpc();
-                let wallet = match load_wallet() {
-                    Ok(w) => w,
-                    Err(e) => {
-                        let _ = tx.send(ServiceCommand::SendMessage(
-                            format!("❌ Wallet load failed: {e}")
-                        ));
-                        return;
-                    }
-                };
-                let pk = wallet.pubkey();
-
-                // свободный SOL
-                let sol_free = rpc.get_balance(&pk).await.unwrap_or(0) as f64 / 1e9;
-                // свободный USDC
-                let mint_b  = Pubkey::from_str(USDC).unwrap();
-                let ata_b   = get_associated_token_address(&pk, &mint_b);
-                let usdc_free = rpc
-                    .get_token_account_balance(&ata_b).await
-                    .ok()
-                    .and_then(|ui| ui.amount.parse::<u64>().ok())
-                    .map(|atoms| atoms as f64 / 1e6)
-                    .unwrap_or(0.0);
-
-                // цена SOL/USDC
-                let sol_usd = match get_sol_price_usd().await {
+                // 3) Быстрая проверка, хватает ли общего капитала под указанный бюджет
+                let (usd_a, usd_b) = match tokens_usd(&pool_cfg).await {
                     Ok(p) => p,
                     Err(e) => {
                         let _ = tx.send(ServiceCommand::SendMessage(
-                            format!("❌ Failed to fetch SOL/USD price: {e}")
+                            format!("❌ Failed to fetch {} prices: {e}", pool_cfg.name)
                         ));
                         return;
                     }
                 };
+                let free = |mint: &str, bal: f64| if mint == WSOL { (bal - 0.07).max(0.0) } else { bal.max(0.0) };
+                let free_a = executor().balance(&pool_cfg.mint_a, pool_cfg.decimal_a as u8).await.unwrap_or(0.0);
+                let free_b = executor().balance(&pool_cfg.mint_b, pool_cfg.decimal_b as u8).await.unwrap_or(0.0);
 
                 // запас 12 % уже внутри increase_liquidity_partial, но сделаем грубый pre-check
-                let total_usd_free = usdc_free + (sol_free.max(0.0) - 0.07).max(0.0) * sol_usd;
+                let total_usd_free = free(&pool_cfg.mint_a, free_a) * usd_a + free(&pool_cfg.mint_b, free_b) * usd_b;
                 if total_usd_free < usd_amount * 1.05 {
                     let _ = tx.send(ServiceCommand::SendMessage(
                         format!(
@@ -994,43 +1234,23 @@ pub fn register_commands(commander: Arc<Commander>, tx: UnboundedSender<ServiceC
                     ));
                 }
 
-                // 5) Формируем PoolConfig (SOL/USDC Whirlpool)
-                let pool_cfg = PoolConfig {
-                    program:      "whirlpool".to_owned(),
-                    name:         "SOL/USDC".to_owned(),
-                    pool_address: std::env::var("SOLUSDC_POOL").expect("SOLUSDC_POOL not set"),
-                    mint_a:       WSOL.to_owned(),
-                    mint_b:       USDC.to_owned(),
-                    decimal_a:    9,
-                    decimal_b:    6,
-                    amount:       0.0,
-                    position_1:   None,
-                    position_2:   None,
-                    position_3:   None,
-                    date_opened:           Utc::now(),
-                    is_closed:             false,
-                    commission_collected_1: 0.0,
-                    commission_collected_2: 0.0,
-                    commission_collected_3: 0.0,
-                    total_value_open:      0.0,
-                    total_value_current:   0.0,
-                    wallet_balance: 0.0
-                };
-
                 let _ = tx.send(ServiceCommand::SendMessage(
-                    format!("🔄 Добавляю ${:.2} в позицию {}", usd_amount, pos_idx)
+                    format!("🔄 Добавляю ${:.2} в позицию {} ({})", usd_amount, pos_idx, pool_cfg.name)
                 ));
 
-                // 6) Добавляем ликвидность (теперь функция принимает *только* USD-бюджет)
-                match increase_liquidity_partial(mint, usd_amount, &pool_cfg, 500).await {
+                // 4) Добавляем ликвидность (теперь функция принимает *только* USD-бюджет)
+                let (res, previews) =
+                    preview::collect(executor().increase_liquidity(mint, usd_amount, &pool_cfg, 500)).await;
+                let sim = preview::format_collected(&previews);
+                match res {
                     Ok(_) => {
                         let _ = tx.send(ServiceCommand::SendMessage(
-                            "✅ Ликвидность добавлена".into()
+                            format!("✅ Ликвидность добавлена{sim}")
                         ));
                     }
                     Err(e) => {
                         let _ = tx.send(ServiceCommand::SendMessage(
-                            format!("❌ increase_liquidity failed: {e}")
+                            format!("❌ increase_liquidity failed: {e}{sim}")
                         ));
                     }
                 }
@@ -1060,6 +1280,18 @@ pub fn register_commands(commander: Arc<Commander>, tx: UnboundedSender<ServiceC
         }
     });
 
+    let bt_help = "<days> <apr%> [three|two|one] [pool] — бэктест стратегии на 1m-свечах SOLUSDT Binance (только SOL/USD-пулы)";
+    commander.add_command_with_help(&["bt"], bt_help, {
+        let tx = Arc::clone(&tx);
+        move |params| {
+            let tx = Arc::clone(&tx);
+            async move {
+                if let Err(e) = run_backtest_command(&params, &tx).await {
+                    let _ = tx.send(ServiceCommand::SendMessage(format!("❌ bt: {e:#}")));
+                }
+            }
+        }
+    });
 
 }
 
@@ -1069,14 +1301,10 @@ pub async fn run_inc_command(
     params: Vec<String>,
     tx:     std::sync::Arc<tokio::sync::mpsc::UnboundedSender<ServiceCommand>>,
 ) {
-    use crate::params::{WSOL, USDC, OVR};
-    use crate::types::PoolConfig;
-    use crate::utils::utils::{init_rpc, load_wallet};
-
     // ── 0. Парсим аргументы ────────────────────────────────────────────────
     if params.len() < 3 {
         let _ = tx.send(ServiceCommand::SendMessage(
-            "Использование: inc --<from> --<to> --<pct>".into(),
+            "Использование: inc --<from> --<to> --<pct> [--pool]".into(),
         ));
         return;
     }
@@ -1097,115 +1325,71 @@ pub async fn run_inc_command(
         return;
     }
 
-    // ── 1. Берём три позиции владельца ─────────────────────────────────────
-    let list = match list_positions_for_owner(None).await {
-        Ok(v) if v.len() == 3 => v,
-        Ok(v) => {
-            let _ = tx.send(ServiceCommand::SendMessage(
-                format!("Ожидалось 3 позиции, найдено {}", v.len()),
-            ));
-            return;
-        }
+    // ── 1-3. Позиции пула «сверху → вниз» (по верхнему ценовому пределу) ──
+    let (pool_cfg, list) = match pool_positions(params.get(3)).await {
+        Ok(v) => v,
         Err(e) => {
-            let _ = tx.send(ServiceCommand::SendMessage(
-                format!("Не удалось получить позиции: {e}"),
-            ));
+            let _ = tx.send(ServiceCommand::SendMessage(format!("❌ {e}")));
             return;
         }
     };
-
-    // ── 2. Сортируем «сверху → вниз» (по верхнему ценовому пределу) ────────
-    let mut info = Vec::<(Pubkey, f64)>::new();
-    for p in &list {
-        if let PositionOrBundle::Position(hp) = p {
-            let up = upper_price(hp.data.tick_upper_index, 9, 6); // SOL/USDC
-            info.push((hp.data.position_mint, up));
-        }
+    if from_idx.max(to_idx) > list.len() {
+        let _ = tx.send(ServiceCommand::SendMessage(
+            format!("В {} всего {} позиций", pool_cfg.name, list.len()),
+        ));
+        return;
     }
-    info.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-
-    let mint_from = info[from_idx - 1].0;
-    let mint_to   = info[to_idx   - 1].0;
-
-    // ── 3. Приготовим PoolConfig (нужен increase_…) ────────────────────────
-    let whirl_pk = if let PositionOrBundle::Position(hp) = list.first().unwrap() {
-        hp.data.whirlpool
-    } else { unreachable!() };
-
-    let pool_cfg = PoolConfig {
-        amount: 0.0,
-        program: "".to_string(),
-        name: "SOL/USDC".to_string(),
-        pool_address: whirl_pk.to_string(),
-        position_1: None, position_2: None, position_3: None,
-        mint_a: WSOL.to_string(), mint_b: USDC.to_string(),
-        decimal_a: 9, decimal_b: 6,
-
-        date_opened:           Utc::now(),     // или любая другая заглушечная дата
-        is_closed:             false,          // позиция ещё не закрыта
-        commission_collected_1: 0.0,            // пока комиссий нет
-        commission_collected_2: 0.0,
-        commission_collected_3: 0.0,
-        total_value_open:      0.0,            // либо исходная TVL
-        total_value_current:   0.0,            // либо текущее TVL
-        wallet_balance: 0.0
-    };
 
-    // ── 4. Замеряем балансы «до» ───────────────────────────────────────────
-    let rpc    = init_rpc();
-    let wallet = load_wallet().unwrap();
-    let dec_b  = 6u8;
+    let pos_from  = &list[from_idx - 1];
+    let mint_from = pos_from.mint;
+    let mint_to   = list[to_idx - 1].mint;
 
-    let (sol_before, usdc_before) = refresh_balances(
-        &rpc, &wallet.pubkey(), &Pubkey::from_str(USDC).unwrap(), dec_b,
-    )
-    .await
-    .expect("refresh_balances failed");
+    // ── 4. Сколько освободится — по точной квоте вывода ────────────────────
+    //    (дельта балансов врёт: в SOL-балансе сидят ещё комиссия сети и рента)
+    let (freed_a, freed_b) = match withdraw_quote(&pool_cfg, pos_from, pct).await {
+        Ok(v) => v,
+        Err(e) => {
+            let _ = tx.send(ServiceCommand::SendMessage(format!("❌ квота вывода: {e}")));
+            return;
+        }
+    };
 
     // ── 5. Снимаем часть ликвидности из исходной позиции ───────────────────
     let _ = tx.send(ServiceCommand::SendMessage(
         format!("🔄 Снимаю {pct:.2}% ликвидности из позиции {from_idx}"),
     ));
-    if let Err(e) = decrease_liquidity_partial(mint_from, pct, 500).await {
+    if let Err(e) = executor().decrease_liquidity(mint_from, pct, 500).await {
         let _ = tx.send(ServiceCommand::SendMessage(format!("❌ decrease_liquidity: {e}")));
         return;
     }
 
-    // ── 6. Замеряем балансы «после» и считаем дельту ───────────────────────
-    let (sol_after, usdc_after) = refresh_balances(
-        &rpc, &wallet.pubkey(), &Pubkey::from_str(USDC).unwrap(), dec_b,
-    )
-    .await
-    .expect("refresh_balances failed");
-
-    let freed_sol  = (sol_after  - sol_before ).max(0.0);
-    let freed_usdc = (usdc_after - usdc_before).max(0.0);
+    // ── 6. Проверяем, что вывод что-то дал ─────────────────────────────────
 
-    if freed_sol + freed_usdc < 1e-9 {
+    if freed_a + freed_b < 1e-9 {
         let _ = tx.send(ServiceCommand::SendMessage(
             "⚠️ Ничего не освободилось – операция прекращена".into(),
         ));
         return;
     }
     let _ = tx.send(ServiceCommand::SendMessage(format!(
-        "➕ Свободно ≈ {:.6} SOL  /  {:.6} USDC. Добавляю в позицию {to_idx}",
-        freed_sol, freed_usdc
+        "➕ Свободно ≈ {:.6} / {:.6} {}. Добавляю в позицию {to_idx}",
+        freed_a, freed_b, pool_cfg.name
     )));
 
     // ── 7. Конвертируем освобождённое в USD‐бюджет ──────────────────────
-    let sol_price_usd = match get_sol_price_usd().await {
+    let (usd_a, usd_b) = match tokens_usd(&pool_cfg).await {
         Ok(p) => p,
         Err(e) => {
             let _ = tx.send(ServiceCommand::SendMessage(
-                format!("Не удалось получить цену SOL: {e}"),
+                format!("Не удалось получить цены {}: {e}", pool_cfg.name),
             ));
             return;
         }
     };
-    let usd_budget = freed_usdc + freed_sol * sol_price_usd;
+    let usd_budget = freed_a * usd_a + freed_b * usd_b;
 
     // ── 8. Увеличиваем ликвидность в целевой позиции ────────────────────
-    if let Err(e) = increase_liquidity_partial(
+    if let Err(e) = executor().increase_liquidity(
         mint_to,
         usd_budget,          // ← теперь передаём общий бюджет в USD
         &pool_cfg,
@@ -1220,7 +1404,206 @@ pub async fn run_inc_command(
     let _ = tx.send(ServiceCommand::SendMessage("✅ Перебалансировка завершена".into()));
 }
 
+/// Сколько токенов A/B придёт при выводе `pct`% ликвидности позиции:
+/// состав из `position_info` (точная квота), доля — линейно по ликвидности.
+async fn withdraw_quote(cfg: &PoolConfig, pos: &OwnedPosition, pct: f64) -> anyhow::Result<(f64, f64)> {
+    let info = executor().position_info(cfg, pos).await?;
+    Ok((info.amount_a * pct / 100.0, info.amount_b * pct / 100.0))
+}
+
 fn upper_price(tick_u: i32, dec_a: u8, dec_b: u8) -> f64 {
     tick_index_to_price(tick_u, dec_a, dec_b)
 }
 
+/// Позиции одного пула для mliq/pliq/inc: `[pool]`, а без него — единственный
+/// включённый пул. Сверху вниз по верхней границе в десятичных этого пула.
+async fn pool_positions(pool: Option<&String>) -> Result<(PoolConfig, Vec<OwnedPosition>), String> {
+    let mut pools = target_pools(pool)?;
+    if pools.len() != 1 {
+        let names: Vec<&str> = pools.iter().map(|c| c.name.as_str()).collect();
+        return Err(format!("укажите пул: {}", names.join(", ")));
+    }
+    let cfg = pools.remove(0);
+    let whirl_pk: Pubkey = cfg.pool_address.parse().map_err(|e| format!("{}: {e}", cfg.name))?;
+    let mut list = owned_in_pool(whirl_pk).await
+        .map_err(|e| format!("Не удалось получить позиции {}: {e}", cfg.name))?;
+    if list.is_empty() {
+        return Err(format!("В {} нет открытых позиций", cfg.name));
+    }
+    let (dec_a, dec_b) = (cfg.decimal_a as u8, cfg.decimal_b as u8);
+    list.sort_by(|a, b| {
+        upper_price(b.tick_upper, dec_a, dec_b).total_cmp(&upper_price(a.tick_upper, dec_a, dec_b))
+    });
+    Ok((cfg, list))
+}
+
+/// USD-цены токенов A и B пула.
+async fn tokens_usd(cfg: &PoolConfig) -> anyhow::Result<(f64, f64)> {
+    Ok((utils::get_token_price_usd(&cfg.mint_a).await?, utils::get_token_price_usd(&cfg.mint_b).await?))
+}
+
+/// Пулы, к которым относится команда: `--SOL/USDC` → один пул, без параметра → все.
+fn target_pools(pool: Option<&String>) -> Result<Vec<PoolConfig>, String> {
+    match pool {
+        Some(key) => pool_registry::find_pool(key)
+            .map(|c| vec![c])
+            .map_err(|e| e.to_string()),
+        None => Ok(pool_registry::enabled_pools()),
+    }
+}
+
+/// Политика выхода пула: сохранённая или политика его стратегии (`false`).
+async fn pool_exit_policy(pool_address: &str) -> anyhow::Result<(ExitPolicy, bool)> {
+    Ok(match exit_policy::get_exit_policy(pool_address).await? {
+        Some(p) => (p, true),
+        None    => (strategy_for_pool(pool_address).await?.exit_policy(), false),
+    })
+}
+
+/// exit up|down --<поле> --<значение> [--pool]; работающий пул подхватит изменения в течение 15 с
+async fn set_exit_rule(tx: &UnboundedSender<ServiceCommand>, side: &str, params: &[String]) {
+    let usage = || { let _ = tx.send(ServiceCommand::SendMessage(format!(
+        "❌ Usage: exit {side} --<wait|twap|confirm|overshoot> --<value> [--pool]"
+    ))); };
+    let (Some(field), Some(value)) = (params.first(), params.get(1)) else { return usage(); };
+    let apply: Box<dyn Fn(&mut ExitRule) + Send + Sync> = match (field.as_str(), value.as_str()) {
+        ("wait", "default") => Box::new(|r: &mut ExitRule| r.wait_min = None),
+        ("wait", v) => match v.parse::<u64>() {
+            Ok(m) => Box::new(move |r: &mut ExitRule| r.wait_min = Some(m)),
+            Err(_) => return usage(),
+        },
+        ("twap", v) => match v.parse::<u64>() {
+            Ok(sec) => Box::new(move |r: &mut ExitRule| r.twap_sec = sec),
+            Err(_) => return usage(),
+        },
+        ("confirm", v) => match v.parse::<u32>() {
+            Ok(n) if n >= 1 => Box::new(move |r: &mut ExitRule| r.confirmations = n),
+            _ => return usage(),
+        },
+        ("overshoot", v) => match v.parse::<f64>() {
+            Ok(pct) if pct >= 0.0 => Box::new(move |r: &mut ExitRule| r.overshoot_pct = pct),
+            _ => return usage(),
+        },
+        _ => return usage(),
+    };
+    let pools = match target_pools(params.get(2)) {
+        Ok(p) => p,
+        Err(e) => { let _ = tx.send(ServiceCommand::SendMessage(format!("❌ {e}"))); return; }
+    };
+    for cfg in pools {
+        let res = async {
+            let (mut p, _) = pool_exit_policy(&cfg.pool_address).await?;
+            let rule = if side == "up" { &mut p.up } else { &mut p.down };
+            apply(rule);
+            let text = rule.describe();
+            exit_policy::set_exit_policy(&cfg.pool_address, &p).await?;
+            Ok::<_, anyhow::Error>(text)
+        }.await;
+        match res {
+            Ok(text) => { let _ = tx.send(ServiceCommand::SendMessage(
+                format!("✅ {}: exit {side} = {text}", cfg.name))); }
+            Err(e) => { let _ = tx.send(ServiceCommand::SendMessage(
+                format!("❌ {}: {e}", cfg.name))); }
+        }
+    }
+}
+
+/// pct_number общий и во всех пулах реестра
+async fn set_pct_number_everywhere(new: u16) -> sqlx::Result<()> {
+    update_pct_number(new).await?;
+    for cfg in pool_registry::enabled_pools() {
+        pool_settings::update_pool_pct_number(&cfg.pool_address, new).await?;
+    }
+    Ok(())
+}
+
+async fn set_pool_enabled(tx: &UnboundedSender<ServiceCommand>, pool: Option<&String>, enabled: bool) {
+    let Some(key) = pool else {
+        let _ = tx.send(ServiceCommand::SendMessage("❌ Usage: pool on|off <pool>".into()));
+        return;
+    };
+    let cfg = match pool_registry::find_pool(key) {
+        Ok(c) => c,
+        Err(e) => { let _ = tx.send(ServiceCommand::SendMessage(format!("❌ {e}"))); return; }
+    };
+    match pool_settings::update_pool_enabled(&cfg.pool_address, enabled).await {
+        Ok(_) => { let _ = tx.send(ServiceCommand::SendMessage(
+            format!("✅ {} {}", cfg.name, if enabled { "включён" } else { "выключен" }))); }
+        Err(e) => { let _ = tx.send(ServiceCommand::SendMessage(
+            format!("❌ Не удалось обновить {}: {}", cfg.name, e))); }
+    }
+}
+
+/// `bt <days> <apr%> [strategy] [pool]` — прогон стратегии по истории.
+async fn run_backtest_command(params: &[String], tx: &UnboundedSender<ServiceCommand>) -> anyhow::Result<()> {
+    let days = params.first().and_then(|p| p.parse::<usize>().ok()).unwrap_or(7).clamp(1, 60);
+    let apr  = params.get(1).and_then(|p| p.parse::<f64>().ok()).unwrap_or(30.0);
+    let pool_name = params.get(3).map(String::as_str).unwrap_or("SOL/USDC");
+
+    let spec = pool_registry::find_spec(pool_name)
+        .ok_or_else(|| anyhow::anyhow!("пул {pool_name} не найден"))?;
+    // свечи — SOLUSDT с Binance: годятся только для SOL к долларовому стейблу
+    if spec.mint_a != WSOL || ![USDC, USDT].contains(&spec.mint_b) {
+        anyhow::bail!("бэктест считается по свечам SOL/USDT — для {} они не подходят", spec.name);
+    }
+    let general = get_general_settings().await?
+        .ok_or_else(|| anyhow::anyhow!("General settings not found in database"))?;
+    // настройки пула берём из БД, если пул включён в .env
+    let pool_set = match spec.to_config() {
+        Ok(cfg) => pool_settings::get_pool_settings(&cfg.pool_address).await?,
+        Err(_)  => None,
+    };
+    let kind = params.get(2)
+        .and_then(|p| p.parse::<Range>().ok())
+        .or_else(|| pool_set.as_ref().map(|s| s.strategy.clone()))
+        .unwrap_or(crate::params::RANGE);
+
+    let strategy = strategy_for(&kind);
+    let exit_policy = match spec.to_config() {
+        Ok(cfg) => exit_policy::get_exit_policy(&cfg.pool_address).await?,
+        Err(_)  => None,
+    }
+    .unwrap_or_else(|| strategy.exit_policy());
+
+    let _ = tx.send(ServiceCommand::SendMessage(format!(
+        "⏳ Бэктест {} [{}] за {} дн., APR {:.1}%…", spec.name, kind.as_str(), days, apr
+    )));
+
+    let candles = crate::exchange::helpers::get_kline_history("SOLUSDT", days * 1440, 1).await?;
+    let last_close = candles.last().map(|c| c.close).unwrap_or_default();
+    let bt = backtest::BacktestConfig {
+        pool_name:   spec.name.to_string(),
+        capital_usd: pool_set.as_ref().map(|s| s.amount).unwrap_or(general.amount),
+        pct_list:    if pool_set.as_ref().map(|s| s.pct_number).unwrap_or(general.pct_number) == 1 {
+                         general.pct_list_1
+                     } else {
+                         general.pct_list_2
+                     },
+        compress:    general.compress,
+        range:       pool_set.as_ref().and_then(|s| s.range),
+        min_restart: pool_set.as_ref().map(|s| s.min_restart).unwrap_or(1),
+        exit:        exit_policy,
+        fees:        backtest::FeeModel::Apr(apr),
+        swap_cost:   backtest::DEFAULT_SWAP_COST,
+        tx_cost_usd: backtest::DEFAULT_TX_FEE_SOL * last_close,
+    };
+    let report = backtest::run_backtest(strategy.as_ref(), &candles, &bt)?;
+
+    let st = &report.stats;
+    let mut msg = format!(
+        "📊 Бэктест {} [{}]\n{} — {}\nСессий: {}\nPnL: {:.2}$\nКомиссии: {:.2}$\nИздержки: {:.2}$\nNet: {:.2}$\nСр. длительность: {} мин",
+        spec.name, kind.as_str(),
+        st.period_start.format("%d.%m %H:%M"), st.period_end.format("%d.%m %H:%M"),
+        st.session_count, st.total_profit, st.total_commissions, st.total_costs, st.net_profit,
+        st.average_duration.num_minutes(),
+    );
+    for s in report.sessions.iter().rev().take(5) {
+        msg.push_str(&format!(
+            "\n• {} → {} [{:.2}; {:.2}] {:.2}$ → {:.2}$ (+{:.2}$)",
+            s.date_opened.format("%d.%m %H:%M"), s.date_closed.format("%d.%m %H:%M"),
+            s.range_lower, s.range_upper, s.sum_open, s.sum_close, s.commissions,
+        ));
+    }
+    let _ = tx.send(ServiceCommand::SendMessage(msg));
+    Ok(())
+}
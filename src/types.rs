@@ -1,8 +1,10 @@
 use ethers::contract::EthDisplay;
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::str::FromStr;
 
 /// Снимок состояния пула, который читает `reporter()`
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -36,6 +38,18 @@ pub struct PoolPositionInfo {
     pub pct_down: f64,
     pub pct_up: f64,
     pub index: u8,
+    /// награды (emissions) Whirlpool, ещё не собранные
+    pub rewards: Vec<PendingReward>,
+    /// их сумма в USD (в `sum` не входит)
+    pub rewards_usd: f64,
+}
+
+/// Несобранная награда позиции по одному `reward_infos[i]` пула.
+#[derive(Debug, Clone)]
+pub struct PendingReward {
+    pub mint:   String,
+    pub amount: f64,
+    pub usd:    f64,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -45,6 +59,63 @@ pub enum Range {
     One
 }
 
+impl Range {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Range::Three => "three",
+            Range::Two   => "two",
+            Range::One   => "one",
+        }
+    }
+}
+
+impl FromStr for Range {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "three" | "3" => Ok(Range::Three),
+            "two"   | "2" => Ok(Range::Two),
+            "one"   | "1" => Ok(Range::One),
+            _             => Err(format!("unknown range mode: {s}")),
+        }
+    }
+}
+
+/// Что делать с собранными комиссиями.
+#[derive(Clone, Debug, PartialEq)]
+pub enum HarvestMode {
+    /// не собирать
+    Off,
+    /// вернуть в позицию, внутри которой сейчас цена
+    Compound,
+    /// перевести в USDC и оставить в кошельке
+    Reserve,
+}
+
+impl HarvestMode {
+    pub fn as_str(&self) -> &str {
+        match self {
+            HarvestMode::Off      => "off",
+            HarvestMode::Compound => "compound",
+            HarvestMode::Reserve  => "reserve",
+        }
+    }
+}
+
+impl FromStr for HarvestMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "off"      => Ok(HarvestMode::Off),
+            "compound" => Ok(HarvestMode::Compound),
+            "reserve"  => Ok(HarvestMode::Reserve),
+            _          => Err(format!("unknown harvest mode: {s}")),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Role {
     Middle,
@@ -54,6 +125,14 @@ pub enum Role {
 }
 
 impl Role {
+    /// Слот в pool_configs (position_1..3), который занимает роль
+    pub fn slot(&self) -> u8 {
+        match self {
+            Role::Up | Role::MiddleSmall => 1,
+            Role::Middle                 => 2,
+            Role::Down                   => 3,
+        }
+    }
     pub fn as_str(&self) -> &str {
         match self {
             Role::MiddleSmall => "MiddleSmall",
@@ -83,7 +162,7 @@ pub struct LiqPosition {
     pub lower_price:      f64,
 }
 
-/// Полная запись пула (единственная строка, id=1)
+/// Полная запись пула (одна строка на pool_address)
 #[derive(Clone, Debug)]
 pub struct PoolConfig {
     pub amount:                f64,
@@ -133,12 +212,17 @@ pub enum BoundType {
     LowerOuter,   // нижняя экстремальная (ещё ниже рынка)
 }
 
+/// Команды воркеру дельта-хеджа (см. `exchange::hl_engine`).
 #[derive(Clone, Debug)]
-pub enum WorkerCommand {
-    /// Start a new cycle with this pool configuration.
-    On(PoolConfig),
-    /// Immediately force-close any open position and go idle.
+pub enum HedgeCommand {
+    /// Включить хедж и сразу подравнять шорт.
+    On,
+    /// Закрыть шорт и перестать хеджировать.
     Off,
+    /// Позиции LP изменились (открытие / закрытие) — пересчитать дельту.
+    Sync,
+    /// Прислать текущее состояние в Telegram.
+    Status,
 }
 
 #[derive(Debug)]
@@ -148,6 +232,8 @@ pub struct OpenPositionResult {
     pub amount_wsol: f64,
     /// Объём USDC (в единицах токена, не в атомах)
     pub amount_usdc: f64,
+    /// подпись транзакции открытия (`None` — бумажный режим)
+    pub signature: Option<Signature>,
 }
 
 #[derive(Debug, Clone, Default)]
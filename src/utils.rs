@@ -19,17 +19,19 @@ use solana_sdk::account::Account;
 use solana_sdk::{
     pubkey::Pubkey,
     signature::{read_keypair_file, Keypair},
-    transaction::Transaction,
 };
 use crate::types::WalletBalanceInfo;
 use crate::params::{WSOL, USDC};
 use crate::dex_services::swap;
+use crate::dex_services::token;
+use crate::dex_services::executor::{self, executor};
 use std::str::FromStr;
 use orca_tx_sender::Signer;
-use orca_tx_sender::ComputeBudgetInstruction;
-use solana_client::rpc_config::RpcSendTransactionConfig;
-use solana_sdk::message::Message;
+use solana_sdk::message::{v0, AddressLookupTableAccount, VersionedMessage};
+use solana_sdk::{hash::Hash, packet::PACKET_DATA_SIZE, transaction::VersionedTransaction};
 use orca_tx_sender::CommitmentConfig;
+use crate::dex_services::{preview, priority};
+use crate::dex_services::tx_sender::{TxOutcome, TxSender};
 
 pub static RPC_ROTATOR: Lazy<RpcRotator> = Lazy::new(RpcRotator::new);
 static PRICE_CACHE: Lazy<tokio::sync::RwLock<(f64, Instant)>> =
@@ -51,47 +53,117 @@ pub mod utils {
             .map_err(op("load_wallet"))
     }
 
+    /// Срочность отправки: срочные (выходы) на каждом переподписании берут
+    /// повышенную приоритетную комиссию и переподписываются больше раз.
+    /// Переподписываем только после истечения blockhash прошлой версии,
+    /// поэтому обе лечь не могут (tx_sender.rs).
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Urgency {
+        Normal,
+        Urgent,
+    }
+
+    /// сколько раз обычная отправка переподписывается со свежим blockhash
+    const NORMAL_ATTEMPTS: usize = 2;
+
     pub async fn send_and_confirm(
         rpc: Arc<RpcClient>,
-        mut instructions: Vec<Instruction>,
+        instructions: Vec<Instruction>,
+        signers: &[&Keypair],
+    ) -> Result<TxOutcome> {
+        send_tx(rpc, instructions, signers, &[], Urgency::Normal).await
+    }
+
+    /// `send_and_confirm` для выходов: не легла — повтор с большей комиссией.
+    pub async fn send_urgent(
+        rpc: Arc<RpcClient>,
+        instructions: Vec<Instruction>,
         signers: &[&Keypair],
-    ) -> Result<()> {
-        // 1. ComputeBudget
-        instructions.insert(0, ComputeBudgetInstruction::set_compute_unit_limit(400_000));
+    ) -> Result<TxOutcome> {
+        send_tx(rpc, instructions, signers, &[], Urgency::Urgent).await
+    }
 
-        // 2. Latest blockhash
+    /// Собрать и подписать v0-транзакцию; адреса из `tables` уходят в lookup.
+    pub fn compile_v0(
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+        tables: &[AddressLookupTableAccount],
+        blockhash: Hash,
+    ) -> Result<VersionedTransaction> {
+        let payer = signers
+            .get(0)
+            .ok_or_else(|| anyhow!("No payer signer"))?;
+        let message = v0::Message::try_compile(&payer.pubkey(), instructions, tables, blockhash)
+            .map_err(op("compile v0 message"))?;
+        VersionedTransaction::try_new(VersionedMessage::V0(message), signers)
+            .map_err(op("sign v0 transaction"))
+    }
+
+    /// Влезает ли транзакция в один пакет (1232 байта).
+    pub fn fits_packet(tx: &VersionedTransaction) -> bool {
+        bincode::serialized_size(tx).map_or(false, |n| n as usize <= PACKET_DATA_SIZE)
+    }
+
+    /// То же, что `send_and_confirm`, но с таблицами адресов.
+    pub async fn send_v0_and_confirm(
+        rpc: Arc<RpcClient>,
+        instructions: Vec<Instruction>,
+        signers: &[&Keypair],
+        tables: &[AddressLookupTableAccount],
+    ) -> Result<TxOutcome> {
+        send_tx(rpc, instructions, signers, tables, Urgency::Normal).await
+    }
+
+    /// Единый путь отправки инструкций: v0-транзакция, симуляция с превью
+    /// балансов (preview.rs), лимит CU по ней, приоритетная комиссия по
+    /// writable-аккаунтам (priority.rs), отправка через `TxSender`.
+    /// Истёк blockhash — переподписываем со свежим; срочная отправка при
+    /// этом повышает комиссию.
+    pub async fn send_tx(
+        rpc: Arc<RpcClient>,
+        instructions: Vec<Instruction>,
+        signers: &[&Keypair],
+        tables: &[AddressLookupTableAccount],
+        urgency: Urgency,
+    ) -> Result<TxOutcome> {
+        // 1. Комиссия; симуляция — лимит CU и превью балансов
+        let fee = priority::fee_for(&rpc, &instructions).await;
         let recent = rpc
             .get_latest_blockhash()
             .await
             .map_err(op("get_latest_blockhash"))?;
-        // 3. Build & sign
-        let payer = signers
-            .get(0)
-            .ok_or_else(|| anyhow!("No payer signer"))?;
-        let message = Message::new(&instructions, Some(&payer.pubkey()));
-        let mut tx = Transaction::new_unsigned(message);
-        tx.try_sign(signers, recent).map_err(op("sign transaction"))?;
-        // 4. Send
-        let sig = rpc
-            .send_transaction_with_config(
-                &tx,
-                RpcSendTransactionConfig {
-                    skip_preflight: false,
-                    preflight_commitment: Some(CommitmentConfig::processed().commitment),
-                    ..Default::default()
-                },
-            )
+        let mut probe = priority::budget_ixs(priority::MAX_CU, fee);
+        probe.extend(instructions.iter().cloned());
+        let sim = preview::simulate(&rpc, &compile_v0(&probe, signers, tables, recent)?).await?;
+        preview::review(&sim.preview)?;
+        let cu = priority::cu_limit(sim.units_consumed);
+
+        // 2. Build & sign → send → confirm
+        let (attempts, escalate) = match urgency {
+            Urgency::Normal => (NORMAL_ATTEMPTS, false),
+            Urgency::Urgent => (priority::urgent_attempts(), true),
+        };
+        TxSender::new(rpc)
+            .send_with_resign(attempts, |recent, attempt| {
+                let price = priority::capped(cu, if escalate { priority::escalated(fee, attempt) } else { fee });
+                if attempt > 0 && escalate {
+                    log::warn!("повтор с комиссией {price} µlamports/CU");
+                }
+                let mut ixs = priority::budget_ixs(cu, price);
+                ixs.extend(instructions.iter().cloned());
+                let tx = compile_v0(&ixs, signers, tables, recent)?;
+                if !fits_packet(&tx) {
+                    return Err(anyhow!("v0 transaction exceeds {PACKET_DATA_SIZE} bytes"));
+                }
+                Ok(tx)
+            })
             .await
-            .map_err(op("send transaction"))?;
-        // 5. Confirm
-        for _ in 0..18 {
-            if let Some(status) = rpc.get_signature_status(&sig).await.map_err(op("get_signature_status"))? {
-                status.map_err(|e| anyhow!("transaction failed: {:?}", e))?;
-                return Ok(());
-            }
-            tokio::time::sleep(Duration::from_secs(1)).await;
-        }
-        Err(anyhow!("Timeout: tx {} not confirmed within 18s", sig))
+    }
+
+    /// Отправить уже подписанную транзакцию и дождаться подтверждения.
+    /// Если она уже в сети (например, легла бандлом) — просто подтверждаем.
+    pub async fn send_signed_and_confirm(rpc: Arc<RpcClient>, tx: &VersionedTransaction) -> Result<TxOutcome> {
+        TxSender::new(rpc).send_signed(tx).await
     }
 }
 
@@ -131,6 +203,22 @@ impl RpcRotator {
         ))
     }
 
+    /// Веб-сокет текущего RPC для подписок: RPC_WS_URL или тот же адрес
+    /// со схемой ws(s).
+    pub fn ws_url(&self) -> String {
+        if let Ok(url) = std::env::var("RPC_WS_URL") {
+            return url;
+        }
+        let http = &self.urls[self.current.load(Ordering::Relaxed)];
+        if let Some(rest) = http.strip_prefix("https://") {
+            format!("wss://{rest}")
+        } else if let Some(rest) = http.strip_prefix("http://") {
+            format!("ws://{rest}")
+        } else {
+            http.clone()
+        }
+    }
+
     /// Сдвигаем указатель на следующий URL (на сетевой ошибке).
     pub fn rotate(&self) {
         let next = (self.current.load(Ordering::Relaxed) + 1) % self.urls.len();
@@ -172,9 +260,9 @@ pub async fn get_token_balance(
         let lamports = rpc.get_balance(wallet).await?;
         Ok(lamports as f64 / 1e9)
     } else {
-        // SPL-token balance
+        // SPL Token / Token-2022 balance
         let mint_pk = Pubkey::from_str(mint)?;
-        let ata     = get_associated_token_address(wallet, &mint_pk);
+        let ata     = token::owner_ata(rpc, wallet, &mint_pk).await?;
         // Запрос возвращает Future<Result<UiTokenAmount, ClientError>>
         let ui = rpc
             .get_token_account_balance(&ata)
@@ -211,6 +299,11 @@ pub async fn balances_for_mints(
 pub async fn sweep_dust_to_usdc(
     dust_mints: &[(&'static str, u8)],
 ) -> Result<String> {
+    // в бумажном режиме «пыль» — это реальные токены кошелька, не трогаем
+    if executor::is_paper() {
+        return Ok("🧪 PAPER: свип пыли пропущен.".into());
+    }
+
     /* 1. сеть / кошелёк */
     let payer:  Keypair = utils::load_wallet()
         .map_err(|e| anyhow!("read_keypair_file failed: {}", e))?;
@@ -258,17 +351,10 @@ pub async fn swap_excess_to_usdc(
 ) -> Result<String> {
     use anyhow::{anyhow, Context};
     use tokio::time::{sleep, Duration};
-    use solana_sdk::{signer::keypair::Keypair, pubkey::Pubkey};
 
     const MAX_ATTEMPTS   : u8  = 3;     // сколько раз пробуем своп
     const FEE_BUFFER_SOL : f64 = 0.10;  // базовый буфер SOL (увеличивается с каждой попыткой)
 
-    // ─── инициализация ───────────────────────────────────────────────
-    let payer : Keypair = utils::load_wallet()
-        .map_err(|e| anyhow!("failed to load wallet: {}", e))?;
-    let wallet: Pubkey  = payer.pubkey();
-    let rpc             = utils::init_rpc();
-
     let mut last_err: Option<anyhow::Error> = None;
 
     // ─── цикл ретраев ────────────────────────────────────────────────
@@ -276,7 +362,7 @@ pub async fn swap_excess_to_usdc(
         log::debug!("swap_excess_to_usdc; attempt {}/{}", attempt, MAX_ATTEMPTS);
 
         // 1) актуальный баланс токена
-        let balance = match get_token_balance(&rpc, &wallet, mint, dec).await {
+        let balance = match executor().balance(mint, dec).await {
             Ok(bal) => bal,
             Err(e)  => {
                 last_err = Some(anyhow!("failed to get balance for {}: {}", mint, e));
@@ -312,7 +398,7 @@ pub async fn swap_excess_to_usdc(
         let to_swap = balance - keep_amount - dyn_buffer;
 
         // 5) пробуем выполнить своп
-        match swap::execute_swap_tokens(mint, USDC, to_swap).await {
+        match executor().swap(mint, USDC, to_swap).await {
             Ok(res) => {
                 return Ok(format!(
                     "🔁 Swapped {:.6} {} → USDC.\n\
@@ -557,6 +643,25 @@ pub async fn get_sol_price_usd(mint: &str, fallback: bool) -> Result<f64> {
     Err(anyhow!("cannot fetch SOL/USD price from Jupiter or CoinGecko"))
 }
 
+/// Цена произвольного токена в USD (Jupiter lite-api).
+/// Стейблы — 1, SOL — через `get_sol_price_usd` с кэшем и фолбэком.
+pub async fn get_token_price_usd(mint: &str) -> Result<f64> {
+    if mint == USDC || mint == params::USDT {
+        return Ok(1.0);
+    }
+    if mint == SOL_MINT {
+        return get_sol_price_usd(SOL_MINT, true).await;
+    }
+    let url  = format!("https://lite-api.jup.ag/price/v2?ids={mint}");
+    let body = http_client().get(&url).send().await?.text().await?;
+    let v: Value = serde_json::from_str(&body)
+        .map_err(|e| anyhow!("Jupiter price JSON parse error: {e}  body={body}"))?;
+    v["data"][mint]["price"]
+        .as_str()
+        .and_then(|p| p.parse::<f64>().ok())
+        .ok_or_else(|| anyhow!("Jupiter: нет цены для {mint}"))
+}
+
 /// сохраняем значение в кэше
 async fn cache_price(p: f64) {
     let mut wr = PRICE_CACHE.write().await;